@@ -116,22 +116,35 @@ pub struct AnimatedTexture2D {
     current_frame: u32,
 }
 
-pub fn update_textures() {
-    let ms = SystemTime::now().duration_since(UNIX_EPOCH).expect("Time went backwards ftw").as_millis();
-    for (_, texture) in unsafe { ANIMATED_TEXTURES.get_mut().iter_mut() } {
-        let mut local_ms = ms % texture.length;
-        let mut frame: u32 = 0;
-
-        for f in &texture.frames {
-            if local_ms > f.length as u128 {
-                local_ms -= f.length as u128;
-            } else {
-                frame = f.id as u32;
-                break;
-            }
+fn now_ms() -> u128 {
+    SystemTime::now().duration_since(UNIX_EPOCH).expect("Time went backwards ftw").as_millis()
+}
+
+/// Gets the frame to show `elapsed_ms` into the animation, with the frames stretched so one loop takes `loop_duration_ms`
+pub fn frame_at(frames: &[Frame], elapsed_ms: u128, loop_duration_ms: u128) -> u32 {
+    let length: u128 = frames.iter().map(|f| f.length as u128).sum();
+    if length == 0 || loop_duration_ms == 0 {
+        return 0;
+    }
+    let mut local_ms = (elapsed_ms % loop_duration_ms) * length / loop_duration_ms;
+    let mut frame: u32 = 0;
+
+    for f in frames {
+        if local_ms > f.length as u128 {
+            local_ms -= f.length as u128;
+        } else {
+            frame = f.id as u32;
+            break;
         }
+    }
 
-        texture.current_frame = frame;
+    frame
+}
+
+pub fn update_textures() {
+    let ms = now_ms();
+    for (_, texture) in unsafe { ANIMATED_TEXTURES.get_mut().iter_mut() } {
+        texture.current_frame = frame_at(&texture.frames, ms, texture.length);
     }
 }
 
@@ -264,6 +277,31 @@ impl AnimatedTexture2D {
             Color::WHITE,
         );
     }
+
+    /// Like `draw_resized_rotated`, but plays the animation so that one loop takes `loop_duration_ms`
+    /// instead of the length of the frames, e.g. to match how fast items move on a belt
+    pub fn draw_resized_rotated_synced(
+        &self,
+        renderer: &mut RaylibDrawHandle,
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+        rotation: Direction,
+        loop_duration_ms: u32,
+    ) {
+        let (rotation, vec) = get_rotation_vec(rotation, Vec2i::new(x, y), width, height);
+        let dest = Rectangle::new(vec.x as f32, vec.y as f32, width as f32, height as f32);
+        let frame = frame_at(&self.frames, now_ms(), loop_duration_ms as u128);
+        renderer.draw_texture_pro(
+            &self.texture,
+            self.get_frame_texture_rect(frame),
+            dest,
+            ORIGIN,
+            rotation,
+            Color::WHITE,
+        );
+    }
 }
 
 pub fn get_rotation_vec(dir: Direction, mut vec: Vec2i, width: i32, height: i32) -> (f32, Vec2i) {
@@ -284,4 +322,39 @@ pub fn get_rotation_vec(dir: Direction, mut vec: Vec2i, width: i32, height: i32)
         }
     };
     (rotation, vec)
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::TICK_DURATION;
+
+    /// 4 frames of 100ms each
+    const FRAMES: [Frame; 4] = [
+        Frame { id: 0, length: 100 },
+        Frame { id: 1, length: 100 },
+        Frame { id: 2, length: 100 },
+        Frame { id: 3, length: 100 },
+    ];
+
+    fn frame_after_ticks(ticks: u32, belt_duration_ms: u128) -> u32 {
+        frame_at(&FRAMES, (TICK_DURATION * ticks).as_millis(), belt_duration_ms)
+    }
+
+    #[test]
+    fn belt_frames_follow_the_belt_duration() {
+        // a 1000ms belt shows each frame for 250ms, 5 ticks
+        assert_eq!(frame_after_ticks(0, 1000), 0);
+        assert_eq!(frame_after_ticks(6, 1000), 1);
+        assert_eq!(frame_after_ticks(11, 1000), 2);
+        assert_eq!(frame_after_ticks(19, 1000), 3);
+        // and starts over once an item crossed it
+        assert_eq!(frame_after_ticks(20, 1000), 0);
+
+        // a belt twice as fast is a frame further along after the same time
+        assert_eq!(frame_after_ticks(6, 500), 2);
+        assert_eq!(frame_after_ticks(10, 500), 0);
+        // without any frames or duration there is nothing to animate
+        assert_eq!(frame_at(&FRAMES, 1234, 0), 0);
+        assert_eq!(frame_at(&[], 1234, 1000), 0);
+    }
+}
@@ -0,0 +1,368 @@
+use std::time::Duration;
+
+use lazy_static::lazy_static;
+use raylib::{
+    color::Color,
+    drawing::{RaylibDraw, RaylibDrawHandle},
+};
+
+use crate::{
+    block_impl_details_with_timer,
+    blocks::{downcast_mut, draw_side_marker},
+    game::RenderLayer,
+    identifier::{GlobalString, Identifier},
+    inventory::Inventory,
+    items::{get_item_by_id, Stack},
+    recipe::{get_recipe_by_id, Recipe},
+    reset_timer,
+    scheduler::{schedule_task, Task},
+    screens::RecipeScreen,
+    serialization::{Buffer, Deserialize, SerializationError, Serialize},
+    world::{record_item_delta, ChunkBlockMetadata, Direction, World},
+    GameConfig,
+};
+
+use super::{now, Block, BlockCategory};
+
+lazy_static! {
+    pub static ref ASSEMBLER_NAME: GlobalString = GlobalString::from("Assembler");
+    pub static ref BLOCK_ASSEMBLER: Identifier =
+        Identifier::from(("placeholder_name_2", "assembler"));
+}
+
+const INPUT_SLOTS: usize = 4;
+const OUTPUT_SLOTS: usize = 4;
+/// how many crafts worth of a single input the assembler takes before refusing more, so one input can't fill up the
+/// slots the others need
+const BUFFERED_CRAFTS: u64 = 10;
+/// power needed every tick while a recipe is selected, a generator can run 2 assemblers
+const POWER_DEMAND: i32 = 2;
+
+// fields: the input inventory, the output inventory and the selected recipe. The timer duration is only the default,
+// each recipe has its own
+block_impl_details_with_timer!(AssemblerBlock, 1000, Inventory, Inventory, Option<Identifier>);
+impl Default for AssemblerBlock {
+    fn default() -> Self {
+        Self(
+            now(),
+            Inventory::new(INPUT_SLOTS, false),
+            Inventory::new(OUTPUT_SLOTS, false),
+            None,
+        )
+    }
+}
+
+impl Block for AssemblerBlock {
+    fn serialize(&self, buf: &mut Vec<u8>) {
+        self.1.serialize(buf);
+        self.2.serialize(buf);
+        self.3.serialize(buf);
+    }
+    fn try_deserialize(&mut self, buf: &mut Buffer) -> Result<(), SerializationError> {
+        self.1 = Inventory::try_deserialize(buf)?;
+        self.2 = Inventory::try_deserialize(buf)?;
+        self.3 = Deserialize::try_deserialize(buf)?;
+        self.1.resize(INPUT_SLOTS);
+        self.2.resize(OUTPUT_SLOTS);
+        Ok(())
+    }
+    fn required_length(&self) -> usize {
+        self.1.required_length() + self.2.required_length() + self.3.required_length()
+    }
+
+    fn description(&self) -> &'static str {
+        "Turns the inputs of its recipe into the outputs, powered by a generator next to it"
+    }
+    fn identifier(&self) -> Identifier {
+        *BLOCK_ASSEMBLER
+    }
+    fn category(&self) -> BlockCategory {
+        BlockCategory::Production
+    }
+    fn throughput_per_min(&self) -> Option<u32> {
+        let recipe = self.3.and_then(get_recipe_by_id)?;
+        let per_craft = recipe.outputs.first().map(|(_, count)| *count).unwrap_or(0);
+        Some((60_000 / recipe.duration_ms.max(1)) as u32 * per_craft)
+    }
+    fn is_machine(&self) -> bool {
+        true
+    }
+    fn dismantle_time_ms(&self) -> u64 {
+        4000
+    }
+    fn name(&self) -> GlobalString {
+        *ASSEMBLER_NAME
+    }
+    fn supports_interaction(&self) -> bool {
+        true
+    }
+    fn custom_interact_message(&self) -> Option<String> {
+        Some(match self.recipe_output().and_then(get_item_by_id) {
+            Some(item) => format!("Press F to change the recipe (currently {})", item.name()),
+            None => "Press F to select a recipe".to_string(),
+        })
+    }
+    fn interact(&mut self, meta: ChunkBlockMetadata, _: &mut GameConfig) {
+        schedule_task(Task::OpenScreenCentered(Box::new(RecipeScreen::new(
+            meta.position,
+            self.name(),
+        ))));
+    }
+    fn recipe(&self) -> Option<Identifier> {
+        self.3
+    }
+    fn copy_config(&self) -> Option<Vec<u8>> {
+        let mut buf = Vec::new();
+        self.3.serialize(&mut buf);
+        Some(buf)
+    }
+    fn apply_config(&mut self, buf: &mut Buffer) -> Result<(), SerializationError> {
+        match Deserialize::try_deserialize(buf)? {
+            Some(recipe) => self.set_recipe(recipe),
+            // copied from an assembler without a recipe
+            None => self.3 = None,
+        }
+        Ok(())
+    }
+    fn carries_power(&self) -> bool {
+        true
+    }
+    fn power_demand(&self) -> i32 {
+        if self.3.is_some() {
+            POWER_DEMAND
+        } else {
+            0
+        }
+    }
+    /// Inputs of the old recipe stay in the assembler until they get used up or the assembler is dismantled
+    fn set_recipe(&mut self, recipe: Identifier) {
+        self.3 = Some(recipe);
+        reset_timer!(self);
+    }
+    fn render(
+        &self,
+        d: &mut RaylibDrawHandle,
+        x: i32,
+        y: i32,
+        w: i32,
+        h: i32,
+        meta: ChunkBlockMetadata,
+        layer: RenderLayer,
+    ) {
+        if layer == RenderLayer::Block || layer == RenderLayer::Preview {
+            d.draw_rectangle(x, y, w, h, Color::DARKPURPLE);
+            draw_side_marker(d, x, y, w, h, meta.direction, Color::BLUE);
+        } else if layer == RenderLayer::OverlayItems {
+            if let Some(item) = self.recipe_output().and_then(get_item_by_id) {
+                item.render(d, x + w / 4, y + h / 4, w / 2, h / 2);
+            }
+        }
+    }
+
+    fn init(&mut self, _: ChunkBlockMetadata) {
+        self.1.resize(INPUT_SLOTS);
+        self.2.resize(OUTPUT_SLOTS);
+    }
+    fn inventory(&self) -> Option<&Inventory> {
+        Some(&self.2)
+    }
+    fn item_count(&self) -> u64 {
+        self.1.total_items() + self.2.total_items()
+    }
+    fn destroy_items(&self) -> Vec<Stack> {
+        let mut items = self.1.destroy_items();
+        items.extend(self.2.destroy_items());
+        items
+    }
+    /// inputs go in everywhere but the front
+    fn has_capability_push(&self, side: Direction, meta: ChunkBlockMetadata) -> bool {
+        side != meta.direction
+    }
+    fn has_capability_pull(&self, _: Direction, _: ChunkBlockMetadata) -> bool {
+        true
+    }
+    fn can_push(&self, side: Direction, item: &Stack, meta: ChunkBlockMetadata) -> bool {
+        self.has_capability_push(side, meta)
+            && self
+                .input_count(item.identifier())
+                .map(|count| self.1.count(item.identifier()) < count as u64 * BUFFERED_CRAFTS)
+                .unwrap_or(false)
+            && self.1.can_push(item)
+    }
+    fn can_pull(&self, side: Direction, meta: ChunkBlockMetadata) -> bool {
+        self.has_capability_pull(side, meta) && self.2.can_pull()
+    }
+    fn push(
+        &mut self,
+        side: Direction,
+        item: Stack,
+        meta: ChunkBlockMetadata,
+    ) -> Option<Stack> {
+        if !self.can_push(side, &item, meta) {
+            return Some(item);
+        }
+        self.1.try_add_item(item)
+    }
+    fn pull(
+        &mut self,
+        _side: Direction,
+        _meta: ChunkBlockMetadata,
+        num_items: u32,
+    ) -> Option<Stack> {
+        self.2.try_pull(num_items)
+    }
+    fn update(&mut self, meta: ChunkBlockMetadata) {
+        schedule_task(Task::WorldUpdateBlock(&Self::update, meta));
+    }
+}
+
+impl AssemblerBlock {
+    fn recipe_output(&self) -> Option<Identifier> {
+        self.3.and_then(get_recipe_by_id)?.main_output()
+    }
+
+    /// How many items of `id` the recipe uses up per craft, None if it isn't an input
+    fn input_count(&self, id: Identifier) -> Option<u32> {
+        let recipe = self.3.and_then(get_recipe_by_id)?;
+        recipe
+            .inputs
+            .iter()
+            .find(|(input, _)| *input == id)
+            .map(|(_, count)| *count)
+    }
+
+    fn outputs(recipe: &Recipe) -> Option<Vec<Stack>> {
+        recipe
+            .outputs
+            .iter()
+            .map(|(id, count)| Stack::of(*id, *count))
+            .collect()
+    }
+
+    /// Uses up the inputs and puts the outputs into the output inventory, once the recipe took long enough, all
+    /// inputs are there and the outputs have space
+    fn craft(meta: ChunkBlockMetadata, world: &mut World) -> Option<()> {
+        if !world.power_networks().is_satisfied(meta.position) {
+            return Some(());
+        }
+        let blk = world.get_block_at_mut(meta.position.x, meta.position.y)?.0;
+        let blk = downcast_mut::<Self>(&mut **blk)?;
+        let recipe = get_recipe_by_id(blk.3?)?;
+
+        if now().saturating_duration_since(blk.0) < Duration::from_millis(recipe.duration_ms) {
+            return Some(());
+        }
+        if recipe
+            .inputs
+            .iter()
+            .any(|(id, count)| blk.1.count(*id) < *count as u64)
+        {
+            return Some(());
+        }
+        let outputs = Self::outputs(&recipe)?;
+        // the outputs are checked against a copy, they could end up in the same slot
+        let mut output_inventory = blk.2.clone();
+        for output in &outputs {
+            if output_inventory.try_add_item(output.clone()).is_some() {
+                return Some(());
+            }
+        }
+
+        for (id, count) in &recipe.inputs {
+            blk.1.remove(*id, *count);
+        }
+        blk.2 = output_inventory;
+        let used: i64 = recipe.inputs.iter().map(|(_, count)| *count as i64).sum();
+        let made: i64 = recipe.outputs.iter().map(|(_, count)| *count as i64).sum();
+        record_item_delta(made - used);
+        reset_timer!(blk);
+
+        Some(())
+    }
+
+    fn update(meta: ChunkBlockMetadata, world: &mut World) {
+        Self::craft(meta, world);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        blocks::generator::GeneratorBlock,
+        game::TPS,
+        items::{COAL_IDENTIFIER, IRON_INGOT_IDENTIFIER, IRON_ORE_IDENTIFIER},
+        test_utils::{item, register_all},
+    };
+
+    /// An assembler making iron ingots with the inputs for `crafts` of them
+    fn stocked_assembler(crafts: u32) -> Box<dyn Block> {
+        let mut assembler = AssemblerBlock::default();
+        assembler.set_recipe(Identifier::from(("placeholder_name_2", "iron_ingot")));
+        assert!(assembler.1.try_add_item(item(*IRON_ORE_IDENTIFIER, crafts)).is_none());
+        assert!(assembler.1.try_add_item(item(*COAL_IDENTIFIER, crafts)).is_none());
+        Box::new(assembler)
+    }
+
+    /// A generator with enough coal to run for the whole test
+    fn fueled_generator() -> Box<dyn Block> {
+        let mut generator = GeneratorBlock::default();
+        let pushed = generator.push(Direction::North, item(*COAL_IDENTIFIER, 5), ChunkBlockMetadata::default());
+        assert!(pushed.is_none());
+        Box::new(generator)
+    }
+
+    fn ingots_at(world: &World, x: i32, y: i32) -> u64 {
+        let blk = world.get_block_at(x, y).unwrap().0;
+        blk.inventory().unwrap().count(*IRON_INGOT_IDENTIFIER)
+    }
+
+    fn run_for_secs(world: &mut World, secs: u32) {
+        let mut config = GameConfig::default();
+        for _ in 0..secs * TPS {
+            world.tick(&mut config);
+        }
+    }
+
+    #[test]
+    fn a_generator_runs_two_assemblers() {
+        register_all();
+        let mut world = World::new(1, 1);
+        world.set_block_at(0, 0, fueled_generator(), Direction::North);
+        world.set_block_at(1, 0, stocked_assembler(2), Direction::North);
+        world.set_block_at(2, 0, stocked_assembler(2), Direction::North);
+
+        run_for_secs(&mut world, 3);
+        assert_eq!(ingots_at(&world, 1, 0), 2);
+        assert_eq!(ingots_at(&world, 2, 0), 2);
+    }
+
+    #[test]
+    fn assemblers_without_a_generator_do_nothing() {
+        register_all();
+        let mut world = World::new(1, 1);
+        for x in 0..3 {
+            world.set_block_at(x, 0, stocked_assembler(2), Direction::North);
+        }
+
+        run_for_secs(&mut world, 3);
+        for x in 0..3 {
+            assert_eq!(ingots_at(&world, x, 0), 0, "{x}");
+            assert_eq!(world.get_block_at(x, 0).unwrap().0.item_count(), 4, "{x}");
+        }
+    }
+
+    #[test]
+    fn assemblers_away_from_the_generator_do_nothing() {
+        register_all();
+        let mut world = World::new(1, 1);
+        world.set_block_at(0, 0, fueled_generator(), Direction::North);
+        world.set_block_at(1, 0, stocked_assembler(1), Direction::North);
+        // one empty cell away from the network
+        world.set_block_at(3, 0, stocked_assembler(1), Direction::North);
+
+        run_for_secs(&mut world, 2);
+        assert_eq!(ingots_at(&world, 1, 0), 1);
+        assert_eq!(ingots_at(&world, 3, 0), 0);
+    }
+}
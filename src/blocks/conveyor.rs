@@ -6,77 +6,104 @@ use raylib::{drawing::RaylibDrawHandle, RaylibHandle, RaylibThread};
 use crate::{
     asset,
     assets::{load_animated_texture, AnimatedTexture2D, Frame},
-    block_impl_details_with_timer,
+    block_impl_details,
     game::RenderLayer,
     identifier::{GlobalString, Identifier},
     initialized_data::InitializedData,
     inventory::Inventory,
-    items::Item,
+    items::Stack,
+    notice_board::{self, NoticeboardEntryRenderable},
     reset_timer,
     scheduler::{schedule_task, Task},
+    screens::BeltInspectScreen,
     simple_single_item_direction_serializable, step_size,
     world::{ChunkBlockMetadata, Direction, Vec2i, World},
     GameConfig,
 };
 
-use super::Block;
+use super::{now, overflow_policy, Block, BlockCategory};
 
 lazy_static! {
     pub static ref CONVEYOR_NAME: GlobalString = GlobalString::from("Conveyor Belt Tier 1");
     pub static ref BLOCK_CONVEYOR: Identifier =
         Identifier::from(("placeholder_name_2", "conveyor_mk1"));
+    pub static ref FAST_CONVEYOR_NAME: GlobalString = GlobalString::from("Conveyor Belt Tier 2");
+    pub static ref BLOCK_FAST_CONVEYOR: Identifier =
+        Identifier::from(("placeholder_name_2", "conveyor_mk2"));
 }
 
-block_impl_details_with_timer!(ConveyorBlock, 1000, Inventory, Direction);
+/// how long a tier 1 belt takes to move an item across
+pub const CONVEYOR_WORK_MS: u32 = 1000;
+/// how long a tier 2 belt takes to move an item across
+pub const FAST_CONVEYOR_WORK_MS: u32 = 500;
+
+// the last field is how long the belt takes to move an item across in ms, which decides the tier
+block_impl_details!(ConveyorBlock, Instant, Inventory, Direction, u32);
 impl Default for ConveyorBlock {
     fn default() -> Self {
         Self(
-            Instant::now(),
+            now(),
             Inventory::new(1, false),
             Direction::default(),
+            CONVEYOR_WORK_MS,
         )
     }
 }
 
+/// Whether `id` is one of the conveyor belt tiers
+pub fn is_conveyor(id: Identifier) -> bool {
+    id == *BLOCK_CONVEYOR || id == *BLOCK_FAST_CONVEYOR
+}
+
 impl Block for ConveyorBlock {
     simple_single_item_direction_serializable!(1, 2);
 
     fn description(&self) -> &'static str {
-        "Moves 60 items per minute"
+        if self.is_fast() {
+            "Moves 120 items per minute"
+        } else {
+            "Moves 60 items per minute"
+        }
     }
 
-    fn interact(&mut self, _: ChunkBlockMetadata, config: &mut GameConfig) {
-        match self.1.take_item(0) {
-            None => {}
-            Some(item) => {
-                if item.metadata() < 1 {
-                    return;
-                }
-                if let Some(item) = config.inventory.try_add_item(item) {
-                    self.1.get_item_mut(0).replace(item);
-                }
-            }
-        }
+    fn interact(&mut self, meta: ChunkBlockMetadata, _: &mut GameConfig) {
+        schedule_task(Task::OpenScreenCentered(Box::new(BeltInspectScreen::new(
+            meta.position,
+        ))));
     }
 
     fn supports_interaction(&self) -> bool {
-        self.1.get_item(0).is_some()
+        true
     }
 
     fn custom_interact_message(&self) -> Option<String> {
-        self.1
-            .get_item(0)
-            .as_ref()
-            .map(|item| format!("Grab {} from {}", item.name(), self.name()))
+        Some(format!("Inspect {}", self.name()))
     }
 
     fn identifier(&self) -> Identifier {
-        *BLOCK_CONVEYOR
+        if self.is_fast() {
+            *BLOCK_FAST_CONVEYOR
+        } else {
+            *BLOCK_CONVEYOR
+        }
+    }
+    fn category(&self) -> BlockCategory {
+        BlockCategory::Logistics
+    }
+    fn throughput_per_min(&self) -> Option<u32> {
+        Some(60_000 / self.3)
+    }
+    fn is_machine(&self) -> bool {
+        true
     }
     fn name(&self) -> GlobalString {
-        *CONVEYOR_NAME
+        if self.is_fast() {
+            *FAST_CONVEYOR_NAME
+        } else {
+            *CONVEYOR_NAME
+        }
     }
-    fn destroy_items(&self) -> Vec<Box<dyn Item>> {
+    fn destroy_items(&self) -> Vec<Stack> {
         self.1.destroy_items()
     }
     fn render(
@@ -90,7 +117,12 @@ impl Block for ConveyorBlock {
         layer: RenderLayer,
     ) {
         if layer == RenderLayer::Block  || layer == RenderLayer::Preview {
-            CONVEYOR_ANIMATION.draw_resized_rotated(d, x, y, w, h, meta.direction);
+            let animation = if self.is_fast() {
+                &FAST_CONVEYOR_ANIMATION
+            } else {
+                &CONVEYOR_ANIMATION
+            };
+            animation.draw_resized_rotated_synced(d, x, y, w, h, meta.direction, self.3);
         } else if layer == RenderLayer::OverlayItems {
             if let Some(item) = &self.1.get_item(0) {
                 let lerp_val = self.duration_lerp_value();
@@ -115,13 +147,16 @@ impl Block for ConveyorBlock {
     fn init(&mut self, _: ChunkBlockMetadata) {
         self.1.resize(1);
     }
-    fn get_inventory_capability<'a>(&'a mut self) -> Option<&'a mut Inventory> {
+    fn inventory(&self) -> Option<&Inventory> {
+        Some(&self.1)
+    }
+    fn get_inventory_capability(&mut self) -> Option<&mut Inventory> {
         if !self.can_do_work() {
             return None;
         }
         Some(&mut self.1)
     }
-    fn can_push(&self, side: Direction, _: &Box<dyn Item>, meta: ChunkBlockMetadata) -> bool {
+    fn can_push(&self, side: Direction, _: &Stack, meta: ChunkBlockMetadata) -> bool {
         self.1.get_item(0).is_none() && self.has_capability_push(side, meta)
     }
     fn has_capability_push(&self, side: Direction, meta: ChunkBlockMetadata) -> bool {
@@ -130,9 +165,9 @@ impl Block for ConveyorBlock {
     fn push(
         &mut self,
         side: Direction,
-        mut item: Box<dyn Item>,
+        mut item: Stack,
         meta: ChunkBlockMetadata,
-    ) -> Option<Box<dyn Item>> {
+    ) -> Option<Stack> {
         if side == meta.direction {
             return Some(item);
         }
@@ -143,13 +178,12 @@ impl Block for ConveyorBlock {
         self.2 = side.opposite();
         reset_timer!(self);
         if item.metadata_is_stack_size() && item.metadata() > 1 {
-            let mut itm = item.clone_item();
-            itm.set_metadata(1);
-            *slot = Some(itm);
-            item.set_metadata(item.metadata() - 1);
+            slot.replace(item.with_count(1));
+            let remaining = item.metadata() - 1;
+            item.set_metadata(remaining);
             Some(item)
         } else {
-            *slot = Some(item);
+            slot.replace(item);
             None
         }
     }
@@ -168,14 +202,54 @@ impl Block for ConveyorBlock {
 }
 
 impl ConveyorBlock {
+    pub fn fast() -> Self {
+        Self(
+            now(),
+            Inventory::new(1, false),
+            Direction::default(),
+            FAST_CONVEYOR_WORK_MS,
+        )
+    }
+
+    fn is_fast(&self) -> bool {
+        self.3 < CONVEYOR_WORK_MS
+    }
+
+    fn can_do_work(&self) -> bool {
+        now().saturating_duration_since(self.0).as_millis() >= self.3 as u128
+    }
+
+    /// How far along the belt the item is, from 0 to 1
+    fn duration_lerp_value(&self) -> f32 {
+        (now().saturating_duration_since(self.0).as_millis() as f32 / self.3 as f32).min(1.0)
+    }
+
+    /// Moves the item on the belt into `inventory`, or leaves it on the belt if it doesn't fit
+    pub fn eject(&mut self, inventory: &mut Inventory) {
+        match self.1.take_item(0) {
+            None => {}
+            Some(item) => {
+                if item.metadata() < 1 {
+                    return;
+                }
+                if let Some(item) = inventory.try_add_item(item) {
+                    notice_board::add_entry(
+                        NoticeboardEntryRenderable::StringRef("Inventory full"),
+                        2,
+                    );
+                    self.1.get_item_mut(0).replace(item);
+                }
+            }
+        }
+    }
+
     pub fn update(meta: ChunkBlockMetadata, world: &mut World) -> Option<()> {
         let mut item = world
             .get_block_at_mut(meta.position.x, meta.position.y)?
             .0
             .get_inventory_capability()?
             .take_item(0)?;
-        let pushto_pos = meta.position.add_directional(&meta.direction, 1);
-        let (pushto, pushto_meta) = world.get_block_at_mut(pushto_pos.x, pushto_pos.y)?;
+        let (pushto, pushto_meta) = world.neighbor_mut(meta.position, meta.direction)?;
 
         let push_dir = meta.direction.opposite();
         if pushto.has_capability_push(push_dir, pushto_meta)
@@ -183,6 +257,10 @@ impl ConveyorBlock {
         {
             item = pushto.push(push_dir, item, pushto_meta)?;
         }
+        // the end of a belt isn't jammed, only a block that refuses the item is
+        if !pushto.is_none() {
+            item = overflow_policy().apply(item)?;
+        }
         world
             .get_block_at_mut(meta.position.x, meta.position.y)?
             .0
@@ -202,9 +280,19 @@ impl ConveyorBlock {
             64,
             None,
         )?);
+        FAST_CONVEYOR_ANIMATION.init(load_animated_texture(
+            rl,
+            thread,
+            asset!("fast_conveyor.png"),
+            Frame::multiple(50, 5),
+            64,
+            64,
+            None,
+        )?);
 
         Ok(())
     }
 }
 
 pub static CONVEYOR_ANIMATION: InitializedData<&'static AnimatedTexture2D> = InitializedData::new();
+pub static FAST_CONVEYOR_ANIMATION: InitializedData<&'static AnimatedTexture2D> = InitializedData::new();
@@ -0,0 +1,216 @@
+use lazy_static::lazy_static;
+use raylib::{
+    color::Color,
+    drawing::{RaylibDraw, RaylibDrawHandle},
+};
+
+use crate::{
+    block_impl_details_with_timer,
+    game::RenderLayer,
+    inventory_block,
+    identifier::{GlobalString, Identifier},
+    inventory::Inventory,
+    items::Stack,
+    reset_timer,
+    scheduler::{schedule_task, Task},
+    world::{ChunkBlockMetadata, Direction, World},
+};
+
+use super::{downcast_mut, draw_side_marker, now, splitter::output_sides, Block, BlockCategory};
+
+lazy_static! {
+    pub static ref DISTRIBUTOR_NAME: GlobalString = GlobalString::from("Distributor");
+    pub static ref BLOCK_DISTRIBUTOR: Identifier =
+        Identifier::from(("placeholder_name_2", "distributor"));
+}
+
+/// one buffered item per output
+const BUFFER_SIZE: usize = 3;
+
+block_impl_details_with_timer!(DistributorBlock, 200, Inventory);
+impl Default for DistributorBlock {
+    fn default() -> Self {
+        Self(now(), Inventory::new(BUFFER_SIZE, false))
+    }
+}
+
+impl Block for DistributorBlock {
+    inventory_block!(1, BUFFER_SIZE);
+
+    fn description(&self) -> &'static str {
+        "Pushes an item to each free output at once, up to 15 items per second"
+    }
+    fn identifier(&self) -> Identifier {
+        *BLOCK_DISTRIBUTOR
+    }
+    fn name(&self) -> GlobalString {
+        *DISTRIBUTOR_NAME
+    }
+    fn category(&self) -> BlockCategory {
+        BlockCategory::Logistics
+    }
+    fn throughput_per_min(&self) -> Option<u32> {
+        Some(BUFFER_SIZE as u32 * 60_000 / Self::WORK_DURATION_MS)
+    }
+    fn flow_outputs(&self, meta: ChunkBlockMetadata) -> Vec<Direction> {
+        output_sides(meta.direction).to_vec()
+    }
+    fn render(
+        &self,
+        d: &mut RaylibDrawHandle,
+        x: i32,
+        y: i32,
+        w: i32,
+        h: i32,
+        meta: ChunkBlockMetadata,
+        render_layer: RenderLayer,
+    ) {
+        if render_layer == RenderLayer::Block || render_layer == RenderLayer::Preview {
+            d.draw_rectangle(x, y, w, h, Color::ORANGE);
+            for side in output_sides(meta.direction) {
+                draw_side_marker(d, x, y, w, h, side, Color::GREEN);
+            }
+        } else if render_layer == RenderLayer::OverlayItems {
+            if let Some(item) = (0..BUFFER_SIZE).find_map(|slot| self.1.get_item(slot)) {
+                item.render(d, x + 5, y + 5, w - 10, h - 10);
+            }
+        }
+    }
+
+    fn has_capability_push(&self, side: Direction, meta: ChunkBlockMetadata) -> bool {
+        side == meta.direction.opposite()
+    }
+    fn can_push(&self, side: Direction, _: &Stack, meta: ChunkBlockMetadata) -> bool {
+        self.has_capability_push(side, meta) && self.1.free_slots() > 0
+    }
+    fn push(
+        &mut self,
+        side: Direction,
+        mut item: Stack,
+        meta: ChunkBlockMetadata,
+    ) -> Option<Stack> {
+        if !self.can_push(side, &item, meta) {
+            return Some(item);
+        }
+        let Some(slot) = (0..BUFFER_SIZE).find(|slot| self.1.get_item(*slot).is_none()) else {
+            return Some(item);
+        };
+        if item.metadata_is_stack_size() && item.metadata() > 1 {
+            self.1.get_item_mut(slot).replace(item.with_count(1));
+            let remaining = item.metadata() - 1;
+            item.set_metadata(remaining);
+            Some(item)
+        } else {
+            self.1.get_item_mut(slot).replace(item);
+            None
+        }
+    }
+
+    fn destroy_items(&self) -> Vec<Stack> {
+        self.1.destroy_items()
+    }
+
+    fn update(&mut self, meta: ChunkBlockMetadata) {
+        if self.can_do_work() && self.1.used_slots() > 0 {
+            schedule_task(Task::WorldUpdateBlock(
+                &|a, b| {
+                    Self::update(a, b);
+                },
+                meta,
+            ));
+        }
+    }
+    fn is_building(&self) -> bool {
+        true
+    }
+    fn is_machine(&self) -> bool {
+        true
+    }
+}
+
+impl DistributorBlock {
+    /// pushes one buffered item into every output that takes it, all in the same tick
+    fn update(meta: ChunkBlockMetadata, world: &mut World) -> Option<()> {
+        let mut pushed = false;
+
+        for side in output_sides(meta.direction) {
+            let inventory = world
+                .get_block_at_mut(meta.position.x, meta.position.y)?
+                .0
+                .get_inventory_capability()?;
+            let Some(slot) = (0..inventory.size()).find(|slot| inventory.get_item(*slot).is_some())
+            else {
+                break;
+            };
+            let item = inventory.take_item(slot)?;
+
+            let rest = match world.neighbor_mut(meta.position, side) {
+                Some((blk, push_meta)) => blk.push(side.opposite(), item, push_meta),
+                None => Some(item),
+            };
+            match rest {
+                Some(item) => {
+                    world
+                        .get_block_at_mut(meta.position.x, meta.position.y)?
+                        .0
+                        .get_inventory_capability()?
+                        .add_item(item, slot);
+                }
+                None => pushed = true,
+            }
+        }
+
+        if pushed {
+            let me = downcast_mut::<Self>(
+                &mut **world.get_block_at_mut(meta.position.x, meta.position.y)?.0,
+            )?;
+            reset_timer!(me);
+        }
+
+        Some(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{
+        blocks::{advance_clock, conveyor::ConveyorBlock},
+        game::GameConfig,
+        items::COAL_IDENTIFIER,
+        test_utils::{item, register_all},
+        world::Vec2i,
+    };
+
+    #[test]
+    fn pushes_to_every_free_output_in_one_tick() {
+        register_all();
+        let mut world = World::new(1, 1);
+        let mut distributor = DistributorBlock::default();
+        let meta = ChunkBlockMetadata::new(Direction::South, Vec2i::new(5, 5));
+        // one item per push, so a stack fills the buffer in as many pushes as there are outputs
+        let mut stack = Some(item(*COAL_IDENTIFIER, 5));
+        for _ in 0..BUFFER_SIZE {
+            stack = distributor.push(Direction::North, stack.unwrap(), meta);
+        }
+        assert_eq!(stack.unwrap().stack_count(), 2);
+        assert!(!distributor.can_push(Direction::North, &item(*COAL_IDENTIFIER, 1), meta));
+        world.set_block_at(5, 5, Box::new(distributor), Direction::South);
+        // an empty belt on each output, facing away from the distributor
+        for side in output_sides(Direction::South) {
+            let pos = Vec2i::new(5, 5).add_directional(&side, 1);
+            world.set_block_at(pos.x, pos.y, Box::new(ConveyorBlock::default()), side);
+        }
+
+        advance_clock(Duration::from_millis(DistributorBlock::WORK_DURATION_MS as u64));
+        let mut config = GameConfig::default();
+        assert!(world.tick(&mut config).is_empty());
+
+        let count = |x, y| world.get_block_at(x, y).unwrap().0.item_count();
+        assert_eq!(count(5, 5), 0);
+        assert_eq!([count(4, 5), count(5, 6), count(6, 5)], [1, 1, 1]);
+        assert_eq!(world.total_item_count(), BUFFER_SIZE as u64);
+    }
+}
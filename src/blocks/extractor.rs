@@ -1,5 +1,3 @@
-use std::time::Instant;
-
 use lazy_static::lazy_static;
 use raylib::{
     color::Color,
@@ -9,7 +7,7 @@ use raylib::{
 
 use crate::{
     block_impl_details_with_timer,
-    blocks::downcast_mut,
+    blocks::{downcast, downcast_mut},
     game::RenderLayer,
     identifier::{GlobalString, Identifier},
     inventory::Inventory,
@@ -19,32 +17,69 @@ use crate::{
     world::{ChunkBlockMetadata, Direction, Vec2i, World},
 };
 
-use super::Block;
+use super::{now, overflow_policy, Block, BlockCategory, OverflowPolicy};
 
 lazy_static! {
     pub static ref EXTRACTOR_NAME: GlobalString = GlobalString::from("Extractor");
     pub static ref BLOCK_EXTRACTOR: Identifier =
         Identifier::from(("placeholder_name_2", "extractor"));
+    pub static ref LONG_EXTRACTOR_NAME: GlobalString = GlobalString::from("Long Extractor");
+    pub static ref BLOCK_LONG_EXTRACTOR: Identifier =
+        Identifier::from(("placeholder_name_2", "long_extractor"));
 }
 
-block_impl_details_with_timer!(ExtractorBlock, 250, Inventory);
+/// how many blocks a long extractor reaches in each direction
+pub const LONG_EXTRACTOR_RANGE: i32 = 4;
+
+// the last field is the range: how many blocks the extractor looks for a block to pull from/push to, skipping empty blocks
+block_impl_details_with_timer!(ExtractorBlock, 250, Inventory, i32);
 impl Default for ExtractorBlock {
     fn default() -> Self {
-        Self(Instant::now(), Inventory::new(1, false))
+        Self(now(), Inventory::new(1, false), 1)
+    }
+}
+
+impl ExtractorBlock {
+    pub fn long() -> Self {
+        Self(now(), Inventory::new(1, false), LONG_EXTRACTOR_RANGE)
     }
 }
 impl Block for ExtractorBlock {
     simple_single_item_serializable!(1);
 
     fn description(&self) -> &'static str {
-        "Extracts 4 Blocks per second from a machine"
+        if self.2 > 1 {
+            "Extracts 4 Blocks per second from a machine up to 4 blocks away"
+        } else {
+            "Extracts 4 Blocks per second from a machine"
+        }
     }
 
     fn identifier(&self) -> Identifier {
-        *BLOCK_EXTRACTOR
+        if self.2 > 1 {
+            *BLOCK_LONG_EXTRACTOR
+        } else {
+            *BLOCK_EXTRACTOR
+        }
+    }
+    fn category(&self) -> BlockCategory {
+        BlockCategory::Logistics
+    }
+    fn throughput_per_min(&self) -> Option<u32> {
+        Some(60_000 / Self::WORK_DURATION_MS)
+    }
+    fn is_machine(&self) -> bool {
+        true
+    }
+    fn dismantle_time_ms(&self) -> u64 {
+        3000
     }
     fn name(&self) -> GlobalString {
-        *EXTRACTOR_NAME
+        if self.2 > 1 {
+            *LONG_EXTRACTOR_NAME
+        } else {
+            *EXTRACTOR_NAME
+        }
     }
     fn render(
         &self,
@@ -57,7 +92,7 @@ impl Block for ExtractorBlock {
         layer: RenderLayer,
     ) {
         if layer == RenderLayer::Block || layer == RenderLayer::Preview {
-            d.draw_rectangle(x, y, w, h, Color::ORANGE);
+            d.draw_rectangle(x, y, w, h, if self.2 > 1 { Color::GOLD } else { Color::ORANGE });
             let (vec_1, vec_2, vec_3) = match meta.direction {
                 Direction::North => (
                     Vector2::new((x + 5) as f32, (y + h) as f32),
@@ -99,13 +134,16 @@ impl Block for ExtractorBlock {
     fn init(&mut self, _: ChunkBlockMetadata) {
         self.1.resize(1);
     }
-    fn get_inventory_capability<'a>(&'a mut self) -> Option<&'a mut Inventory> {
+    fn inventory(&self) -> Option<&Inventory> {
+        Some(&self.1)
+    }
+    fn get_inventory_capability(&mut self) -> Option<&mut Inventory> {
         if !self.can_do_work() {
             return None;
         }
         Some(&mut self.1)
     }
-    fn destroy_items(&self) -> Vec<Box<dyn crate::items::Item>> {
+    fn destroy_items(&self) -> Vec<crate::items::Stack> {
         self.1.destroy_items()
     }
     fn update(&mut self, meta: ChunkBlockMetadata) {
@@ -114,16 +152,40 @@ impl Block for ExtractorBlock {
 }
 
 impl ExtractorBlock {
-    fn update_pull(meta: ChunkBlockMetadata, world: &mut World) -> Option<()> {
-        let block_pull_pos = meta.position.add_directional(&meta.direction, -1);
+    fn get_range(meta: ChunkBlockMetadata, world: &World) -> i32 {
+        world
+            .get_block_at(meta.position.x, meta.position.y)
+            .and_then(|(blk, _)| downcast::<Self>(&**blk).map(|blk| blk.2))
+            .unwrap_or(1)
+    }
+
+    /// Gets the position of the first non-empty block within `range` blocks in `dir`
+    fn find_target(
+        meta: ChunkBlockMetadata,
+        world: &World,
+        dir: Direction,
+        range: i32,
+    ) -> Option<Vec2i> {
+        (1..=range)
+            .map(|i| meta.position.add_directional(&dir, i))
+            .find(|pos| {
+                world
+                    .get_block_at(pos.x, pos.y)
+                    .map(|(blk, _)| !blk.is_none())
+                    .unwrap_or(false)
+            })
+    }
+
+    fn update_pull(meta: ChunkBlockMetadata, world: &mut World, range: i32) -> Option<()> {
         if let Some((me, _)) = world.get_block_at_mut(meta.position.x, meta.position.y) {
             let inv = me.get_inventory_capability()?;
             if inv.get_item(0).is_some() {
                 return Some(());
             }
         }
+        let pull_pos = Self::find_target(meta, world, meta.direction.opposite(), range)?;
         let item = world
-            .get_block_at_mut(block_pull_pos.x, block_pull_pos.y)
+            .get_block_at_mut(pull_pos.x, pull_pos.y)
             .and_then(|(blk, blk_meta)| {
                 if blk.can_pull(meta.direction.opposite(), blk_meta) {
                     blk.pull(meta.direction.opposite(), blk_meta, 1)
@@ -134,21 +196,34 @@ impl ExtractorBlock {
         let blk = world.get_block_at_mut(meta.position.x, meta.position.y)?.0;
         let blk = downcast_mut::<Self>(&mut **blk)?;
         reset_timer!(blk);
-        *blk.1.get_item_mut(0) = Some(item);
+        blk.1.get_item_mut(0).replace(item);
 
         Some(())
     }
 
-    fn update_push(meta: ChunkBlockMetadata, world: &mut World) -> Option<()> {
-        let block_push_pos = meta.position.add_directional(&meta.direction, 1);
+    fn update_push(meta: ChunkBlockMetadata, world: &mut World, range: i32) -> Option<()> {
+        Self::update_push_with(meta, world, range, overflow_policy())
+    }
+
+    /// `update_push` with `policy` instead of the one set in the options
+    fn update_push_with(
+        meta: ChunkBlockMetadata,
+        world: &mut World,
+        range: i32,
+        policy: OverflowPolicy,
+    ) -> Option<()> {
+        let push_pos = Self::find_target(meta, world, meta.direction, range);
         let mut item = world
             .get_block_at_mut(meta.position.x, meta.position.y)?
             .0
             .get_inventory_capability()?
             .take_item(0)?;
 
-        if let Some((blk, push_meta)) = world.get_block_at_mut(block_push_pos.x, block_push_pos.y) {
+        if let Some((blk, push_meta)) =
+            push_pos.and_then(|pos| world.get_block_at_mut(pos.x, pos.y))
+        {
             item = blk.push(meta.direction.opposite(), item, push_meta)?;
+            item = policy.apply(item)?;
         }
 
         world
@@ -161,7 +236,70 @@ impl ExtractorBlock {
     }
 
     fn update(meta: ChunkBlockMetadata, world: &mut World) {
-        Self::update_pull(meta, world);
-        Self::update_push(meta, world);
+        let range = Self::get_range(meta, world);
+        Self::update_pull(meta, world, range);
+        Self::update_push(meta, world, range);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{
+        blocks::{advance_clock, StorageContainer},
+        items::COAL_IDENTIFIER,
+        test_utils::{item, register_all},
+    };
+
+    #[test]
+    fn read_only_inventory_ignores_the_work_timer() {
+        register_all();
+        let mut inventory = Inventory::new(1, false);
+        inventory.try_add_item(item(*COAL_IDENTIFIER, 1));
+        let mut extractor = ExtractorBlock(now(), inventory, 1);
+
+        // the timer just started, so nothing may take the item yet
+        assert!(extractor.get_inventory_capability().is_none());
+        let contents = extractor.inventory().unwrap().get_item(0).unwrap();
+        assert_eq!(contents.identifier(), *COAL_IDENTIFIER);
+        assert_eq!(extractor.item_count(), 1);
+
+        advance_clock(Duration::from_millis(ExtractorBlock::WORK_DURATION_MS as u64));
+        assert!(extractor.get_inventory_capability().is_some());
+        assert_eq!(extractor.inventory().unwrap().get_item(0).unwrap().stack_count(), 1);
+    }
+
+    /// An extractor holding a single coal that is done working, in front of a container that is full
+    fn blocked_extractor() -> World {
+        let mut world = World::new(1, 1);
+        let mut inventory = Inventory::new(1, false);
+        inventory.try_add_item(item(*COAL_IDENTIFIER, 1));
+        world.set_block_at(0, 1, Box::new(ExtractorBlock(now(), inventory, 1)), Direction::South);
+
+        let mut container = StorageContainer::default();
+        let inv = container.get_inventory_capability().unwrap();
+        while inv.try_add_item(item(*COAL_IDENTIFIER, 1)).is_none() {}
+        world.set_block_at(0, 2, Box::new(container), Direction::South);
+
+        advance_clock(Duration::from_millis(ExtractorBlock::WORK_DURATION_MS as u64));
+        world
+    }
+
+    #[test]
+    fn voiding_overflow_discards_what_the_target_wont_take() {
+        register_all();
+        for (policy, kept) in [(OverflowPolicy::Void, 0), (OverflowPolicy::Block, 1)] {
+            let mut world = blocked_extractor();
+            let full = world.get_block_at(0, 2).unwrap().0.item_count();
+            let meta = world.get_block_at(0, 1).unwrap().1;
+
+            // the policy in the options is shared by every test, so it is passed in instead
+            ExtractorBlock::update_push_with(meta, &mut world, 1, policy);
+
+            assert_eq!(world.get_block_at(0, 1).unwrap().0.item_count(), kept, "{:?}", policy);
+            assert_eq!(world.get_block_at(0, 2).unwrap().0.item_count(), full, "{:?}", policy);
+        }
     }
 }
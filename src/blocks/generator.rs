@@ -0,0 +1,255 @@
+use lazy_static::lazy_static;
+use raylib::{
+    color::Color,
+    drawing::{RaylibDraw, RaylibDrawHandle},
+};
+
+use crate::{
+    block_impl_details_with_timer,
+    blocks::downcast_mut,
+    game::{RenderLayer, MSPT},
+    identifier::{GlobalString, Identifier},
+    inventory::Inventory,
+    items::Stack,
+    reset_timer,
+    scheduler::{schedule_task, Task},
+    serialization::{Buffer, Deserialize, SerializationError, Serialize},
+    world::{record_item_delta, ChunkBlockMetadata, Direction, World},
+};
+
+use super::{now, Block, BlockCategory, PowerSource};
+
+lazy_static! {
+    pub static ref GENERATOR_NAME: GlobalString = GlobalString::from("Generator");
+    pub static ref BLOCK_GENERATOR: Identifier =
+        Identifier::from(("placeholder_name_2", "generator"));
+}
+
+/// power produced every tick while something is burning
+pub const POWER_PER_TICK: u32 = 5;
+/// the generator stops burning fuel once it stores this much power
+pub const MAX_STORED_POWER: u32 = 10_000;
+
+// fields: the fuel slot, how many ms the current fuel still burns and the stored power. The timer runs from when the
+// last fuel was lit, for the flame; it is as long as coal burns
+block_impl_details_with_timer!(GeneratorBlock, 4000, Inventory, u32, u32);
+impl Default for GeneratorBlock {
+    fn default() -> Self {
+        Self(now(), Inventory::new(1, false), 0, 0)
+    }
+}
+
+impl Block for GeneratorBlock {
+    fn serialize(&self, buf: &mut Vec<u8>) {
+        self.1.slot(0).serialize(buf);
+        self.2.serialize(buf);
+        self.3.serialize(buf);
+    }
+    fn try_deserialize(&mut self, buf: &mut Buffer) -> Result<(), SerializationError> {
+        self.1.resize(1);
+        *self.1.get_item_mut(0) = Deserialize::try_deserialize(buf)?;
+        self.2 = u32::try_deserialize(buf)?;
+        self.3 = u32::try_deserialize(buf)?.min(MAX_STORED_POWER);
+        Ok(())
+    }
+    fn required_length(&self) -> usize {
+        self.1.slot(0).required_length() + self.2.required_length() + self.3.required_length()
+    }
+
+    fn description(&self) -> &'static str {
+        "Burns fuel like coal to produce power for the machines next to it"
+    }
+    fn identifier(&self) -> Identifier {
+        *BLOCK_GENERATOR
+    }
+    fn category(&self) -> BlockCategory {
+        BlockCategory::Production
+    }
+    fn is_machine(&self) -> bool {
+        true
+    }
+    fn dismantle_time_ms(&self) -> u64 {
+        3000
+    }
+    fn name(&self) -> GlobalString {
+        *GENERATOR_NAME
+    }
+    fn render(
+        &self,
+        d: &mut RaylibDrawHandle,
+        x: i32,
+        y: i32,
+        w: i32,
+        h: i32,
+        _: ChunkBlockMetadata,
+        layer: RenderLayer,
+    ) {
+        if layer == RenderLayer::Block || layer == RenderLayer::Preview {
+            d.draw_rectangle(x, y, w, h, Color::DARKGRAY);
+            d.draw_rectangle(x + w / 4, y + h / 4, w / 2, h / 2, Color::BLACK);
+        } else if layer == RenderLayer::OverlayItems && self.2 > 0 {
+            // the flame burns down along with the fuel
+            let flame_h = ((1.0 - self.duration_lerp_value()) * (h / 2) as f32).max(2.0) as i32;
+            d.draw_rectangle(x + w / 4 + 2, y + h * 3 / 4 - flame_h, w / 2 - 4, flame_h, Color::ORANGE);
+        }
+    }
+
+    fn init(&mut self, _: ChunkBlockMetadata) {
+        self.1.resize(1);
+    }
+    fn inventory(&self) -> Option<&Inventory> {
+        Some(&self.1)
+    }
+    fn destroy_items(&self) -> Vec<Stack> {
+        self.1.destroy_items()
+    }
+    fn has_capability_push(&self, _: Direction, _: ChunkBlockMetadata) -> bool {
+        true
+    }
+    fn can_push(&self, side: Direction, item: &Stack, meta: ChunkBlockMetadata) -> bool {
+        self.has_capability_push(side, meta) && item.burn_time_ms().is_some() && self.1.can_push(item)
+    }
+    fn push(
+        &mut self,
+        side: Direction,
+        item: Stack,
+        meta: ChunkBlockMetadata,
+    ) -> Option<Stack> {
+        if !self.can_push(side, &item, meta) {
+            return Some(item);
+        }
+        self.1.try_add_item(item)
+    }
+    fn carries_power(&self) -> bool {
+        true
+    }
+    fn power_production(&self) -> i32 {
+        if self.2 > 0 {
+            POWER_PER_TICK as i32
+        } else {
+            0
+        }
+    }
+    fn as_power_source_mut(&mut self) -> Option<&mut dyn PowerSource> {
+        Some(self)
+    }
+    fn as_power_source(&self) -> Option<&dyn PowerSource> {
+        Some(self)
+    }
+    fn update(&mut self, meta: ChunkBlockMetadata) {
+        schedule_task(Task::WorldUpdateBlock(&Self::update, meta));
+    }
+}
+
+impl PowerSource for GeneratorBlock {
+    fn power_output(&self) -> u32 {
+        self.3
+    }
+    fn take_power(&mut self, amount: u32) -> u32 {
+        let taken = amount.min(self.3);
+        self.3 -= taken;
+        taken
+    }
+}
+
+#[cfg(test)]
+impl GeneratorBlock {
+    /// A generator without fuel that already stored `power`
+    pub fn with_stored_power(power: u32) -> Self {
+        Self(now(), Inventory::new(1, false), 0, power)
+    }
+}
+
+impl GeneratorBlock {
+    /// Burns the current fuel for a tick, lighting the next one from the fuel slot when it ran out
+    fn burn(meta: ChunkBlockMetadata, world: &mut World) -> Option<()> {
+        let blk = world.get_block_at_mut(meta.position.x, meta.position.y)?.0;
+        let blk = downcast_mut::<Self>(&mut **blk)?;
+
+        if blk.2 == 0 {
+            if blk.3 >= MAX_STORED_POWER {
+                return Some(());
+            }
+            let fuel = blk.1.try_pull(1)?;
+            blk.2 = fuel.burn_time_ms().unwrap_or(0);
+            record_item_delta(-1);
+            reset_timer!(blk);
+        }
+
+        blk.2 = blk.2.saturating_sub(MSPT as u32);
+        blk.3 = (blk.3 + POWER_PER_TICK).min(MAX_STORED_POWER);
+
+        Some(())
+    }
+
+    fn update(meta: ChunkBlockMetadata, world: &mut World) {
+        Self::burn(meta, world);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        blocks::downcast,
+        items::COAL_IDENTIFIER,
+        test_utils::{item, register_all},
+        GameConfig,
+    };
+
+    /// The fuel left in the slot, how long the current fuel still burns and the stored power
+    fn state(world: &World) -> (u64, u32, u32) {
+        let blk = world.get_block_at(0, 0).unwrap().0;
+        let generator = downcast::<GeneratorBlock>(&**blk).unwrap();
+        (generator.1.count(*COAL_IDENTIFIER), generator.2, generator.3)
+    }
+
+    fn fueled(mut generator: GeneratorBlock, coal: u32) -> World {
+        let pushed = generator.push(Direction::North, item(*COAL_IDENTIFIER, coal), ChunkBlockMetadata::default());
+        assert!(pushed.is_none());
+        let mut world = World::new(1, 1);
+        world.set_block_at(0, 0, Box::new(generator), Direction::North);
+        world
+    }
+
+    #[test]
+    fn burning_fuel_stores_power() {
+        register_all();
+        let mut world = fueled(GeneratorBlock::default(), 2);
+        let mut config = GameConfig::default();
+        let ticks_per_coal = 4000 / MSPT as u32;
+
+        world.tick(&mut config);
+        assert_eq!(state(&world), (1, 4000 - MSPT as u32, POWER_PER_TICK));
+        assert_eq!(world.get_block_at(0, 0).unwrap().0.power_production(), POWER_PER_TICK as i32);
+
+        // the second coal gets lit once the first burned down
+        for _ in 1..=ticks_per_coal {
+            world.tick(&mut config);
+        }
+        assert_eq!(state(&world).0, 0);
+        for _ in ticks_per_coal + 1..ticks_per_coal * 2 {
+            world.tick(&mut config);
+        }
+        let stored = ticks_per_coal * 2 * POWER_PER_TICK;
+        assert_eq!(state(&world), (0, 0, stored));
+        assert_eq!(world.get_block_at(0, 0).unwrap().0.power_production(), 0);
+
+        // without fuel nothing more is stored
+        for _ in 0..10 {
+            world.tick(&mut config);
+        }
+        assert_eq!(state(&world), (0, 0, stored));
+    }
+
+    #[test]
+    fn full_generators_keep_their_fuel() {
+        register_all();
+        let mut world = fueled(GeneratorBlock::with_stored_power(MAX_STORED_POWER), 1);
+        let mut config = GameConfig::default();
+        for _ in 0..10 {
+            world.tick(&mut config);
+        }
+        assert_eq!(state(&world), (1, 0, MAX_STORED_POWER));
+    }
+}
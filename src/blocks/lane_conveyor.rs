@@ -0,0 +1,335 @@
+use std::time::Instant;
+
+use lazy_static::lazy_static;
+use raylib::{
+    color::Color,
+    drawing::{RaylibDraw, RaylibDrawHandle},
+};
+
+use crate::{
+    block_impl_details,
+    game::RenderLayer,
+    identifier::{GlobalString, Identifier},
+    inventory::Inventory,
+    inventory_block,
+    items::Stack,
+    notice_board::{self, NoticeboardEntryRenderable},
+    scheduler::{schedule_task, Task},
+    step_size,
+    world::{ChunkBlockMetadata, Direction, Vec2i, World},
+    GameConfig,
+};
+
+use super::{conveyor::CONVEYOR_ANIMATION, downcast_mut, now, Block, BlockCategory};
+
+lazy_static! {
+    pub static ref LANE_CONVEYOR_NAME: GlobalString = GlobalString::from("Two-Lane Conveyor Belt");
+    pub static ref BLOCK_LANE_CONVEYOR: Identifier =
+        Identifier::from(("placeholder_name_2", "conveyor_two_lane"));
+}
+
+const WORK_DURATION_MS: u32 = 1000;
+
+/// One of the two item streams of a belt, relative to the direction it moves in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lane {
+    Left,
+    Right,
+}
+
+impl Lane {
+    pub const ALL: [Lane; 2] = [Lane::Left, Lane::Right];
+
+    fn slot(self) -> usize {
+        self as usize
+    }
+
+    /// The side of a belt facing `direction` that feeds this lane
+    pub fn side(self, direction: Direction) -> Direction {
+        direction.next(self == Lane::Right)
+    }
+
+    /// The lane an item coming in from `side` ends up on. Items from the back can go on either lane
+    pub fn from_side(side: Direction, direction: Direction) -> Option<Lane> {
+        Self::ALL.into_iter().find(|lane| lane.side(direction) == side)
+    }
+}
+
+// each lane has its own timer, so items on one lane never hold up the other one
+block_impl_details!(LaneConveyorBlock, [Instant; 2], Inventory);
+impl Default for LaneConveyorBlock {
+    fn default() -> Self {
+        Self([now(); 2], Inventory::new(Lane::ALL.len(), false))
+    }
+}
+
+impl LaneConveyorBlock {
+    fn lane_can_do_work(&self, lane: Lane) -> bool {
+        now()
+            .saturating_duration_since(self.0[lane.slot()])
+            .as_millis()
+            >= WORK_DURATION_MS as u128
+    }
+
+    fn lane_lerp_value(&self, lane: Lane) -> f32 {
+        (now()
+            .saturating_duration_since(self.0[lane.slot()])
+            .as_millis()
+            .min(WORK_DURATION_MS as u128) as f32
+            / WORK_DURATION_MS as f32)
+            .min(1.0)
+    }
+
+    /// The lane an item from `side` goes on. Side inputs always go on their own lane, items from the back
+    /// use `hint` or the first free lane
+    fn target_lane(&self, side: Direction, meta: ChunkBlockMetadata, hint: Option<Lane>) -> Option<Lane> {
+        if let Some(lane) = Lane::from_side(side, meta.direction) {
+            return Some(lane);
+        }
+        if let Some(lane) = hint {
+            return Some(lane);
+        }
+        Lane::ALL
+            .into_iter()
+            .find(|lane| self.1.get_item(lane.slot()).is_none())
+    }
+
+    fn push_into_lane(
+        &mut self,
+        side: Direction,
+        mut item: Stack,
+        meta: ChunkBlockMetadata,
+        hint: Option<Lane>,
+    ) -> Option<Stack> {
+        if !self.has_capability_push(side, meta) {
+            return Some(item);
+        }
+        let Some(lane) = self.target_lane(side, meta, hint) else {
+            return Some(item);
+        };
+        let slot = self.1.get_item_mut(lane.slot());
+        if slot.is_some() {
+            return Some(item);
+        }
+        self.0[lane.slot()] = now();
+        if item.metadata_is_stack_size() && item.metadata() > 1 {
+            slot.replace(item.with_count(1));
+            let remaining = item.metadata() - 1;
+            item.set_metadata(remaining);
+            Some(item)
+        } else {
+            slot.replace(item);
+            None
+        }
+    }
+
+    /// moves the item of every lane that is done onto the same lane of the next block
+    pub fn update(meta: ChunkBlockMetadata, world: &mut World) -> Option<()> {
+        for lane in Lane::ALL {
+            let me = downcast_mut::<Self>(
+                &mut **world.get_block_at_mut(meta.position.x, meta.position.y)?.0,
+            )?;
+            if !me.lane_can_do_work(lane) {
+                continue;
+            }
+            let Some(item) = me.1.take_item(lane.slot()) else {
+                continue;
+            };
+
+            let rest = match world.neighbor_mut(meta.position, meta.direction) {
+                Some((pushto, pushto_meta)) => {
+                    pushto.push_lane(meta.direction.opposite(), item, pushto_meta, lane)
+                }
+                None => Some(item),
+            };
+            if let Some(item) = rest {
+                world
+                    .get_block_at_mut(meta.position.x, meta.position.y)?
+                    .0
+                    .get_inventory_capability()?
+                    .add_item(item, lane.slot());
+            }
+        }
+
+        Some(())
+    }
+}
+
+impl Block for LaneConveyorBlock {
+    inventory_block!(1, Lane::ALL.len());
+
+    fn description(&self) -> &'static str {
+        "Moves 60 items per minute on each of its two lanes. Items from the sides stay on their side"
+    }
+
+    fn interact(&mut self, _: ChunkBlockMetadata, config: &mut GameConfig) {
+        let Some(lane) = Lane::ALL
+            .into_iter()
+            .find(|lane| self.1.get_item(lane.slot()).is_some())
+        else {
+            return;
+        };
+        if let Some(item) = self.1.take_item(lane.slot()) {
+            if let Some(item) = config.inventory.try_add_item(item) {
+                notice_board::add_entry(NoticeboardEntryRenderable::StringRef("Inventory full"), 2);
+                self.1.get_item_mut(lane.slot()).replace(item);
+            }
+        }
+    }
+    fn supports_interaction(&self) -> bool {
+        self.1.used_slots() > 0
+    }
+    fn custom_interact_message(&self) -> Option<String> {
+        Lane::ALL
+            .into_iter()
+            .find_map(|lane| self.1.get_item(lane.slot()))
+            .map(|item| format!("Grab {} from {}", item.name(), self.name()))
+    }
+
+    fn identifier(&self) -> Identifier {
+        *BLOCK_LANE_CONVEYOR
+    }
+    fn category(&self) -> BlockCategory {
+        BlockCategory::Logistics
+    }
+    fn throughput_per_min(&self) -> Option<u32> {
+        Some(Lane::ALL.len() as u32 * 60_000 / WORK_DURATION_MS)
+    }
+    fn is_machine(&self) -> bool {
+        true
+    }
+    fn name(&self) -> GlobalString {
+        *LANE_CONVEYOR_NAME
+    }
+    fn destroy_items(&self) -> Vec<Stack> {
+        self.1.destroy_items()
+    }
+    fn render(
+        &self,
+        d: &mut RaylibDrawHandle,
+        x: i32,
+        y: i32,
+        w: i32,
+        h: i32,
+        meta: ChunkBlockMetadata,
+        layer: RenderLayer,
+    ) {
+        if layer == RenderLayer::Block || layer == RenderLayer::Preview {
+            CONVEYOR_ANIMATION.draw_resized_rotated_synced(
+                d,
+                x,
+                y,
+                w,
+                h,
+                meta.direction,
+                WORK_DURATION_MS,
+            );
+            // the line between the two lanes
+            if matches!(meta.direction, Direction::North | Direction::South) {
+                d.draw_rectangle(x + w / 2 - 1, y, 2, h, Color::YELLOW);
+            } else {
+                d.draw_rectangle(x, y + h / 2 - 1, w, 2, Color::YELLOW);
+            }
+        } else if layer == RenderLayer::OverlayItems {
+            let step_size = step_size!(meta.direction, w, h);
+            for lane in Lane::ALL {
+                let Some(item) = self.1.get_item(lane.slot()) else {
+                    continue;
+                };
+                let lerp = ((self.lane_lerp_value(lane) - 0.5) * step_size as f32).floor() as i32;
+                let mut vec = Vec2i::new(x + w / 4 + 3, y + h / 4 + 3);
+                vec.add_directional_assign(&lane.side(meta.direction), step_size / 4);
+                vec.add_directional_assign(&meta.direction, lerp);
+                item.render(d, vec.x, vec.y, w / 2 - 6, h / 2 - 6);
+            }
+        }
+    }
+
+    fn has_capability_push(&self, side: Direction, meta: ChunkBlockMetadata) -> bool {
+        side != meta.direction
+    }
+    fn can_push(&self, side: Direction, _: &Stack, meta: ChunkBlockMetadata) -> bool {
+        self.has_capability_push(side, meta)
+            && self
+                .target_lane(side, meta, None)
+                .is_some_and(|lane| self.1.get_item(lane.slot()).is_none())
+    }
+    fn push(
+        &mut self,
+        side: Direction,
+        item: Stack,
+        meta: ChunkBlockMetadata,
+    ) -> Option<Stack> {
+        self.push_into_lane(side, item, meta, None)
+    }
+    fn push_lane(
+        &mut self,
+        side: Direction,
+        item: Stack,
+        meta: ChunkBlockMetadata,
+        lane: Lane,
+    ) -> Option<Stack> {
+        self.push_into_lane(side, item, meta, Some(lane))
+    }
+    fn update(&mut self, meta: ChunkBlockMetadata) {
+        self.1.update();
+        if Lane::ALL
+            .into_iter()
+            .any(|lane| self.1.get_item(lane.slot()).is_some() && self.lane_can_do_work(lane))
+        {
+            schedule_task(Task::WorldUpdateBlock(
+                &|a, b| {
+                    Self::update(a, b);
+                },
+                meta,
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        items::{COAL_IDENTIFIER, IRON_ORE_IDENTIFIER},
+        test_utils::{item, register_all},
+    };
+
+    /// the identifier of the item on `lane` of the belt at `pos`
+    fn lane_item(world: &World, pos: Vec2i, lane: Lane) -> Option<Identifier> {
+        let (blk, _) = world.get_block_at(pos.x, pos.y)?;
+        blk.inventory()?.get_item(lane.slot()).map(|item| item.identifier())
+    }
+
+    #[test]
+    fn lanes_stay_apart_along_a_belt_line() {
+        register_all();
+        let mut world = World::new(1, 1);
+        let line = (0..4).map(|y| Vec2i::new(5, y)).collect::<Vec<_>>();
+        for pos in &line {
+            world.set_block_at(pos.x, pos.y, Box::new(LaneConveyorBlock::default()), Direction::South);
+        }
+        // coal comes in from the left side of the first belt, iron ore from the right
+        let (first, meta) = world.get_block_at_mut(line[0].x, line[0].y).unwrap();
+        let left = Lane::Left.side(Direction::South);
+        let right = Lane::Right.side(Direction::South);
+        assert!(first.push(left, item(*COAL_IDENTIFIER, 1), meta).is_none());
+        assert!(first.push(right, item(*IRON_ORE_IDENTIFIER, 1), meta).is_none());
+
+        let mut config = GameConfig::default();
+        // 1s per belt, and the last one has nowhere to go
+        let ticks_per_belt = WORK_DURATION_MS / crate::game::TICK_DURATION.as_millis() as u32;
+        for _ in 0..(line.len() as u32 + 1) * ticks_per_belt {
+            assert!(world.tick(&mut config).is_empty());
+            for pos in &line {
+                assert_ne!(lane_item(&world, *pos, Lane::Left), Some(*IRON_ORE_IDENTIFIER));
+                assert_ne!(lane_item(&world, *pos, Lane::Right), Some(*COAL_IDENTIFIER));
+            }
+        }
+
+        let last = line[line.len() - 1];
+        assert_eq!(lane_item(&world, last, Lane::Left), Some(*COAL_IDENTIFIER));
+        assert_eq!(lane_item(&world, last, Lane::Right), Some(*IRON_ORE_IDENTIFIER));
+        assert_eq!(world.total_item_count(), 2);
+    }
+}
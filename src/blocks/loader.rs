@@ -0,0 +1,210 @@
+use lazy_static::lazy_static;
+use raylib::{
+    color::Color,
+    drawing::{RaylibDraw, RaylibDrawHandle},
+};
+
+use crate::{
+    block_impl_details_with_timer,
+    blocks::{downcast_mut, draw_side_marker},
+    game::RenderLayer,
+    identifier::{GlobalString, Identifier},
+    inventory::Inventory,
+    items::{get_item_by_id, Stack},
+    reset_timer,
+    scheduler::{schedule_task, Task},
+    serialization::{Buffer, Deserialize, SerializationError, Serialize},
+    world::{ChunkBlockMetadata, World},
+    GameConfig,
+};
+
+use super::{now, Block, BlockCategory};
+
+lazy_static! {
+    pub static ref LOADER_NAME: GlobalString = GlobalString::from("Loader");
+    pub static ref BLOCK_LOADER: Identifier = Identifier::from(("placeholder_name_2", "loader"));
+}
+
+block_impl_details_with_timer!(LoaderBlock, 250, Inventory, Option<Identifier>);
+impl Default for LoaderBlock {
+    fn default() -> Self {
+        Self(now(), Inventory::new(1, false), None)
+    }
+}
+
+impl Block for LoaderBlock {
+    fn serialize(&self, buf: &mut Vec<u8>) {
+        self.1.slot(0).serialize(buf);
+        self.2.serialize(buf);
+    }
+    fn try_deserialize(&mut self, buf: &mut Buffer) -> Result<(), SerializationError> {
+        self.1.resize(1);
+        *self.1.get_item_mut(0) = Deserialize::try_deserialize(buf)?;
+        self.2 = Deserialize::try_deserialize(buf)?;
+        Ok(())
+    }
+    fn required_length(&self) -> usize {
+        self.1.slot(0).required_length() + self.2.required_length()
+    }
+
+    fn description(&self) -> &'static str {
+        "Loads 4 items per second of the selected type from your inventory onto a belt"
+    }
+    fn identifier(&self) -> Identifier {
+        *BLOCK_LOADER
+    }
+    fn category(&self) -> BlockCategory {
+        BlockCategory::Logistics
+    }
+    fn throughput_per_min(&self) -> Option<u32> {
+        Some(60_000 / Self::WORK_DURATION_MS)
+    }
+    fn is_machine(&self) -> bool {
+        true
+    }
+    fn name(&self) -> GlobalString {
+        *LOADER_NAME
+    }
+    fn supports_interaction(&self) -> bool {
+        true
+    }
+    fn custom_interact_message(&self) -> Option<String> {
+        Some(match self.2.and_then(get_item_by_id) {
+            Some(item) => format!("Press F to change the loaded item (currently {})", item.name()),
+            None => "Press F to select an item to load".to_string(),
+        })
+    }
+    /// cycles through the item types in the player's inventory
+    fn interact(&mut self, _: ChunkBlockMetadata, config: &mut GameConfig) {
+        let mut ids: Vec<Identifier> = Vec::new();
+        for i in 0..config.inventory.size() {
+            if let Some(item) = config.inventory.get_item(i) {
+                if !ids.contains(&item.identifier()) {
+                    ids.push(item.identifier());
+                }
+            }
+        }
+
+        self.2 = match self.2.and_then(|id| ids.iter().position(|el| *el == id)) {
+            Some(idx) => ids.get(idx + 1).copied(),
+            None => ids.first().copied(),
+        };
+    }
+    fn render(
+        &self,
+        d: &mut RaylibDrawHandle,
+        x: i32,
+        y: i32,
+        w: i32,
+        h: i32,
+        meta: ChunkBlockMetadata,
+        layer: RenderLayer,
+    ) {
+        if layer == RenderLayer::Block || layer == RenderLayer::Preview {
+            d.draw_rectangle(x, y, w, h, Color::LIME);
+            draw_side_marker(d, x, y, w, h, meta.direction, Color::BLUE);
+        } else if layer == RenderLayer::OverlayItems {
+            if let Some(item) = self.2.and_then(get_item_by_id) {
+                item.render(d, x + w / 4, y + h / 4, w / 2, h / 2);
+            }
+        }
+    }
+
+    fn init(&mut self, _: ChunkBlockMetadata) {
+        self.1.resize(1);
+    }
+    fn inventory(&self) -> Option<&Inventory> {
+        Some(&self.1)
+    }
+    fn get_inventory_capability(&mut self) -> Option<&mut Inventory> {
+        Some(&mut self.1)
+    }
+    fn destroy_items(&self) -> Vec<Stack> {
+        self.1.destroy_items()
+    }
+    fn update(&mut self, meta: ChunkBlockMetadata) {
+        schedule_task(Task::WorldUpdateBlockWithConfig(&Self::update, meta));
+    }
+}
+
+impl LoaderBlock {
+    fn update_pull(meta: ChunkBlockMetadata, world: &mut World, config: &mut GameConfig) -> Option<()> {
+        let blk = world.get_block_at_mut(meta.position.x, meta.position.y)?.0;
+        let blk = downcast_mut::<Self>(&mut **blk)?;
+        if blk.1.get_item(0).is_some() || !blk.can_do_work() {
+            return Some(());
+        }
+        let item = config.inventory.try_pull_item(blk.2?, 1)?;
+        reset_timer!(blk);
+        blk.1.get_item_mut(0).replace(item);
+
+        Some(())
+    }
+
+    fn update_push(meta: ChunkBlockMetadata, world: &mut World) -> Option<()> {
+        let mut item = world
+            .get_block_at_mut(meta.position.x, meta.position.y)?
+            .0
+            .get_inventory_capability()?
+            .take_item(0)?;
+
+        if let Some((blk, push_meta)) = world.neighbor_mut(meta.position, meta.direction) {
+            item = blk.push(meta.direction.opposite(), item, push_meta)?;
+        }
+
+        world
+            .get_block_at_mut(meta.position.x, meta.position.y)?
+            .0
+            .get_inventory_capability()?
+            .add_item(item, 0);
+
+        Some(())
+    }
+
+    fn update(meta: ChunkBlockMetadata, world: &mut World, config: &mut GameConfig) {
+        Self::update_pull(meta, world, config);
+        Self::update_push(meta, world);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        blocks::{conveyor::ConveyorBlock, StorageContainer},
+        items::COAL_IDENTIFIER,
+        test_utils::{item, register_all},
+        world::Direction,
+    };
+
+    #[test]
+    fn loads_the_player_inventory_onto_belts() {
+        register_all();
+        let mut config = GameConfig::default();
+        config.inventory.try_add_item(item(*COAL_IDENTIFIER, 10));
+        let mut loader = LoaderBlock::default();
+        loader.interact(ChunkBlockMetadata::default(), &mut config);
+        assert_eq!(loader.2, Some(*COAL_IDENTIFIER));
+
+        let mut world = World::new(1, 1);
+        world.set_block_at(0, 0, Box::new(loader), Direction::South);
+        for y in 1..4 {
+            world.set_block_at(0, y, Box::new(ConveyorBlock::default()), Direction::South);
+        }
+        world.set_block_at(0, 4, Box::new(StorageContainer::default()), Direction::North);
+
+        for _ in 0..20 {
+            world.tick(&mut config);
+        }
+        // a second in the loader took coal out of the inventory and put the first one on the belt
+        assert!(config.inventory.total_items() < 10);
+        assert_eq!(world.get_block_at(0, 1).unwrap().0.item_count(), 1);
+
+        for _ in 0..280 {
+            world.tick(&mut config);
+            assert_eq!(config.inventory.total_items() + world.total_item_count(), 10);
+        }
+        assert_eq!(config.inventory.total_items(), 0);
+        assert_eq!(world.get_block_at(0, 4).unwrap().0.item_count(), 10);
+    }
+}
@@ -3,106 +3,110 @@ macro_rules! block_impl_details {
     ($name: ident) => {
         #[derive(Clone)]
         pub struct $name;
-        impl crate::blocks::BlockImplDetails for $name {
-            fn clone_block(&self) -> Box<dyn Block> {
+        impl $crate::blocks::BlockImplDetails for $name {
+            fn clone_block(&self) -> Box<dyn $crate::blocks::Block> {
                 Box::new(self.clone())
             }
         }
-        derive_as_any!($name);
+        $crate::derive_as_any!($name);
     };
     ($name: ident, $clone_fn: block) => {
         pub struct $name;
-        impl crate::blocks::BlockImplDetails for $name {
-            fn clone_block(&self) -> Box<dyn Block> {
+        impl $crate::blocks::BlockImplDetails for $name {
+            fn clone_block(&self) -> Box<dyn $crate::blocks::Block> {
                 $clone_fn(self)
             }
         }
-        derive_as_any!($name);
+        $crate::derive_as_any!($name);
     };
     ($name: ident, $($y:ty),*) => {
         #[derive(Clone)]
         pub struct $name($($y),*);
-        impl crate::blocks::BlockImplDetails for $name {
-            fn clone_block(&self) -> Box<dyn crate::blocks::Block> {
+        impl $crate::blocks::BlockImplDetails for $name {
+            fn clone_block(&self) -> Box<dyn $crate::blocks::Block> {
                 Box::new(self.clone())
             }
         }
-        crate::derive_as_any!($name);
+        $crate::derive_as_any!($name);
     };
     ($name: ident, $clone_fn: expr, $($y:ty),*) => {
         pub struct $name($($y),*);
-        impl crate::blocks::BlockImplDetails for $name {
-            fn clone_block(&self) -> Box<dyn Block> {
+        impl $crate::blocks::BlockImplDetails for $name {
+            fn clone_block(&self) -> Box<dyn $crate::blocks::Block> {
                 $clone_fn(self)
             }
         }
-        derive_as_any!($name);
+        $crate::derive_as_any!($name);
     };
 
     (default $name: ident) => {
         #[derive(Clone, Default)]
         pub struct $name;
-        impl crate::blocks::BlockImplDetails for $name {
-            fn clone_block(&self) -> Box<dyn Block> {
+        impl $crate::blocks::BlockImplDetails for $name {
+            fn clone_block(&self) -> Box<dyn $crate::blocks::Block> {
                 Box::new(self.clone())
             }
         }
-        derive_as_any!($name);
+        $crate::derive_as_any!($name);
     };
     (default $name: ident, $clone_fn: block) => {
         #[derive(Default)]
         pub struct $name;
-        impl crate::blocks::BlockImplDetails for $name {
-            fn clone_block(&self) -> Box<dyn Block> {
+        impl $crate::blocks::BlockImplDetails for $name {
+            fn clone_block(&self) -> Box<dyn $crate::blocks::Block> {
                 $clone_fn(self)
             }
         }
-        derive_as_any!($name);
+        $crate::derive_as_any!($name);
     };
     (default $name: ident, $($y:ty),*) => {
         #[derive(Clone, Default)]
         pub struct $name($($y),*);
-        impl crate::blocks::BlockImplDetails for $name {
-            fn clone_block(&self) -> Box<dyn Block> {
+        impl $crate::blocks::BlockImplDetails for $name {
+            fn clone_block(&self) -> Box<dyn $crate::blocks::Block> {
                 Box::new(self.clone())
             }
         }
-        derive_as_any!($name);
+        $crate::derive_as_any!($name);
     };
     (default $name: ident, $clone_fn: expr, $($y:ty),*) => {
         #[derive(Default)]
         pub struct $name($($y),*);
-        impl crate::blocks::BlockImplDetails for $name {
-            fn clone_block(&self) -> Box<dyn Block> {
+        impl $crate::blocks::BlockImplDetails for $name {
+            fn clone_block(&self) -> Box<dyn $crate::blocks::Block> {
                 $clone_fn(self)
             }
         }
-        derive_as_any!($name);
+        $crate::derive_as_any!($name);
     };
 }
 
 #[macro_export]
 macro_rules! block_impl_details_with_timer {
     ($name: ident, $duration: expr) => {
-        block_impl_details!($name, std::time::Instant);
-        block_impl_details_with_timer!(__ $name, $duration);
+        $crate::block_impl_details!($name, std::time::Instant);
+        $crate::block_impl_details_with_timer!(__ $name, $duration);
     };
     ($name: ident, $duration: expr, $clone_fn: block) => {
-        block_impl_details!($name, $clone_fn, std::time::Instant);
-        block_impl_details_with_timer!(__ $name, $duration);
+        $crate::block_impl_details!($name, $clone_fn, std::time::Instant);
+        $crate::block_impl_details_with_timer!(__ $name, $duration);
     };
     ($name: ident, $duration: expr, $($y:ty),*) => {
         $crate::block_impl_details!($name, std::time::Instant, $($y),*);
-        block_impl_details_with_timer!(__ $name, $duration);
+        $crate::block_impl_details_with_timer!(__ $name, $duration);
     };
     ($name: ident, $duration: expr, $clone_fn: expr, $($y:ty),*) => {
-        block_impl_details!($name, {$clone_fn}, std::time::Instant, $($y),*);
-        block_impl_details_with_timer!(__ $name, $duration);
+        $crate::block_impl_details!($name, {$clone_fn}, std::time::Instant, $($y),*);
+        $crate::block_impl_details_with_timer!(__ $name, $duration);
     };
     (__ $name: ident, $duration: expr) => {
         impl $name {
+            #[allow(dead_code)]
+            const WORK_DURATION_MS: u32 = $duration;
+
+            #[allow(dead_code)]
             fn can_do_work(&self) -> bool {
-                if std::time::Instant::now().saturating_duration_since(self.0).as_millis() >= ($duration as u128) {
+                if $crate::blocks::now().saturating_duration_since(self.0).as_millis() >= ($duration as u128) {
                     true
                 } else {
                     false
@@ -111,7 +115,7 @@ macro_rules! block_impl_details_with_timer {
 
             #[allow(dead_code)]
             fn duration_lerp_value(&self) -> f32 {
-                ((std::time::Instant::now().saturating_duration_since(self.0).as_millis().min($duration as u128)) as f32 / $duration as f32).min(1.0)
+                (($crate::blocks::now().saturating_duration_since(self.0).as_millis().min($duration as u128)) as f32 / $duration as f32).min(1.0)
             }
         }
     };
@@ -120,7 +124,7 @@ macro_rules! block_impl_details_with_timer {
 #[macro_export]
 macro_rules! reset_timer {
     ($self: expr) => {
-        $self.0 = std::time::Instant::now();
+        $self.0 = $crate::blocks::now();
     };
 }
 
@@ -128,7 +132,7 @@ macro_rules! reset_timer {
 macro_rules! register_blocks {
     ($($block: ty),*) => {
         $(
-            register_block(Box::new(<$block>::default()));
+            $crate::blocks::register_block(Box::new(<$block>::default()));
         )*
     };
 }
@@ -137,7 +141,10 @@ macro_rules! register_blocks {
 macro_rules! empty_serializable {
     () => {
         fn serialize(&self, _: &mut Vec<u8>) {}
-        fn try_deserialize(&mut self, _: &mut Buffer) -> Result<(), SerializationError> {
+        fn try_deserialize(
+            &mut self,
+            _: &mut $crate::serialization::Buffer,
+        ) -> Result<(), $crate::serialization::SerializationError> {
             Ok(())
         }
         fn required_length(&self) -> usize {
@@ -151,21 +158,21 @@ macro_rules! simple_single_item_serializable {
     ($index: tt) => {
         fn try_deserialize(
             &mut self,
-            buf: &mut crate::serialization::Buffer,
-        ) -> Result<(), crate::serialization::SerializationError> {
-            use crate::serialization::Deserialize;
-            let item = <Option<Box<dyn crate::items::Item>>>::try_deserialize(buf)?;
+            buf: &mut $crate::serialization::Buffer,
+        ) -> Result<(), $crate::serialization::SerializationError> {
+            use $crate::serialization::Deserialize;
+            let item = <$crate::inventory::Slot>::try_deserialize(buf)?;
             self.$index.resize(1);
             *self.$index.get_item_mut(0) = item;
             Ok(())
         }
         fn required_length(&self) -> usize {
-            use crate::serialization::Serialize;
-            self.$index.get_item(0).required_length()
+            use $crate::serialization::Serialize;
+            self.$index.slot(0).required_length()
         }
         fn serialize(&self, buf: &mut Vec<u8>) {
-            use crate::serialization::Serialize;
-            self.$index.get_item(0).serialize(buf)
+            use $crate::serialization::Serialize;
+            self.$index.slot(0).serialize(buf)
         }
     };
 }
@@ -175,30 +182,64 @@ macro_rules! simple_single_item_direction_serializable {
     ($item: tt, $direction: tt) => {
         fn try_deserialize(
             &mut self,
-            buf: &mut crate::serialization::Buffer,
-        ) -> Result<(), crate::serialization::SerializationError> {
-            use crate::serialization::Deserialize;
+            buf: &mut $crate::serialization::Buffer,
+        ) -> Result<(), $crate::serialization::SerializationError> {
+            use $crate::serialization::Deserialize;
             self.$item.resize(1);
             *self.$item.get_item_mut(0) = Deserialize::try_deserialize(buf)?;
             self.$direction = Deserialize::try_deserialize(buf)?;
             Ok(())
         }
         fn required_length(&self) -> usize {
-            use crate::serialization::Serialize;
-            self.$item.get_item(0).required_length() + self.$direction.required_length()
+            use $crate::serialization::Serialize;
+            self.$item.slot(0).required_length() + self.$direction.required_length()
         }
         fn serialize(&self, buf: &mut Vec<u8>) {
-            use crate::serialization::Serialize;
-            self.$item.get_item(0).serialize(buf);
+            use $crate::serialization::Serialize;
+            self.$item.slot(0).serialize(buf);
             self.$direction.serialize(buf);
         }
     };
 }
 
+/// Implements `init`, `inventory`, `get_inventory_capability` and the serialization for a block whose only state
+/// worth saving is the `$slots` slot inventory at `$index`
+#[macro_export]
+macro_rules! inventory_block {
+    ($index: tt, $slots: expr) => {
+        fn init(&mut self, _: $crate::world::ChunkBlockMetadata) {
+            self.$index.resize($slots);
+        }
+        fn inventory(&self) -> Option<&$crate::inventory::Inventory> {
+            Some(&self.$index)
+        }
+        fn get_inventory_capability(&mut self) -> Option<&mut $crate::inventory::Inventory> {
+            Some(&mut self.$index)
+        }
+        fn try_deserialize(
+            &mut self,
+            buf: &mut $crate::serialization::Buffer,
+        ) -> Result<(), $crate::serialization::SerializationError> {
+            use $crate::serialization::Deserialize;
+            self.$index = $crate::inventory::Inventory::try_deserialize(buf)?;
+            self.$index.resize($slots);
+            Ok(())
+        }
+        fn required_length(&self) -> usize {
+            use $crate::serialization::Serialize;
+            self.$index.required_length()
+        }
+        fn serialize(&self, buf: &mut Vec<u8>) {
+            use $crate::serialization::Serialize;
+            self.$index.serialize(buf)
+        }
+    };
+}
+
 #[macro_export]
 macro_rules! step_size {
     ($dir: expr, $w: expr, $h: expr) => {
-        if matches!($dir, Direction::North | Direction::South) {
+        if matches!($dir, $crate::world::Direction::North | $crate::world::Direction::South) {
             $h
         } else {
             $w
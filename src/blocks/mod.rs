@@ -1,5 +1,10 @@
+pub mod assembler;
 pub mod conveyor;
+pub mod distributor;
 pub mod extractor;
+pub mod generator;
+pub mod lane_conveyor;
+pub mod loader;
 mod macros;
 pub mod splitter;
 pub mod tunnel;
@@ -7,22 +12,37 @@ pub mod tunnel;
 use crate::{
     as_any::AsAny,
     block_impl_details,
+    cstr,
     blocks::{
-        conveyor::ConveyorBlock, extractor::ExtractorBlock, splitter::ConveyorSplitter,
-        tunnel::TunnelBlock,
+        assembler::AssemblerBlock, conveyor::ConveyorBlock, distributor::DistributorBlock, extractor::ExtractorBlock,
+        generator::GeneratorBlock,
+        lane_conveyor::{Lane, LaneConveyorBlock}, loader::LoaderBlock,
+        splitter::{ConveyorSplitter, BLOCK_CONVEYOR_SPLITTER}, tunnel::TunnelBlock,
     },
-    derive_as_any, downcast_for, empty_serializable,
-    game::{RenderLayer, RENDER_LAYERS},
+    downcast_for, empty_serializable, inventory_block,
+    game::{RenderLayer, PREVIEW_LAYERS, RENDER_LAYERS},
     identifier::{GlobalString, Identifier},
     inventory::Inventory,
-    items::{get_item_by_id, register_block_item, Item, COAL_IDENTIFIER},
+    items::{
+        get_item_by_id, register_block_item, Stack, COAL_IDENTIFIER, COPPER_ORE_IDENTIFIER,
+        IRON_ORE_IDENTIFIER,
+    },
+    notice_board::{self, NoticeboardEntryRenderable},
     register_blocks as m_register_blocks,
     scheduler::{schedule_task, Task},
     screens::ContainerInventoryScreen,
-    serialization::{Buffer, Deserialize, SerializationError, Serialize},
-    world::{ChunkBlockMetadata, Direction, Vec2i, World},
+    serialization::{Buffer, Deserialize, SerializationError, Serialize, LEGACY_SAVE_VERSION},
+    world::{record_item_delta, ChunkBlockMetadata, Direction, Vec2i, World},
     GameConfig,
 };
+use std::{
+    ffi::CStr,
+    sync::atomic::{AtomicU8, Ordering},
+    time::Instant,
+};
+#[cfg(test)]
+use std::{cell::Cell, sync::OnceLock, time::Duration};
+
 use lazy_static::lazy_static;
 use raylib::{
     color::Color,
@@ -34,19 +54,153 @@ lazy_static! {
     pub static ref BLOCK_EMPTY: Identifier = Identifier::from(("placeholder_name_2", "empty"));
     pub static ref BLOCK_RESOURCE_NODE_BROWN: Identifier =
         Identifier::from(("placeholder_name_2", "resource_node_brown"));
+    pub static ref BLOCK_RESOURCE_NODE_BLUE: Identifier =
+        Identifier::from(("placeholder_name_2", "resource_node_blue"));
+    pub static ref BLOCK_RESOURCE_NODE_GREEN: Identifier =
+        Identifier::from(("placeholder_name_2", "resource_node_green"));
     pub static ref BLOCK_STORAGE_CONTAINER: Identifier =
         Identifier::from(("placeholder_name_2", "storage_container"));
+    /// old identifier of the conveyor splitter
+    pub static ref BLOCK_CONVEYOR_MERGER: Identifier =
+        Identifier::from(("placeholder_name_2", "conveyor_merger"));
     pub static ref EMPTY_NAME: GlobalString = GlobalString::from("ENAMENOTSET");
     pub static ref COAL_NODE_NAME: GlobalString = GlobalString::from("Coal Node");
+    pub static ref IRON_NODE_NAME: GlobalString = GlobalString::from("Iron Node");
+    pub static ref COPPER_NODE_NAME: GlobalString = GlobalString::from("Copper Node");
     pub static ref CONTAINER_NAME: GlobalString = GlobalString::from("Storage Container");
 }
 
+/// The time the work timers of blocks go by
+#[cfg(not(test))]
+pub fn now() -> Instant {
+    Instant::now()
+}
+
+#[cfg(test)]
+thread_local! {
+    /// how far the test running on this thread moved the block timers forward
+    static CLOCK_OFFSET: Cell<Duration> = const { Cell::new(Duration::ZERO) };
+}
+
+/// In tests time stands still for blocks unless the test moves it forward with `advance_clock`, so machines do
+/// their work on the same ticks every time
+#[cfg(test)]
+pub fn now() -> Instant {
+    static START: OnceLock<Instant> = OnceLock::new();
+    *START.get_or_init(Instant::now) + clock_offset()
+}
+
+/// How far `advance_clock` moved the block timers of this thread
+#[cfg(test)]
+pub fn clock_offset() -> Duration {
+    CLOCK_OFFSET.with(Cell::get)
+}
+
+#[cfg(test)]
+pub fn advance_clock(by: Duration) {
+    CLOCK_OFFSET.with(|offset| offset.set(offset.get() + by));
+}
+
 impl Clone for Box<dyn Block> {
     fn clone(&self) -> Self {
         self.clone_block()
     }
 }
 
+/// Draws a 5 pixel wide bar along the edge of a block that faces `side`. East is towards -x, so it is the left
+/// edge on screen
+pub fn draw_side_marker(
+    d: &mut RaylibDrawHandle,
+    x: i32,
+    y: i32,
+    w: i32,
+    h: i32,
+    side: Direction,
+    color: Color,
+) {
+    match side {
+        Direction::North => d.draw_rectangle(x, y, w, 5, color),
+        Direction::South => d.draw_rectangle(x, y + h - 5, w, 5, color),
+        Direction::East => d.draw_rectangle(x, y, 5, h, color),
+        Direction::West => d.draw_rectangle(x + w - 5, y, 5, h, color),
+    }
+}
+
+/// What a machine does with an item the block in front of it won't take
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[repr(u8)]
+pub enum OverflowPolicy {
+    /// keep the item and wait until it can be passed on
+    #[default]
+    Block,
+    /// throw the item away, so the machine never jams
+    Void,
+}
+
+static OVERFLOW_POLICY: AtomicU8 = AtomicU8::new(OverflowPolicy::Block as u8);
+
+pub fn overflow_policy() -> OverflowPolicy {
+    match OVERFLOW_POLICY.load(Ordering::Relaxed) {
+        1 => OverflowPolicy::Void,
+        _ => OverflowPolicy::Block,
+    }
+}
+
+pub fn set_overflow_policy(policy: OverflowPolicy) {
+    OVERFLOW_POLICY.store(policy as u8, Ordering::Relaxed);
+}
+
+impl OverflowPolicy {
+    /// Applies the policy to an item that couldn't be pushed. Returns the item if the machine should keep it
+    pub fn apply(self, item: Stack) -> Option<Stack> {
+        match self {
+            Self::Block => Some(item),
+            Self::Void => {
+                record_item_delta(-(item.stack_count() as i64));
+                None
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BlockCategory {
+    Logistics,
+    Production,
+    Storage,
+    Resource,
+    #[default]
+    Misc,
+}
+
+impl BlockCategory {
+    pub const ALL: [BlockCategory; 5] = [
+        Self::Logistics,
+        Self::Production,
+        Self::Storage,
+        Self::Resource,
+        Self::Misc,
+    ];
+
+    pub fn name(&self) -> &'static CStr {
+        match self {
+            Self::Logistics => cstr!("Logistics"),
+            Self::Production => cstr!("Production"),
+            Self::Storage => cstr!("Storage"),
+            Self::Resource => cstr!("Resources"),
+            Self::Misc => cstr!("Misc"),
+        }
+    }
+}
+
+/// Blocks that produce power, which machines next to them can use
+pub trait PowerSource {
+    /// how much power the block has stored and could hand out right now
+    fn power_output(&self) -> u32;
+    /// Takes up to `amount` power out of the block and returns how much it got
+    fn take_power(&mut self, amount: u32) -> u32;
+}
+
 pub trait BlockImplDetails: Send + Sync + AsAny {
     fn clone_block(&self) -> Box<dyn Block>;
 }
@@ -61,6 +215,15 @@ pub trait Block: BlockImplDetails {
     fn on_before_place(&mut self, meta: ChunkBlockMetadata, world: &mut World) {}
     #[allow(unused_variables)]
     fn on_after_dismantle(&mut self, meta: ChunkBlockMetadata, world: &mut World) {}
+    /// Called after the placed block got turned to `meta.direction`. The block isn't in the world while this runs
+    #[allow(unused_variables)]
+    fn on_rotate(&mut self, meta: ChunkBlockMetadata, world: &mut World) {}
+    /// The direction this block wants to face when placed at `pos` to connect to its neighbors, if it has a
+    /// preference. Only used with smart orient enabled, the player can still rotate the block afterwards
+    #[allow(unused_variables)]
+    fn preferred_direction(&self, pos: Vec2i, world: &World) -> Option<Direction> {
+        None
+    }
     fn description(&self) -> &'static str;
     fn render(
         &self,
@@ -97,13 +260,38 @@ pub trait Block: BlockImplDetails {
             self.render(d, x, y, w, h, meta, *l);
         }
     }
-    fn destroy_items(&self) -> Vec<Box<dyn Item>> {
+    fn render_preview(
+        &self,
+        d: &mut RaylibDrawHandle,
+        x: i32,
+        y: i32,
+        w: i32,
+        h: i32,
+        meta: ChunkBlockMetadata,
+    ) {
+        for l in &PREVIEW_LAYERS {
+            self.render(d, x, y, w, h, meta, *l);
+        }
+    }
+    fn destroy_items(&self) -> Vec<Stack> {
         Vec::new()
     }
     fn is_building(&self) -> bool {
         false
     }
+    /// whether the block does work on its own in `update`; chunks containing machines are never suspended
+    fn is_machine(&self) -> bool {
+        false
+    }
+    /// how long the dismantle button has to be held to take this block down
+    fn dismantle_time_ms(&self) -> u64 {
+        2000
+    }
     fn identifier(&self) -> Identifier;
+    /// the tab the block shows up under in the selector screen
+    fn category(&self) -> BlockCategory {
+        BlockCategory::Misc
+    }
     fn supports_interaction(&self) -> bool {
         false
     }
@@ -115,7 +303,64 @@ pub trait Block: BlockImplDetails {
     fn custom_interact_message(&self) -> Option<String> {
         None
     }
-    fn get_inventory_capability<'a>(&'a mut self) -> Option<&'a mut Inventory> {
+    /// the theoretical amount of items this block can move or produce per minute
+    fn throughput_per_min(&self) -> Option<u32> {
+        None
+    }
+    /// the sides this block moves items out of, used to estimate the item flow of a network
+    fn flow_outputs(&self, meta: ChunkBlockMetadata) -> Vec<Direction> {
+        if self.throughput_per_min().is_some() {
+            vec![meta.direction]
+        } else {
+            Vec::new()
+        }
+    }
+    /// read-only access to the inventory, for UIs and stats; unlike `get_inventory_capability` this ignores
+    /// whether the block is currently able to accept or hand out items
+    fn inventory(&self) -> Option<&Inventory> {
+        None
+    }
+    /// whether the block connects to a power network; only blocks that do produce or use power
+    fn carries_power(&self) -> bool {
+        false
+    }
+    /// power the block adds to its network every tick
+    fn power_production(&self) -> i32 {
+        0
+    }
+    /// power the block needs from its network every tick to work
+    fn power_demand(&self) -> i32 {
+        0
+    }
+    /// the block as a power source, for blocks that produce power
+    fn as_power_source(&self) -> Option<&dyn PowerSource> {
+        None
+    }
+    fn as_power_source_mut(&mut self) -> Option<&mut dyn PowerSource> {
+        None
+    }
+    /// the recipe the block is set to, for machines that make recipes
+    fn recipe(&self) -> Option<Identifier> {
+        None
+    }
+    #[allow(unused_variables)]
+    fn set_recipe(&mut self, recipe: Identifier) {}
+    /// The settings the player can copy onto other blocks of the same kind (filters, recipes, ...), without any
+    /// items. None for blocks that have nothing to configure besides their direction
+    fn copy_config(&self) -> Option<Vec<u8>> {
+        None
+    }
+    /// Applies settings written by `copy_config` of a block of the same kind
+    #[allow(unused_variables)]
+    fn apply_config(&mut self, buf: &mut Buffer) -> Result<(), SerializationError> {
+        Ok(())
+    }
+    /// the number of items the block holds, for the item audit. Blocks with more than one inventory have to add up
+    /// the ones `inventory` doesn't return
+    fn item_count(&self) -> u64 {
+        self.inventory().map(Inventory::total_items).unwrap_or(0)
+    }
+    fn get_inventory_capability(&mut self) -> Option<&mut Inventory> {
         None
     }
     #[allow(unused_variables)]
@@ -127,18 +372,33 @@ pub trait Block: BlockImplDetails {
         false
     }
     #[allow(unused_variables)]
-    fn can_push(&self, _side: Direction, item: &Box<dyn Item>, meta: ChunkBlockMetadata) -> bool {
+    fn can_push(&self, _side: Direction, item: &Stack, meta: ChunkBlockMetadata) -> bool {
         false
     }
     #[allow(unused_variables)]
     fn push(
         &mut self,
         side: Direction,
-        item: Box<dyn Item>,
+        item: Stack,
         meta: ChunkBlockMetadata,
-    ) -> Option<Box<dyn Item>> {
+    ) -> Option<Stack> {
         Some(item)
     }
+    /// Pushes an item that sits on `lane` of a belt. Only blocks with lanes care about it, so by default this is
+    /// a normal push
+    fn push_lane(
+        &mut self,
+        side: Direction,
+        item: Stack,
+        meta: ChunkBlockMetadata,
+        _lane: Lane,
+    ) -> Option<Stack> {
+        if self.has_capability_push(side, meta) && self.can_push(side, &item, meta) {
+            self.push(side, item, meta)
+        } else {
+            Some(item)
+        }
+    }
     #[allow(unused_variables)]
     fn can_pull(&self, side: Direction, meta: ChunkBlockMetadata) -> bool {
         false
@@ -149,7 +409,7 @@ pub trait Block: BlockImplDetails {
         side: Direction,
         meta: ChunkBlockMetadata,
         num_items: u32,
-    ) -> Option<Box<dyn Item>> {
+    ) -> Option<Stack> {
         None
     }
 
@@ -189,11 +449,71 @@ impl Block for EmptyBlock {
     }
 }
 
-block_impl_details!(default ResourceNodeBrown);
-impl Block for ResourceNodeBrown {
-    empty_serializable!();
+// the field is the item the node yields, which also decides which node it is
+block_impl_details!(ResourceNode, Identifier);
+impl Default for ResourceNode {
+    fn default() -> Self {
+        Self::brown()
+    }
+}
+
+impl ResourceNode {
+    pub fn brown() -> Self {
+        Self(*COAL_IDENTIFIER)
+    }
+    pub fn blue() -> Self {
+        Self(*IRON_ORE_IDENTIFIER)
+    }
+    pub fn green() -> Self {
+        Self(*COPPER_ORE_IDENTIFIER)
+    }
+
+    fn color(&self) -> Color {
+        if self.0 == *IRON_ORE_IDENTIFIER {
+            Color::BLUE
+        } else if self.0 == *COPPER_ORE_IDENTIFIER {
+            Color::GREEN
+        } else {
+            Color::BROWN
+        }
+    }
+
+    fn yield_item(&self, amount: u32) -> Option<Stack> {
+        let item = Stack::of(self.0, amount)?;
+        record_item_delta(item.stack_count() as i64);
+        Some(item)
+    }
+}
+
+impl Block for ResourceNode {
+    fn serialize(&self, buf: &mut Vec<u8>) {
+        self.0.serialize(buf);
+    }
+    fn try_deserialize(&mut self, buf: &mut Buffer) -> Result<(), SerializationError> {
+        // legacy nodes yield what their registered block does
+        if buf.version() > LEGACY_SAVE_VERSION {
+            let id = Identifier::try_deserialize(buf)?;
+            if get_item_by_id(id).is_none() {
+                return Err(SerializationError::InvalidData);
+            }
+            self.0 = id;
+        }
+        Ok(())
+    }
+    fn required_length(&self) -> usize {
+        self.0.required_length()
+    }
     fn identifier(&self) -> Identifier {
-        *BLOCK_RESOURCE_NODE_BROWN
+        if self.0 == *IRON_ORE_IDENTIFIER {
+            *BLOCK_RESOURCE_NODE_BLUE
+        } else if self.0 == *COPPER_ORE_IDENTIFIER {
+            *BLOCK_RESOURCE_NODE_GREEN
+        } else {
+            *BLOCK_RESOURCE_NODE_BROWN
+        }
+    }
+    fn category(&self) -> BlockCategory {
+        BlockCategory::Resource
     }
     fn render(
         &self,
@@ -206,7 +526,7 @@ impl Block for ResourceNodeBrown {
         layer: RenderLayer,
     ) {
         if layer == RenderLayer::Block || layer == RenderLayer::Preview {
-            d.draw_rectangle(sc_x, sc_y, sc_w, sc_h, Color::BROWN);
+            d.draw_rectangle(sc_x, sc_y, sc_w, sc_h, self.color());
             
             let dir = meta.direction;
     
@@ -226,17 +546,27 @@ impl Block for ResourceNodeBrown {
         true
     }
     fn interact(&mut self, _meta: ChunkBlockMetadata, config: &mut GameConfig) {
-        let mut item = get_item_by_id(*COAL_IDENTIFIER).unwrap().clone_item();
-        item.set_metadata(8);
+        let Some(item) = self.yield_item(8) else {
+            return;
+        };
         if config.inventory.try_add_item(item).is_some() {
-            println!("Could not add item");
+            notice_board::add_entry(NoticeboardEntryRenderable::StringRef("Inventory full"), 2);
         }
     }
     fn custom_interact_message(&self) -> Option<String> {
-        Some("Press F to mine Coal Ore".to_string())
+        Some(match get_item_by_id(self.0) {
+            Some(item) => format!("Press F to mine {}", item.name()),
+            None => "Press F to mine".to_string(),
+        })
     }
     fn name(&self) -> GlobalString {
-        *COAL_NODE_NAME
+        if self.0 == *IRON_ORE_IDENTIFIER {
+            *IRON_NODE_NAME
+        } else if self.0 == *COPPER_ORE_IDENTIFIER {
+            *COPPER_NODE_NAME
+        } else {
+            *COAL_NODE_NAME
+        }
     }
     fn has_capability_pull(&self, _: Direction, _: ChunkBlockMetadata) -> bool {
         true
@@ -244,26 +574,32 @@ impl Block for ResourceNodeBrown {
     fn can_pull(&self, _: Direction, _: ChunkBlockMetadata) -> bool {
         true
     }
-    fn pull(&mut self, _: Direction, _: ChunkBlockMetadata, _: u32) -> Option<Box<dyn Item>> {
-        let mut item = get_item_by_id(*COAL_IDENTIFIER)?.clone_item();
-        item.set_metadata(1);
-        Some(item)
+    fn pull(&mut self, _: Direction, _: ChunkBlockMetadata, _: u32) -> Option<Stack> {
+        self.yield_item(1)
     }
     fn description(&self) -> &'static str {
-        "An Ore Node to extract coal from"
+        if self.0 == *IRON_ORE_IDENTIFIER {
+            "An Ore Node to extract iron ore from"
+        } else if self.0 == *COPPER_ORE_IDENTIFIER {
+            "An Ore Node to extract copper ore from"
+        } else {
+            "An Ore Node to extract coal from"
+        }
     }
 }
 
+const CONTAINER_SLOTS: usize = 5 * 9;
+
 block_impl_details!(StorageContainer, Inventory);
 
 impl Default for StorageContainer {
     fn default() -> Self {
-        Self(Inventory::new(5 * 9, false))
+        Self(Inventory::new(CONTAINER_SLOTS, false))
     }
 }
 
 impl Block for StorageContainer {
-    fn destroy_items(&self) -> Vec<Box<dyn Item>> {
+    fn destroy_items(&self) -> Vec<Stack> {
         self.0.destroy_items()
     }
 
@@ -271,19 +607,14 @@ impl Block for StorageContainer {
         "A 5x9 Container able to hold a total of 11475 items"
     }
 
-    fn serialize(&self, buf: &mut Vec<u8>) {
-        self.0.serialize(buf);
-    }
-    fn try_deserialize(&mut self, buf: &mut Buffer) -> Result<(), SerializationError> {
-        self.0 = Inventory::try_deserialize(buf)?;
-        Ok(())
-    }
-    fn required_length(&self) -> usize {
-        self.0.required_length()
-    }
+    inventory_block!(0, CONTAINER_SLOTS);
+
     fn identifier(&self) -> Identifier {
         *BLOCK_STORAGE_CONTAINER
     }
+    fn category(&self) -> BlockCategory {
+        BlockCategory::Storage
+    }
     fn name(&self) -> GlobalString {
         *CONTAINER_NAME
     }
@@ -300,15 +631,9 @@ impl Block for StorageContainer {
     fn is_building(&self) -> bool {
         true
     }
-    fn get_inventory_capability<'a>(&'a mut self) -> Option<&'a mut Inventory> {
-        Some(&mut self.0)
-    }
     fn supports_interaction(&self) -> bool {
         true
     }
-    fn init(&mut self, _: ChunkBlockMetadata) {
-        self.0.resize(5 * 9)
-    }
     fn render(
         &self,
         d: &mut RaylibDrawHandle,
@@ -329,18 +654,25 @@ impl Block for StorageContainer {
     fn has_capability_pull(&self, side: Direction, meta: ChunkBlockMetadata) -> bool {
         side == meta.direction || side + Direction::South == meta.direction
     }
+    /// Lines the container up with a neighbor that moves items into it, so it can push them
+    fn preferred_direction(&self, pos: Vec2i, world: &World) -> Option<Direction> {
+        Direction::all().into_iter().zip(world.neighbors(pos)).find_map(|(dir, neighbor)| {
+            let (blk, meta) = neighbor?;
+            blk.flow_outputs(meta).contains(&dir.opposite()).then_some(dir)
+        })
+    }
     fn can_pull(&self, side: Direction, meta: ChunkBlockMetadata) -> bool {
         self.has_capability_pull(side, meta) && self.0.can_pull()
     }
-    fn can_push(&self, side: Direction, item: &Box<dyn Item>, meta: ChunkBlockMetadata) -> bool {
+    fn can_push(&self, side: Direction, item: &Stack, meta: ChunkBlockMetadata) -> bool {
         self.has_capability_push(side, meta) && self.0.can_push(item)
     }
     fn push(
         &mut self,
         _side: Direction,
-        item: Box<dyn Item>,
+        item: Stack,
         _meta: ChunkBlockMetadata,
-    ) -> Option<Box<dyn Item>> {
+    ) -> Option<Stack> {
         self.0.try_add_item(item)
     }
     fn pull(
@@ -348,7 +680,7 @@ impl Block for StorageContainer {
         _side: Direction,
         _meta: ChunkBlockMetadata,
         num_items: u32,
-    ) -> Option<Box<dyn Item>> {
+    ) -> Option<Stack> {
         self.0.try_pull(num_items)
     }
 }
@@ -358,13 +690,22 @@ pub static mut BLOCKS: Vec<Box<dyn Block>> = Vec::new();
 pub fn register_blocks() {
     m_register_blocks!(
         EmptyBlock,
-        ResourceNodeBrown,
+        ResourceNode,
         StorageContainer,
         ExtractorBlock,
         ConveyorBlock,
+        LaneConveyorBlock,
         ConveyorSplitter,
-        TunnelBlock
+        DistributorBlock,
+        TunnelBlock,
+        LoaderBlock,
+        AssemblerBlock,
+        GeneratorBlock
     );
+    register_block(Box::new(ExtractorBlock::long()));
+    register_block(Box::new(ConveyorBlock::fast()));
+    register_block(Box::new(ResourceNode::blue()));
+    register_block(Box::new(ResourceNode::green()));
 }
 
 pub fn register_block(block: Box<dyn Block>) {
@@ -382,6 +723,7 @@ pub fn load_block_files(rl: &mut RaylibHandle, thread: &RaylibThread) -> Result<
 }
 
 pub fn get_block_by_id(id: Identifier) -> Option<&'static Box<dyn Block>> {
+    let id = resolve_block_alias(id);
     unsafe {
         for blk in &BLOCKS {
             if blk.identifier() == id {
@@ -392,8 +734,289 @@ pub fn get_block_by_id(id: Identifier) -> Option<&'static Box<dyn Block>> {
     None
 }
 
+/// Maps identifiers of blocks that got renamed to their current identifier, so old saves still load
+fn resolve_block_alias(id: Identifier) -> Identifier {
+    if id == *BLOCK_CONVEYOR_MERGER {
+        *BLOCK_CONVEYOR_SPLITTER
+    } else {
+        id
+    }
+}
+
+/// Gets all registered blocks (except the empty block) with their index in `BLOCKS`, optionally only of a single category
+pub fn blocks_in_category(
+    category: Option<BlockCategory>,
+) -> impl Iterator<Item = (usize, &'static Box<dyn Block>)> {
+    unsafe { BLOCKS.iter() }
+        .enumerate()
+        .skip(1)
+        .filter(move |(_, blk)| {
+            !blk.is_none() && category.map(|cat| blk.category() == cat).unwrap_or(true)
+        })
+}
+
+/// Checks that every registered block writes exactly `required_length` bytes and reads back what it wrote.
+/// Returns the identifier of the first block that doesn't
+pub fn validate_serialization() -> Result<(), Identifier> {
+    for (_, blk) in blocks_in_category(None) {
+        // the block's own layout, not the boxed one with the identifier in front
+        let blk: &dyn Block = blk.as_ref();
+        let mut buf = Vec::with_capacity(blk.required_length());
+        blk.serialize(&mut buf);
+        if buf.len() != blk.required_length() {
+            return Err(blk.identifier());
+        }
+
+        let mut read_back = blk.clone_block();
+        let mut buffer = Buffer::new(buf.clone());
+        if read_back.try_deserialize(&mut buffer).is_err() || buffer.len() != 0 {
+            return Err(blk.identifier());
+        }
+        let mut written_again = Vec::with_capacity(buf.len());
+        Block::serialize(&*read_back, &mut written_again);
+        if written_again != buf {
+            return Err(blk.identifier());
+        }
+    }
+    Ok(())
+}
+
 pub fn empty_block() -> &'static Box<dyn Block> {
     unsafe { &BLOCKS[0] }
 }
 
 downcast_for!(Block);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        game::GameConfig,
+        inventory::Slot,
+        serialization::SerializationTrap,
+        test_utils::{item, register_all},
+    };
+
+    #[test]
+    fn blocks_are_sorted_into_their_categories() {
+        register_all();
+
+        let expected = [
+            (*BLOCK_RESOURCE_NODE_BROWN, BlockCategory::Resource),
+            (*BLOCK_RESOURCE_NODE_BLUE, BlockCategory::Resource),
+            (*BLOCK_RESOURCE_NODE_GREEN, BlockCategory::Resource),
+            (*BLOCK_STORAGE_CONTAINER, BlockCategory::Storage),
+            (*extractor::BLOCK_EXTRACTOR, BlockCategory::Logistics),
+            (*extractor::BLOCK_LONG_EXTRACTOR, BlockCategory::Logistics),
+            (*conveyor::BLOCK_CONVEYOR, BlockCategory::Logistics),
+            (*conveyor::BLOCK_FAST_CONVEYOR, BlockCategory::Logistics),
+            (*lane_conveyor::BLOCK_LANE_CONVEYOR, BlockCategory::Logistics),
+            (*splitter::BLOCK_CONVEYOR_SPLITTER, BlockCategory::Logistics),
+            (*distributor::BLOCK_DISTRIBUTOR, BlockCategory::Logistics),
+            (*tunnel::BLOCK_TUNNEL, BlockCategory::Logistics),
+            (*loader::BLOCK_LOADER, BlockCategory::Logistics),
+            (*assembler::BLOCK_ASSEMBLER, BlockCategory::Production),
+            (*generator::BLOCK_GENERATOR, BlockCategory::Production),
+        ];
+        for (id, category) in expected {
+            let blk = get_block_by_id(id).expect("the block is registered");
+            assert_eq!(blk.category(), category, "{:?}", id);
+        }
+        // every registered block is in the list above
+        assert_eq!(blocks_in_category(None).count(), expected.len());
+
+        for category in BlockCategory::ALL {
+            let in_category: Vec<_> = blocks_in_category(Some(category)).collect();
+            assert_eq!(
+                in_category.len(),
+                expected.iter().filter(|(_, cat)| *cat == category).count(),
+                "{:?}",
+                category
+            );
+            assert!(in_category.iter().all(|(_, blk)| blk.category() == category));
+        }
+    }
+
+    #[test]
+    fn throughput_follows_the_work_duration() {
+        assert_eq!(ExtractorBlock::default().throughput_per_min(), Some(240));
+        assert_eq!(ConveyorBlock::default().throughput_per_min(), Some(60));
+        assert_eq!(ConveyorBlock::fast().throughput_per_min(), Some(120));
+        assert_eq!(StorageContainer::default().throughput_per_min(), None);
+    }
+
+    #[test]
+    fn conveyor_merger_loads_as_the_splitter() {
+        register_all();
+        let splitters: Vec<_> = blocks_in_category(None)
+            .filter(|(_, blk)| downcast::<splitter::ConveyorSplitter>(blk.as_ref()).is_some())
+            .collect();
+        assert_eq!(splitters.len(), 1);
+        assert_eq!(splitters[0].1.identifier(), *splitter::BLOCK_CONVEYOR_SPLITTER);
+        assert_eq!(
+            get_block_by_id(*BLOCK_CONVEYOR_MERGER).unwrap().identifier(),
+            *splitter::BLOCK_CONVEYOR_SPLITTER
+        );
+
+        // a world saved while the splitter was still called conveyor_merger
+        let mut world = World::new(2, 2);
+        world.set_block_at(3, 4, Box::new(splitter::ConveyorSplitter::default()), Direction::West);
+        let mut data = Vec::new();
+        world.serialize(&mut data);
+        let (mut new_id, mut old_id) = (Vec::new(), Vec::new());
+        splitter::BLOCK_CONVEYOR_SPLITTER.serialize(&mut new_id);
+        BLOCK_CONVEYOR_MERGER.serialize(&mut old_id);
+        let at = data.windows(new_id.len()).position(|window| window == new_id).unwrap();
+        data.splice(at..at + new_id.len(), old_id);
+
+        let loaded = World::try_deserialize(&mut Buffer::new(data)).unwrap();
+        let (blk, meta) = loaded.get_block_at(3, 4).unwrap();
+        assert_eq!(blk.identifier(), *splitter::BLOCK_CONVEYOR_SPLITTER);
+        assert_eq!(meta.direction, Direction::West);
+    }
+
+    #[test]
+    fn nodes_yield_their_own_items() {
+        register_all();
+        let meta = ChunkBlockMetadata::new(Direction::North, Vec2i::new(0, 0));
+        let yields = |id: Identifier| {
+            let mut node = get_block_by_id(id).unwrap().clone_block();
+            node.pull(Direction::South, meta, 1).unwrap().identifier()
+        };
+        assert_eq!(yields(*BLOCK_RESOURCE_NODE_BLUE), *IRON_ORE_IDENTIFIER);
+        assert_eq!(yields(*BLOCK_RESOURCE_NODE_GREEN), *COPPER_ORE_IDENTIFIER);
+        assert_eq!(yields(*BLOCK_RESOURCE_NODE_BROWN), *COAL_IDENTIFIER);
+    }
+
+    #[test]
+    fn nodes_save_their_yield() {
+        register_all();
+        let node: Box<dyn Block> = Box::new(ResourceNode::green());
+        let mut data = Vec::new();
+        node.serialize(&mut data);
+        assert_eq!(data.len(), node.required_length());
+        let mut id = Vec::new();
+        COPPER_ORE_IDENTIFIER.serialize(&mut id);
+        assert!(data.ends_with(&id));
+
+        let mut buf = Buffer::new(data);
+        let mut loaded = <Box<dyn Block>>::try_deserialize(&mut buf).unwrap();
+        assert_eq!(buf.len(), 0);
+        assert_eq!(loaded.identifier(), *BLOCK_RESOURCE_NODE_GREEN);
+        let meta = ChunkBlockMetadata::new(Direction::North, Vec2i::new(0, 0));
+        assert_eq!(loaded.pull(Direction::South, meta, 1).unwrap().identifier(), *COPPER_ORE_IDENTIFIER);
+
+        // a yield that isn't an item
+        let mut broken = saved_block_header(*BLOCK_RESOURCE_NODE_GREEN);
+        BLOCK_RESOURCE_NODE_GREEN.serialize(&mut broken);
+        assert!(<Box<dyn Block>>::try_deserialize(&mut Buffer::new(broken)).is_err());
+    }
+
+    /// the start of a saved block, everything up to its own layout
+    fn saved_block_header(id: Identifier) -> Vec<u8> {
+        let mut buf = Vec::new();
+        SerializationTrap::Block.serialize(&mut buf);
+        false.serialize(&mut buf);
+        id.serialize(&mut buf);
+        buf
+    }
+
+    #[test]
+    fn old_block_layouts_load_at_their_version() {
+        register_all();
+        let load = |data: Vec<u8>, version: u16| {
+            let mut buf = Buffer::with_version(data, version);
+            let loaded = <Box<dyn Block>>::try_deserialize(&mut buf).unwrap();
+            assert_eq!(buf.len(), 0, "version {version}");
+            loaded
+        };
+
+        // a node from before nodes wrote their yield yields what its registered block does
+        let v1_node = saved_block_header(*BLOCK_RESOURCE_NODE_BLUE);
+        let mut node = load(v1_node, LEGACY_SAVE_VERSION);
+        assert_eq!(node.identifier(), *BLOCK_RESOURCE_NODE_BLUE);
+        let meta = ChunkBlockMetadata::new(Direction::North, Vec2i::new(0, 0));
+        assert_eq!(node.pull(Direction::South, meta, 1).unwrap().identifier(), *IRON_ORE_IDENTIFIER);
+
+        // a legacy splitter is just its item
+        let mut v1_splitter = saved_block_header(*splitter::BLOCK_CONVEYOR_SPLITTER);
+        Slot::from(item(*COAL_IDENTIFIER, 1)).serialize(&mut v1_splitter);
+        let mut v2_splitter = v1_splitter.clone();
+        for filter in [None, Some(*IRON_ORE_IDENTIFIER), None] {
+            filter.serialize(&mut v2_splitter);
+        }
+        let splitter = load(v1_splitter, LEGACY_SAVE_VERSION);
+        let splitter = downcast::<splitter::ConveyorSplitter>(&*splitter).unwrap();
+        assert_eq!(splitter.filters(), [None; 3]);
+        assert_eq!(splitter.inventory().unwrap().get_item(0).unwrap().identifier(), *COAL_IDENTIFIER);
+        let splitter = load(v2_splitter, crate::serialization::CURRENT_SAVE_VERSION);
+        let filters = downcast::<splitter::ConveyorSplitter>(&*splitter).unwrap().filters();
+        assert_eq!(filters, [None, Some(*IRON_ORE_IDENTIFIER), None]);
+    }
+
+    /// what is in each slot of the inventory of `blk`
+    fn slots(blk: &dyn Block) -> Vec<Option<(Identifier, u32)>> {
+        let inventory = blk.inventory().unwrap();
+        (0..inventory.size())
+            .map(|slot| inventory.get_item(slot).map(|item| (item.identifier(), item.stack_count())))
+            .collect()
+    }
+
+    #[test]
+    fn inventory_blocks_keep_all_their_slots() {
+        register_all();
+        let mut container = StorageContainer::default();
+        container.init(ChunkBlockMetadata::new(Direction::North, Vec2i::new(0, 0)));
+        let mut distributor = DistributorBlock::default();
+        distributor.init(ChunkBlockMetadata::new(Direction::North, Vec2i::new(0, 0)));
+        let blocks: [(Box<dyn Block>, usize); 2] =
+            [(Box::new(container), CONTAINER_SLOTS), (Box::new(distributor), 3)];
+
+        for (mut blk, num_slots) in blocks {
+            let inventory = blk.get_inventory_capability().unwrap();
+            assert_eq!(inventory.size(), num_slots);
+            inventory.add_item(item(*COAL_IDENTIFIER, 7), 0);
+            inventory.add_item(item(*IRON_ORE_IDENTIFIER, 1), num_slots - 1);
+
+            let mut data = Vec::new();
+            blk.serialize(&mut data);
+            assert_eq!(data.len(), blk.required_length());
+            let mut buf = Buffer::new(data);
+            let loaded = <Box<dyn Block>>::try_deserialize(&mut buf).unwrap();
+            assert_eq!(buf.len(), 0);
+            assert_eq!(loaded.identifier(), blk.identifier());
+            assert_eq!(slots(loaded.as_ref()), slots(blk.as_ref()));
+        }
+    }
+
+    #[test]
+    fn every_block_passes_serialization_validation() {
+        register_all();
+        assert_eq!(validate_serialization(), Ok(()));
+    }
+
+    #[test]
+    fn containers_face_the_belt_feeding_them() {
+        register_all();
+        let mut world = World::new(1, 1);
+        let pos = Vec2i::new(5, 5);
+        // faces the container but never hands anything out, so it doesn't count
+        world.set_block_at(5, 4, Box::new(StorageContainer::default()), Direction::South);
+        // a belt running past the container
+        world.set_block_at(4, 5, Box::new(conveyor::ConveyorBlock::default()), Direction::North);
+        // and an extractor emptying a container into it
+        let mut source = StorageContainer::default();
+        source.get_inventory_capability().unwrap().try_add_item(item(*COAL_IDENTIFIER, 3));
+        world.set_block_at(7, 5, Box::new(source), Direction::East);
+        world.set_block_at(6, 5, Box::new(extractor::ExtractorBlock::default()), Direction::East);
+
+        let direction = StorageContainer::default().preferred_direction(pos, &world);
+        assert_eq!(direction, Some(Direction::West));
+        world.set_block_at(pos.x, pos.y, Box::new(StorageContainer::default()), Direction::West);
+        let mut config = GameConfig::default();
+        for _ in 0..100 {
+            world.tick(&mut config);
+        }
+        assert!(world.get_block_at(pos.x, pos.y).unwrap().0.item_count() > 0);
+    }
+}
@@ -1,5 +1,3 @@
-use std::time::Instant;
-
 use lazy_static::lazy_static;
 use raylib::{
     color::Color,
@@ -12,14 +10,17 @@ use crate::{
     game::RenderLayer,
     identifier::{GlobalString, Identifier},
     inventory::Inventory,
-    items::Item,
+    items::Stack,
     reset_timer,
     scheduler::{schedule_task, Task},
-    simple_single_item_serializable, step_size,
+    screens::SplitterFilterScreen,
+    serialization::{Buffer, Deserialize, SerializationError, Serialize, LEGACY_SAVE_VERSION},
+    step_size,
     world::{ChunkBlockMetadata, Direction, Vec2i, World},
+    GameConfig,
 };
 
-use super::{downcast, downcast_mut, Block};
+use super::{downcast, downcast_mut, now, Block, BlockCategory};
 
 lazy_static! {
     pub static ref CONVEYOR_SPLITTER: GlobalString = GlobalString::from("Conveyor Splitter");
@@ -27,22 +28,89 @@ lazy_static! {
         Identifier::from(("placeholder_name_2", "conveyor_splitter"));
 }
 
-block_impl_details_with_timer!(ConveyorSplitter, 200, Inventory, usize, Option<Direction>);
+/// The sides a splitter facing `direction` outputs to: left, front and right
+pub fn output_sides(direction: Direction) -> [Direction; 3] {
+    [direction.next(false), direction, direction.next(true)]
+}
+
+// fields: the item, the side to try first, the side the item goes to and the item filter of each side in the order
+// of `output_sides`
+block_impl_details_with_timer!(
+    ConveyorSplitter,
+    200,
+    Inventory,
+    usize,
+    Option<Direction>,
+    [Option<Identifier>; 3]
+);
 impl Default for ConveyorSplitter {
     fn default() -> Self {
-        Self(Instant::now(), Inventory::new(1, false), 0, None)
+        Self(now(), Inventory::new(1, false), 0, None, [None; 3])
     }
 }
 impl Block for ConveyorSplitter {
-    simple_single_item_serializable!(1);
+    fn serialize(&self, buf: &mut Vec<u8>) {
+        self.1.slot(0).serialize(buf);
+        for filter in &self.4 {
+            filter.serialize(buf);
+        }
+    }
+    fn try_deserialize(&mut self, buf: &mut Buffer) -> Result<(), SerializationError> {
+        self.1.resize(1);
+        *self.1.get_item_mut(0) = Deserialize::try_deserialize(buf)?;
+        if buf.version() > LEGACY_SAVE_VERSION {
+            for filter in &mut self.4 {
+                *filter = Deserialize::try_deserialize(buf)?;
+            }
+        }
+        Ok(())
+    }
+    fn required_length(&self) -> usize {
+        self.1.slot(0).required_length()
+            + self.4.iter().map(|filter| filter.required_length()).sum::<usize>()
+    }
+
+    fn copy_config(&self) -> Option<Vec<u8>> {
+        let mut buf = Vec::new();
+        for filter in &self.4 {
+            filter.serialize(&mut buf);
+        }
+        Some(buf)
+    }
+    fn apply_config(&mut self, buf: &mut Buffer) -> Result<(), SerializationError> {
+        for side in 0..self.4.len() {
+            self.set_filter(side, Deserialize::try_deserialize(buf)?);
+        }
+        Ok(())
+    }
 
     fn description(&self) -> &'static str {
-        "Splits incoming items evenly between all 3 outputs using round robin at a rate of 5 per second"
+        "Splits incoming items evenly between all 3 outputs using round robin at a rate of 5 per second. Outputs with a filter only get the filtered item"
+    }
+    fn supports_interaction(&self) -> bool {
+        true
+    }
+    fn custom_interact_message(&self) -> Option<String> {
+        Some("Press F to set the output filters".to_string())
+    }
+    fn interact(&mut self, meta: ChunkBlockMetadata, _: &mut GameConfig) {
+        schedule_task(Task::OpenScreenCentered(Box::new(SplitterFilterScreen::new(
+            meta.position,
+        ))));
     }
 
     fn identifier(&self) -> Identifier {
         *BLOCK_CONVEYOR_SPLITTER
     }
+    fn category(&self) -> BlockCategory {
+        BlockCategory::Logistics
+    }
+    fn throughput_per_min(&self) -> Option<u32> {
+        Some(60_000 / Self::WORK_DURATION_MS)
+    }
+    fn flow_outputs(&self, meta: ChunkBlockMetadata) -> Vec<Direction> {
+        output_sides(meta.direction).to_vec()
+    }
     fn init(&mut self, _: ChunkBlockMetadata) {
         self.1.resize(1);
     }
@@ -114,7 +182,7 @@ impl Block for ConveyorSplitter {
         }
     }
 
-    fn can_push(&self, side: Direction, _: &Box<dyn Item>, meta: ChunkBlockMetadata) -> bool {
+    fn can_push(&self, side: Direction, _: &Stack, meta: ChunkBlockMetadata) -> bool {
         self.1.get_item(0).is_none() && self.has_capability_push(side, meta)
     }
 
@@ -125,9 +193,9 @@ impl Block for ConveyorSplitter {
     fn push(
         &mut self,
         side: Direction,
-        mut item: Box<dyn Item>,
+        mut item: Stack,
         meta: ChunkBlockMetadata,
-    ) -> Option<Box<dyn Item>> {
+    ) -> Option<Stack> {
         if !self.can_push(side, &item, meta) {
             return Some(item);
         }
@@ -137,18 +205,20 @@ impl Block for ConveyorSplitter {
         }
         reset_timer!(self);
         if item.metadata_is_stack_size() && item.metadata() > 1 {
+            slot.replace(item.with_count(1));
             let remaining = item.metadata() - 1;
-            item.set_metadata(1);
-            slot.replace(item.clone_item());
             item.set_metadata(remaining);
             Some(item)
         } else {
-            slot.replace(item.clone_item());
+            slot.replace(item);
             None
         }
     }
 
-    fn get_inventory_capability<'a>(&'a mut self) -> Option<&'a mut Inventory> {
+    fn inventory(&self) -> Option<&Inventory> {
+        Some(&self.1)
+    }
+    fn get_inventory_capability(&mut self) -> Option<&mut Inventory> {
         Some(&mut self.1)
     }
 
@@ -174,37 +244,55 @@ impl Block for ConveyorSplitter {
         true
     }
 
-    fn destroy_items(&self) -> Vec<Box<dyn Item>> {
+    fn is_machine(&self) -> bool {
+        true
+    }
+
+    fn destroy_items(&self) -> Vec<Stack> {
         self.1.destroy_items()
     }
 }
 
 impl ConveyorSplitter {
+    /// The item filter of each output, in the order of `output_sides`
+    pub fn filters(&self) -> [Option<Identifier>; 3] {
+        self.4
+    }
+
+    /// Sets the filter of the output `side` (an index into `output_sides`), None lets every item through
+    pub fn set_filter(&mut self, side: usize, filter: Option<Identifier>) {
+        if let Some(slot) = self.4.get_mut(side) {
+            *slot = filter;
+            // the item waiting to leave might not be allowed to go where it was headed anymore
+            self.3 = None;
+        }
+    }
+
     fn determine_direction(meta: ChunkBlockMetadata, world: &mut World) -> Option<()> {
-        let last_direction =
-            downcast::<Self>(&**world.get_block_at_mut(meta.position.x, meta.position.y)?.0)?.2;
+        let (last_direction, filters) = downcast::<Self>(
+            &**world.get_block_at_mut(meta.position.x, meta.position.y)?.0,
+        )
+        .map(|me| (me.2, me.4))?;
         let itm = world
             .get_block_at_mut(meta.position.x, meta.position.y)?
             .0
             .get_inventory_capability()?
             .get_item(0);
         let itm = if let Some(itm) = itm {
-            itm.clone_item()
+            Stack::from(itm)
         } else {
             return None;
         };
-        let sides_to_pushto = [
-            meta.direction.next(false),
-            meta.direction,
-            meta.direction.next(true),
-        ];
+        let sides_to_pushto = output_sides(meta.direction);
 
         let mut last_idx = 3_usize;
         let mut side = None;
         for i in last_direction..last_direction + 3 {
             let s = sides_to_pushto[i % 3];
-            let pos = meta.position.add_directional(&s, 1);
-            if let Some((blk, push_meta)) = world.get_block_at(pos.x, pos.y) {
+            if filters[i % 3].is_some_and(|filter| filter != itm.identifier()) {
+                continue;
+            }
+            if let Some((blk, push_meta)) = world.neighbor(meta.position, s) {
                 if blk.can_push(s.opposite(), &itm, push_meta) {
                     side = Some(s);
                     last_idx = (i + 1) % 3;
@@ -241,8 +329,7 @@ impl ConveyorSplitter {
             .0
             .get_inventory_capability()?
             .take_item(0);
-        let pos = meta.position.add_directional(&direction, 1);
-        if let Some((blk, pushto_meta)) = world.get_block_at_mut(pos.x, pos.y) {
+        if let Some((blk, pushto_meta)) = world.neighbor_mut(meta.position, direction) {
             itm = blk.push(direction.opposite(), itm, pushto_meta)?;
         }
         world
@@ -254,3 +341,46 @@ impl ConveyorSplitter {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        items::{COAL_IDENTIFIER, IRON_ORE_IDENTIFIER},
+        serialization::CURRENT_SAVE_VERSION,
+        test_utils::{item, register_all},
+    };
+
+    fn written(splitter: &ConveyorSplitter) -> Vec<u8> {
+        let mut buf = Vec::new();
+        Block::serialize(splitter, &mut buf);
+        assert_eq!(buf.len(), Block::required_length(splitter));
+        buf
+    }
+
+    #[test]
+    fn filters_are_only_read_from_saves_that_have_them() {
+        register_all();
+        let mut splitter = ConveyorSplitter::default();
+        splitter.1.get_item_mut(0).replace(item(*COAL_IDENTIFIER, 1));
+        splitter.set_filter(0, Some(*IRON_ORE_IDENTIFIER));
+        splitter.set_filter(2, Some(*COAL_IDENTIFIER));
+
+        let mut read = ConveyorSplitter::default();
+        let mut buf = Buffer::with_version(written(&splitter), CURRENT_SAVE_VERSION);
+        Block::try_deserialize(&mut read, &mut buf).unwrap();
+        assert_eq!(buf.len(), 0);
+        assert_eq!(read.filters(), splitter.filters());
+        assert_eq!(read.1.get_item(0).unwrap().identifier(), *COAL_IDENTIFIER);
+
+        // a splitter from before the filters is nothing but its item, and lets everything through
+        let mut old = Vec::new();
+        splitter.1.slot(0).serialize(&mut old);
+        let mut read = ConveyorSplitter::default();
+        let mut buf = Buffer::with_version(old, LEGACY_SAVE_VERSION);
+        Block::try_deserialize(&mut read, &mut buf).unwrap();
+        assert_eq!(buf.len(), 0);
+        assert_eq!(read.filters(), [None; 3]);
+        assert_eq!(read.1.get_item(0).unwrap().identifier(), *COAL_IDENTIFIER);
+    }
+}
@@ -1,9 +1,7 @@
-use std::time::Instant;
-
 use lazy_static::lazy_static;
 use raylib::{
-    color::Color, drawing::RaylibDraw, math::{Rectangle, Vector2}, texture::Texture2D, RaylibHandle,
-    RaylibThread,
+    color::Color, drawing::RaylibDraw, math::{Rectangle, Vector2}, text::measure_text,
+    texture::Texture2D, RaylibHandle, RaylibThread,
 };
 
 use crate::{
@@ -22,7 +20,7 @@ use crate::{
     world::{ChunkBlockMetadata, Direction, Vec2i, World},
 };
 
-use super::{conveyor::CONVEYOR_ANIMATION, Block};
+use super::{conveyor::CONVEYOR_ANIMATION, now, Block, BlockCategory};
 
 lazy_static! {
     pub static ref TUNNEL_NAME: GlobalString = GlobalString::from("Tunnel tier 1");
@@ -37,6 +35,43 @@ pub enum TunnelType {
     None,
 }
 
+impl TunnelType {
+    /// The tint of the overlay, so entrances and exits of a tunnel can be told apart
+    pub fn overlay_tint(&self) -> Color {
+        match self {
+            Self::Pushing(..) => Color::LIME,
+            Self::Receiving(..) => Color::ORANGE,
+            Self::None => Color::WHITE,
+        }
+    }
+
+    /// How many blocks away the other end of the tunnel is
+    pub fn length(&self, position: Vec2i) -> Option<i32> {
+        match self {
+            Self::Pushing(other) | Self::Receiving(other) => {
+                Some((other.x - position.x).abs().max((other.y - position.y).abs()))
+            }
+            Self::None => None,
+        }
+    }
+
+    /// The "N/7" shown on the overlay of a linked tunnel
+    pub fn length_label(&self, position: Vec2i) -> Option<String> {
+        self.length(position).map(|length| format!("{length}/{MAX_LENGTH}"))
+    }
+
+    /// Where the overlay arrow of a tunnel facing `facing` points. Exits point back at their entrance
+    pub fn overlay_direction(&self, facing: Direction) -> Direction {
+        match self {
+            Self::None | Self::Pushing(..) => facing,
+            Self::Receiving(..) => facing.opposite(),
+        }
+    }
+}
+
+/// How far apart the two ends of a tunnel can be
+const MAX_LENGTH: i32 = 7;
+
 impl Serialize for TunnelType {
     fn required_length(&self) -> usize {
         u8::required_length(&0)
@@ -49,14 +84,14 @@ impl Serialize for TunnelType {
     fn serialize(&self, buf: &mut Vec<u8>) {
         match self {
             Self::Pushing(vec) => {
-                0.serialize(buf);
+                0u8.serialize(buf);
                 vec.serialize(buf);
             }
             Self::Receiving(vec) => {
-                1.serialize(buf);
+                1u8.serialize(buf);
                 vec.serialize(buf);
             }
-            Self::None => 2.serialize(buf),
+            Self::None => 2u8.serialize(buf),
         }
     }
 }
@@ -77,7 +112,7 @@ block_impl_details_with_timer!(TunnelBlock, 500, Inventory, Direction, TunnelTyp
 impl Default for TunnelBlock {
     fn default() -> Self {
         Self(
-            Instant::now(),
+            now(),
             Inventory::new(1, false),
             Default::default(),
             TunnelType::None,
@@ -90,6 +125,10 @@ impl Block for TunnelBlock {
         true
     }
 
+    fn is_machine(&self) -> bool {
+        true
+    }
+
     fn required_length(&self) -> usize {
         self.1.required_length() + self.2.required_length() + self.3.required_length()
     }
@@ -116,6 +155,12 @@ impl Block for TunnelBlock {
     fn identifier(&self) -> Identifier {
         *BLOCK_TUNNEL
     }
+    fn category(&self) -> BlockCategory {
+        BlockCategory::Logistics
+    }
+    fn throughput_per_min(&self) -> Option<u32> {
+        Some(60_000 / Self::WORK_DURATION_MS)
+    }
     fn description(&self) -> &'static str {
         "Moves 60 items per minute; Max length: 7 Blocks"
     }
@@ -127,7 +172,7 @@ impl Block for TunnelBlock {
     fn can_push(
         &self,
         side: Direction,
-        _: &Box<dyn crate::items::Item>,
+        _: &crate::items::Stack,
         meta: crate::world::ChunkBlockMetadata,
     ) -> bool {
         self.has_capability_push(side, meta) && self.1.get_item(0).is_none()
@@ -136,9 +181,9 @@ impl Block for TunnelBlock {
     fn push(
         &mut self,
         side: Direction,
-        item: Box<dyn crate::items::Item>,
+        item: crate::items::Stack,
         meta: crate::world::ChunkBlockMetadata,
-    ) -> Option<Box<dyn crate::items::Item>> {
+    ) -> Option<crate::items::Stack> {
         if !self.can_push(side, &item, meta) {
             Some(item)
         } else {
@@ -159,8 +204,16 @@ impl Block for TunnelBlock {
         meta: crate::world::ChunkBlockMetadata,
         render_layer: crate::game::RenderLayer,
     ) {
-        if render_layer == RenderLayer::Block {
-            CONVEYOR_ANIMATION.draw_resized_rotated(d, x, y, w, h, meta.direction);
+        if render_layer == RenderLayer::Block || render_layer == RenderLayer::Preview {
+            CONVEYOR_ANIMATION.draw_resized_rotated_synced(
+                d,
+                x,
+                y,
+                w,
+                h,
+                meta.direction,
+                Self::WORK_DURATION_MS,
+            );
         } else if render_layer == RenderLayer::OverlayItems {
             match self.3 {
                 TunnelType::None | TunnelType::Pushing(..) => {
@@ -188,19 +241,7 @@ impl Block for TunnelBlock {
                     }
                 }
             }
-            let dir = match self.3 {
-                TunnelType::None | TunnelType::Pushing(..) => meta.direction,
-                TunnelType::Receiving(..) => meta.direction.opposite(),
-            };
-            let (rot, vec) = get_rotation_vec(dir, Vec2i::new(x, y), w, h);
-            d.draw_texture_ex(&*TUNNEL_OVERLAY, vec.as_vec2f(), rot, 1.0, Color::WHITE);
-        } else if render_layer == RenderLayer::Preview {
-            CONVEYOR_ANIMATION.draw_resized_rotated(d, x, y, w, h, meta.direction);
-
-            let dir = match self.3 {
-                TunnelType::None | TunnelType::Pushing(..) => meta.direction,
-                TunnelType::Receiving(..) => meta.direction.opposite(),
-            };
+            let dir = self.3.overlay_direction(meta.direction);
             let (rot, vec) = get_rotation_vec(dir, Vec2i::new(x, y), w, h);
             d.draw_texture_pro(
                 &*TUNNEL_OVERLAY,
@@ -208,8 +249,18 @@ impl Block for TunnelBlock {
                 Rectangle::new(vec.x as f32, vec.y as f32, w as f32, h as f32),
                 Vector2::zero(),
                 rot,
-                Color::WHITE,
-            )
+                self.3.overlay_tint(),
+            );
+            if let Some(text) = self.3.length_label(meta.position) {
+                let font_size = (h / 4).max(10);
+                d.draw_text(
+                    text.as_str(),
+                    x + w - measure_text(text.as_str(), font_size) - 2,
+                    y + h - font_size - 2,
+                    font_size,
+                    Color::WHITE,
+                );
+            }
         }
     }
 
@@ -228,6 +279,9 @@ impl Block for TunnelBlock {
     fn init(&mut self, _: ChunkBlockMetadata) {
         self.1.resize(1);
     }
+    fn inventory(&self) -> Option<&Inventory> {
+        Some(&self.1)
+    }
 
     fn on_after_dismantle(&mut self, _: ChunkBlockMetadata, world: &mut World) {
         let vec = match self.3 {
@@ -240,13 +294,19 @@ impl Block for TunnelBlock {
             .map(|other| other.3 = TunnelType::None);
     }
 
-    fn destroy_items(&self) -> Vec<Box<dyn crate::items::Item>> {
+    fn destroy_items(&self) -> Vec<crate::items::Stack> {
         self.1.destroy_items()
     }
 
+    /// the old partner doesn't line up anymore, so the tunnel looks for a new one in its new direction
+    fn on_rotate(&mut self, meta: ChunkBlockMetadata, world: &mut World) {
+        self.on_after_dismantle(meta, world);
+        self.on_before_place(meta, world);
+    }
+
     fn on_before_place(&mut self, meta: ChunkBlockMetadata, world: &mut crate::world::World) {
         let mut blk_pos: Option<Vec2i> = None;
-        for i in -7..=7 {
+        for i in -MAX_LENGTH..=MAX_LENGTH {
             if i == 0 {
                 continue;
             }
@@ -358,9 +418,10 @@ impl TunnelBlock {
             }
             TunnelType::Receiving(..) => {
                 let mut item = self_blk.1.take_item(0)?;
-                let vec = meta.position.add_directional(&meta.direction, 1);
 
-                if let Some((other_blk, other_meta)) = world.get_block_at_mut(vec.x, vec.y) {
+                if let Some((other_blk, other_meta)) =
+                    world.neighbor_mut(meta.position, meta.direction)
+                {
                     item = other_blk.push(meta.direction.opposite(), item, other_meta)?;
                 }
 
@@ -388,3 +449,53 @@ impl TunnelBlock {
 }
 
 pub static TUNNEL_OVERLAY: InitializedData<Texture2D> = InitializedData::new();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::serialization::Buffer;
+
+    #[test]
+    fn entrances_and_exits_look_different() {
+        let position = Vec2i::new(2, 2);
+        let entrance = TunnelType::Pushing(Vec2i::new(2, 6));
+        let exit = TunnelType::Receiving(Vec2i::new(-1, 2));
+        let unlinked = TunnelType::None;
+
+        assert_eq!(entrance.overlay_tint(), Color::LIME);
+        assert_eq!(exit.overlay_tint(), Color::ORANGE);
+        assert_eq!(unlinked.overlay_tint(), Color::WHITE);
+
+        assert_eq!(entrance.overlay_direction(Direction::East), Direction::East);
+        assert_eq!(exit.overlay_direction(Direction::East), Direction::West);
+        assert_eq!(unlinked.overlay_direction(Direction::East), Direction::East);
+
+        assert_eq!(entrance.length_label(position).as_deref(), Some("4/7"));
+        assert_eq!(exit.length_label(position).as_deref(), Some("3/7"));
+        assert_eq!(unlinked.length_label(position), None);
+    }
+
+    #[test]
+    fn tunnel_ends_round_trip() {
+        let ends = [
+            TunnelType::Pushing(Vec2i::new(3, -4)),
+            TunnelType::Receiving(Vec2i::new(-7, 2)),
+            TunnelType::None,
+        ];
+        for end in ends {
+            let mut buf = Vec::new();
+            end.serialize(&mut buf);
+            assert_eq!(buf.len(), end.required_length(), "{:?}", end);
+
+            let mut buffer = Buffer::new(buf);
+            let read = TunnelType::try_deserialize(&mut buffer).expect("the end reads back");
+            assert_eq!(buffer.len(), 0);
+            match (&end, &read) {
+                (TunnelType::Pushing(a), TunnelType::Pushing(b))
+                | (TunnelType::Receiving(a), TunnelType::Receiving(b)) => assert_eq!(a, b),
+                (TunnelType::None, TunnelType::None) => {}
+                _ => panic!("{:?} read back as {:?}", end, read),
+            }
+        }
+    }
+}
@@ -1,17 +1,32 @@
-use std::time::{Duration, Instant};
+use std::{
+    collections::{hash_map::DefaultHasher, VecDeque},
+    hash::{Hash, Hasher},
+    sync::atomic::{AtomicBool, AtomicU32, Ordering},
+    time::{Duration, Instant},
+};
 
 use crate::{
     assets::update_textures,
-    blocks::{empty_block, Block, BLOCK_EMPTY},
+    blocks::{
+        conveyor::is_conveyor, empty_block, get_block_by_id, lane_conveyor::BLOCK_LANE_CONVEYOR, Block, BLOCK_EMPTY,
+    },
+    identifier::Identifier,
+    input::InputState,
     inventory::{Inventory, NUM_SLOTS_PLAYER},
+    items::{BlockItem, Item, Stack},
+    keybinds::{key_bindings, key_name},
     notice_board::{self, NoticeboardEntryRenderable},
-    scheduler::{get_tasks, schedule_task, Task},
+    scheduler::{self, get_tasks, schedule_task, schedule_tasks, Task},
     screens::{
-        close_screen, CurrentScreen, EscapeScreen, PlayerInventoryScreen, ScreenDimensions, SelectorScreen
+        close_screen, draw_tooltip_box, CurrentScreen, EscapeScreen, IdentifierDebugScreen, PlayerInventoryScreen, ScreenDimensions, SelectorScreen
     },
     serialization::{self, Deserialize, SerializationTrap, Serialize},
-    world::{ChunkBlockMetadata, Direction, Vec2i, World, BLOCK_DEFAULT_H, BLOCK_DEFAULT_W},
-    RenderFn, RENDER_STEP,
+    world::{
+        item_audit, take_item_delta, ChunkBlockMetadata, Direction, Vec2i, World, BLOCK_DEFAULT_H,
+        BLOCK_DEFAULT_W,
+    },
+    log_warn,
+    set_render_step, RenderFn,
 };
 use raylib::{
     color::Color,
@@ -19,22 +34,337 @@ use raylib::{
     math::{Rectangle, Vector2},
     RaylibHandle,
 };
-use raylib::{drawing::RaylibDraw, ffi::KeyboardKey};
+use raylib::{
+    drawing::RaylibDraw,
+    ffi::KeyboardKey,
+};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum RenderLayer {
     Block,
     OverlayItems,
     Preview,
+    /// the direction arrows of logistics blocks, toggled with F4. Blocks don't draw anything themselves here
+    FlowArrows,
+}
+
+/// Layers are drawn in order, each one as a full pass over every visible block, so anything drawn in
+/// `OverlayItems` always ends up on top of the `Block` layer of every block, regardless of chunk borders.
+/// Previews (selector, notice board, held block, ...) draw `PREVIEW_LAYERS` instead, which swaps `Block`
+/// for `Preview` and then draws the same overlay layers.
+pub const RENDER_LAYERS: [RenderLayer; 2] = [RenderLayer::Block, RenderLayer::OverlayItems];
+pub const PREVIEW_LAYERS: [RenderLayer; 2] = [RenderLayer::Preview, RenderLayer::OverlayItems];
+
+static SNAP_MOVEMENT: AtomicBool = AtomicBool::new(false);
+
+/// whether the player moves a whole block per key press instead of freely
+pub fn snap_movement() -> bool {
+    SNAP_MOVEMENT.load(Ordering::Relaxed)
+}
+
+pub fn set_snap_movement(enabled: bool) {
+    SNAP_MOVEMENT.store(enabled, Ordering::Relaxed);
+}
+
+static SMART_ORIENT: AtomicBool = AtomicBool::new(false);
+
+/// whether belts placed next to a belt continue its direction, and blocks with a preferred direction face their
+/// neighbors
+pub fn smart_orient() -> bool {
+    SMART_ORIENT.load(Ordering::Relaxed)
+}
+
+pub fn set_smart_orient(enabled: bool) {
+    SMART_ORIENT.store(enabled, Ordering::Relaxed);
 }
 
-impl RenderLayer {
-    pub fn default_preview() -> Self {
-        Self::Preview
+/// The direction a belt should face to continue the belt the cursor came from. `approach` is the direction the
+/// cursor moved in. Only belts in line with the movement are continued, a belt running sideways gives None
+pub fn continue_belt_direction(neighbor: Direction, approach: Direction) -> Option<Direction> {
+    if neighbor == approach || neighbor == approach.opposite() {
+        Some(neighbor)
+    } else {
+        None
     }
 }
 
-pub const RENDER_LAYERS: [RenderLayer; 2] = [RenderLayer::Block, RenderLayer::OverlayItems];
+fn is_belt(blk: &dyn Block) -> bool {
+    let id = blk.identifier();
+    is_conveyor(id) || id == *BLOCK_LANE_CONVEYOR
+}
+
+/// The direction after turning the mouse wheel by `wheel`. Nothing changes while the orientation is locked
+pub fn wheel_rotation(direction: Direction, wheel: f32, locked: bool) -> Direction {
+    if locked || wheel == 0.0 {
+        direction
+    } else {
+        direction.next(wheel > 0.0)
+    }
+}
+
+/// Moves the player by one frame of input. The stick always moves freely, the movement keys move a whole block per
+/// press when `snap` is on
+pub fn move_player(config: &mut GameConfig, input: &InputState, dt: f64, snap: bool) {
+    let speed = (dt * 0.8) as f32 * if input.boost { 1.5 } else { 1.0 };
+    let mut direction = input.stick * speed;
+    if !snap {
+        direction += input.keys * speed;
+    } else if input.step.x != 0 || input.step.y != 0 {
+        config.player.x = snap_to_grid(config.player.x, config.block_size) + input.step.x * config.block_size as i32;
+        config.player.y = snap_to_grid(config.player.y, config.block_size) + input.step.y * config.block_size as i32;
+    }
+    config.player.x += direction.x as i32;
+    config.player.y += direction.y as i32;
+}
+
+/// Rounds `pos` to the nearest multiple of `step`, works the same for negative positions
+pub fn snap_to_grid(pos: i32, step: u32) -> i32 {
+    let off = pos.rem_euclid(step as i32);
+    if off * 2 < step as i32 {
+        pos - off
+    } else {
+        pos - off + step as i32
+    }
+}
+
+/// The block under the cursor at the screen position `cursor`, with the player offset `player` and blocks of
+/// `blk_w`x`blk_h` pixels
+pub fn block_under_cursor(cursor: Vec2i, player: Vec2i, blk_w: u32, blk_h: u32) -> Vec2i {
+    let (x, y) = (cursor.x + player.x, cursor.y + player.y);
+    let mut block = Vec2i::new(x / blk_w as i32, y / blk_h as i32);
+    if x < 0 {
+        block.x -= 1;
+    }
+    if y < 0 {
+        block.y -= 1;
+    }
+    block
+}
+
+/// A small padlock, 16x20 pixels
+fn draw_lock(d: &mut RaylibDrawHandle, x: i32, y: i32) {
+    d.draw_rectangle_lines_ex(Rectangle::new((x + 3) as f32, y as f32, 10.0, 12.0), 2, Color::BLACK);
+    d.draw_rectangle(x, y + 8, 16, 12, Color::GOLD);
+    d.draw_rectangle_lines(x, y + 8, 16, 12, Color::BLACK);
+}
+
+fn draw_inventory_bar(d: &mut RaylibDrawHandle, inventory: &Inventory, x: i32, y: i32) {
+    let used = inventory.used_slots();
+    let total = inventory.size().max(1);
+    let color = if inventory.free_slots() == 0 {
+        Color::RED
+    } else {
+        Color::DARKGREEN
+    };
+
+    d.draw_rectangle(x, y, 100, 20, Color::LIGHTGRAY.fade(0.5));
+    d.draw_rectangle(x, y, (100 * used / total) as i32, 20, color.fade(0.75));
+    d.draw_rectangle_lines(x, y, 100, 20, Color::BLACK);
+    d.draw_text(
+        format!("{used}/{}", inventory.size()).as_str(),
+        x + 105,
+        y,
+        20,
+        color,
+    );
+}
+
+/// The most recent frame and world update times in milliseconds, for the performance overlay toggled with F7
+#[derive(Default)]
+struct PerfHistory {
+    frames: VecDeque<f32>,
+    updates: VecDeque<f32>,
+}
+
+impl PerfHistory {
+    const FRAMES: usize = 120;
+    const UPDATES: usize = 40;
+    const WIDTH: i32 = 240;
+    const HEIGHT: i32 = 80;
+    /// the time at the top of the graph, longer frames get cut off
+    const MAX_MS: f32 = 100.0;
+
+    fn push_frame(&mut self, ms: f32) {
+        if self.frames.len() >= Self::FRAMES {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(ms);
+    }
+
+    fn push_update(&mut self, ms: f32) {
+        if self.updates.len() >= Self::UPDATES {
+            self.updates.pop_front();
+        }
+        self.updates.push_back(ms);
+    }
+
+    fn graph_y(y: i32, ms: f32) -> f32 {
+        (y + Self::HEIGHT) as f32 - ms.min(Self::MAX_MS) / Self::MAX_MS * Self::HEIGHT as f32
+    }
+
+    /// Draws the frame times as bars and the update times as a line over them, oldest on the left. The dashed
+    /// line marks the time of a tick, updates above it make the world fall behind
+    fn draw(&self, d: &mut RaylibDrawHandle, x: i32, y: i32) {
+        d.draw_rectangle(x, y, Self::WIDTH, Self::HEIGHT, Color::BLACK.fade(0.6));
+
+        let bar_w = Self::WIDTH / Self::FRAMES as i32;
+        for (i, &ms) in self.frames.iter().enumerate() {
+            let top = Self::graph_y(y, ms) as i32;
+            let color = if ms > MSPT as f32 { Color::RED } else { Color::LIME };
+            d.draw_rectangle(x + i as i32 * bar_w, top, bar_w, y + Self::HEIGHT - top, color.fade(0.8));
+        }
+
+        let step = Self::WIDTH as f32 / (Self::UPDATES - 1) as f32;
+        let points: Vec<Vector2> = self
+            .updates
+            .iter()
+            .enumerate()
+            .map(|(i, &ms)| Vector2::new(x as f32 + i as f32 * step, Self::graph_y(y, ms)))
+            .collect();
+        for line in points.windows(2) {
+            d.draw_line_ex(line[0], line[1], 2.0, Color::ORANGE);
+        }
+
+        let tick_y = Self::graph_y(y, MSPT as f32) as i32;
+        for dash_x in (x..x + Self::WIDTH).step_by(8) {
+            d.draw_line(dash_x, tick_y, dash_x + 4, tick_y, Color::WHITE);
+        }
+        d.draw_rectangle_lines(x, y, Self::WIDTH, Self::HEIGHT, Color::DARKGRAY);
+
+        let last = |times: &VecDeque<f32>| times.back().copied().unwrap_or_default();
+        d.draw_text(
+            format!("Frame: {:.1}ms | Update: {:.2}ms", last(&self.frames), last(&self.updates)).as_str(),
+            x,
+            y + Self::HEIGHT + 2,
+            10,
+            Color::DARKGREEN,
+        );
+    }
+}
+
+/// Warns when the items in the world and the player's inventory don't add up to `before` plus what got recorded
+fn check_item_audit(before: u64, world: &World, config: &GameConfig) {
+    let expected = before as i64 + take_item_delta();
+    let after = (world.total_item_count() + config.inventory.total_items()) as i64;
+    if after != expected {
+        log_warn!("Item audit: expected {expected} items after this tick, found {after}");
+    }
+}
+
+/// Runs the scheduled block updates right away and leaves every other task for the start of the next frame
+fn run_world_tasks(world: &mut World, config: &mut GameConfig) {
+    let items_before = item_audit().then(|| world.total_item_count() + config.inventory.total_items());
+    schedule_tasks(world.run_tasks(get_tasks(), config));
+    if let Some(before) = items_before {
+        check_item_audit(before, world, config);
+    }
+}
+
+/// Draws the hotbar centered at the bottom of the screen, with the slot of the block being built outlined
+fn draw_hotbar(d: &mut RaylibDrawHandle, config: &GameConfig, screen: &ScreenDimensions) {
+    const SLOT_SIZE: i32 = 48;
+    const SLOT_GAP: i32 = 6;
+    let width = HOTBAR_SLOTS as i32 * (SLOT_SIZE + SLOT_GAP) - SLOT_GAP;
+    let y = screen.height - SLOT_SIZE - 20;
+
+    for (i, slot) in config.hotbar.iter().enumerate() {
+        let x = (screen.width - width) / 2 + i as i32 * (SLOT_SIZE + SLOT_GAP);
+        d.draw_rectangle(x, y, SLOT_SIZE, SLOT_SIZE, Color::LIGHTGRAY.fade(0.5));
+        if let Some(blk) = slot {
+            blk.render_preview(d, x + 4, y + 4, SLOT_SIZE - 8, SLOT_SIZE - 8, ChunkBlockMetadata::default());
+        }
+        let selected = config.interaction_mode == InteractionMode::Building
+            && slot.is_some_and(|blk| blk.identifier() == config.current_selected_block.identifier());
+        let rect = Rectangle::new(x as f32, y as f32, SLOT_SIZE as f32, SLOT_SIZE as f32);
+        if selected {
+            d.draw_rectangle_lines_ex(rect, 3, Color::BLACK);
+        } else {
+            d.draw_rectangle_lines_ex(rect, 1, Color::DARKGRAY);
+        }
+        d.draw_text(format!("{}", i + 1).as_str(), x + 3, y + 2, 10, Color::BLACK);
+    }
+}
+
+/// A color that stays the same for every block of a type, so the minimap shows different buildings apart
+fn minimap_color(id: Identifier) -> Color {
+    let mut hasher = DefaultHasher::new();
+    id.hash(&mut hasher);
+    Color::color_from_hsv((hasher.finish() % 360) as f32, 0.7, 0.9)
+}
+
+/// Draws every placed block of the loaded chunks into the top right corner, scaled so all of them fit, and marks the
+/// block in the middle of the screen
+fn draw_minimap(d: &mut RaylibDrawHandle, world: &World, config: &GameConfig, screen: &ScreenDimensions) {
+    const SIZE: i32 = 160;
+    const MARGIN: i32 = 10;
+    // blocks never get drawn bigger than this, so a small base doesn't fill the whole map with a few squares
+    const MAX_BLOCK_PIXELS: f32 = 6.0;
+    let x = screen.width - SIZE - MARGIN;
+    let y = MARGIN;
+
+    let player = Vec2i::new(
+        (config.player.x + screen.width / 2).div_euclid(config.block_size as i32),
+        (config.player.y + screen.height / 2).div_euclid(config.block_size as i32),
+    );
+    let blocks = || {
+        world
+            .chunks
+            .values()
+            .flat_map(|chunk| chunk.blocks.iter())
+            .filter(|blk| !blk.is_none())
+    };
+    let (mut min, mut max) = (player, player);
+    for blk in blocks() {
+        let pos = blk.position();
+        min = Vec2i::new(min.x.min(pos.x), min.y.min(pos.y));
+        max = Vec2i::new(max.x.max(pos.x), max.y.max(pos.y));
+    }
+    let span = (max.x - min.x + 1).max(max.y - min.y + 1);
+    let scale = (SIZE as f32 / span as f32).min(MAX_BLOCK_PIXELS);
+    let pixel = (scale.ceil() as i32).max(1);
+    // center the used area inside the map
+    let off_x = x + (SIZE - ((max.x - min.x + 1) as f32 * scale) as i32) / 2;
+    let off_y = y + (SIZE - ((max.y - min.y + 1) as f32 * scale) as i32) / 2;
+    let to_map = |pos: Vec2i| {
+        (
+            off_x + ((pos.x - min.x) as f32 * scale) as i32,
+            off_y + ((pos.y - min.y) as f32 * scale) as i32,
+        )
+    };
+
+    d.draw_rectangle(x, y, SIZE, SIZE, Color::BLACK.fade(0.6));
+    for blk in blocks() {
+        let (px, py) = to_map(blk.position());
+        d.draw_rectangle(px, py, pixel, pixel, minimap_color(blk.identifier()));
+    }
+    let (px, py) = to_map(player);
+    d.draw_circle(px + pixel / 2, py + pixel / 2, 3.0, Color::WHITE);
+    d.draw_rectangle_lines(x, y, SIZE, SIZE, Color::DARKGRAY);
+}
+
+/// The title and text of the tooltip of a placed block: its description and the sides items can be pushed or pulled
+/// through, to see why two blocks don't connect
+fn block_tooltip(block: &dyn Block, meta: ChunkBlockMetadata) -> (String, String) {
+    let sides = |capable: &dyn Fn(Direction) -> bool| {
+        let sides: Vec<String> = Direction::all()
+            .into_iter()
+            .filter(|&side| capable(side))
+            .map(|side| format!("{side:?}"))
+            .collect();
+        if sides.is_empty() {
+            "none".to_string()
+        } else {
+            sides.join(", ")
+        }
+    };
+    let push = sides(&|side| block.has_capability_push(side, meta));
+    let pull = sides(&|side| block.has_capability_pull(side, meta));
+
+    (
+        block.name().to_string(),
+        format!("{}\nPush: {push}\nPull: {pull}", block.description()),
+    )
+}
 
 fn make_abs(val: i32) -> u32 {
     if val >= 0 {
@@ -51,26 +381,91 @@ pub struct GameConfig {
     pub inventory: Inventory,
     pub player: Vec2i,
     pub interaction_mode: InteractionMode,
+    /// the world was loaded from a read-only save
+    pub read_only: bool,
+    /// building doesn't use up block items. Toggled with F6
+    pub creative: bool,
+    /// blocks selected with the number keys; a block gets put into a slot by holding the key while picking it in the
+    /// selector
+    pub hotbar: [Option<&'static Box<dyn Block>>; HOTBAR_SLOTS],
+    /// width and height of a block on screen in pixels, changed by zooming. `player` is measured at this size, saves
+    /// store it at `BLOCK_DEFAULT_W`
+    pub block_size: u32,
 }
 
-#[derive(Debug, Clone)]
+pub const MIN_BLOCK_SIZE: u32 = 16;
+pub const MAX_BLOCK_SIZE: u32 = 96;
+const ZOOM_STEP: u32 = 8;
+
+pub const HOTBAR_SLOTS: usize = 9;
+pub const HOTBAR_KEYS: [KeyboardKey; HOTBAR_SLOTS] = [
+    KeyboardKey::KEY_ONE,
+    KeyboardKey::KEY_TWO,
+    KeyboardKey::KEY_THREE,
+    KeyboardKey::KEY_FOUR,
+    KeyboardKey::KEY_FIVE,
+    KeyboardKey::KEY_SIX,
+    KeyboardKey::KEY_SEVEN,
+    KeyboardKey::KEY_EIGHT,
+    KeyboardKey::KEY_NINE,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum InteractionMode {
     None,
     Building,
     Dismantling,
+    /// drag-selecting a rectangular region of blocks
+    Selecting,
+}
+
+impl Serialize for InteractionMode {
+    fn required_length(&self) -> usize {
+        1
+    }
+    fn serialize(&self, buf: &mut Vec<u8>) {
+        (*self as u8).serialize(buf)
+    }
+}
+
+impl Deserialize for InteractionMode {
+    fn try_deserialize(
+        buf: &mut serialization::Buffer,
+    ) -> Result<Self, serialization::SerializationError> {
+        Ok(Self::from(u8::try_deserialize(buf)?))
+    }
+}
+
+impl From<u8> for InteractionMode {
+    fn from(value: u8) -> Self {
+        match value {
+            1 => Self::Building,
+            2 => Self::Dismantling,
+            3 => Self::Selecting,
+            _ => Self::None,
+        }
+    }
 }
 
 impl Serialize for GameConfig {
     fn required_length(&self) -> usize {
         SerializationTrap::required_length()
             + self.inventory.required_length()
-            + self.player.required_length()
+            + self.saved_player().required_length()
+            + self.hotbar_identifiers().required_length()
+            + self.selected_identifier().required_length()
+            + self.direction.required_length()
+            + self.interaction_mode.required_length()
     }
 
     fn serialize(&self, buf: &mut Vec<u8>) {
         SerializationTrap::GameCfg.serialize(buf);
-        self.player.serialize(buf);
+        self.saved_player().serialize(buf);
         self.inventory.serialize(buf);
+        self.hotbar_identifiers().serialize(buf);
+        self.selected_identifier().serialize(buf);
+        self.direction.serialize(buf);
+        self.interaction_mode.serialize(buf);
     }
 }
 
@@ -78,15 +473,7 @@ impl Deserialize for GameConfig {
     fn try_deserialize(
         buf: &mut serialization::Buffer,
     ) -> Result<Self, serialization::SerializationError> {
-        SerializationTrap::GameCfg.try_deserialize(buf)?;
-        let player = Vec2i::try_deserialize(buf)?;
-        let inventory = Inventory::try_deserialize(buf)?;
-
-        Ok(Self {
-            player,
-            inventory,
-            ..Self::default()
-        })
+        Self::try_deserialize_with_version(buf, serialization::CURRENT_SAVE_VERSION)
     }
 }
 
@@ -98,27 +485,358 @@ impl GameConfig {
             inventory: Inventory::new(NUM_SLOTS_PLAYER, true),
             player: Vec2i::ZERO,
             interaction_mode: InteractionMode::None,
+            read_only: false,
+            // nothing hands out block items yet, so without creative mode there would be nothing to build with
+            creative: true,
+            hotbar: [None; HOTBAR_SLOTS],
+            block_size: BLOCK_DEFAULT_W,
+        }
+    }
+
+    /// The player position at the default zoom level, which is what saves store
+    fn saved_player(&self) -> Vec2i {
+        Vec2i::new(
+            self.player.x * BLOCK_DEFAULT_W as i32 / self.block_size as i32,
+            self.player.y * BLOCK_DEFAULT_H as i32 / self.block_size as i32,
+        )
+    }
+
+    /// Zooms in (`wheel` > 0) or out, keeping the world position in the middle of the screen where it is
+    pub fn zoom(&mut self, wheel: f32, screen: &ScreenDimensions) {
+        let size = if wheel > 0.0 {
+            (self.block_size + ZOOM_STEP).min(MAX_BLOCK_SIZE)
+        } else {
+            self.block_size.saturating_sub(ZOOM_STEP).max(MIN_BLOCK_SIZE)
+        };
+        if size == self.block_size {
+            return;
+        }
+
+        let (half_w, half_h) = (screen.width / 2, screen.height / 2);
+        self.player.x = (self.player.x + half_w) * size as i32 / self.block_size as i32 - half_w;
+        self.player.y = (self.player.y + half_h) * size as i32 / self.block_size as i32 - half_h;
+        self.block_size = size;
+    }
+
+    /// What escape does in the game: drops the selected block and leaves building, dismantling or selecting.
+    /// Returns false if there was nothing to cancel, which opens the escape menu instead
+    pub fn cancel_interaction(&mut self) -> bool {
+        if self.current_selected_block.is_none() && self.interaction_mode == InteractionMode::None {
+            return false;
+        }
+        self.current_selected_block = empty_block();
+        self.interaction_mode = InteractionMode::None;
+        true
+    }
+
+    fn hotbar_identifiers(&self) -> Vec<Option<Identifier>> {
+        self.hotbar.iter().map(|slot| slot.map(|blk| blk.identifier())).collect()
+    }
+
+    /// `None` when nothing is selected
+    fn selected_identifier(&self) -> Option<Identifier> {
+        let id = self.current_selected_block.identifier();
+        (id != empty_block().identifier()).then_some(id)
+    }
+
+    /// Reads a config written by a save of `version`
+    pub fn try_deserialize_with_version(
+        buf: &mut serialization::Buffer,
+        version: u16,
+    ) -> Result<Self, serialization::SerializationError> {
+        SerializationTrap::GameCfg.try_deserialize(buf)?;
+        let player = Vec2i::try_deserialize(buf)?;
+        let inventory = Inventory::try_deserialize(buf)?;
+
+        let mut hotbar = [None; HOTBAR_SLOTS];
+        if version > serialization::LEGACY_SAVE_VERSION {
+            let ids = <Vec<Option<Identifier>>>::try_deserialize(buf)?;
+            // blocks that don't exist anymore leave their slot empty
+            for (slot, id) in hotbar.iter_mut().zip(ids) {
+                *slot = id.and_then(get_block_by_id);
+            }
+        }
+
+        let mut config = Self {
+            player,
+            inventory,
+            hotbar,
+            ..Self::default()
+        };
+        if version > serialization::LEGACY_SAVE_VERSION {
+            // a block that doesn't exist anymore leaves nothing selected
+            if let Some(blk) = <Option<Identifier>>::try_deserialize(buf)?.and_then(get_block_by_id) {
+                config.current_selected_block = blk;
+            }
+            config.direction = Direction::try_deserialize(buf)?;
+            config.interaction_mode = InteractionMode::try_deserialize(buf)?;
+        }
+
+        Ok(config)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuildError {
+    /// there already is a block, or the position is outside of the world
+    Occupied,
+    /// the player doesn't have a block item for the selected block
+    MissingItems,
+}
+
+/// Places the selected block at `pos`, using up one of its block items unless the player is in creative mode
+pub fn try_build(
+    config: &mut GameConfig,
+    world: &mut World,
+    pos: Vec2i,
+    direction: Direction,
+) -> Result<(), BuildError> {
+    if !world
+        .get_block_at(pos.x, pos.y)
+        .map(|(blk, _)| blk.is_none())
+        .unwrap_or(false)
+    {
+        return Err(BuildError::Occupied);
+    }
+    let id = config.current_selected_block.identifier();
+    if !config.creative && !config.inventory.remove(id, 1) {
+        return Err(BuildError::MissingItems);
+    }
+
+    let mut blk = config.current_selected_block.clone_block();
+    blk.on_before_place(ChunkBlockMetadata::new(direction, pos), world);
+    world.set_block_at(pos.x, pos.y, blk, direction);
+    Ok(())
+}
+
+/// One of the item that places `block`
+fn block_item(block: Box<dyn Block>) -> Stack {
+    Stack::from(Box::new(BlockItem::new(1, block)) as Box<dyn Item>)
+}
+
+/// Gives the block item of a dismantled block back, outside of creative mode building used one up
+fn refund_block(config: &mut GameConfig, block: Box<dyn Block>) {
+    if !config.creative {
+        config.inventory.try_add_item(block_item(block));
+    }
+}
+
+/// The direction and settings of a block, copied with the middle mouse button and pasted onto other blocks by
+/// clicking them while holding the modifier
+struct CopiedConfig {
+    id: Identifier,
+    direction: Direction,
+    config: Option<Vec<u8>>,
+}
+
+/// how many building and dismantling actions Ctrl+Z can take back
+const UNDO_LIMIT: usize = 64;
+
+/// A building or dismantling action of the player
+enum UndoEntry {
+    /// a block was built at `pos`; `consumed` is whether that used up a block item
+    Placed { pos: Vec2i, id: Identifier, consumed: bool },
+    /// a block was dismantled. It is kept the way it was, with the items it dropped into the player inventory still
+    /// in it, and `refunded` is whether its block item was given back
+    Dismantled { block: Box<dyn Block>, meta: ChunkBlockMetadata, refunded: bool },
+}
+
+/// The last `UNDO_LIMIT` actions, the oldest ones get forgotten
+#[derive(Default)]
+struct UndoStack(VecDeque<UndoEntry>);
+
+impl UndoStack {
+    fn push(&mut self, entry: UndoEntry) {
+        if self.0.len() >= UNDO_LIMIT {
+            self.0.pop_front();
+        }
+        self.0.push_back(entry);
+    }
+
+    /// Builds a block and remembers it, see `try_build`
+    fn build(
+        &mut self,
+        config: &mut GameConfig,
+        world: &mut World,
+        pos: Vec2i,
+        direction: Direction,
+    ) -> Result<(), BuildError> {
+        let consumed = !config.creative;
+        try_build(config, world, pos, direction)?;
+        self.push(UndoEntry::Placed {
+            pos,
+            id: config.current_selected_block.identifier(),
+            consumed,
+        });
+        Ok(())
+    }
+
+    /// Dismantles the block at `pos` and remembers it, giving its items and block item to the player
+    fn dismantle(&mut self, config: &mut GameConfig, world: &mut World, pos: Vec2i) {
+        let Some((mut blk, meta)) = world.destroy_block_at(pos.x, pos.y, &mut config.inventory) else {
+            return;
+        };
+        self.push(UndoEntry::Dismantled {
+            block: blk.clone_block(),
+            meta,
+            refunded: !config.creative,
+        });
+        blk.on_after_dismantle(meta, world);
+        refund_block(config, blk);
+    }
+
+    /// Takes back the last action that can still be taken back. Actions whose block got changed since are skipped,
+    /// one that gives back more than fits into the inventory stays on the stack
+    fn undo(&mut self, config: &mut GameConfig, world: &mut World) -> Result<(), UndoError> {
+        while let Some(entry) = self.0.pop_back() {
+            match undo_entry(entry, config, world) {
+                Ok(()) => return Ok(()),
+                Err(None) => continue,
+                Err(Some(entry)) => {
+                    self.0.push_back(entry);
+                    return Err(UndoError::InventoryFull);
+                }
+            }
+        }
+        Err(UndoError::NothingLeft)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum UndoError {
+    /// every action was taken back already or can't be anymore
+    NothingLeft,
+    /// the player inventory has no room for what taking back the last action gives back
+    InventoryFull,
+}
+
+/// Takes back `entry`. Fails with None if its block got changed since, and gives the entry back if the inventory
+/// has no room for what it would give back
+fn undo_entry(entry: UndoEntry, config: &mut GameConfig, world: &mut World) -> Result<(), Option<UndoEntry>> {
+    match entry {
+        UndoEntry::Placed { pos, id, consumed } => {
+            let Some((placed, _)) = world.get_block_at(pos.x, pos.y).filter(|(blk, _)| blk.identifier() == id)
+            else {
+                return Err(None);
+            };
+            // the items in the block and its block item go into the inventory, none of them may get lost
+            let mut items = placed.destroy_items();
+            if consumed {
+                items.push(block_item(placed.clone_block()));
+            }
+            let mut inventory = config.inventory.clone();
+            if !items.into_iter().all(|item| inventory.try_add_item(item).is_none()) {
+                return Err(Some(entry));
+            }
+            let Some((mut blk, meta)) = world.destroy_block_at(pos.x, pos.y, &mut config.inventory) else {
+                return Err(None);
+            };
+            blk.on_after_dismantle(meta, world);
+            if consumed {
+                config.inventory.try_add_item(block_item(blk));
+            }
+            Ok(())
+        }
+        UndoEntry::Dismantled { mut block, meta, refunded } => {
+            let pos = meta.position;
+            if !world
+                .get_block_at(pos.x, pos.y)
+                .map(|(blk, _)| blk.is_none())
+                .unwrap_or(false)
+            {
+                return Err(None);
+            }
+            // the block still holds its items, so the copies it dropped have to come out of the inventory again.
+            // If some of them are gone already the block can't come back without duplicating them
+            let mut inventory = config.inventory.clone();
+            let mut items = block.destroy_items();
+            if refunded {
+                items.push(block_item(block.clone_block()));
+            }
+            if !items
+                .iter()
+                .all(|item| inventory.remove(item.identifier(), item.stack_count()))
+            {
+                return Err(None);
+            }
+            config.inventory = inventory;
+            block.on_before_place(meta, world);
+            world.set_block_at(pos.x, pos.y, block, meta.direction);
+            Ok(())
         }
     }
 }
 
 pub const TPS: u32 = 20;
 pub const MSPT: u128 = (1000 / TPS) as u128;
+pub const TICK_DURATION: Duration = Duration::from_millis(MSPT as u64);
+/// the most ticks run in one frame to catch up after a slow one. Time beyond that is dropped, so a world that takes
+/// longer than a tick to update slows down instead of falling further behind every frame
+const MAX_TICKS_PER_FRAME: u32 = 5;
 
-macro_rules! lerp_step {
-    ($lerp: expr, $step: expr, $num_steps: expr) => {{
-        let _ = $lerp / 1.0_f32;
-        let _ = $step / 1.0_f32;
-        let _ = $num_steps / 1.0_f32;
-        let computed_step_off: f32 = 1.0 / $num_steps * $step;
-        if $lerp < computed_step_off {
-            0.0
-        } else if $lerp >= 1.0 / $num_steps * ($step + 1.0) {
-            1.0
-        } else {
-            (($lerp - computed_step_off) * $num_steps)
+pub const DEFAULT_LOW_TPS_THRESHOLD: u32 = 15;
+/// how long the average tps has to stay below the threshold before the player gets warned
+const LOW_TPS_DURATION: Duration = Duration::from_secs(5);
+/// the number of ticks the average tps is taken over
+const TPS_AVERAGE_SAMPLES: usize = TPS as usize;
+
+static LOW_TPS_THRESHOLD: AtomicU32 = AtomicU32::new(DEFAULT_LOW_TPS_THRESHOLD);
+
+/// the average tps below which the game warns about being overloaded, 0 never warns
+pub fn low_tps_threshold() -> u32 {
+    LOW_TPS_THRESHOLD.load(Ordering::Relaxed)
+}
+
+pub fn set_low_tps_threshold(threshold: u32) {
+    LOW_TPS_THRESHOLD.store(threshold.min(TPS), Ordering::Relaxed);
+}
+
+/// Keeps a rolling average of the tps to notice when the game can't keep up for a while
+#[derive(Debug, Default)]
+pub struct LowTpsDetector {
+    samples: VecDeque<u32>,
+    low_since: Option<Instant>,
+    warned: bool,
+}
+
+impl LowTpsDetector {
+    pub fn average(&self) -> u32 {
+        if self.samples.is_empty() {
+            return TPS;
+        }
+        self.samples.iter().sum::<u32>() / self.samples.len() as u32
+    }
+
+    /// Adds the tps of a tick that happened at `now`. Returns true once the average stayed below `threshold` for
+    /// `LOW_TPS_DURATION`; it only warns again after the average recovered
+    pub fn sample(&mut self, tps: u32, threshold: u32, now: Instant) -> bool {
+        if self.samples.len() >= TPS_AVERAGE_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(tps);
+
+        if self.average() >= threshold {
+            self.low_since = None;
+            self.warned = false;
+            return false;
+        }
+        let low_since = *self.low_since.get_or_insert(now);
+        if self.warned || now.duration_since(low_since) < LOW_TPS_DURATION {
+            return false;
         }
-    }};
+        self.warned = true;
+        true
+    }
+}
+
+/// How far along `step` (of `num_steps` equally long steps) `lerp` is, from 0.0 to 1.0. Steps outside of
+/// `0..num_steps` and `num_steps` of 0 never start
+pub fn lerp_step(lerp: f32, step: f32, num_steps: f32) -> f32 {
+    if num_steps <= 0.0 || step < 0.0 || step >= num_steps {
+        return 0.0;
+    }
+    let step_start = step / num_steps;
+    ((lerp - step_start) * num_steps).clamp(0.0, 1.0)
 }
 
 pub fn run_game(
@@ -129,8 +847,12 @@ pub fn run_game(
 ) {
     world.init();
 
-    let mut last_update = Instant::now();
+    // real time that passed and still has to be simulated in ticks
+    let mut tick_accumulator = Duration::ZERO;
+    let mut last_tick_check = Instant::now();
+    let mut last_residency_update = Instant::now();
     let mut ticks_per_second = 20;
+    let mut low_tps = LowTpsDetector::default();
 
     let mut last_render_start = Instant::now();
     let mut last_screen_size = ScreenDimensions {
@@ -141,18 +863,38 @@ pub fn run_game(
     let mut dismantle_timer: Option<Instant> = None;
     let mut dismantle_timer_start: Option<Instant> = None;
     let mut dismantle_positions: Vec<Vec2i> = Vec::new();
+    // how long the running dismantle takes, in milliseconds
+    let mut dismantle_duration: u64 = 0;
+    // the hovered block and the batch the running dismantle was started on
+    let mut dismantle_target: Option<(Option<Vec2i>, Vec<Vec2i>)> = None;
 
-    let blk_w = BLOCK_DEFAULT_W;
-    let blk_h = BLOCK_DEFAULT_H;
+    let mut selection_start: Option<Vec2i> = None;
+    let mut selection: Option<(Vec2i, Vec2i)> = None;
+    // the cell the cursor was in last frame, to know which side it entered the current one from
+    let mut last_cursor: Option<Vec2i> = None;
+    let mut show_flow_arrows = false;
+    let mut show_minimap = false;
+    let mut show_perf_overlay = false;
+    let mut perf_history = PerfHistory::default();
+    let mut last_failed_build: Option<Vec2i> = None;
+    let mut orientation_locked = false;
+    let mut undo_stack = UndoStack::default();
+    let mut copied_config: Option<CopiedConfig> = None;
+    // the block the copied settings were last pasted onto, so holding the button doesn't paste every frame
+    let mut last_paste: Option<Vec2i> = None;
 
     while !rl.window_should_close() {
         update_textures();
+        let bindings = key_bindings();
+        let blk_w = config.block_size;
+        let blk_h = config.block_size;
 
         let dt = Instant::now().duration_since(last_render_start).as_millis() as f64;
         if dt < 2.0 {
             continue;
         }
         last_render_start = Instant::now();
+        perf_history.push_frame(dt as f32);
 
         let screen_size: ScreenDimensions = ScreenDimensions {
             width: rl.get_screen_width(),
@@ -168,6 +910,10 @@ pub fn run_game(
 
         let tasks = get_tasks();
 
+        // machines only move items around, so apart from what got recorded the count has to stay the same
+        let items_before = item_audit()
+            .then(|| world.total_item_count() + config.inventory.total_items());
+
         // run updates
         let update_start = Instant::now();
         let mut had_gameupdate_scheduled = false;
@@ -180,7 +926,10 @@ pub fn run_game(
 
             match t {
                 // Task::Custom(func) => func(),
-                Task::ExitGame => return,
+                Task::ExitGame => {
+                    set_render_step(RenderFn::Exit);
+                    return;
+                }
                 Task::OpenScreenCentered(screen) => {
                     CurrentScreen::open_centered(screen, &screen_size)
                 }
@@ -189,8 +938,19 @@ pub fn run_game(
                     had_gameupdate_scheduled = true;
                     func(meta, &mut world);
                 }
+                Task::WorldUpdateBlockWithConfig(func, meta) => {
+                    had_gameupdate_scheduled = true;
+                    func(meta, &mut world, &mut config);
+                }
+                Task::WakeBlock(pos) => {
+                    had_gameupdate_scheduled = true;
+                    if let Some((blk, meta)) = world.get_block_at_mut(pos.x, pos.y) {
+                        blk.update(meta);
+                    }
+                }
                 Task::CloseWorld => {
-                    *RENDER_STEP.lock().unwrap() = RenderFn::StartMenu;
+                    scheduler::clear_timed_tasks();
+                    set_render_step(RenderFn::StartMenu);
                     return;
                 }
                 Task::OpenWorld(..) | Task::CreateWorld | Task::__OpnWrld(..) => {
@@ -203,6 +963,9 @@ pub fn run_game(
                 }
             }
         }
+        if let Some(before) = items_before.filter(|_| had_gameupdate_scheduled) {
+            check_item_audit(before, &world, &config);
+        }
         if had_gameupdate_scheduled {
             ticks_per_second = (1000
                 / Instant::now()
@@ -210,89 +973,139 @@ pub fn run_game(
                     .as_millis()
                     .max(1))
             .min(20);
+            if low_tps.sample(ticks_per_second as u32, low_tps_threshold(), Instant::now()) {
+                notice_board::add_entry(
+                    NoticeboardEntryRenderable::String(format!(
+                        "The game can't keep up ({} TPS), try using fewer machines",
+                        low_tps.average()
+                    )),
+                    10,
+                );
+            }
         }
 
         let game_focused = !CurrentScreen::is_screen_open();
+        let input = InputState::read(rl, &bindings);
 
         if game_focused {
-            let mut direction: Vector2 = Vector2::default();
-            if rl.is_key_down(KeyboardKey::KEY_W) {
-                direction.y -= (dt * 0.8) as f32;
-            }
-            if rl.is_key_down(KeyboardKey::KEY_S) {
-                direction.y += (dt * 0.8) as f32;
-            }
-            if rl.is_key_down(KeyboardKey::KEY_A) {
-                direction.x -= (dt * 0.8) as f32;
-            }
-            if rl.is_key_down(KeyboardKey::KEY_D) {
-                direction.x += (dt * 0.8) as f32;
-            }
-            if direction.x != 0.0 && direction.y != 0.0 {
-                direction.x *= 0.7;
-                direction.y *= 0.7;
-            }
-            if rl.is_key_down(KeyboardKey::KEY_LEFT_SHIFT) {
-                direction.x *= 1.5;
-                direction.y *= 1.5;
-            }
-            // if rl.is_key_pressed(KeyboardKey::KEY_ZERO) && is_ctrl!(rl) {
-            //     blk_w = BLOCK_DEFAULT_W;
-            //     blk_h = BLOCK_DEFAULT_H;
-            // }
-            // if rl.is_key_pressed(KeyboardKey::KEY_UP) && is_ctrl!(rl) {
-            //     blk_w += 5;
-            //     blk_h += 5;
-            // }
-            // if rl.is_key_pressed(KeyboardKey::KEY_DOWN) && is_ctrl!(rl) && blk_w > 8 && blk_h > 8 {
-            //     blk_w -= 8;
-            //     blk_h -= 8;
-            // }
-            config.player.x += direction.x as i32;
-            config.player.y += direction.y as i32;
-            if rl.is_key_down(KeyboardKey::KEY_TAB) {
+            // the right stick moves the cursor, which is used for building, dismantling and interacting
+            if input.cursor.x != 0.0 || input.cursor.y != 0.0 {
+                let cursor_pos = rl.get_mouse_position();
+                rl.set_mouse_position(cursor_pos + input.cursor * (dt * 0.8) as f32);
+            }
+            move_player(&mut config, &input, dt, snap_movement());
+            if rl.is_key_pressed(bindings.snap_to_grid) {
+                config.player.x = snap_to_grid(config.player.x, blk_w);
+                config.player.y = snap_to_grid(config.player.y, blk_h);
+            }
+            if input.inventory {
                 CurrentScreen::open_centered(
                     Box::new(PlayerInventoryScreen::default()),
                     &screen_size,
                 );
             }
-            if rl.is_key_pressed(KeyboardKey::KEY_B) {
-                CurrentScreen::open_centered(Box::new(SelectorScreen), &screen_size);
+            if input.block_selector {
+                CurrentScreen::open_centered(Box::new(SelectorScreen::default()), &screen_size);
+            }
+            for (key, slot) in HOTBAR_KEYS.iter().zip(config.hotbar) {
+                if let Some(blk) = slot.filter(|_| rl.is_key_pressed(*key)) {
+                    config.current_selected_block = blk;
+                    config.interaction_mode = InteractionMode::Building;
+                }
+            }
+            if rl.is_key_pressed(bindings.select) {
+                config.interaction_mode = InteractionMode::Selecting;
+                selection = None;
+            }
+            if rl.is_key_pressed(bindings.rotate_selection)
+                && matches!(config.interaction_mode, InteractionMode::Selecting)
+            {
+                if let Some((from, to)) = selection {
+                    let mut cells = Vec::new();
+                    for y in from.y..=to.y {
+                        for x in from.x..=to.x {
+                            cells.push(Vec2i::new(x, y));
+                        }
+                    }
+                    world.rotate_region(&cells, !rl.is_key_down(bindings.modifier));
+                }
+            }
+            if rl.is_key_pressed(KeyboardKey::KEY_F4) {
+                show_flow_arrows = !show_flow_arrows;
             }
-            if rl.is_key_pressed(KeyboardKey::KEY_G) {
+            if rl.is_key_pressed(bindings.minimap) {
+                show_minimap = !show_minimap;
+            }
+            if rl.is_key_pressed(KeyboardKey::KEY_F6) {
+                config.creative = !config.creative;
+                notice_board::add_entry(
+                    NoticeboardEntryRenderable::StringRef(if config.creative {
+                        "Creative mode enabled"
+                    } else {
+                        "Creative mode disabled"
+                    }),
+                    3,
+                );
+            }
+            if rl.is_key_pressed(KeyboardKey::KEY_Z)
+                && (rl.is_key_down(KeyboardKey::KEY_LEFT_CONTROL)
+                    || rl.is_key_down(KeyboardKey::KEY_RIGHT_CONTROL))
+            {
+                match undo_stack.undo(&mut config, &mut world) {
+                    Ok(()) => {}
+                    Err(UndoError::NothingLeft) => {
+                        notice_board::add_entry(NoticeboardEntryRenderable::StringRef("Nothing left to undo"), 2);
+                    }
+                    Err(UndoError::InventoryFull) => {
+                        notice_board::add_entry(
+                            NoticeboardEntryRenderable::StringRef("Your inventory is too full to undo that"),
+                            2,
+                        );
+                    }
+                }
+            }
+            if rl.is_key_pressed(KeyboardKey::KEY_F3) {
+                CurrentScreen::open_centered(Box::new(IdentifierDebugScreen::default()), &screen_size);
+            }
+            if rl.is_key_pressed(KeyboardKey::KEY_F7) {
+                show_perf_overlay = !show_perf_overlay;
+            }
+            if input.dismantle {
                 config.interaction_mode = InteractionMode::Dismantling;
             }
-            if rl.get_mouse_wheel_move() != 0.0 {
-                let right = rl.get_mouse_wheel_move() > 0.0;
-                config.direction = config.direction.next(right);
+            if rl.is_key_pressed(bindings.lock_orientation) {
+                orientation_locked = !orientation_locked;
             }
-        }
-        if rl.is_key_pressed(KeyboardKey::KEY_ESCAPE) {
-            if !game_focused {
-                CurrentScreen::close();
-            } else if !config.current_selected_block.is_none()
-                || matches!(
-                    config.interaction_mode,
-                    InteractionMode::Building | InteractionMode::Dismantling
-                )
+            let wheel = rl.get_mouse_wheel_move();
+            if rl.is_key_down(KeyboardKey::KEY_LEFT_CONTROL)
+                || rl.is_key_down(KeyboardKey::KEY_RIGHT_CONTROL)
             {
-                config.current_selected_block = empty_block();
-                config.interaction_mode = InteractionMode::None;
+                if wheel != 0.0 {
+                    config.zoom(wheel, &screen_size);
+                }
             } else {
+                config.direction = wheel_rotation(config.direction, wheel, orientation_locked);
+            }
+            if let Some(clockwise) = input.rotate {
+                config.direction = config.direction.next(clockwise);
+            }
+        }
+        if input.back {
+            if !game_focused {
+                CurrentScreen::back();
+            } else if !config.cancel_interaction() {
                 CurrentScreen::open_centered(Box::new(EscapeScreen), &screen_size);
             }
         }
 
         let cursor_pos = rl.get_mouse_position();
-        let mut cursor_x = (cursor_pos.x as i32 + config.player.x) / blk_w as i32;
-        let mut cursor_y = (cursor_pos.y as i32 + config.player.y) / blk_h as i32;
-
-        if (cursor_pos.x as i32 + config.player.x) < 0 {
-            cursor_x -= 1;
-        }
-        if (cursor_pos.y as i32 + config.player.y) < 0 {
-            cursor_y -= 1;
-        }
+        let cursor = block_under_cursor(
+            Vec2i::new(cursor_pos.x as i32, cursor_pos.y as i32),
+            config.player,
+            blk_w,
+            blk_h,
+        );
+        let (cursor_x, cursor_y) = (cursor.x, cursor.y);
 
         let mut off_x = config.player.x % blk_w as i32;
         let mut off_y = config.player.y % blk_h as i32;
@@ -300,7 +1113,7 @@ pub fn run_game(
             off_x += blk_w as i32;
         }
         if off_y < 0 {
-            off_y += blk_w as i32;
+            off_y += blk_h as i32;
         }
 
         let overlay_x =
@@ -308,6 +1121,24 @@ pub fn run_game(
         let overlay_y =
             (make_abs(cursor_pos.y as i32 + off_y).wrapping_div(blk_h) * blk_h) as i32 - off_y;
 
+        if smart_orient()
+            && matches!(config.interaction_mode, InteractionMode::Building)
+            && is_belt(&**config.current_selected_block)
+        {
+            let approach = last_cursor
+                .and_then(|last| Direction::from_delta(last, cursor).map(|dir| (last, dir)));
+            if let Some((last, approach)) = approach {
+                if let Some(direction) = world
+                    .get_block_at(last.x, last.y)
+                    .filter(|&(blk, _)| is_belt(&**blk))
+                    .and_then(|(_, meta)| continue_belt_direction(meta.direction, approach))
+                {
+                    config.direction = direction;
+                }
+            }
+        }
+        last_cursor = Some(cursor);
+
         let (can_build, can_dismantle) = {
             let blk = world.get_block_at(cursor_x, cursor_y);
             (
@@ -332,47 +1163,114 @@ pub fn run_game(
             }
         }
 
-        if rl.is_mouse_button_down(raylib::ffi::MouseButton::MOUSE_LEFT_BUTTON) && game_focused {
+        let primary_action_down = input.primary_action;
+
+        if game_focused
+            && rl.is_mouse_button_pressed(raylib::ffi::MouseButton::MOUSE_MIDDLE_BUTTON)
+        {
+            if let Some((blk, meta)) = world.get_block_at(cursor_x, cursor_y).filter(|(blk, _)| !blk.is_none()) {
+                copied_config = Some(CopiedConfig {
+                    id: blk.identifier(),
+                    direction: meta.direction,
+                    config: blk.copy_config(),
+                });
+                notice_board::add_entry(
+                    NoticeboardEntryRenderable::String(format!("Copied the settings of the {}", blk.name())),
+                    2,
+                );
+            }
+        }
+        if game_focused
+            && can_dismantle
+            && config.interaction_mode != InteractionMode::Dismantling
+            && rl.is_mouse_button_pressed(raylib::ffi::MouseButton::MOUSE_RIGHT_BUTTON)
+        {
+            world.rotate_block_at(cursor_x, cursor_y);
+        }
+        let pasting = primary_action_down
+            && game_focused
+            && rl.is_key_down(bindings.modifier)
+            && !matches!(config.interaction_mode, InteractionMode::Dismantling);
+        if !pasting {
+            last_paste = None;
+        }
+
+        if pasting && copied_config.is_some() {
+            if let Some(copied) = copied_config.as_ref().filter(|_| last_paste != Some(cursor)) {
+                if world.paste_config(cursor, copied.id, copied.direction, copied.config.as_deref()) {
+                    last_paste = Some(cursor);
+                }
+            }
+        } else if primary_action_down && game_focused {
             match config.interaction_mode {
+                InteractionMode::Selecting if selection_start.is_none() => {
+                    selection_start = Some(Vec2i::new(cursor_x, cursor_y));
+                }
                 InteractionMode::Building if can_build => {
-                    let mut blk = config.current_selected_block.clone_block();
-                    blk.on_before_place(
-                        ChunkBlockMetadata::new(config.direction, Vec2i::new(cursor_x, cursor_y)),
-                        &mut world,
-                    );
-                    world.set_block_at(cursor_x, cursor_y, blk, config.direction);
+                    let direction = if smart_orient() {
+                        config
+                            .current_selected_block
+                            .preferred_direction(cursor, &world)
+                            .unwrap_or(config.direction)
+                    } else {
+                        config.direction
+                    };
+                    let result = undo_stack.build(&mut config, &mut world, cursor, direction);
+                    // holding the button down tries again every frame, only tell the player once per block
+                    if result == Err(BuildError::MissingItems) && last_failed_build != Some(cursor) {
+                        notice_board::add_entry(
+                            NoticeboardEntryRenderable::String(format!(
+                                "You don't have any {} left",
+                                config.current_selected_block.name()
+                            )),
+                            3,
+                        );
+                        last_failed_build = Some(cursor);
+                    }
                 }
                 InteractionMode::Dismantling if can_dismantle || dismantle_positions.len() > 0 => {
-                    if let Some(timer) = dismantle_timer {
-                        if timer <= Instant::now() {
+                    // moving onto another block or changing the batch starts over, so the time always matches
+                    // the blocks that come down
+                    let target = (can_dismantle.then_some(cursor), dismantle_positions.clone());
+                    if dismantle_target.as_ref() != Some(&target) {
+                        dismantle_timer = None;
+                    }
+                    match dismantle_timer {
+                        Some(timer) if timer <= Instant::now() => {
                             if can_dismantle {
-                                if let Some((mut blk, meta)) = world.destroy_block_at(cursor_x, cursor_y, &mut config.inventory) {
-                                    blk.on_after_dismantle(meta, &mut world);
-                                }
+                                undo_stack.dismantle(&mut config, &mut world, cursor);
                             }
                             for vec in &dismantle_positions {
-                                if let Some((mut blk, meta)) = world.destroy_block_at(vec.x, vec.y, &mut config.inventory) {
-                                    blk.on_after_dismantle(meta, &mut world);
-                                }
+                                undo_stack.dismantle(&mut config, &mut world, *vec);
                             }
                             dismantle_positions.clear();
-                            let mut now = Instant::now();
-                            now += Duration::new(2, 0);
-                            dismantle_timer = Some(now);
+                            // the next one starts once the cursor is on a block again
+                            dismantle_timer = None;
+                            dismantle_timer_start = None;
+                            dismantle_target = None;
+                        }
+                        Some(_) => {}
+                        None => {
+                            // the whole batch comes down at once, so it takes as long as its slowest block
+                            dismantle_duration = target
+                                .0
+                                .iter()
+                                .chain(target.1.iter())
+                                .filter_map(|pos| world.get_block_at(pos.x, pos.y))
+                                .map(|(blk, _)| blk.dismantle_time_ms())
+                                .max()
+                                .unwrap_or(0);
+                            dismantle_timer = Some(Instant::now() + Duration::from_millis(dismantle_duration));
                             dismantle_timer_start = Some(Instant::now());
+                            dismantle_target = Some(target);
                         }
-                    } else {
-                        let mut now = Instant::now();
-                        now += Duration::new(2, 0);
-                        dismantle_timer = Some(now);
-                        dismantle_timer_start = Some(Instant::now());
                     }
                 }
                 _ => {}
             }
         }
         if (dismantle_timer.is_some() || dismantle_timer_start.is_some())
-            && (!rl.is_mouse_button_down(raylib::ffi::MouseButton::MOUSE_LEFT_BUTTON)
+            && (!primary_action_down
                 || !game_focused
                 || !matches!(config.interaction_mode, InteractionMode::Dismantling)
                 || (!can_dismantle && dismantle_positions.len() < 1))
@@ -385,25 +1283,61 @@ pub fn run_game(
         {
             dismantle_positions.clear();
         }
+        if !matches!(config.interaction_mode, InteractionMode::Selecting) {
+            selection_start = None;
+            selection = None;
+        } else if !primary_action_down || !game_focused {
+            if let Some(start) = selection_start.take() {
+                selection = Some((
+                    Vec2i::new(start.x.min(cursor_x), start.y.min(cursor_y)),
+                    Vec2i::new(start.x.max(cursor_x), start.y.max(cursor_y)),
+                ));
+            }
+        }
 
         let mut d = rl.begin_drawing(&thread);
 
         d.clear_background(Color::WHITE);
 
         // schedule updates
-        if Instant::now().duration_since(last_update).as_millis() >= MSPT {
+        let now = Instant::now();
+        tick_accumulator += now.duration_since(last_tick_check);
+        last_tick_check = now;
+        let mut ticks = 0;
+        while tick_accumulator >= TICK_DURATION {
+            // the tasks of the last tick run at the start of the next frame, the ones of catch up ticks have to
+            // run before the world updates again
+            if ticks > 0 {
+                run_world_tasks(&mut world, &mut config);
+            }
             world.update();
+            perf_history.push_update(world.last_update_nanos() as f32 / 1_000_000.0);
+            scheduler::advance_tick();
             schedule_task(Task::WorldUpdateBlock(
                 &|_, _| {},
                 ChunkBlockMetadata::default(),
             ));
             notice_board::update_entries();
-            last_update = Instant::now();
+            tick_accumulator -= TICK_DURATION;
+            ticks += 1;
+            if ticks >= MAX_TICKS_PER_FRAME {
+                tick_accumulator = Duration::ZERO;
+                break;
+            }
+        }
+        if Instant::now().duration_since(last_residency_update).as_millis() >= 1000 {
+            world.update_residency(Vec2i::new(
+                (config.player.x + screen_size.width / 2).div_euclid(blk_w as i32),
+                (config.player.y + screen_size.height / 2).div_euclid(blk_h as i32),
+            ));
+            last_residency_update = Instant::now();
         }
 
+        // blocks rendered this frame over all layers, for the debug text
+        let mut rendered_blocks = 0;
         if screen_size.width >= 0 && screen_size.height >= 0 {
             for l in RENDER_LAYERS {
-                world.render(
+                rendered_blocks += world.render(
                     &mut d,
                     config.player.x,
                     config.player.y,
@@ -414,10 +1348,73 @@ pub fn run_game(
                     blk_h,
                 );
             }
+            if show_flow_arrows {
+                rendered_blocks += world.render(
+                    &mut d,
+                    config.player.x,
+                    config.player.y,
+                    screen_size.width as u32,
+                    screen_size.height as u32,
+                    RenderLayer::FlowArrows,
+                    blk_w,
+                    blk_h,
+                );
+            }
         }
 
+        // debug view: hold N to see which blocks are connected to the hovered one
+        if game_focused && d.is_key_down(bindings.show_network) {
+            let network = world.connected_network(Vec2i::new(cursor_x, cursor_y));
+            let flow = match world.network_flow(&network) {
+                Some(flow) => format!("Flow into sinks: {flow} items/min"),
+                None => "Flow into sinks: nothing reaches a sink".to_string(),
+            };
+            for &pos in &network {
+                let pos = world.get_effective_render_position(pos, config.player, blk_w, blk_h);
+                d.draw_rectangle(
+                    pos.x,
+                    pos.y,
+                    blk_w as i32,
+                    blk_h as i32,
+                    Color::PURPLE.fade(0.35),
+                );
+            }
+            if !network.is_empty() {
+                d.draw_text(flow.as_str(), 20, screen_size.height - 92, 20, Color::PURPLE);
+            }
+        }
+
+        let mut hovered_tooltip = None;
         if game_focused {
             match config.interaction_mode {
+                InteractionMode::Selecting => {
+                    let region = match selection_start {
+                        Some(start) => Some((
+                            Vec2i::new(start.x.min(cursor_x), start.y.min(cursor_y)),
+                            Vec2i::new(start.x.max(cursor_x), start.y.max(cursor_y)),
+                        )),
+                        None => selection,
+                    };
+                    if let Some((from, to)) = region {
+                        let pos =
+                            world.get_effective_render_position(from, config.player, blk_w, blk_h);
+                        d.draw_rectangle(
+                            pos.x,
+                            pos.y,
+                            (to.x - from.x + 1) * blk_w as i32,
+                            (to.y - from.y + 1) * blk_h as i32,
+                            Color::SKYBLUE.fade(0.25),
+                        );
+                    } else {
+                        d.draw_rectangle(
+                            overlay_x,
+                            overlay_y,
+                            blk_w as i32,
+                            blk_h as i32,
+                            Color::SKYBLUE.fade(0.25),
+                        );
+                    }
+                }
                 InteractionMode::Building if can_build => {
                     config.current_selected_block.render_build_overlay(
                         &mut d,
@@ -438,7 +1435,8 @@ pub fn run_game(
                 }
                 InteractionMode::Dismantling if can_dismantle || dismantle_positions.len() > 0 => {
                     if let Some(timer_start) = dismantle_timer_start {
-                        let lerp = (Instant::now() - timer_start).as_millis() as f32 / 2000 as f32;
+                        let lerp = (Instant::now() - timer_start).as_millis() as f32
+                            / dismantle_duration.max(1) as f32;
                         if can_dismantle {
                             draw_dismantle_animation(
                                 &mut d,
@@ -502,40 +1500,57 @@ pub fn run_game(
             }
 
             if let Some((block, data)) = world.get_block_at_mut(cursor_x, cursor_y) {
+                if !block.is_none() {
+                    hovered_tooltip = Some(block_tooltip(&**block, data));
+                }
                 if block.supports_interaction() {
                     d.draw_text(
                         block
                             .custom_interact_message()
                             .unwrap_or_else(|| format!("Press F to interact with {}", block.name()))
+                            // blocks write their hints for the default bindings
+                            .replacen("Press F", &format!("Press {}", key_name(bindings.interact)), 1)
                             .as_str(),
                         overlay_x,
                         overlay_y + blk_h as i32 + 5,
                         20,
                         Color::BLACK,
                     );
-                    if d.is_key_pressed(KeyboardKey::KEY_F) {
+                    if input.interact {
                         block.interact(data, &mut config);
                     }
                 }
+                if let Some(throughput) = block.throughput_per_min() {
+                    let y_off = if block.supports_interaction() { 30 } else { 5 };
+                    d.draw_text(
+                        format!("{} items/min", throughput).as_str(),
+                        overlay_x,
+                        overlay_y + blk_h as i32 + y_off,
+                        20,
+                        Color::DARKGRAY,
+                    );
+                }
             }
         }
 
         match config.interaction_mode {
             InteractionMode::Building => {
-                config.current_selected_block.render(
+                config.current_selected_block.render_preview(
                     &mut d,
                     20,
                     screen_size.height - 68,
                     48,
                     48,
                     ChunkBlockMetadata::from(config.direction),
-                    RenderLayer::default_preview(),
                 );
                 d.draw_rectangle_lines_ex(
                     Rectangle::new(17.0, (screen_size.height - 68 - 3) as f32, 54.0, 54.0),
                     3,
                     Color::BLACK,
                 );
+                if orientation_locked {
+                    draw_lock(&mut d, 76, screen_size.height - 71);
+                }
             }
             InteractionMode::Dismantling => {
                 d.draw_text(
@@ -554,12 +1569,33 @@ pub fn run_game(
                 );
                 d.draw_text("Dismantling", 20, screen_size.height - 68, 20, Color::RED);
             }
+            InteractionMode::Selecting => {
+                d.draw_text(
+                    format!("Selecting ({} to rotate)", key_name(bindings.rotate_selection)).as_str(),
+                    20,
+                    screen_size.height - 68,
+                    20,
+                    Color::BLUE,
+                );
+            }
             InteractionMode::None => {}
         }
 
+        draw_hotbar(&mut d, &config, &screen_size);
+        if show_minimap {
+            draw_minimap(&mut d, &world, &config, &screen_size);
+        }
         d.draw_fps(5, 45);
+        if show_perf_overlay {
+            perf_history.draw(&mut d, 5, 100);
+        }
+        draw_inventory_bar(&mut d, &config.inventory, 5, 70);
         d.draw_text(
-            format!("TPS: {ticks_per_second}").as_str(),
+            format!(
+                "TPS: {ticks_per_second} | Update: {:.2}ms | Blocks drawn: {rendered_blocks}",
+                world.last_update_nanos() as f64 / 1_000_000.0
+            )
+            .as_str(),
             5,
             5,
             20,
@@ -577,9 +1613,34 @@ pub fn run_game(
             Color::DARKGREEN,
         );
 
+        if let Some((name, text)) = hovered_tooltip {
+            draw_tooltip_box(&mut d, name.as_str(), text.as_str());
+        }
+
         CurrentScreen::render(&mut config, &mut d, &screen_size, &mut world);
 
-        notice_board::render_entries(&mut d, screen_size.height / 2, screen_size.height);
+        notice_board::render_entries(&mut d, screen_size.height / 2, screen_size.height, Color::WHITE);
+    }
+}
+
+/// The corners of an arrow pointing in `direction`, centered in the block at `x`, `y`. They are in the order
+/// raylib expects for `draw_triangle`
+pub fn arrow_vertices(x: i32, y: i32, w: i32, h: i32, direction: Direction) -> [Vector2; 3] {
+    let center = Vec2i::new(x + w / 2, y + h / 2);
+    let size = w.min(h);
+    let side = direction.next(true);
+
+    let tip = center.add_directional(&direction, size / 3);
+    let back = center.add_directional(&direction.opposite(), size / 4);
+    let corner_a = back.add_directional(&side, size / 4);
+    let corner_b = back.add_directional(&side.opposite(), size / 4);
+
+    let [a, b, c] = [tip, corner_a, corner_b].map(Vec2i::as_vec2f);
+    // raylib only draws triangles whose corners go counter-clockwise on screen
+    if (b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x) > 0.0 {
+        [a, c, b]
+    } else {
+        [a, b, c]
     }
 }
 
@@ -600,10 +1661,10 @@ fn draw_dismantle_animation(
 
     d.draw_rectangle(x, y, blk_w as i32, blk_h as i32, Color::BLACK.fade(0.5));
 
-    let lerp_step_1 = lerp_step!(lerp, 0.0, 4.0) * blk_w as f32;
-    let lerp_step_2 = lerp_step!(lerp, 1.0, 4.0) * blk_h as f32;
-    let lerp_step_3 = lerp_step!(lerp, 2.0, 4.0) * (blk_w - 1) as f32;
-    let lerp_step_4 = lerp_step!(lerp, 3.0, 4.0) * (blk_h - 1) as f32;
+    let lerp_step_1 = lerp_step(lerp, 0.0, 4.0) * blk_w as f32;
+    let lerp_step_2 = lerp_step(lerp, 1.0, 4.0) * blk_h as f32;
+    let lerp_step_3 = lerp_step(lerp, 2.0, 4.0) * (blk_w - 1) as f32;
+    let lerp_step_4 = lerp_step(lerp, 3.0, 4.0) * (blk_h - 1) as f32;
 
     d.draw_rectangle(x, y, lerp_step_1 as i32, 2, Color::RED);
     d.draw_rectangle(x + blk_w as i32 - 2, y, 2, lerp_step_2 as i32, Color::RED);
@@ -623,3 +1684,396 @@ fn draw_dismantle_animation(
         Color::RED,
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_layers_cover_every_layer() {
+        let all = [
+            RenderLayer::Block,
+            RenderLayer::OverlayItems,
+            RenderLayer::Preview,
+            RenderLayer::FlowArrows,
+        ];
+        for layer in all {
+            // no wildcard, so a new layer has to be sorted in here before it compiles
+            let drawn = match layer {
+                RenderLayer::Block => RENDER_LAYERS.contains(&layer) && !PREVIEW_LAYERS.contains(&layer),
+                RenderLayer::Preview => PREVIEW_LAYERS.contains(&layer) && !RENDER_LAYERS.contains(&layer),
+                RenderLayer::OverlayItems => RENDER_LAYERS.contains(&layer) && PREVIEW_LAYERS.contains(&layer),
+                // only drawn when toggled, on top of everything else
+                RenderLayer::FlowArrows => !RENDER_LAYERS.contains(&layer) && !PREVIEW_LAYERS.contains(&layer),
+            };
+            assert!(drawn, "{layer:?} isn't drawn where it should be");
+        }
+        // items on belts have to end up on top of the blocks
+        assert_eq!(RENDER_LAYERS.last(), Some(&RenderLayer::OverlayItems));
+        assert_eq!(PREVIEW_LAYERS.last(), Some(&RenderLayer::OverlayItems));
+    }
+
+    #[test]
+    fn tooltips_list_the_sides_items_go_through() {
+        let belt = crate::blocks::conveyor::ConveyorBlock::default();
+        let (name, text) = block_tooltip(&belt, ChunkBlockMetadata::new(Direction::North, Vec2i::ZERO));
+        assert_eq!(name, "Conveyor Belt Tier 1");
+        // belts take items everywhere but their front and only hand them on by themselves
+        assert_eq!(text, "Moves 60 items per minute\nPush: East, South, West\nPull: none");
+        let (_, text) = block_tooltip(&belt, ChunkBlockMetadata::new(Direction::West, Vec2i::ZERO));
+        assert!(text.ends_with("\nPush: North, East, South\nPull: none"), "{text}");
+    }
+
+    #[test]
+    fn hotbar_and_selection_survive_saving() {
+        crate::test_utils::register_all();
+        let conveyor = get_block_by_id(*crate::blocks::conveyor::BLOCK_CONVEYOR).unwrap();
+        let splitter = get_block_by_id(*crate::blocks::splitter::BLOCK_CONVEYOR_SPLITTER).unwrap();
+        let mut config = GameConfig::default();
+        config.hotbar[0] = Some(conveyor);
+        config.hotbar[HOTBAR_SLOTS - 1] = Some(splitter);
+        config.current_selected_block = splitter;
+        config.direction = Direction::West;
+        config.interaction_mode = InteractionMode::Building;
+        let hotbar = |config: &GameConfig| config.hotbar.map(|slot| slot.map(|blk| blk.identifier()));
+
+        let mut buf = Vec::new();
+        config.serialize(&mut buf);
+        assert_eq!(buf.len(), config.required_length());
+        let mut buf = serialization::Buffer::new(buf);
+        let loaded = GameConfig::try_deserialize(&mut buf).unwrap();
+        assert_eq!(buf.len(), 0);
+        assert_eq!(hotbar(&loaded), hotbar(&config));
+        assert_eq!(loaded.current_selected_block.identifier(), splitter.identifier());
+        assert_eq!(loaded.direction, Direction::West);
+        assert_eq!(loaded.interaction_mode, InteractionMode::Building);
+
+        // legacy saves end after the inventory, so nothing is in the hotbar or selected
+        let mut legacy = Vec::new();
+        SerializationTrap::GameCfg.serialize(&mut legacy);
+        config.saved_player().serialize(&mut legacy);
+        config.inventory.serialize(&mut legacy);
+        let mut buf = serialization::Buffer::new(legacy);
+        let loaded = GameConfig::try_deserialize_with_version(&mut buf, serialization::LEGACY_SAVE_VERSION).unwrap();
+        assert_eq!(buf.len(), 0);
+        assert_eq!(hotbar(&loaded), [None; HOTBAR_SLOTS]);
+        assert!(loaded.current_selected_block.is_none());
+        assert_eq!(loaded.interaction_mode, InteractionMode::None);
+    }
+
+    #[test]
+    fn gamepad_stick_moves_the_player() {
+        crate::test_utils::register_all();
+        let mut config = GameConfig::default();
+        let start = config.player;
+        let input = InputState {
+            stick: Vector2::new(1.0, -0.5),
+            ..Default::default()
+        };
+        // 100ms at 0.8 px/ms
+        move_player(&mut config, &input, 100.0, false);
+        assert_eq!(config.player, Vec2i::new(start.x + 80, start.y - 40));
+
+        let boosted = InputState { boost: true, ..input };
+        move_player(&mut config, &boosted, 100.0, false);
+        assert_eq!(config.player, Vec2i::new(start.x + 200, start.y - 100));
+
+        // snap movement only applies to the movement keys, the stick keeps moving freely
+        move_player(&mut config, &input, 100.0, true);
+        assert_eq!(config.player, Vec2i::new(start.x + 280, start.y - 140));
+    }
+
+    #[test]
+    fn snap_movement_steps_a_block_per_press() {
+        crate::test_utils::register_all();
+        let mut config = GameConfig::default();
+        config.player = Vec2i::new(3, -5);
+        let input = InputState {
+            keys: Vector2::new(1.0, 0.0),
+            step: Vec2i::new(1, 0),
+            ..Default::default()
+        };
+        move_player(&mut config, &input, 100.0, true);
+        assert_eq!(config.player, Vec2i::new(config.block_size as i32, 0));
+    }
+
+    #[test]
+    fn escape_leaves_selecting() {
+        crate::test_utils::register_all();
+        let mut config = GameConfig::default();
+        config.interaction_mode = InteractionMode::Selecting;
+        assert!(config.cancel_interaction());
+        assert_eq!(config.interaction_mode, InteractionMode::None);
+        assert!(config.current_selected_block.is_none());
+        // nothing left to cancel, so the next escape opens the menu
+        assert!(!config.cancel_interaction());
+        assert_eq!(config.interaction_mode, InteractionMode::None);
+    }
+
+    #[test]
+    fn snaps_to_the_nearest_block() {
+        assert_eq!(snap_to_grid(0, 64), 0);
+        assert_eq!(snap_to_grid(31, 64), 0);
+        assert_eq!(snap_to_grid(32, 64), 64);
+        assert_eq!(snap_to_grid(100, 64), 128);
+        assert_eq!(snap_to_grid(-31, 64), 0);
+        assert_eq!(snap_to_grid(-33, 64), -64);
+        assert_eq!(snap_to_grid(-100, 64), -128);
+        assert_eq!(snap_to_grid(-128, 64), -128);
+        // other zoom levels
+        assert_eq!(snap_to_grid(-13, 16), -16);
+        assert_eq!(snap_to_grid(13, 16), 16);
+    }
+
+    #[test]
+    fn belts_are_continued_in_line_with_the_cursor() {
+        for belt in Direction::all() {
+            for approach in Direction::all() {
+                let expected = (approach == belt || approach == belt.opposite()).then_some(belt);
+                assert_eq!(continue_belt_direction(belt, approach), expected, "{belt:?} from {approach:?}");
+            }
+        }
+        // moving back along a belt doesn't turn it around
+        assert_eq!(continue_belt_direction(Direction::North, Direction::South), Some(Direction::North));
+    }
+
+    #[test]
+    fn lerp_step_of_the_first_step() {
+        assert_eq!(lerp_step(0.0, 0.0, 4.0), 0.0);
+        assert_eq!(lerp_step(0.125, 0.0, 4.0), 0.5);
+        assert_eq!(lerp_step(0.25, 0.0, 4.0), 1.0);
+        // done steps stay done
+        assert_eq!(lerp_step(0.9, 0.0, 4.0), 1.0);
+    }
+
+    #[test]
+    fn lerp_step_of_the_last_step() {
+        assert_eq!(lerp_step(0.5, 3.0, 4.0), 0.0);
+        assert_eq!(lerp_step(0.875, 3.0, 4.0), 0.5);
+        assert_eq!(lerp_step(1.0, 3.0, 4.0), 1.0);
+        // past the end of the animation
+        assert_eq!(lerp_step(1.5, 3.0, 4.0), 1.0);
+    }
+
+    #[test]
+    fn lerp_step_out_of_range() {
+        for lerp in [0.0, 0.5, 1.0] {
+            assert_eq!(lerp_step(lerp, 4.0, 4.0), 0.0);
+            assert_eq!(lerp_step(lerp, 17.0, 4.0), 0.0);
+            assert_eq!(lerp_step(lerp, -1.0, 4.0), 0.0);
+            // no steps at all, instead of dividing by 0
+            assert_eq!(lerp_step(lerp, 0.0, 0.0), 0.0);
+            assert_eq!(lerp_step(lerp, 0.0, -2.0), 0.0);
+        }
+    }
+
+    #[test]
+    fn arrows_point_where_the_block_faces() {
+        // the tip is a third of the block away from the center, towards the facing of the block
+        let tips = [
+            (Direction::North, Vector2::new(45.0, 15.0)),
+            (Direction::South, Vector2::new(45.0, 35.0)),
+            (Direction::East, Vector2::new(35.0, 25.0)),
+            (Direction::West, Vector2::new(55.0, 25.0)),
+        ];
+        for (direction, tip) in tips {
+            let [a, b, c] = arrow_vertices(30, 10, 30, 30, direction);
+            assert_eq!(a, tip, "{direction:?}");
+            // the back corners are level with each other, across the direction of the arrow
+            match direction {
+                Direction::North | Direction::South => assert_eq!(b.y, c.y),
+                Direction::East | Direction::West => assert_eq!(b.x, c.x),
+            }
+            for corner in [a, b, c] {
+                assert!((30.0..=60.0).contains(&corner.x) && (10.0..=40.0).contains(&corner.y), "{direction:?}");
+            }
+            // counter-clockwise on screen, otherwise raylib doesn't draw it
+            assert!((b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x) < 0.0, "{direction:?}");
+        }
+    }
+
+    #[test]
+    fn only_sustained_low_tps_warns() {
+        let mut detector = LowTpsDetector::default();
+        let start = Instant::now();
+        let tick = Duration::from_millis(50);
+        let mut now = start;
+        let mut warnings = Vec::new();
+        let mut run = |detector: &mut LowTpsDetector, tps: u32, duration: Duration, warnings: &mut Vec<Duration>| {
+            let end = now + duration;
+            while now < end {
+                if detector.sample(tps, DEFAULT_LOW_TPS_THRESHOLD, now) {
+                    warnings.push(now - start);
+                }
+                now += tick;
+            }
+        };
+
+        run(&mut detector, TPS, Duration::from_secs(10), &mut warnings);
+        // a short hiccup drags the average down, but not for long enough
+        run(&mut detector, 2, Duration::from_secs(2), &mut warnings);
+        run(&mut detector, TPS, Duration::from_secs(3), &mut warnings);
+        assert!(warnings.is_empty());
+
+        // a factory that stays too slow gets one warning once it was slow for a while
+        run(&mut detector, 10, Duration::from_secs(20), &mut warnings);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0] >= Duration::from_secs(15 + 5) && warnings[0] < Duration::from_secs(15 + 7));
+        assert_eq!(detector.average(), 10);
+
+        // it can warn again after it recovered
+        run(&mut detector, TPS, Duration::from_secs(3), &mut warnings);
+        run(&mut detector, 10, Duration::from_secs(10), &mut warnings);
+        assert_eq!(warnings.len(), 2);
+
+        // a threshold of 0 never warns
+        let mut detector = LowTpsDetector::default();
+        assert!((0..500).all(|i| !detector.sample(0, 0, start + tick * i)));
+    }
+
+    /// a config outside of creative mode with `num_belts` belt items that builds belts
+    fn building_belts(num_belts: u32) -> GameConfig {
+        crate::test_utils::register_all();
+        let mut config = GameConfig::default();
+        let belt = get_block_by_id(*crate::blocks::conveyor::BLOCK_CONVEYOR).unwrap();
+        config.current_selected_block = belt;
+        config.creative = false;
+        config.inventory = Inventory::new(4, true);
+        for _ in 0..num_belts {
+            config.inventory.try_add_item(block_item(belt.clone_block()));
+        }
+        config
+    }
+
+    #[test]
+    fn building_uses_up_a_block_item() {
+        let mut config = building_belts(2);
+        let mut world = World::new(1, 1);
+        let belt = config.current_selected_block.identifier();
+        assert_eq!(try_build(&mut config, &mut world, Vec2i::new(3, 4), Direction::West), Ok(()));
+        let (blk, meta) = world.get_block_at(3, 4).unwrap();
+        assert_eq!((blk.identifier(), meta.direction), (belt, Direction::West));
+        assert_eq!(config.inventory.count(belt), 1);
+
+        // the block is already there, so the item stays
+        assert_eq!(
+            try_build(&mut config, &mut world, Vec2i::new(3, 4), Direction::North),
+            Err(BuildError::Occupied)
+        );
+        assert_eq!(config.inventory.count(belt), 1);
+    }
+
+    #[test]
+    fn building_without_block_items_fails() {
+        let mut config = building_belts(0);
+        let mut world = World::new(1, 1);
+        assert_eq!(
+            try_build(&mut config, &mut world, Vec2i::new(3, 4), Direction::West),
+            Err(BuildError::MissingItems)
+        );
+        assert!(world.get_block_at(3, 4).unwrap().0.is_none());
+    }
+
+    #[test]
+    fn creative_mode_builds_for_free() {
+        let mut config = building_belts(1);
+        config.creative = true;
+        let mut world = World::new(1, 1);
+        let belt = config.current_selected_block.identifier();
+        for x in 0..3 {
+            assert_eq!(try_build(&mut config, &mut world, Vec2i::new(x, 4), Direction::West), Ok(()));
+            assert_eq!(world.get_block_at(x, 4).unwrap().0.identifier(), belt);
+        }
+        assert_eq!(config.inventory.count(belt), 1);
+    }
+
+    #[test]
+    fn undoing_a_build_gives_the_block_item_back() {
+        let mut config = building_belts(2);
+        let mut world = World::new(1, 1);
+        let mut undo = UndoStack::default();
+        let belt = config.current_selected_block.identifier();
+        assert_eq!(undo.build(&mut config, &mut world, Vec2i::new(3, 4), Direction::West), Ok(()));
+        assert_eq!(config.inventory.count(belt), 1);
+
+        assert_eq!(undo.undo(&mut config, &mut world), Ok(()));
+        assert!(world.get_block_at(3, 4).unwrap().0.is_none());
+        assert_eq!(config.inventory.count(belt), 2);
+        assert_eq!(undo.undo(&mut config, &mut world), Err(UndoError::NothingLeft));
+    }
+
+    #[test]
+    fn undoing_a_dismantle_puts_the_block_back() {
+        let mut config = building_belts(1);
+        let mut world = World::new(1, 1);
+        let mut undo = UndoStack::default();
+        let belt = config.current_selected_block.identifier();
+        undo.build(&mut config, &mut world, Vec2i::new(3, 4), Direction::West).unwrap();
+        undo.dismantle(&mut config, &mut world, Vec2i::new(3, 4));
+        assert!(world.get_block_at(3, 4).unwrap().0.is_none());
+        assert_eq!(config.inventory.count(belt), 1);
+
+        assert_eq!(undo.undo(&mut config, &mut world), Ok(()));
+        let (blk, meta) = world.get_block_at(3, 4).unwrap();
+        assert_eq!((blk.identifier(), meta.direction), (belt, Direction::West));
+        assert_eq!(config.inventory.count(belt), 0);
+    }
+
+    #[test]
+    fn undo_skips_cells_that_changed() {
+        let mut config = building_belts(2);
+        let mut world = World::new(1, 1);
+        let mut undo = UndoStack::default();
+        let belt = config.current_selected_block.identifier();
+        undo.build(&mut config, &mut world, Vec2i::new(3, 4), Direction::West).unwrap();
+        undo.build(&mut config, &mut world, Vec2i::new(5, 4), Direction::West).unwrap();
+        undo.dismantle(&mut config, &mut world, Vec2i::new(5, 4));
+        // something else took the place of both belts since
+        for x in [3, 5] {
+            world.destroy_block_at(x, 4, &mut Inventory::new(1, false));
+            world.set_block_at(x, 4, Box::new(crate::blocks::StorageContainer::default()), Direction::North);
+        }
+
+        assert_eq!(undo.undo(&mut config, &mut world), Err(UndoError::NothingLeft));
+        for x in [3, 5] {
+            assert_ne!(world.get_block_at(x, 4).unwrap().0.identifier(), belt);
+        }
+        assert_eq!(config.inventory.count(belt), 1);
+    }
+
+    #[test]
+    fn undoing_a_build_into_a_full_inventory_is_refused() {
+        let mut config = building_belts(1);
+        let mut world = World::new(1, 1);
+        let mut undo = UndoStack::default();
+        let belt = config.current_selected_block.identifier();
+        undo.build(&mut config, &mut world, Vec2i::new(3, 4), Direction::West).unwrap();
+        let coal = *crate::items::COAL_IDENTIFIER;
+        for _ in 0..config.inventory.size() {
+            let stack = crate::test_utils::item(coal, crate::inventory::MAX_ITEMS_PER_SLOT);
+            assert!(config.inventory.try_add_item(stack).is_none());
+        }
+
+        assert_eq!(undo.undo(&mut config, &mut world), Err(UndoError::InventoryFull));
+        assert_eq!(world.get_block_at(3, 4).unwrap().0.identifier(), belt);
+        assert_eq!(config.inventory.count(belt), 0);
+
+        // the build can still be taken back once there is room
+        config.inventory.take_item(0);
+        assert_eq!(undo.undo(&mut config, &mut world), Ok(()));
+        assert!(world.get_block_at(3, 4).unwrap().0.is_none());
+        assert_eq!(config.inventory.count(belt), 1);
+    }
+
+    #[test]
+    fn the_wheel_only_rotates_while_unlocked() {
+        for direction in Direction::all() {
+            assert_eq!(wheel_rotation(direction, 1.0, false), direction.next(true));
+            assert_eq!(wheel_rotation(direction, -0.5, false), direction.next(false));
+            assert_eq!(wheel_rotation(direction, 0.0, false), direction);
+            for wheel in [1.0, -1.0, 0.0] {
+                assert_eq!(wheel_rotation(direction, wheel, true), direction, "{direction:?} {wheel}");
+            }
+        }
+    }
+}
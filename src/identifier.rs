@@ -1,4 +1,7 @@
-use std::fmt::{Debug, Display, Write};
+use std::{
+    fmt::{Debug, Display, Write},
+    str::FromStr,
+};
 
 use crate::serialization::{Deserialize, Serialize};
 
@@ -101,7 +104,7 @@ impl Deserialize for GlobalString {
     }
 }
 
-#[derive(PartialEq, Eq, Clone, Copy)]
+#[derive(PartialEq, Eq, Clone, Copy, Hash)]
 pub struct Identifier {
     major: GlobalString,
     minor: GlobalString,
@@ -176,4 +179,17 @@ impl From<&(&str, &str)> for Identifier {
     fn from(&(major, minor): &(&str, &str)) -> Self {
         Self { major: GlobalString::from(major), minor: GlobalString::from(minor) }
     }
-}
\ No newline at end of file
+}
+impl FromStr for Identifier {
+    type Err = ();
+
+    /// parses an identifier in the form of `namespace:path`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().split_once(':') {
+            Some((major, minor)) if !major.is_empty() && !minor.is_empty() && !minor.contains(':') => {
+                Ok(Self::from((major, minor)))
+            }
+            _ => Err(()),
+        }
+    }
+}
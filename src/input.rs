@@ -0,0 +1,128 @@
+use raylib::{
+    ffi::{GamepadAxis, GamepadButton, KeyboardKey},
+    math::Vector2,
+    RaylibHandle,
+};
+
+use crate::{keybinds::KeyBindings, world::Vec2i};
+
+pub const GAMEPAD: i32 = 0;
+const GAMEPAD_DEADZONE: f32 = 0.2;
+
+fn gamepad_axis(rl: &RaylibHandle, axis: GamepadAxis) -> f32 {
+    let value = rl.get_gamepad_axis_movement(GAMEPAD, axis);
+    if value.abs() < GAMEPAD_DEADZONE {
+        0.0
+    } else {
+        value
+    }
+}
+
+/// Everything the player pressed in a frame, read from the keyboard and the first gamepad at once. The game only looks
+/// at this instead of asking raylib, so it doesn't matter which of the two the input came from
+#[derive(Debug, Clone, Copy, Default)]
+pub struct InputState {
+    /// the held movement keys, -1 to 1 on each axis and shortened on diagonals
+    pub keys: Vector2,
+    /// the movement keys pressed this frame, used instead of `keys` when snap movement is on
+    pub step: Vec2i,
+    /// the left stick
+    pub stick: Vector2,
+    /// the right stick, moves the cursor
+    pub cursor: Vector2,
+    pub boost: bool,
+    pub inventory: bool,
+    pub block_selector: bool,
+    pub dismantle: bool,
+    pub interact: bool,
+    pub primary_action: bool,
+    /// the block that's being built was rotated this frame, `true` is clockwise
+    pub rotate: Option<bool>,
+    /// escape or start: closes the screen, stops building or opens the escape menu
+    pub back: bool,
+    /// moves the focus of the open screen by this many buttons, from the d-pad
+    pub focus_step: i32,
+    /// presses the focused button of the open screen
+    pub confirm: bool,
+}
+
+impl InputState {
+    pub fn read(rl: &RaylibHandle, bindings: &KeyBindings) -> Self {
+        let mut input = Self::default();
+
+        if rl.is_key_down(bindings.up) {
+            input.keys.y -= 1.0;
+        }
+        if rl.is_key_down(bindings.down) {
+            input.keys.y += 1.0;
+        }
+        if rl.is_key_down(bindings.left) {
+            input.keys.x -= 1.0;
+        }
+        if rl.is_key_down(bindings.right) {
+            input.keys.x += 1.0;
+        }
+        if input.keys.x != 0.0 && input.keys.y != 0.0 {
+            input.keys *= 0.7;
+        }
+        if rl.is_key_pressed(bindings.up) {
+            input.step.y -= 1;
+        }
+        if rl.is_key_pressed(bindings.down) {
+            input.step.y += 1;
+        }
+        if rl.is_key_pressed(bindings.left) {
+            input.step.x -= 1;
+        }
+        if rl.is_key_pressed(bindings.right) {
+            input.step.x += 1;
+        }
+        input.boost = rl.is_key_down(bindings.modifier);
+        input.inventory = rl.is_key_down(bindings.inventory);
+        input.block_selector = rl.is_key_pressed(bindings.block_selector);
+        input.dismantle = rl.is_key_pressed(bindings.dismantle);
+        input.interact = rl.is_key_pressed(bindings.interact);
+        input.primary_action = rl.is_mouse_button_down(raylib::ffi::MouseButton::MOUSE_LEFT_BUTTON);
+        input.back = rl.is_key_pressed(KeyboardKey::KEY_ESCAPE);
+
+        if rl.is_gamepad_available(GAMEPAD) {
+            let pressed = |button| rl.is_gamepad_button_pressed(GAMEPAD, button);
+
+            input.stick = Vector2::new(
+                gamepad_axis(rl, GamepadAxis::GAMEPAD_AXIS_LEFT_X),
+                gamepad_axis(rl, GamepadAxis::GAMEPAD_AXIS_LEFT_Y),
+            );
+            input.cursor = Vector2::new(
+                gamepad_axis(rl, GamepadAxis::GAMEPAD_AXIS_RIGHT_X),
+                gamepad_axis(rl, GamepadAxis::GAMEPAD_AXIS_RIGHT_Y),
+            );
+            input.boost |= rl.is_gamepad_button_down(GAMEPAD, GamepadButton::GAMEPAD_BUTTON_LEFT_THUMB);
+            input.inventory |= pressed(GamepadButton::GAMEPAD_BUTTON_MIDDLE_LEFT);
+            input.block_selector |= pressed(GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_LEFT);
+            input.dismantle |= pressed(GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_UP);
+            input.interact |= pressed(GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_RIGHT);
+            input.primary_action |=
+                rl.is_gamepad_button_down(GAMEPAD, GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_DOWN);
+            input.back |= pressed(GamepadButton::GAMEPAD_BUTTON_MIDDLE_RIGHT);
+            input.confirm = pressed(GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_DOWN);
+
+            if pressed(GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_RIGHT) {
+                input.rotate = Some(true);
+            } else if pressed(GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_LEFT) {
+                input.rotate = Some(false);
+            }
+            if pressed(GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_DOWN)
+                || pressed(GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_RIGHT)
+            {
+                input.focus_step += 1;
+            }
+            if pressed(GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_UP)
+                || pressed(GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_LEFT)
+            {
+                input.focus_step -= 1;
+            }
+        }
+
+        input
+    }
+}
@@ -1,33 +1,108 @@
 use std::fmt::Debug;
 
 use crate::{
-    items::Item,
+    identifier::Identifier,
+    items::{get_item_by_id, Item, ItemStack, Stack},
     notice_board::{self, NoticeboardEntryRenderable},
-    serialization::{Buffer, Deserialize, SerializationError, Serialize},
+    serialization::{
+        item_required_length, serialize_item, Buffer, Deserialize, SerializationError, SerializationTrap,
+        Serialize,
+    },
 };
 
 pub const NUM_SLOTS_PLAYER: usize = 5 * 9;
+/// the default `Item::max_stack_size`
 pub const MAX_ITEMS_PER_SLOT: u32 = 255;
 
+/// What an inventory slot holds. Plain stacks are stored in the slot itself, so only items with state of their own
+/// need a box
 #[derive(Default)]
-pub struct Inventory {
-    items: Vec<Option<Box<dyn Item>>>,
-    pub is_player: bool,
+pub enum Slot {
+    /// a free slot
+    #[default]
+    Empty,
+    Stack(ItemStack),
+    Special(Box<dyn Item>),
+}
+
+impl Slot {
+    pub fn is_some(&self) -> bool {
+        !self.is_none()
+    }
+
+    pub fn is_none(&self) -> bool {
+        matches!(self, Self::Empty)
+    }
+
+    /// The item in the slot, if there is one
+    pub fn item(&self) -> Option<&dyn Item> {
+        match self {
+            Self::Empty => None,
+            Self::Stack(stack) => Some(stack),
+            Self::Special(item) => Some(&**item),
+        }
+    }
+
+    pub fn item_mut(&mut self) -> Option<&mut dyn Item> {
+        match self {
+            Self::Empty => None,
+            Self::Stack(stack) => Some(stack),
+            Self::Special(item) => Some(&mut **item),
+        }
+    }
+
+    /// Takes the item out of the slot, leaving it empty
+    pub fn take(&mut self) -> Option<Stack> {
+        match std::mem::take(self) {
+            Self::Empty => None,
+            Self::Stack(stack) => Some(Stack::Plain(stack)),
+            Self::Special(item) => Some(Stack::Special(item)),
+        }
+    }
+
+    /// Puts `item` into the slot and gives back what was in it before
+    pub fn replace(&mut self, item: Stack) -> Option<Stack> {
+        std::mem::replace(self, Self::from(item)).take()
+    }
+}
+
+impl From<Stack> for Slot {
+    fn from(item: Stack) -> Self {
+        match item {
+            Stack::Plain(stack) => Self::Stack(stack),
+            Stack::Special(item) => Self::Special(item),
+        }
+    }
+}
+
+impl From<Option<Stack>> for Slot {
+    fn from(item: Option<Stack>) -> Self {
+        item.map(Self::from).unwrap_or_default()
+    }
 }
 
-impl Clone for Inventory {
+impl Clone for Slot {
     fn clone(&self) -> Self {
-        let mut new = Self::new(self.items.len(), self.is_player);
-        for i in 0..self.items.len() {
-            new.items[i] = match &self.items[i] {
-                None => None,
-                Some(item) => Some(item.clone_item()),
-            }
+        match self {
+            Self::Empty => Self::Empty,
+            Self::Stack(stack) => Self::Stack(*stack),
+            Self::Special(item) => Self::Special(item.clone_item()),
         }
-        new
     }
 }
 
+impl Debug for Slot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.item().fmt(f)
+    }
+}
+
+#[derive(Default, Clone)]
+pub struct Inventory {
+    items: Vec<Slot>,
+    pub is_player: bool,
+}
+
 impl Debug for Inventory {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         if self.is_player {
@@ -47,17 +122,17 @@ impl Debug for Inventory {
 }
 
 impl Inventory {
-    pub fn destroy_items(&self) -> Vec<Box<dyn Item>> {
+    pub fn destroy_items(&self) -> Vec<Stack> {
         self.items
             .iter()
             .filter(|el| el.is_some())
-            .map(|el| el.clone().unwrap())
+            .map(|el| el.clone().take().unwrap())
             .collect()
     }
 
     pub fn update(&mut self) {
         for itm in &mut self.items {
-            if let Some(item) = itm {
+            if let Some(item) = itm.item() {
                 if item.metadata() == 0 {
                     itm.take();
                 }
@@ -69,34 +144,49 @@ impl Inventory {
         self.items.len()
     }
 
+    pub fn used_slots(&self) -> usize {
+        self.items.iter().filter(|item| item.is_some()).count()
+    }
+
+    /// The number of items in all slots together
+    pub fn total_items(&self) -> u64 {
+        self.items().map(|item| item.stack_count() as u64).sum()
+    }
+
+    pub fn free_slots(&self) -> usize {
+        self.items.len() - self.used_slots()
+    }
+
     pub fn resize(&mut self, new_size: usize) {
-        self.items.resize_with(new_size, || None);
+        self.items.resize_with(new_size, Slot::default);
     }
 
     pub fn new(size: usize, is_player: bool) -> Self {
         let mut items = Vec::with_capacity(size);
 
         for _ in 0..size {
-            items.push(None);
+            items.push(Slot::Empty);
         }
 
         Self { items, is_player }
     }
 
+    /// The items in all slots that aren't empty
+    fn items(&self) -> impl Iterator<Item = &dyn Item> {
+        self.items.iter().filter_map(Slot::item)
+    }
+
     pub fn switch_items(&mut self, slot_a: usize, slot_b: usize) -> bool {
         if slot_a >= self.items.len() || slot_b >= self.items.len() {
             return false;
         }
 
-        let val_a = self.items[slot_a].take();
-        let val_b = self.items[slot_b].take();
-        self.items[slot_a] = val_b;
-        self.items[slot_b] = val_a;
+        self.items.swap(slot_a, slot_b);
 
         true
     }
 
-    pub fn take_item(&mut self, slot: usize) -> Option<Box<dyn Item>> {
+    pub fn take_item(&mut self, slot: usize) -> Option<Stack> {
         if slot >= self.items.len() {
             None
         } else {
@@ -104,16 +194,13 @@ impl Inventory {
             if let Some(item) = &item {
                 if self.is_player {
                     notice_board::add_entry(
-                        NoticeboardEntryRenderable::Joiner(
-                            Box::new(NoticeboardEntryRenderable::NamedItem(item.clone_item())),
-                            Box::new(NoticeboardEntryRenderable::String(format!(
-                                "- {}",
-                                if item.metadata_is_stack_size() {
-                                    item.metadata()
-                                } else {
-                                    1
-                                }
-                            ))),
+                        NoticeboardEntryRenderable::ItemCount(
+                            item.clone(),
+                            -if item.metadata_is_stack_size() {
+                                item.metadata() as i64
+                            } else {
+                                1
+                            },
                         ),
                         5,
                     );
@@ -123,7 +210,7 @@ impl Inventory {
         }
     }
 
-    pub fn add_item(&mut self, mut item: Box<dyn Item>, slot: usize) -> Option<Box<dyn Item>> {
+    pub fn add_item(&mut self, mut item: Stack, slot: usize) -> Option<Stack> {
         if slot >= self.items.len() {
             return Some(item);
         }
@@ -133,40 +220,35 @@ impl Inventory {
         } else {
             1
         };
-        match &mut self.items[slot] {
+        let max_stack_size = item.max_stack_size();
+        match self.items[slot].item_mut() {
             None => {
+                let leftover = split_off_overflow(&mut item);
                 if self.is_player {
                     notice_board::add_entry(
-                        NoticeboardEntryRenderable::Joiner(
-                            Box::new(NoticeboardEntryRenderable::NamedItem(item.clone_item())),
-                            Box::new(NoticeboardEntryRenderable::String(format!("+ {}", orig_sz))),
-                        ),
+                        NoticeboardEntryRenderable::ItemCount(item.clone(), item.stack_count() as i64),
                         5,
                     );
                 }
-                self.items[slot].replace(item)
+                self.items[slot] = Slot::from(item);
+                leftover
             }
             Some(slot_item) => {
                 if slot_item.identifier() == item.identifier() && slot_item.metadata_is_stack_size()
                 {
-                    if slot_item.metadata() >= MAX_ITEMS_PER_SLOT {
+                    if slot_item.metadata() >= max_stack_size {
                         return Some(item);
                     }
                     let new_sz = slot_item.metadata() + item.metadata();
-                    if new_sz > MAX_ITEMS_PER_SLOT {
-                        slot_item.set_metadata(MAX_ITEMS_PER_SLOT);
-                        item.set_metadata(new_sz - MAX_ITEMS_PER_SLOT);
+                    if new_sz > max_stack_size {
+                        slot_item.set_metadata(max_stack_size);
+                        item.set_metadata(new_sz - max_stack_size);
 
                         if self.is_player {
                             notice_board::add_entry(
-                                NoticeboardEntryRenderable::Joiner(
-                                    Box::new(NoticeboardEntryRenderable::NamedItem(
-                                        item.clone_item(),
-                                    )),
-                                    Box::new(NoticeboardEntryRenderable::String(format!(
-                                        "+ {}",
-                                        orig_sz - item.metadata()
-                                    ))),
+                                NoticeboardEntryRenderable::ItemCount(
+                                    item.clone(),
+                                    (orig_sz - item.metadata()) as i64,
                                 ),
                                 5,
                             );
@@ -175,13 +257,9 @@ impl Inventory {
                     } else {
                         if self.is_player {
                             notice_board::add_entry(
-                                NoticeboardEntryRenderable::Joiner(
-                                    Box::new(NoticeboardEntryRenderable::NamedItem(
-                                        item.clone_item(),
-                                    )),
-                                    Box::new(NoticeboardEntryRenderable::String(format!(
-                                        "+ {orig_sz}"
-                                    ))),
+                                NoticeboardEntryRenderable::ItemCount(
+                                    item.clone(),
+                                    orig_sz as i64,
                                 ),
                                 5,
                             );
@@ -192,11 +270,9 @@ impl Inventory {
                 } else {
                     if self.is_player {
                         notice_board::add_entry(
-                            NoticeboardEntryRenderable::Joiner(
-                                Box::new(NoticeboardEntryRenderable::NamedItem(item.clone_item())),
-                                Box::new(NoticeboardEntryRenderable::String(format!(
-                                    "+ {orig_sz}"
-                                ))),
+                            NoticeboardEntryRenderable::ItemCount(
+                                item.clone(),
+                                orig_sz as i64,
                             ),
                             5,
                         );
@@ -207,18 +283,19 @@ impl Inventory {
         }
     }
 
-    pub fn get_item<'a>(&'a self, slot: usize) -> &'a Option<Box<dyn Item>> {
-        if slot >= self.items.len() {
-            return &None;
-        }
+    pub fn get_item(&self, slot: usize) -> Option<&dyn Item> {
+        self.items.get(slot).and_then(Slot::item)
+    }
+
+    pub fn slot(&self, slot: usize) -> &Slot {
         &self.items[slot]
     }
 
-    pub fn get_item_mut<'a>(&'a mut self, slot: usize) -> &'a mut Option<Box<dyn Item>> {
+    pub fn get_item_mut(&mut self, slot: usize) -> &mut Slot {
         &mut self.items[slot]
     }
 
-    pub fn try_add_item(&mut self, mut item: Box<dyn Item>) -> Option<Box<dyn Item>> {
+    pub fn try_add_item(&mut self, mut item: Stack) -> Option<Stack> {
         let can_extend_amount = item.metadata_is_stack_size();
         let identifier = item.identifier();
 
@@ -227,42 +304,43 @@ impl Inventory {
         } else {
             1
         };
+        let max_stack_size = item.max_stack_size();
         for slot in 0..self.items.len() {
-            match &mut self.items[slot] {
+            match self.items[slot].item_mut() {
                 None => {
+                    let leftover = split_off_overflow(&mut item);
                     if self.is_player {
                         notice_board::add_entry(
-                            NoticeboardEntryRenderable::Joiner(
-                                Box::new(NoticeboardEntryRenderable::NamedItem(item.clone_item())),
-                                Box::new(NoticeboardEntryRenderable::String(format!(
-                                    "+ {orig_sz}"
-                                ))),
+                            NoticeboardEntryRenderable::ItemCount(
+                                item.clone(),
+                                item.stack_count() as i64,
                             ),
                             5,
                         );
                     }
-                    self.items[slot] = Some(item);
-                    return None;
+                    self.items[slot] = Slot::from(item);
+                    match leftover {
+                        None => return None,
+                        Some(leftover) => {
+                            item = leftover;
+                            orig_sz = item.metadata();
+                        }
+                    }
                 }
                 Some(other_item) => {
                     if other_item.identifier() == identifier && can_extend_amount {
-                        if other_item.metadata() >= MAX_ITEMS_PER_SLOT {
+                        if other_item.metadata() >= max_stack_size {
                             continue;
                         }
                         let new_sz = other_item.metadata() + item.metadata();
-                        if new_sz > MAX_ITEMS_PER_SLOT {
-                            other_item.set_metadata(MAX_ITEMS_PER_SLOT);
-                            item.set_metadata(new_sz - MAX_ITEMS_PER_SLOT);
+                        if new_sz > max_stack_size {
+                            other_item.set_metadata(max_stack_size);
+                            item.set_metadata(new_sz - max_stack_size);
                             if self.is_player {
                                 notice_board::add_entry(
-                                    NoticeboardEntryRenderable::Joiner(
-                                        Box::new(NoticeboardEntryRenderable::NamedItem(
-                                            item.clone_item(),
-                                        )),
-                                        Box::new(NoticeboardEntryRenderable::String(format!(
-                                            "+ {}",
-                                            orig_sz - item.metadata()
-                                        ))),
+                                    NoticeboardEntryRenderable::ItemCount(
+                                        item.clone(),
+                                        (orig_sz - item.metadata()) as i64,
                                     ),
                                     5,
                                 );
@@ -271,13 +349,9 @@ impl Inventory {
                         } else {
                             if self.is_player {
                                 notice_board::add_entry(
-                                    NoticeboardEntryRenderable::Joiner(
-                                        Box::new(NoticeboardEntryRenderable::NamedItem(
-                                            item.clone_item(),
-                                        )),
-                                        Box::new(NoticeboardEntryRenderable::String(format!(
-                                            "+ {orig_sz}"
-                                        ))),
+                                    NoticeboardEntryRenderable::ItemCount(
+                                        item.clone(),
+                                        orig_sz as i64,
                                     ),
                                     5,
                                 );
@@ -289,10 +363,11 @@ impl Inventory {
                 }
             }
         }
-        if can_extend_amount && item.metadata() > 0 {
-            Some(item)
-        } else {
+        // an item that isn't a stack doesn't fit anywhere at this point and has to be handed back as well
+        if can_extend_amount && item.metadata() == 0 {
             None
+        } else {
+            Some(item)
         }
     }
 
@@ -305,7 +380,7 @@ impl Inventory {
         false
     }
 
-    pub fn can_push(&self, item: &Box<dyn Item>) -> bool {
+    pub fn can_push(&self, item: &Stack) -> bool {
         let mut count_remaining = if item.metadata_is_stack_size() {
             item.metadata()
         } else {
@@ -313,15 +388,21 @@ impl Inventory {
         };
 
         for i in &self.items {
-            match i {
-                None => return true,
+            match i.item() {
+                None => {
+                    count_remaining = count_remaining.saturating_sub(item.max_stack_size());
+                    if count_remaining == 0 {
+                        return true;
+                    }
+                }
                 Some(item_inner) => {
                     if item_inner.identifier() != item.identifier() {
                         continue;
                     }
                     if item_inner.metadata_is_stack_size() {
-                        count_remaining = count_remaining
-                            .wrapping_sub(MAX_ITEMS_PER_SLOT.wrapping_sub(item_inner.metadata()));
+                        // a slot over the limit has no space left rather than "negative" space
+                        let space = item_inner.max_stack_size().saturating_sub(item_inner.metadata());
+                        count_remaining = count_remaining.saturating_sub(space);
                         if count_remaining == 0 {
                             return true;
                         }
@@ -334,26 +415,141 @@ impl Inventory {
         false
     }
 
-    pub fn try_pull(&mut self, num: u32) -> Option<Box<dyn Item>> {
+    pub fn try_pull(&mut self, num: u32) -> Option<Stack> {
         for i in 0..self.items.len() {
-            match &mut self.items[i] {
+            match self.items[i].item_mut() {
                 None => continue,
                 Some(item) => {
                     if item.metadata_is_stack_size() && item.metadata() > num {
                         item.set_metadata(item.metadata() - num);
-                        let mut return_item = item.clone_item();
-                        return_item.set_metadata(num);
-                        return Some(return_item);
+                        return Some(split_off(&self.items[i], num));
+                    } else {
+                        return self.items[i].take();
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// Moves `num` items of the stack in `slot` into the first free slot and returns that slot
+    pub fn split_stack(&mut self, slot: usize, num: u32) -> Option<usize> {
+        let free_slot = self.items.iter().position(|item| item.is_none())?;
+        let item = self.items.get_mut(slot)?.item_mut()?;
+        if !item.metadata_is_stack_size() || num == 0 || num >= item.metadata() {
+            return None;
+        }
+
+        item.set_metadata(item.metadata() - num);
+        self.items[free_slot] = Slot::from(split_off(&self.items[slot], num));
+
+        Some(free_slot)
+    }
+
+    /// The number of items with the identifier `id` in all slots together
+    pub fn count(&self, id: Identifier) -> u64 {
+        self.items()
+            .filter(|item| item.identifier() == id)
+            .map(|item| item.stack_count() as u64)
+            .sum()
+    }
+
+    /// Removes `num` items with the identifier `id`, taking them from the last slots first. Nothing gets removed
+    /// when there aren't enough of them
+    pub fn remove(&mut self, id: Identifier, num: u32) -> bool {
+        if self.count(id) < num as u64 {
+            return false;
+        }
+
+        let mut remaining = num;
+        for slot in (0..self.items.len()).rev() {
+            if remaining == 0 {
+                break;
+            }
+            let Some(item) = self.items[slot].item_mut() else {
+                continue;
+            };
+            if item.identifier() != id {
+                continue;
+            }
+            if item.metadata_is_stack_size() && item.metadata() > remaining {
+                item.set_metadata(item.metadata() - remaining);
+                remaining = 0;
+            } else {
+                remaining -= item.stack_count();
+                self.items[slot] = Slot::Empty;
+            }
+        }
+        if self.is_player && num > 0 {
+            if let Some(item) = get_item_by_id(id) {
+                notice_board::add_entry(
+                    NoticeboardEntryRenderable::ItemCount(Stack::from(item.clone_item()), -(num as i64)),
+                    5,
+                );
+            }
+        }
+        true
+    }
+
+    pub fn try_pull_item(&mut self, id: Identifier, num: u32) -> Option<Stack> {
+        for i in 0..self.items.len() {
+            match self.items[i].item_mut() {
+                Some(item) if item.identifier() == id => {
+                    if item.metadata_is_stack_size() && item.metadata() > num {
+                        item.set_metadata(item.metadata() - num);
+                        return Some(split_off(&self.items[i], num));
                     } else {
                         return self.items[i].take();
                     }
                 }
+                _ => continue,
             }
         }
         None
     }
 }
 
+/// A stack of `num` of the item in `slot`, which must not be empty
+fn split_off(slot: &Slot, num: u32) -> Stack {
+    let mut item = slot.clone().take().expect("only stacks get split");
+    item.set_metadata(num);
+    item
+}
+
+/// Cuts `item` down to its max stack size and returns the rest, if there is any
+fn split_off_overflow(item: &mut Stack) -> Option<Stack> {
+    if !item.metadata_is_stack_size() || item.metadata() <= item.max_stack_size() {
+        return None;
+    }
+    let leftover = item.with_count(item.metadata() - item.max_stack_size());
+    let max = item.max_stack_size();
+    item.set_metadata(max);
+    Some(leftover)
+}
+
+/// Written the same way as an `Option<Box<dyn Item>>`, which is what slots used to be
+impl Serialize for Slot {
+    fn required_length(&self) -> usize {
+        SerializationTrap::required_length()
+            + bool::required_length(&false)
+            + self.item().map_or(0, item_required_length)
+    }
+
+    fn serialize(&self, buf: &mut Vec<u8>) {
+        SerializationTrap::Option.serialize(buf);
+        self.is_some().serialize(buf);
+        if let Some(item) = self.item() {
+            serialize_item(item, buf);
+        }
+    }
+}
+
+impl Deserialize for Slot {
+    fn try_deserialize(buf: &mut Buffer) -> Result<Self, SerializationError> {
+        Ok(Self::from(Option::<Stack>::try_deserialize(buf)?))
+    }
+}
+
 impl Serialize for Inventory {
     fn required_length(&self) -> usize {
         bool::required_length(&false) + self.items.required_length()
@@ -367,14 +563,280 @@ impl Serialize for Inventory {
 
 impl Deserialize for Inventory {
     fn deserialize(buf: &mut Buffer) -> Self {
-        let items = <Vec<Option<Box<dyn Item>>>>::deserialize(buf);
+        let items = <Vec<Slot>>::deserialize(buf);
         let is_player = bool::deserialize(buf);
         Self { is_player, items }
     }
 
     fn try_deserialize(buf: &mut Buffer) -> Result<Self, SerializationError> {
-        let items = <Vec<Option<Box<dyn Item>>>>::try_deserialize(buf)?;
+        let mut items = <Vec<Slot>>::try_deserialize(buf)?;
         let is_player = bool::try_deserialize(buf)?;
+        // a hand-edited save could hold stacks that are too big for a slot, which breaks the space calculations
+        for item in items.iter_mut().filter_map(Slot::item_mut) {
+            if item.metadata_is_stack_size() && item.metadata() > item.max_stack_size() {
+                item.set_metadata(item.max_stack_size());
+            }
+        }
         Ok(Self { is_player, items })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use raylib::drawing::RaylibDrawHandle;
+
+    use super::*;
+    use crate::{
+        blocks::conveyor::ConveyorBlock,
+        identifier::{GlobalString, Identifier},
+        items::{BlockItem, COAL_IDENTIFIER, IRON_ORE_IDENTIFIER},
+        test_utils::{item, register_all},
+    };
+
+    #[test]
+    fn counts_used_and_free_slots() {
+        register_all();
+        let mut inventory = Inventory::new(5, false);
+        assert_eq!((inventory.used_slots(), inventory.free_slots()), (0, 5));
+
+        inventory.add_item(item(*COAL_IDENTIFIER, 3), 0);
+        inventory.add_item(item(*IRON_ORE_IDENTIFIER, 1), 2);
+        inventory.add_item(item(*COAL_IDENTIFIER, MAX_ITEMS_PER_SLOT), 4);
+        assert_eq!((inventory.used_slots(), inventory.free_slots()), (3, 2));
+        assert_eq!(inventory.total_items(), 3 + 1 + MAX_ITEMS_PER_SLOT as u64);
+
+        inventory.take_item(2);
+        assert_eq!((inventory.used_slots(), inventory.free_slots()), (2, 3));
+    }
+
+    /// two coal slots with room for 15 more coal between them
+    fn almost_full() -> Inventory {
+        let mut inventory = Inventory::new(2, false);
+        inventory.add_item(item(*COAL_IDENTIFIER, MAX_ITEMS_PER_SLOT - 10), 0);
+        inventory.add_item(item(*COAL_IDENTIFIER, MAX_ITEMS_PER_SLOT - 5), 1);
+        inventory
+    }
+
+    #[test]
+    fn pushing_exactly_the_free_space() {
+        register_all();
+        let mut inventory = almost_full();
+        assert!(inventory.can_push(&item(*COAL_IDENTIFIER, 15)));
+        assert!(inventory.try_add_item(item(*COAL_IDENTIFIER, 15)).is_none());
+        assert_eq!(inventory.total_items(), MAX_ITEMS_PER_SLOT as u64 * 2);
+    }
+
+    #[test]
+    fn pushing_one_more_than_the_free_space() {
+        register_all();
+        let mut inventory = almost_full();
+        assert!(!inventory.can_push(&item(*COAL_IDENTIFIER, 16)));
+        let rest = inventory.try_add_item(item(*COAL_IDENTIFIER, 16)).unwrap();
+        assert_eq!(rest.stack_count(), 1);
+        assert_eq!(inventory.total_items(), MAX_ITEMS_PER_SLOT as u64 * 2);
+    }
+
+    #[test]
+    fn pushing_into_a_full_inventory() {
+        register_all();
+        let mut inventory = almost_full();
+        inventory.try_add_item(item(*COAL_IDENTIFIER, 15));
+        assert!(!inventory.can_push(&item(*COAL_IDENTIFIER, 1)));
+        assert!(!inventory.can_push(&item(*IRON_ORE_IDENTIFIER, 1)));
+        assert_eq!(inventory.try_add_item(item(*COAL_IDENTIFIER, 1)).unwrap().stack_count(), 1);
+
+        // a slot that is over the limit has no room rather than wrapping around to a lot of room
+        let mut inventory = Inventory::new(1, false);
+        inventory.get_item_mut(0).replace(item(*COAL_IDENTIFIER, MAX_ITEMS_PER_SLOT + 5));
+        assert!(!inventory.can_push(&item(*COAL_IDENTIFIER, 1)));
+    }
+
+    #[test]
+    fn oversized_stacks_are_clamped_on_load() {
+        register_all();
+        let mut inventory = Inventory::new(2, false);
+        // like a hand-edited save
+        inventory.get_item_mut(0).replace(item(*COAL_IDENTIFIER, 9999));
+        inventory.add_item(item(*COAL_IDENTIFIER, 5), 1);
+        let mut data = Vec::new();
+        inventory.serialize(&mut data);
+
+        let mut buf = Buffer::new(data);
+        let loaded = Inventory::try_deserialize(&mut buf).unwrap();
+        assert_eq!(buf.len(), 0);
+        assert_eq!(loaded.get_item(0).unwrap().stack_count(), MAX_ITEMS_PER_SLOT);
+        assert_eq!(loaded.get_item(1).unwrap().stack_count(), 5);
+        // only the second slot has room left
+        assert!(loaded.can_push(&item(*COAL_IDENTIFIER, MAX_ITEMS_PER_SLOT - 5)));
+        assert!(!loaded.can_push(&item(*COAL_IDENTIFIER, MAX_ITEMS_PER_SLOT - 4)));
+    }
+
+    /// An item that only fits `self.1` times into a slot
+    struct Capped(u32, u32);
+
+    impl Item for Capped {
+        fn clone_item(&self) -> Box<dyn Item> {
+            Box::new(Capped(self.0, self.1))
+        }
+        fn identifier(&self) -> Identifier {
+            Identifier::from(("tests", "capped"))
+        }
+        fn name(&self) -> GlobalString {
+            GlobalString::from("Capped")
+        }
+        fn metadata(&self) -> u32 {
+            self.0
+        }
+        fn max_stack_size(&self) -> u32 {
+            self.1
+        }
+        fn description(&self) -> &'static str {
+            ""
+        }
+        fn render(&self, _: &mut RaylibDrawHandle, _: i32, _: i32, _: i32, _: i32) {}
+        fn set_metadata(&mut self, new_data: u32) {
+            self.0 = new_data
+        }
+        fn serialize(&self, _: &mut Vec<u8>) {}
+        fn try_deserialize(&mut self, _: &mut Buffer) -> Result<(), SerializationError> {
+            Ok(())
+        }
+        fn required_length(&self) -> usize {
+            0
+        }
+    }
+
+    fn capped(count: u32, cap: u32) -> Stack {
+        Stack::Special(Box::new(Capped(count, cap)))
+    }
+
+    fn counts(inventory: &Inventory) -> Vec<Option<u32>> {
+        (0..inventory.size()).map(|slot| inventory.get_item(slot).map(|item| item.stack_count())).collect()
+    }
+
+    #[test]
+    fn items_with_a_cap_of_one_take_a_slot_each() {
+        let empty = Inventory::new(2, false);
+        assert!(empty.can_push(&capped(2, 1)));
+        assert!(!empty.can_push(&capped(3, 1)));
+
+        let mut inventory = Inventory::new(2, false);
+        assert_eq!(inventory.add_item(capped(3, 1), 0).unwrap().stack_count(), 2);
+        assert_eq!(counts(&inventory), [Some(1), None]);
+        // a full slot takes nothing, not even a single item
+        assert_eq!(inventory.add_item(capped(1, 1), 0).unwrap().stack_count(), 1);
+        assert!(inventory.can_push(&capped(1, 1)));
+        assert!(!inventory.can_push(&capped(2, 1)));
+
+        let mut inventory = Inventory::new(2, false);
+        assert_eq!(inventory.try_add_item(capped(3, 1)).unwrap().stack_count(), 1);
+        assert_eq!(counts(&inventory), [Some(1), Some(1)]);
+        assert!(!inventory.can_push(&capped(1, 1)));
+    }
+
+    #[test]
+    fn items_with_a_large_cap_fill_slots_past_the_default() {
+        let empty = Inventory::new(2, false);
+        assert!(empty.can_push(&capped(2000, 1000)));
+        assert!(!empty.can_push(&capped(2001, 1000)));
+
+        let mut inventory = Inventory::new(2, false);
+        assert_eq!(inventory.add_item(capped(1200, 1000), 0).unwrap().stack_count(), 200);
+        assert_eq!(counts(&inventory), [Some(1000), None]);
+
+        let mut inventory = Inventory::new(2, false);
+        assert!(inventory.try_add_item(capped(1500, 1000)).is_none());
+        assert_eq!(counts(&inventory), [Some(1000), Some(500)]);
+        assert!(inventory.can_push(&capped(500, 1000)));
+        assert!(!inventory.can_push(&capped(501, 1000)));
+        assert_eq!(inventory.try_add_item(capped(501, 1000)).unwrap().stack_count(), 1);
+        assert_eq!(counts(&inventory), [Some(1000), Some(1000)]);
+    }
+
+    /// `count` of `id` as a clone of the registered item, the way every stack used to be stored
+    fn boxed(id: Identifier, count: u32) -> Stack {
+        let mut item = get_item_by_id(id).unwrap().clone_item();
+        item.set_metadata(count);
+        Stack::Special(item)
+    }
+
+    fn written(value: &impl Serialize) -> Vec<u8> {
+        let mut data = Vec::new();
+        value.serialize(&mut data);
+        assert_eq!(data.len(), value.required_length());
+        data
+    }
+
+    /// what an `Option<Box<dyn Item>>` used to write
+    fn old_layout(item: Option<&dyn Item>) -> Vec<u8> {
+        let mut data = Vec::new();
+        SerializationTrap::Option.serialize(&mut data);
+        item.is_some().serialize(&mut data);
+        if let Some(item) = item {
+            serialize_item(item, &mut data);
+        }
+        data
+    }
+
+    #[test]
+    fn slots_are_written_like_the_old_boxed_items() {
+        register_all();
+        let block_item: Box<dyn Item> = Box::new(BlockItem::new(3, Box::new(ConveyorBlock::default())));
+        let slots = [
+            Slot::Empty,
+            Slot::from(item(*COAL_IDENTIFIER, 7)),
+            Slot::from(boxed(*COAL_IDENTIFIER, 7)),
+            Slot::from(Stack::from(block_item)),
+        ];
+        assert!(matches!(slots[1], Slot::Stack(_)));
+        assert!(matches!(slots[2], Slot::Special(_)));
+        // block items have no state of their own either, the block comes from the registry when it's placed
+        assert!(matches!(slots[3], Slot::Stack(_)));
+        for slot in &slots {
+            assert_eq!(written(slot), old_layout(slot.item()));
+        }
+        assert_eq!(written(&slots[1]), written(&slots[2]));
+
+        // plain items come back as stacks, no matter how they were stored when they got written
+        for slot in &slots {
+            let mut buf = Buffer::new(written(slot));
+            let loaded = Slot::try_deserialize(&mut buf).unwrap();
+            assert_eq!(buf.len(), 0);
+            assert_eq!(loaded.is_some(), slot.is_some());
+            assert_eq!(matches!(loaded, Slot::Stack(_)), slot.item().and_then(ItemStack::of).is_some());
+            assert_eq!(written(&loaded), written(slot));
+        }
+    }
+
+    #[test]
+    fn plain_and_boxed_stacks_behave_the_same() {
+        register_all();
+        let contents = |inventory: &Inventory| {
+            (0..inventory.size())
+                .map(|slot| inventory.get_item(slot).map(|item| (item.identifier(), item.stack_count())))
+                .collect::<Vec<_>>()
+        };
+        let mut results = Vec::new();
+        for make in [item as fn(Identifier, u32) -> Stack, boxed] {
+            let mut inventory = Inventory::new(4, false);
+            let leftovers = [
+                inventory.try_add_item(make(*COAL_IDENTIFIER, MAX_ITEMS_PER_SLOT + 20)),
+                inventory.try_add_item(make(*IRON_ORE_IDENTIFIER, 12)),
+                inventory.try_add_item(make(*COAL_IDENTIFIER, MAX_ITEMS_PER_SLOT * 3)),
+            ];
+            inventory.switch_items(0, 3);
+            let taken = inventory.take_item(1);
+            let pulled = inventory.try_pull(5);
+            let counts = |stacks: &[Option<Stack>]| {
+                stacks.iter().map(|stack| stack.as_ref().map(|item| item.stack_count())).collect::<Vec<_>>()
+            };
+            results.push((
+                contents(&inventory),
+                counts(&leftovers),
+                counts(&[taken, pulled]),
+                written(&inventory),
+            ));
+        }
+        assert_eq!(results[0], results[1]);
+    }
+}
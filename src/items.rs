@@ -1,4 +1,7 @@
-use std::fmt::Debug;
+use std::{
+    fmt::Debug,
+    ops::{Deref, DerefMut},
+};
 
 use lazy_static::lazy_static;
 use raylib::{
@@ -8,8 +11,8 @@ use raylib::{
 
 use crate::{
     blocks::Block,
-    game::RenderLayer,
     identifier::{GlobalString, Identifier},
+    inventory::MAX_ITEMS_PER_SLOT,
     serialization::{Buffer, SerializationError},
     world::{ChunkBlockMetadata, Direction},
 };
@@ -29,15 +32,50 @@ pub trait Item: Send + Sync {
     fn metadata_is_stack_size(&self) -> bool {
         true
     }
+    /// how many of this item fit into a single slot
+    fn max_stack_size(&self) -> u32 {
+        MAX_ITEMS_PER_SLOT
+    }
+    /// how many items this is, 1 if the metadata is durability
+    fn stack_count(&self) -> u32 {
+        if self.metadata_is_stack_size() {
+            self.metadata()
+        } else {
+            1
+        }
+    }
     fn description(&self) -> &'static str;
+    /// how long the item burns in a generator, None if it doesn't burn
+    fn burn_time_ms(&self) -> Option<u32> {
+        None
+    }
     fn render(&self, renderer: &mut RaylibDrawHandle, x: i32, y: i32, w: i32, h: i32);
+    /// Renders a stack of `count` items, e.g. in inventory slots. Defaults to rendering a single item
+    #[allow(unused_variables)]
+    fn render_stack(
+        &self,
+        renderer: &mut RaylibDrawHandle,
+        x: i32,
+        y: i32,
+        w: i32,
+        h: i32,
+        count: u32,
+    ) {
+        self.render(renderer, x, y, w, h)
+    }
+    /// Where `render_stack` draws the lumps of a stack of `count`, in quarters of the slot. Empty if the stack is
+    /// drawn as a single item
+    #[allow(unused_variables)]
+    fn stack_lumps(&self, count: u32) -> &'static [(i32, i32)] {
+        &[]
+    }
     fn set_metadata(&mut self, new_data: u32);
     fn serialize(&self, vec: &mut Vec<u8>);
     fn try_deserialize(&mut self, buf: &mut Buffer) -> Result<(), SerializationError>;
     fn required_length(&self) -> usize;
 }
 
-impl Debug for dyn Item {
+impl Debug for dyn Item + '_ {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_fmt(format_args!(
             "{}({:?})[{}]",
@@ -55,6 +93,18 @@ impl Debug for dyn Item {
 lazy_static! {
     pub static ref COAL_IDENTIFIER: Identifier = Identifier::from(("placeholder_name_2", "coal"));
     pub static ref COAL_NAME: GlobalString = GlobalString::from("Coal");
+    pub static ref IRON_ORE_IDENTIFIER: Identifier =
+        Identifier::from(("placeholder_name_2", "iron_ore"));
+    pub static ref IRON_ORE_NAME: GlobalString = GlobalString::from("Iron Ore");
+    pub static ref COPPER_ORE_IDENTIFIER: Identifier =
+        Identifier::from(("placeholder_name_2", "copper_ore"));
+    pub static ref COPPER_ORE_NAME: GlobalString = GlobalString::from("Copper Ore");
+    pub static ref IRON_INGOT_IDENTIFIER: Identifier =
+        Identifier::from(("placeholder_name_2", "iron_ingot"));
+    pub static ref IRON_INGOT_NAME: GlobalString = GlobalString::from("Iron Ingot");
+    pub static ref COPPER_INGOT_IDENTIFIER: Identifier =
+        Identifier::from(("placeholder_name_2", "copper_ingot"));
+    pub static ref COPPER_INGOT_NAME: GlobalString = GlobalString::from("Copper Ingot");
 }
 
 macro_rules! empty_serializable {
@@ -69,41 +119,137 @@ macro_rules! empty_serializable {
     };
 }
 
-pub struct ItemCoal(u32);
+macro_rules! some_or_none {
+    () => {
+        None
+    };
+    ($value: expr) => {
+        Some($value)
+    };
+}
 
-impl Item for ItemCoal {
-    empty_serializable!();
-    fn description(&self) -> &'static str {
-        "Coal is most commonly used as a fuel for generators"
-    }
-    fn clone_item(&self) -> Box<dyn Item> {
-        Box::new(Self(self.0))
-    }
-    fn identifier(&self) -> Identifier {
-        *COAL_IDENTIFIER
-    }
-    fn name(&self) -> GlobalString {
-        *COAL_NAME
-    }
-    fn metadata(&self) -> u32 {
-        self.0
-    }
-    fn render(&self, renderer: &mut RaylibDrawHandle, x: i32, y: i32, w: i32, h: i32) {
-        renderer.draw_ellipse(
-            x + w / 2,
-            y + h / 2,
-            w as f32 / 3.0,
-            h as f32 / 2.0,
-            Color::BLACK,
-        );
-    }
-    fn set_metadata(&mut self, new_data: u32) {
-        self.0 = new_data
+/// Where the lumps of a stack of `count` resources are drawn, in quarters of the slot. Single items are drawn as one
+/// big lump instead
+fn resource_lumps(count: u32) -> &'static [(i32, i32)] {
+    match count {
+        0 | 1 => &[],
+        2 => &[(1, 1), (3, 3)],
+        _ => &[(1, 3), (3, 3), (2, 1)],
     }
 }
 
+macro_rules! resource_item {
+    ($name: ident, $identifier: expr, $item_name: expr, $description: expr, $color: expr $(, burns for $burn_time: expr)?) => {
+        pub struct $name(u32);
+
+        impl Item for $name {
+            empty_serializable!();
+            fn description(&self) -> &'static str {
+                $description
+            }
+            fn burn_time_ms(&self) -> Option<u32> {
+                some_or_none!($($burn_time)?)
+            }
+            fn clone_item(&self) -> Box<dyn Item> {
+                Box::new(Self(self.0))
+            }
+            fn identifier(&self) -> Identifier {
+                *$identifier
+            }
+            fn name(&self) -> GlobalString {
+                *$item_name
+            }
+            fn metadata(&self) -> u32 {
+                self.0
+            }
+            fn render(&self, renderer: &mut RaylibDrawHandle, x: i32, y: i32, w: i32, h: i32) {
+                renderer.draw_ellipse(
+                    x + w / 2,
+                    y + h / 2,
+                    w as f32 / 3.0,
+                    h as f32 / 2.0,
+                    $color,
+                );
+            }
+            /// draws up to 3 smaller lumps for stacks
+            fn render_stack(
+                &self,
+                renderer: &mut RaylibDrawHandle,
+                x: i32,
+                y: i32,
+                w: i32,
+                h: i32,
+                count: u32,
+            ) {
+                let lumps = self.stack_lumps(count);
+                if lumps.is_empty() {
+                    return self.render(renderer, x, y, w, h);
+                }
+                for (lump_x, lump_y) in lumps {
+                    renderer.draw_ellipse(
+                        x + w * lump_x / 4,
+                        y + h * lump_y / 4,
+                        w as f32 / 5.0,
+                        h as f32 / 4.0,
+                        $color,
+                    );
+                }
+            }
+            fn stack_lumps(&self, count: u32) -> &'static [(i32, i32)] {
+                resource_lumps(count)
+            }
+            fn set_metadata(&mut self, new_data: u32) {
+                self.0 = new_data
+            }
+        }
+    };
+}
+
+resource_item!(
+    ItemCoal,
+    COAL_IDENTIFIER,
+    COAL_NAME,
+    "Coal is most commonly used as a fuel for generators",
+    Color::BLACK,
+    burns for 4000
+);
+resource_item!(
+    ItemIronOre,
+    IRON_ORE_IDENTIFIER,
+    IRON_ORE_NAME,
+    "Raw iron, waiting to be smelted",
+    Color::GRAY
+);
+resource_item!(
+    ItemCopperOre,
+    COPPER_ORE_IDENTIFIER,
+    COPPER_ORE_NAME,
+    "Raw copper, waiting to be smelted",
+    Color::ORANGE
+);
+resource_item!(
+    ItemIronIngot,
+    IRON_INGOT_IDENTIFIER,
+    IRON_INGOT_NAME,
+    "Smelted iron, ready to be made into something",
+    Color::LIGHTGRAY
+);
+resource_item!(
+    ItemCopperIngot,
+    COPPER_INGOT_IDENTIFIER,
+    COPPER_INGOT_NAME,
+    "Smelted copper, ready to be made into something",
+    Color::GOLD
+);
+
 pub struct BlockItem(u32, Box<dyn Block>);
 
+impl BlockItem {
+    pub fn new(count: u32, block: Box<dyn Block>) -> Self {
+        Self(count, block)
+    }
+}
+
 impl Item for BlockItem {
     empty_serializable!();
 
@@ -123,14 +269,13 @@ impl Item for BlockItem {
         self.0
     }
     fn render(&self, renderer: &mut RaylibDrawHandle, x: i32, y: i32, w: i32, h: i32) {
-        self.1.render(
+        self.1.render_preview(
             renderer,
             x,
             y,
             w,
             h,
             ChunkBlockMetadata::from(Direction::North),
-            RenderLayer::default_preview(),
         )
     }
     fn set_metadata(&mut self, new_data: u32) {
@@ -138,10 +283,166 @@ impl Item for BlockItem {
     }
 }
 
+/// A stack of a plain item, one that is nothing but its identifier and a count. Everything else about it comes from
+/// the registered item, so it can be copied and moved around without an allocation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ItemStack {
+    pub id: Identifier,
+    pub count: u32,
+}
+
+impl ItemStack {
+    /// The stack for `item`, if it is a plain item
+    pub fn of(item: &dyn Item) -> Option<Self> {
+        let is_plain = item.metadata_is_stack_size() && item.required_length() == 0;
+        (is_plain && get_item_by_id(item.identifier()).is_some()).then(|| Self {
+            id: item.identifier(),
+            count: item.metadata(),
+        })
+    }
+
+    fn registered(&self) -> &'static dyn Item {
+        &**get_item_by_id(self.id).expect("plain stacks are only made of registered items")
+    }
+}
+
+impl Item for ItemStack {
+    empty_serializable!();
+
+    fn clone_item(&self) -> Box<dyn Item> {
+        Box::new(*self)
+    }
+    fn identifier(&self) -> Identifier {
+        self.id
+    }
+    fn name(&self) -> GlobalString {
+        self.registered().name()
+    }
+    fn metadata(&self) -> u32 {
+        self.count
+    }
+    fn max_stack_size(&self) -> u32 {
+        self.registered().max_stack_size()
+    }
+    fn description(&self) -> &'static str {
+        self.registered().description()
+    }
+    fn burn_time_ms(&self) -> Option<u32> {
+        self.registered().burn_time_ms()
+    }
+    fn render(&self, renderer: &mut RaylibDrawHandle, x: i32, y: i32, w: i32, h: i32) {
+        self.registered().render(renderer, x, y, w, h)
+    }
+    fn render_stack(
+        &self,
+        renderer: &mut RaylibDrawHandle,
+        x: i32,
+        y: i32,
+        w: i32,
+        h: i32,
+        count: u32,
+    ) {
+        self.registered().render_stack(renderer, x, y, w, h, count)
+    }
+    fn stack_lumps(&self, count: u32) -> &'static [(i32, i32)] {
+        self.registered().stack_lumps(count)
+    }
+    fn set_metadata(&mut self, new_data: u32) {
+        self.count = new_data
+    }
+}
+
+/// An item as it is moved between inventories and blocks. Plain items are kept as an `ItemStack`, only items with
+/// state of their own are boxed
+pub enum Stack {
+    Plain(ItemStack),
+    Special(Box<dyn Item>),
+}
+
+impl Stack {
+    /// `count` of the registered item `id`
+    pub fn of(id: Identifier, count: u32) -> Option<Self> {
+        let registered = get_item_by_id(id)?;
+        Some(match ItemStack::of(&**registered) {
+            Some(_) => Self::Plain(ItemStack { id, count }),
+            None => {
+                let mut item = registered.clone_item();
+                item.set_metadata(count);
+                Self::Special(item)
+            }
+        })
+    }
+
+    /// The same item, but `count` of it
+    pub fn with_count(&self, count: u32) -> Self {
+        let mut stack = self.clone();
+        stack.set_metadata(count);
+        stack
+    }
+}
+
+impl From<Box<dyn Item>> for Stack {
+    fn from(item: Box<dyn Item>) -> Self {
+        match ItemStack::of(&*item) {
+            Some(stack) => Self::Plain(stack),
+            None => Self::Special(item),
+        }
+    }
+}
+
+/// A copy of `item`, which only needs an allocation if it isn't a plain item
+impl From<&dyn Item> for Stack {
+    fn from(item: &dyn Item) -> Self {
+        match ItemStack::of(item) {
+            Some(stack) => Self::Plain(stack),
+            None => Self::Special(item.clone_item()),
+        }
+    }
+}
+
+impl Clone for Stack {
+    fn clone(&self) -> Self {
+        match self {
+            Self::Plain(stack) => Self::Plain(*stack),
+            Self::Special(item) => Self::Special(item.clone_item()),
+        }
+    }
+}
+
+impl Deref for Stack {
+    type Target = dyn Item;
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            Self::Plain(stack) => stack,
+            Self::Special(item) => &**item,
+        }
+    }
+}
+
+impl DerefMut for Stack {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        match self {
+            Self::Plain(stack) => stack,
+            Self::Special(item) => &mut **item,
+        }
+    }
+}
+
+impl Debug for Stack {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
 pub static mut ITEMS: Vec<Box<dyn Item>> = Vec::new();
 
 pub fn register_items() {
     register_item(Box::new(ItemCoal(1)));
+    register_item(Box::new(ItemIronOre(1)));
+    register_item(Box::new(ItemCopperOre(1)));
+    register_item(Box::new(ItemIronIngot(1)));
+    register_item(Box::new(ItemCopperIngot(1)));
 }
 
 pub fn register_item(item: Box<dyn Item>) {
@@ -165,4 +466,36 @@ pub fn get_item_by_id(id: Identifier) -> Option<&'static Box<dyn Item>> {
         }
     }
     None
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        blocks::assembler::BLOCK_ASSEMBLER,
+        test_utils::{item, register_all},
+    };
+
+    #[test]
+    fn resource_stacks_are_drawn_as_lumps() {
+        assert!(resource_lumps(1).is_empty());
+        assert_eq!(resource_lumps(2).len(), 2);
+        assert_eq!(resource_lumps(3).len(), 3);
+        assert_eq!(resource_lumps(MAX_ITEMS_PER_SLOT), resource_lumps(3));
+        // every lump stays inside the slot
+        for &(x, y) in resource_lumps(3) {
+            assert!((1..4).contains(&x) && (1..4).contains(&y));
+        }
+    }
+
+    #[test]
+    fn coal_stacks_use_the_resource_lumps() {
+        register_all();
+        // plain stacks hand rendering on to the registered coal item
+        let coal = item(*COAL_IDENTIFIER, 50);
+        assert_eq!(coal.stack_lumps(50), resource_lumps(3));
+        assert_eq!(coal.stack_lumps(1), resource_lumps(1));
+        // other items keep drawing a single item
+        let assembler = get_item_by_id(*BLOCK_ASSEMBLER).unwrap();
+        assert!(assembler.stack_lumps(50).is_empty());
+    }
+}
@@ -0,0 +1,427 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Mutex,
+};
+
+use raylib::ffi::KeyboardKey;
+
+use crate::game::HOTBAR_KEYS;
+
+/// The keys of every keyboard action in the game
+#[derive(Debug, Clone, Copy)]
+pub struct KeyBindings {
+    pub up: KeyboardKey,
+    pub down: KeyboardKey,
+    pub left: KeyboardKey,
+    pub right: KeyboardKey,
+    /// held to move faster, or to rotate a selection the other way
+    pub modifier: KeyboardKey,
+    pub snap_to_grid: KeyboardKey,
+    pub inventory: KeyboardKey,
+    pub block_selector: KeyboardKey,
+    pub select: KeyboardKey,
+    pub rotate_selection: KeyboardKey,
+    pub dismantle: KeyboardKey,
+    pub interact: KeyboardKey,
+    pub show_network: KeyboardKey,
+    /// stops the mouse wheel from rotating the block that is being built
+    pub lock_orientation: KeyboardKey,
+    pub minimap: KeyboardKey,
+}
+
+pub const DEFAULT_BINDINGS: KeyBindings = KeyBindings {
+    up: KeyboardKey::KEY_W,
+    down: KeyboardKey::KEY_S,
+    left: KeyboardKey::KEY_A,
+    right: KeyboardKey::KEY_D,
+    modifier: KeyboardKey::KEY_LEFT_SHIFT,
+    snap_to_grid: KeyboardKey::KEY_V,
+    inventory: KeyboardKey::KEY_TAB,
+    block_selector: KeyboardKey::KEY_B,
+    select: KeyboardKey::KEY_C,
+    rotate_selection: KeyboardKey::KEY_R,
+    dismantle: KeyboardKey::KEY_G,
+    interact: KeyboardKey::KEY_F,
+    show_network: KeyboardKey::KEY_N,
+    lock_orientation: KeyboardKey::KEY_CAPS_LOCK,
+    minimap: KeyboardKey::KEY_M,
+};
+
+/// movement on IJKL and the actions around it, so the left hand stays on the mouse
+pub const LEFT_HANDED_BINDINGS: KeyBindings = KeyBindings {
+    up: KeyboardKey::KEY_I,
+    down: KeyboardKey::KEY_K,
+    left: KeyboardKey::KEY_J,
+    right: KeyboardKey::KEY_L,
+    modifier: KeyboardKey::KEY_RIGHT_SHIFT,
+    snap_to_grid: KeyboardKey::KEY_M,
+    inventory: KeyboardKey::KEY_ENTER,
+    block_selector: KeyboardKey::KEY_P,
+    select: KeyboardKey::KEY_COMMA,
+    rotate_selection: KeyboardKey::KEY_Y,
+    dismantle: KeyboardKey::KEY_O,
+    interact: KeyboardKey::KEY_U,
+    show_network: KeyboardKey::KEY_PERIOD,
+    lock_orientation: KeyboardKey::KEY_H,
+    minimap: KeyboardKey::KEY_SLASH,
+};
+
+static LEFT_HANDED: AtomicBool = AtomicBool::new(false);
+
+pub fn left_handed() -> bool {
+    LEFT_HANDED.load(Ordering::Relaxed)
+}
+
+pub fn set_left_handed(enabled: bool) {
+    LEFT_HANDED.store(enabled, Ordering::Relaxed);
+}
+
+/// The actions the player can rebind in the options
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    OpenInventory,
+    OpenBuildMenu,
+    Dismantle,
+    Interact,
+}
+
+pub const NUM_ACTIONS: usize = 8;
+
+impl Action {
+    pub const ALL: [Action; NUM_ACTIONS] = [
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::OpenInventory,
+        Action::OpenBuildMenu,
+        Action::Dismantle,
+        Action::Interact,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::MoveUp => "Move up",
+            Self::MoveDown => "Move down",
+            Self::MoveLeft => "Move left",
+            Self::MoveRight => "Move right",
+            Self::OpenInventory => "Open inventory",
+            Self::OpenBuildMenu => "Open build menu",
+            Self::Dismantle => "Dismantle",
+            Self::Interact => "Interact",
+        }
+    }
+}
+
+impl KeyBindings {
+    pub fn key(&self, action: Action) -> KeyboardKey {
+        match action {
+            Action::MoveUp => self.up,
+            Action::MoveDown => self.down,
+            Action::MoveLeft => self.left,
+            Action::MoveRight => self.right,
+            Action::OpenInventory => self.inventory,
+            Action::OpenBuildMenu => self.block_selector,
+            Action::Dismantle => self.dismantle,
+            Action::Interact => self.interact,
+        }
+    }
+
+    /// Every key of the bindings, including the ones that can't be rebound
+    fn all_keys(&self) -> [KeyboardKey; 15] {
+        [
+            self.up,
+            self.down,
+            self.left,
+            self.right,
+            self.modifier,
+            self.snap_to_grid,
+            self.inventory,
+            self.block_selector,
+            self.select,
+            self.rotate_selection,
+            self.dismantle,
+            self.interact,
+            self.show_network,
+            self.lock_orientation,
+            self.minimap,
+        ]
+    }
+
+    fn set_key(&mut self, action: Action, key: KeyboardKey) {
+        let field = match action {
+            Action::MoveUp => &mut self.up,
+            Action::MoveDown => &mut self.down,
+            Action::MoveLeft => &mut self.left,
+            Action::MoveRight => &mut self.right,
+            Action::OpenInventory => &mut self.inventory,
+            Action::OpenBuildMenu => &mut self.block_selector,
+            Action::Dismantle => &mut self.dismantle,
+            Action::Interact => &mut self.interact,
+        };
+        *field = key;
+    }
+}
+
+/// Keys the player picked in the options, indexed like `Action::ALL`. They replace the key of the preset
+static KEY_OVERRIDES: Mutex<[Option<KeyboardKey>; NUM_ACTIONS]> = Mutex::new([None; NUM_ACTIONS]);
+
+pub fn key_overrides() -> [Option<KeyboardKey>; NUM_ACTIONS] {
+    *KEY_OVERRIDES.lock().unwrap()
+}
+
+pub fn set_key_overrides(overrides: [Option<KeyboardKey>; NUM_ACTIONS]) {
+    *KEY_OVERRIDES.lock().unwrap() = overrides;
+}
+
+/// Why `rebind` didn't bind a key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RebindError {
+    /// the game handles the key itself, e.g. Escape closes screens
+    Reserved,
+    /// a binding that can't be changed in the options already uses the key
+    Taken,
+}
+
+/// Keys the game handles itself besides the hotbar keys: Escape, the debug and cheat toggles and Ctrl+Z for undo
+const RESERVED_KEYS: [KeyboardKey; 8] = [
+    KeyboardKey::KEY_ESCAPE,
+    KeyboardKey::KEY_F3,
+    KeyboardKey::KEY_F4,
+    KeyboardKey::KEY_F6,
+    KeyboardKey::KEY_F7,
+    KeyboardKey::KEY_Z,
+    KeyboardKey::KEY_LEFT_CONTROL,
+    KeyboardKey::KEY_RIGHT_CONTROL,
+];
+
+/// The overrides that bind `action` to `key` in `bindings`, which are `overrides` applied to a preset. Another action
+/// on `key` gets the old key of `action` instead, so no two bindings ever share a key
+pub fn rebound(
+    bindings: &KeyBindings,
+    mut overrides: [Option<KeyboardKey>; NUM_ACTIONS],
+    action: Action,
+    key: KeyboardKey,
+) -> Result<[Option<KeyboardKey>; NUM_ACTIONS], RebindError> {
+    if RESERVED_KEYS.contains(&key) || HOTBAR_KEYS.contains(&key) {
+        return Err(RebindError::Reserved);
+    }
+    let old_key = bindings.key(action);
+    if let Some(idx) = Action::ALL.iter().position(|other| bindings.key(*other) == key) {
+        overrides[idx] = Some(old_key);
+    } else if bindings.all_keys().contains(&key) {
+        return Err(RebindError::Taken);
+    }
+    if let Some(idx) = Action::ALL.iter().position(|other| *other == action) {
+        overrides[idx] = Some(key);
+    }
+    Ok(overrides)
+}
+
+/// Binds `action` to `key`, swapping keys with the action that had it before. See `rebound`
+pub fn rebind(action: Action, key: KeyboardKey) -> Result<(), RebindError> {
+    set_key_overrides(rebound(&key_bindings(), key_overrides(), action, key)?);
+    Ok(())
+}
+
+/// The bindings of the selected preset, with the keys the player picked instead
+pub fn key_bindings() -> KeyBindings {
+    let mut bindings = if left_handed() {
+        LEFT_HANDED_BINDINGS
+    } else {
+        DEFAULT_BINDINGS
+    };
+    for (action, key) in Action::ALL.into_iter().zip(key_overrides()) {
+        if let Some(key) = key {
+            bindings.set_key(action, key);
+        }
+    }
+    bindings
+}
+
+const LETTERS: [&str; 26] = [
+    "A", "B", "C", "D", "E", "F", "G", "H", "I", "J", "K", "L", "M", "N", "O", "P", "Q", "R", "S", "T", "U", "V",
+    "W", "X", "Y", "Z",
+];
+const DIGITS: [&str; 10] = ["0", "1", "2", "3", "4", "5", "6", "7", "8", "9"];
+const FUNCTION_KEYS: [&str; 12] = ["F1", "F2", "F3", "F4", "F5", "F6", "F7", "F8", "F9", "F10", "F11", "F12"];
+const KEYPAD_DIGITS: [&str; 10] = [
+    "Keypad 0", "Keypad 1", "Keypad 2", "Keypad 3", "Keypad 4", "Keypad 5", "Keypad 6", "Keypad 7", "Keypad 8",
+    "Keypad 9",
+];
+
+/// The label of `key` in hints like "Press F to interact"
+pub fn key_name(key: KeyboardKey) -> &'static str {
+    let code = key as i32;
+    match key {
+        _ if (KeyboardKey::KEY_A as i32..=KeyboardKey::KEY_Z as i32).contains(&code) => {
+            LETTERS[(code - KeyboardKey::KEY_A as i32) as usize]
+        }
+        _ if (KeyboardKey::KEY_ZERO as i32..=KeyboardKey::KEY_NINE as i32).contains(&code) => {
+            DIGITS[(code - KeyboardKey::KEY_ZERO as i32) as usize]
+        }
+        _ if (KeyboardKey::KEY_F1 as i32..=KeyboardKey::KEY_F12 as i32).contains(&code) => {
+            FUNCTION_KEYS[(code - KeyboardKey::KEY_F1 as i32) as usize]
+        }
+        _ if (KeyboardKey::KEY_KP_0 as i32..=KeyboardKey::KEY_KP_9 as i32).contains(&code) => {
+            KEYPAD_DIGITS[(code - KeyboardKey::KEY_KP_0 as i32) as usize]
+        }
+        KeyboardKey::KEY_SPACE => "Space",
+        KeyboardKey::KEY_ENTER => "Enter",
+        KeyboardKey::KEY_TAB => "Tab",
+        KeyboardKey::KEY_BACKSPACE => "Backspace",
+        KeyboardKey::KEY_CAPS_LOCK => "Caps Lock",
+        KeyboardKey::KEY_LEFT_SHIFT | KeyboardKey::KEY_RIGHT_SHIFT => "Shift",
+        KeyboardKey::KEY_LEFT_CONTROL | KeyboardKey::KEY_RIGHT_CONTROL => "Ctrl",
+        KeyboardKey::KEY_LEFT_ALT | KeyboardKey::KEY_RIGHT_ALT => "Alt",
+        KeyboardKey::KEY_UP => "Up",
+        KeyboardKey::KEY_DOWN => "Down",
+        KeyboardKey::KEY_LEFT => "Left",
+        KeyboardKey::KEY_RIGHT => "Right",
+        KeyboardKey::KEY_COMMA => ",",
+        KeyboardKey::KEY_PERIOD => ".",
+        KeyboardKey::KEY_MINUS => "-",
+        KeyboardKey::KEY_SEMICOLON => ";",
+        KeyboardKey::KEY_APOSTROPHE => "'",
+        KeyboardKey::KEY_SLASH => "/",
+        KeyboardKey::KEY_BACKSLASH => "\\",
+        KeyboardKey::KEY_EQUAL => "=",
+        KeyboardKey::KEY_LEFT_BRACKET => "[",
+        KeyboardKey::KEY_RIGHT_BRACKET => "]",
+        KeyboardKey::KEY_GRAVE => "`",
+        KeyboardKey::KEY_ESCAPE => "Escape",
+        KeyboardKey::KEY_INSERT => "Insert",
+        KeyboardKey::KEY_DELETE => "Delete",
+        KeyboardKey::KEY_HOME => "Home",
+        KeyboardKey::KEY_END => "End",
+        KeyboardKey::KEY_PAGE_UP => "Page Up",
+        KeyboardKey::KEY_PAGE_DOWN => "Page Down",
+        KeyboardKey::KEY_LEFT_SUPER | KeyboardKey::KEY_RIGHT_SUPER => "Super",
+        KeyboardKey::KEY_KP_DECIMAL => "Keypad .",
+        KeyboardKey::KEY_KP_DIVIDE => "Keypad /",
+        KeyboardKey::KEY_KP_MULTIPLY => "Keypad *",
+        KeyboardKey::KEY_KP_SUBTRACT => "Keypad -",
+        KeyboardKey::KEY_KP_ADD => "Keypad +",
+        KeyboardKey::KEY_KP_ENTER => "Keypad Enter",
+        KeyboardKey::KEY_KP_EQUAL => "Keypad =",
+        _ => "?",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn left_handed_preset_moves_with_ijkl() {
+        let movement = [Action::MoveUp, Action::MoveLeft, Action::MoveDown, Action::MoveRight];
+        assert_eq!(
+            movement.map(|action| LEFT_HANDED_BINDINGS.key(action)),
+            [KeyboardKey::KEY_I, KeyboardKey::KEY_J, KeyboardKey::KEY_K, KeyboardKey::KEY_L]
+        );
+        assert_eq!(
+            movement.map(|action| DEFAULT_BINDINGS.key(action)),
+            [KeyboardKey::KEY_W, KeyboardKey::KEY_A, KeyboardKey::KEY_S, KeyboardKey::KEY_D]
+        );
+
+        // every key moved and no preset uses a key twice
+        let left_handed = LEFT_HANDED_BINDINGS.all_keys();
+        let default = DEFAULT_BINDINGS.all_keys();
+        for keys in [left_handed, default] {
+            for (i, key) in keys.iter().enumerate() {
+                assert!(!keys[i + 1..].contains(key), "{key:?} is bound twice");
+            }
+        }
+        for (left, default) in left_handed.into_iter().zip(default) {
+            assert_ne!(left, default);
+        }
+    }
+
+    /// `overrides` applied to the default preset
+    fn with_overrides(overrides: [Option<KeyboardKey>; NUM_ACTIONS]) -> KeyBindings {
+        let mut bindings = DEFAULT_BINDINGS;
+        for (action, key) in Action::ALL.into_iter().zip(overrides) {
+            if let Some(key) = key {
+                bindings.set_key(action, key);
+            }
+        }
+        bindings
+    }
+
+    #[test]
+    fn rebinding_to_a_used_key_swaps() {
+        let none = [None; NUM_ACTIONS];
+        // a free key just gets bound
+        let overrides = rebound(&DEFAULT_BINDINGS, none, Action::Interact, KeyboardKey::KEY_E).unwrap();
+        assert_eq!(with_overrides(overrides).key(Action::Interact), KeyboardKey::KEY_E);
+
+        // B opens the build menu, which gets W instead
+        let overrides = rebound(&DEFAULT_BINDINGS, none, Action::MoveUp, KeyboardKey::KEY_B).unwrap();
+        let bindings = with_overrides(overrides);
+        assert_eq!(bindings.key(Action::MoveUp), KeyboardKey::KEY_B);
+        assert_eq!(bindings.key(Action::OpenBuildMenu), KeyboardKey::KEY_W);
+        let keys = bindings.all_keys();
+        for (i, key) in keys.iter().enumerate() {
+            assert!(!keys[i + 1..].contains(key), "{key:?} is bound twice");
+        }
+
+        // binding an action to its own key changes nothing
+        let same = rebound(&bindings, overrides, Action::MoveUp, KeyboardKey::KEY_B).unwrap();
+        assert_eq!(with_overrides(same).all_keys(), bindings.all_keys());
+    }
+
+    #[test]
+    fn reserved_and_fixed_keys_cant_be_bound() {
+        let none = [None; NUM_ACTIONS];
+        let escape = rebound(&DEFAULT_BINDINGS, none, Action::MoveUp, KeyboardKey::KEY_ESCAPE);
+        assert_eq!(escape, Err(RebindError::Reserved));
+        // shift and C can't be rebound in the options, so nothing could take their place
+        let shift = rebound(&DEFAULT_BINDINGS, none, Action::MoveUp, KeyboardKey::KEY_LEFT_SHIFT);
+        assert_eq!(shift, Err(RebindError::Taken));
+        let select = rebound(&DEFAULT_BINDINGS, none, Action::Dismantle, KeyboardKey::KEY_C);
+        assert_eq!(select, Err(RebindError::Taken));
+    }
+
+    #[test]
+    fn keys_the_game_handles_cant_be_bound() {
+        let overrides = rebound(&DEFAULT_BINDINGS, [None; NUM_ACTIONS], Action::Interact, KeyboardKey::KEY_E).unwrap();
+        for key in [KeyboardKey::KEY_ONE, KeyboardKey::KEY_F3] {
+            assert_eq!(
+                rebound(&with_overrides(overrides), overrides, Action::MoveUp, key),
+                Err(RebindError::Reserved),
+                "{key:?}"
+            );
+        }
+        assert_eq!(with_overrides(overrides).key(Action::MoveUp), KeyboardKey::KEY_W);
+        assert_eq!(with_overrides(overrides).key(Action::Interact), KeyboardKey::KEY_E);
+    }
+
+    #[test]
+    fn every_bindable_key_has_a_name() {
+        let cases = [
+            (KeyboardKey::KEY_Q, "Q"),
+            (KeyboardKey::KEY_SEVEN, "7"),
+            (KeyboardKey::KEY_F1, "F1"),
+            (KeyboardKey::KEY_F12, "F12"),
+            (KeyboardKey::KEY_KP_0, "Keypad 0"),
+            (KeyboardKey::KEY_KP_9, "Keypad 9"),
+            (KeyboardKey::KEY_KP_ADD, "Keypad +"),
+            (KeyboardKey::KEY_KP_ENTER, "Keypad Enter"),
+            (KeyboardKey::KEY_SLASH, "/"),
+            (KeyboardKey::KEY_BACKSLASH, "\\"),
+            (KeyboardKey::KEY_LEFT_BRACKET, "["),
+            (KeyboardKey::KEY_GRAVE, "`"),
+            (KeyboardKey::KEY_PAGE_DOWN, "Page Down"),
+        ];
+        for (key, name) in cases {
+            assert_eq!(key_name(key), name, "{key:?}");
+        }
+        for key in DEFAULT_BINDINGS.all_keys().into_iter().chain(LEFT_HANDED_BINDINGS.all_keys()) {
+            assert_ne!(key_name(key), "?", "{key:?}");
+        }
+    }
+}
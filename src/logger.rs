@@ -0,0 +1,169 @@
+use std::{
+    fs::File,
+    io::Write,
+    sync::{
+        atomic::{AtomicBool, AtomicU8, Ordering},
+        Mutex,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::asset;
+
+/// env var to set the minimum log level (`debug`, `info`, `warn` or `error`)
+const LOG_LEVEL_ENV: &str = "PLACEHOLDER_NAME_2_LOG";
+/// env var to also print log messages to stdout (`1`/`true`) or not (`0`/`false`). Defaults to on in debug builds
+const LOG_STDOUT_ENV: &str = "PLACEHOLDER_NAME_2_LOG_STDOUT";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(u8)]
+pub enum LogLevel {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl LogLevel {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Debug => "DEBUG",
+            Self::Info => "INFO",
+            Self::Warn => "WARN",
+            Self::Error => "ERROR",
+        }
+    }
+
+    pub fn parse(str: &str) -> Option<Self> {
+        match str.trim().to_ascii_lowercase().as_str() {
+            "debug" => Some(Self::Debug),
+            "info" => Some(Self::Info),
+            "warn" | "warning" => Some(Self::Warn),
+            "error" => Some(Self::Error),
+            _ => None,
+        }
+    }
+
+    fn from_u8(value: u8) -> Self {
+        match value {
+            0 => Self::Debug,
+            1 => Self::Info,
+            2 => Self::Warn,
+            _ => Self::Error,
+        }
+    }
+}
+
+static MIN_LEVEL: AtomicU8 = AtomicU8::new(LogLevel::Info as u8);
+static LOG_TO_STDOUT: AtomicBool = AtomicBool::new(cfg!(debug_assertions));
+static LOG_FILE: Mutex<Option<File>> = Mutex::new(None);
+
+pub fn min_level() -> LogLevel {
+    LogLevel::from_u8(MIN_LEVEL.load(Ordering::Relaxed))
+}
+
+pub fn set_min_level(level: LogLevel) {
+    MIN_LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+pub fn set_log_to_stdout(enabled: bool) {
+    LOG_TO_STDOUT.store(enabled, Ordering::Relaxed);
+}
+
+pub fn should_log(min_level: LogLevel, level: LogLevel) -> bool {
+    level >= min_level
+}
+
+/// Opens `assets/logs/latest.log` and reads the log level from the environment. Until this is called, messages
+/// only go to stdout
+pub fn init() {
+    if let Some(level) = std::env::var(LOG_LEVEL_ENV).ok().as_deref().and_then(LogLevel::parse) {
+        set_min_level(level);
+    }
+    match std::env::var(LOG_STDOUT_ENV).ok().as_deref().map(str::trim) {
+        Some("1" | "true") => set_log_to_stdout(true),
+        Some("0" | "false") => set_log_to_stdout(false),
+        _ => {}
+    }
+
+    let file = std::fs::create_dir_all(asset!("logs"))
+        .and_then(|_| File::create(asset!("logs", "latest.log")));
+    match file {
+        Ok(file) => *LOG_FILE.lock().unwrap() = Some(file),
+        Err(e) => println!("[WARN] Could not open the log file: {e}"),
+    }
+}
+
+pub fn log(level: LogLevel, msg: &str) {
+    if !should_log(min_level(), level) {
+        return;
+    }
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|v| v.as_secs())
+        .unwrap_or_default();
+    let line = format!("[{time}] [{}] {msg}", level.name());
+
+    if LOG_TO_STDOUT.load(Ordering::Relaxed) || LOG_FILE.lock().unwrap().is_none() {
+        println!("{line}");
+    }
+    if let Some(file) = LOG_FILE.lock().unwrap().as_mut() {
+        // there's nowhere left to report a failing log file to
+        let _ = writeln!(file, "{line}");
+    }
+}
+
+#[macro_export]
+macro_rules! log_debug {
+    ($($arg: tt)*) => {
+        $crate::logger::log($crate::logger::LogLevel::Debug, &format!($($arg)*))
+    };
+}
+
+#[macro_export]
+macro_rules! log_info {
+    ($($arg: tt)*) => {
+        $crate::logger::log($crate::logger::LogLevel::Info, &format!($($arg)*))
+    };
+}
+
+#[macro_export]
+macro_rules! log_warn {
+    ($($arg: tt)*) => {
+        $crate::logger::log($crate::logger::LogLevel::Warn, &format!($($arg)*))
+    };
+}
+
+#[macro_export]
+macro_rules! log_error {
+    ($($arg: tt)*) => {
+        $crate::logger::log($crate::logger::LogLevel::Error, &format!($($arg)*))
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEVELS: [LogLevel; 4] = [LogLevel::Debug, LogLevel::Info, LogLevel::Warn, LogLevel::Error];
+
+    #[test]
+    fn messages_below_the_minimum_level_are_dropped() {
+        let logged = |min| LEVELS.map(|level| should_log(min, level));
+        assert_eq!(logged(LogLevel::Debug), [true, true, true, true]);
+        assert_eq!(logged(LogLevel::Info), [false, true, true, true]);
+        assert_eq!(logged(LogLevel::Warn), [false, false, true, true]);
+        assert_eq!(logged(LogLevel::Error), [false, false, false, true]);
+    }
+
+    #[test]
+    fn levels_parse_from_the_env_var() {
+        assert_eq!(LogLevel::parse(" Debug "), Some(LogLevel::Debug));
+        assert_eq!(LogLevel::parse("warning"), Some(LogLevel::Warn));
+        assert_eq!(LogLevel::parse("verbose"), None);
+        for level in LEVELS {
+            assert_eq!(LogLevel::parse(level.name()), Some(level));
+            assert_eq!(LogLevel::from_u8(level as u8), level);
+        }
+    }
+}
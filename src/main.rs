@@ -1,6 +1,6 @@
 use std::{sync::Mutex, thread};
 
-use blocks::{load_block_files, register_blocks};
+use blocks::{load_block_files, register_blocks, validate_serialization};
 use game::{run_game, GameConfig};
 use items::register_items;
 use notice_board::NoticeboardEntryRenderable;
@@ -10,12 +10,15 @@ use raylib::{
     ffi::KeyboardKey,
     RaylibHandle,
 };
+use recipe::register_recipes;
+use rng::Rng;
 use scheduler::{get_tasks, schedule_task, Task};
 use screens::{
     close_screen, CurrentScreen, MainScreen, ScreenDimensions,
 };
-use serialization::load_game;
-use world::World;
+use serialization::{load_game, SaveLoadPermit};
+use settings::{Settings, WindowSettings};
+use world::{Vec2i, World};
 
 mod as_any;
 mod assets;
@@ -23,12 +26,21 @@ mod blocks;
 mod identifier;
 mod game;
 mod initialized_data;
+mod input;
 mod inventory;
 mod items;
+mod keybinds;
+mod logger;
 mod notice_board;
+mod power;
+mod recipe;
+mod rng;
 mod scheduler;
 mod screens;
 mod serialization;
+mod settings;
+#[cfg(test)]
+mod test_utils;
 mod ui;
 mod world;
 
@@ -41,10 +53,13 @@ macro_rules! cstr {
 
 static RENDER_STEP: Mutex<RenderFn> = Mutex::new(RenderFn::StartMenu);
 
+/// What the main loop runs next. Every render function sets this before returning, `None` is only left
+/// behind by `take` and means the last one returned without picking a next step.
 pub enum RenderFn {
     None,
     Game(World, GameConfig),
     StartMenu,
+    Exit,
 }
 
 impl RenderFn {
@@ -53,29 +68,62 @@ impl RenderFn {
     }
 }
 
+pub fn set_render_step(step: RenderFn) {
+    *RENDER_STEP.lock().unwrap() = step;
+}
+
+/// env var to turn vsync on (`1`/`true`) or off (`0`/`false`) for one run, overriding the setting
+const VSYNC_ENV: &str = "PLACEHOLDER_NAME_2_VSYNC";
+
+/// Decides whether to use vsync from the setting and the value of `VSYNC_ENV`, ignoring values it doesn't understand
+fn resolve_vsync(setting: bool, env_override: Option<&str>) -> bool {
+    match env_override.map(|v| v.trim().to_ascii_lowercase()).as_deref() {
+        Some("1" | "true" | "on" | "yes") => true,
+        Some("0" | "false" | "off" | "no") => false,
+        _ => setting,
+    }
+}
 
 fn main() {
-    #[cfg(target_os = "linux")]
-    let (mut rl, thread) = raylib::init()
-        .size(1280, 720)
-        .title("Placeholder Name 2")
-        .build();
-    #[cfg(not(target_os = "linux"))]
-    let (mut rl, thread) = raylib::init()
-        .size(1280, 720)
-        .title("Placeholder Name 2 with vsync")
-        .vsync() // nvidia fucks with vsync :sob:
-        .build();
+    logger::init();
+    if std::env::var_os("PLACEHOLDER_NAME_2_AUDIT_ITEMS").is_some() {
+        world::set_item_audit(true);
+    }
+    // raylib only takes the vsync flag and the window size on init, so the settings have to be read before creating
+    // the window
+    let settings = Settings::load();
+    let vsync = resolve_vsync(settings.vsync, std::env::var(VSYNC_ENV).ok().as_deref());
+    let window_settings = settings.window.clamped();
+
+    let mut builder = raylib::init();
+    builder
+        .size(window_settings.width, window_settings.height)
+        .resizable();
+    if vsync {
+        builder.title("Placeholder Name 2 with vsync").vsync();
+    } else {
+        builder.title("Placeholder Name 2");
+    }
+    let (mut rl, thread) = builder.build();
+    if let Some((x, y)) = window_settings.position {
+        rl.set_window_position(x, y);
+    }
 
     rl.set_exit_key(None);
 
-    styles::dark();
+    settings.apply();
 
     if let Err(e) = load_block_files(&mut rl, &thread) {
         panic!("Encountered an error while trying to load the block files:\n{e}");
     }
     register_blocks();
+    if cfg!(debug_assertions) {
+        if let Err(id) = validate_serialization() {
+            panic!("Block {id:?} doesn't survive being saved and loaded again");
+        }
+    }
     register_items();
+    register_recipes();
 
     while !rl.window_should_close() {
         let render_fn = RENDER_STEP.lock().unwrap().take();
@@ -83,11 +131,26 @@ fn main() {
         reset_all();
 
         match render_fn {
-            RenderFn::None => break,
+            RenderFn::Exit => break,
+            RenderFn::None => {
+                log_warn!("Render step returned without choosing the next one, going back to the start menu");
+                render_menu(&mut rl, &thread);
+            }
             RenderFn::StartMenu => render_menu(&mut rl, &thread),
             RenderFn::Game(world, cfg) => run_game(&mut rl, &thread, world, cfg),
         }
     }
+
+    let position = rl.get_window_position();
+    settings::set_window(
+        WindowSettings {
+            width: rl.get_screen_width(),
+            height: rl.get_screen_height(),
+            position: Some((position.x as i32, position.y as i32)),
+        }
+        .clamped(),
+    );
+    Settings::save_current();
 }
 
 pub fn render_menu(rl: &mut RaylibHandle, thread: &raylib::prelude::RaylibThread) {
@@ -113,29 +176,54 @@ pub fn render_menu(rl: &mut RaylibHandle, thread: &raylib::prelude::RaylibThread
 
         for t in get_tasks() {
             match t {
-                Task::CloseWorld | Task::WorldUpdateBlock(..) => {}
+                Task::CloseWorld
+                | Task::WorldUpdateBlock(..)
+                | Task::WorldUpdateBlockWithConfig(..)
+                | Task::WakeBlock(..) => {}
                 Task::CloseScreen => close_screen(),
                 Task::OpenScreenCentered(screen) => CurrentScreen::open_centered(screen, &sc),
-                Task::ExitGame => return,
+                Task::ExitGame => {
+                    set_render_step(RenderFn::Exit);
+                    return;
+                }
                 // Task::Custom(func) => func(),
                 Task::CreateWorld => {
-                    *RENDER_STEP.lock().unwrap() =
-                        RenderFn::Game(World::new(20, 20), GameConfig::default());
+                    scheduler::clear_timed_tasks();
+                    // start out looking at the spawn area, which world generation keeps free
+                    let mut cfg = GameConfig::default();
+                    cfg.player = World::center_player_on(Vec2i::ZERO, &sc);
+                    set_render_step(RenderFn::Game(
+                        World::generate(20, 20, Rng::from_time().next_u64()),
+                        cfg,
+                    ));
                     return;
                 }
-                Task::__OpnWrld(world, cfg) => {
-                    *RENDER_STEP.lock().unwrap() = RenderFn::Game(world, cfg);
+                Task::__OpnWrld(world, cfg, timed_tasks) => {
+                    scheduler::replace_timed_tasks(timed_tasks);
+                    set_render_step(RenderFn::Game(world, cfg));
                     return;
                 }
                 Task::OpenWorld(file) => {
+                    let Some(permit) = SaveLoadPermit::try_acquire() else {
+                        notice_board::add_entry(
+                            NoticeboardEntryRenderable::StringRef(
+                                "Another save or load is still in progress",
+                            ),
+                            5,
+                        );
+                        schedule_task(Task::CloseScreen);
+                        continue;
+                    };
                     thread::spawn(move || match load_game(file) {
-                        Ok((world, cfg, _)) => {
-                            schedule_task(Task::__OpnWrld(world, cfg));
+                        Ok((world, cfg, _, timed_tasks)) => {
+                            drop(permit);
+                            schedule_task(Task::__OpnWrld(world, cfg, timed_tasks));
                         }
                         Err(e) => {
+                            drop(permit);
                             notice_board::add_entry(
                                 NoticeboardEntryRenderable::String(format!(
-                                    "Couldn't load World: {e:?}"
+                                    "Couldn't load World: {e}"
                                 )),
                                 20,
                             );
@@ -146,19 +234,20 @@ pub fn render_menu(rl: &mut RaylibHandle, thread: &raylib::prelude::RaylibThread
             }
         }
 
-        if rl.is_key_down(KeyboardKey::KEY_ESCAPE) {
-            CurrentScreen::close();
+        if rl.is_key_pressed(KeyboardKey::KEY_ESCAPE) {
+            CurrentScreen::back();
         }
 
         let mut d = rl.begin_drawing(thread);
 
-        d.clear_background(Color::new(0x1e, 0x1e, 0x2e, 0xff));
+        let background = Color::new(0x1e, 0x1e, 0x2e, 0xff);
+        d.clear_background(background);
 
         if !CurrentScreen::is_screen_open() {
-            CurrentScreen::open_centered(Box::new(MainScreen), &sc);
+            CurrentScreen::open_centered(Box::new(MainScreen::new()), &sc);
         }
         CurrentScreen::render(&mut cfg, &mut d, &sc, &mut empty_world);
-        notice_board::render_entries(&mut d, sc.height / 2, sc.height);
+        notice_board::render_entries(&mut d, sc.height / 2, sc.height, background);
     }
 }
 
@@ -169,7 +258,10 @@ pub fn reset_all() {
 }
 
 pub mod styles {
-    use std::ffi::CStr;
+    use std::{
+        ffi::CStr,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
 
     macro_rules! apply_set_style {
         ($(p $ctrl: expr, $prop: expr, $val: expr,)*) => {
@@ -392,4 +484,59 @@ pub mod styles {
         (CHERRY, &cherry),
         (LIGHT, &light),
     ];
+    /// the index of dark in `STYLES`
+    pub const DEFAULT_STYLE: usize = 7;
+
+    static CURRENT_STYLE: AtomicUsize = AtomicUsize::new(DEFAULT_STYLE);
+
+    /// Applies the style at `index` in `STYLES`, falling back to the default one for unknown indices
+    pub fn apply(index: usize) {
+        let index = if index < STYLES.len() { index } else { DEFAULT_STYLE };
+        STYLES[index].1();
+        CURRENT_STYLE.store(index, Ordering::Relaxed);
+    }
+
+    pub fn current() -> usize {
+        CURRENT_STYLE.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn taking_a_render_step_leaves_none() {
+        test_utils::register_all();
+        let steps = [
+            RenderFn::StartMenu,
+            RenderFn::Game(World::new(1, 1), GameConfig::default()),
+            RenderFn::Exit,
+            RenderFn::None,
+        ];
+        for step in steps {
+            let expected = std::mem::discriminant(&step);
+            let mut current = step;
+            assert_eq!(std::mem::discriminant(&current.take()), expected);
+            assert!(matches!(current, RenderFn::None));
+            // a render function that returns without picking the next step leaves None behind again
+            assert!(matches!(current.take(), RenderFn::None));
+        }
+    }
+
+    #[test]
+    fn vsync_follows_the_setting_unless_overridden() {
+        assert!(!resolve_vsync(false, None));
+        assert!(resolve_vsync(true, None));
+
+        for on in ["1", "true", " ON ", "yes"] {
+            assert!(resolve_vsync(false, Some(on)), "{on:?}");
+        }
+        for off in ["0", "False", "off", "no"] {
+            assert!(!resolve_vsync(true, Some(off)), "{off:?}");
+        }
+        // values it doesn't understand keep the setting
+        assert!(resolve_vsync(true, Some("maybe")));
+        assert!(!resolve_vsync(false, Some("")));
+    }
 }
@@ -1,6 +1,9 @@
 use std::{
     ops::Add,
-    sync::Mutex,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
     time::{Duration, SystemTime},
 };
 
@@ -11,7 +14,7 @@ use raylib::{
 };
 
 use crate::{
-    blocks::Block, game::RenderLayer, items::Item, world::{ChunkBlockMetadata, Direction}
+    blocks::Block, items::Stack, world::{ChunkBlockMetadata, Direction}
 };
 
 #[allow(dead_code)]
@@ -20,8 +23,11 @@ pub enum NoticeboardEntryRenderable {
     StringRef(&'static str),
     Block(Box<dyn Block>, Direction),
     NamedBlock(Box<dyn Block>, Direction),
-    Item(Box<dyn Item>),
-    NamedItem(Box<dyn Item>),
+    Item(Stack),
+    NamedItem(Stack),
+    /// an item with the amount that got added (positive) or removed (negative); consecutive entries of the
+    /// same item get merged
+    ItemCount(Stack, i64),
     Joiner(
         Box<NoticeboardEntryRenderable>,
         Box<NoticeboardEntryRenderable>,
@@ -29,49 +35,77 @@ pub enum NoticeboardEntryRenderable {
 }
 
 impl NoticeboardEntryRenderable {
-    pub fn render(&self, x: i32, y: i32, renderer: &mut RaylibDrawHandle) -> i32 {
+    /// `text_only` renders items and blocks as just their name, which is a lot cheaper than their full render.
+    /// `alpha` fades the entry. Item and block previews can't be drawn with a tint, so they get covered with
+    /// `background`, the colour behind the board, as far as the rest has faded
+    pub fn render(
+        &self,
+        x: i32,
+        y: i32,
+        renderer: &mut RaylibDrawHandle,
+        text_only: bool,
+        alpha: f32,
+        background: Color,
+    ) -> i32 {
+        if text_only {
+            match self {
+                Self::Block(blk, _) | Self::NamedBlock(blk, _) => {
+                    return Self::StringRef(blk.name().as_str()).render(x, y, renderer, false, alpha, background);
+                }
+                Self::Item(item) | Self::NamedItem(item) => {
+                    return Self::StringRef(item.name().as_str()).render(x, y, renderer, false, alpha, background);
+                }
+                Self::ItemCount(item, count) => {
+                    let width = Self::StringRef(item.name().as_str()).render(x, y, renderer, false, alpha, background);
+                    let label = Self::count_label(*count);
+                    return width + label.render(x + width, y, renderer, false, alpha, background);
+                }
+                _ => {}
+            }
+        }
+
         match self {
             Self::String(str) => {
                 let width = measure_text(str.as_str(), 20) + 10;
-                renderer.draw_rectangle(x, y, width, ENTRY_SIZE, Color::WHITE.fade(0.5));
-                renderer.draw_text(str.as_str(), x + 5, y + 5, 20, Color::BLACK);
+                renderer.draw_rectangle(x, y, width, ENTRY_SIZE, Color::WHITE.fade(0.5 * alpha));
+                renderer.draw_text(str.as_str(), x + 5, y + 5, 20, Color::BLACK.fade(alpha));
                 width
             }
             Self::StringRef(str) => {
                 let width = measure_text(str, 20) + 10;
-                renderer.draw_rectangle(x, y, width, ENTRY_SIZE, Color::WHITE.fade(0.5));
-                renderer.draw_text(str, x + 5, y + 5, 20, Color::BLACK);
+                renderer.draw_rectangle(x, y, width, ENTRY_SIZE, Color::WHITE.fade(0.5 * alpha));
+                renderer.draw_text(str, x + 5, y + 5, 20, Color::BLACK.fade(alpha));
                 width
             }
             Self::Joiner(a, b) => {
-                let width = a.render(x, y, renderer);
-                width + b.render(x + width, y, renderer)
+                let width = a.render(x, y, renderer, text_only, alpha, background);
+                width + b.render(x + width, y, renderer, text_only, alpha, background)
             }
             Self::Block(block, dir) => {
-                renderer.draw_rectangle(x, y, ENTRY_SIZE, ENTRY_SIZE, Color::WHITE.fade(0.5));
-                block.render(
+                renderer.draw_rectangle(x, y, ENTRY_SIZE, ENTRY_SIZE, Color::WHITE.fade(0.5 * alpha));
+                block.render_preview(
                     renderer,
                     x + 3,
                     y + 3,
                     ENTRY_SIZE - 6,
                     ENTRY_SIZE - 6,
                     ChunkBlockMetadata::from(*dir),
-                    RenderLayer::default_preview(),
                 );
+                fade_preview(renderer, x, y, alpha, background);
 
                 ENTRY_SIZE
             }
             Self::NamedBlock(blk, dir) => {
-                renderer.draw_rectangle(x, y, ENTRY_SIZE, ENTRY_SIZE, Color::WHITE.fade(0.5));
-                blk.render(
+                renderer.draw_rectangle(x, y, ENTRY_SIZE, ENTRY_SIZE, Color::WHITE.fade(0.5 * alpha));
+                blk.render_preview(
                     renderer,
                     x + 3,
                     y + 3,
                     ENTRY_SIZE - 6,
                     ENTRY_SIZE - 6,
                     ChunkBlockMetadata::from(*dir),
-                    RenderLayer::default_preview(),
                 );
+                fade_preview(renderer, x, y, alpha, background);
 
                 let width = measure_text(blk.name().as_str(), 20) + 10;
                 renderer.draw_rectangle(
@@ -79,62 +113,141 @@ impl NoticeboardEntryRenderable {
                     y,
                     width,
                     ENTRY_SIZE,
-                    Color::WHITE.fade(0.5),
+                    Color::WHITE.fade(0.5 * alpha),
                 );
                 renderer.draw_text(
                     blk.name().as_str(),
                     x + 5 + ENTRY_SIZE,
                     y + 5,
                     20,
-                    Color::BLACK,
+                    Color::BLACK.fade(alpha),
                 );
                 width + ENTRY_SIZE
             }
             Self::Item(item) => {
-                renderer.draw_rectangle(x, y, ENTRY_SIZE, ENTRY_SIZE, Color::WHITE.fade(0.5));
+                renderer.draw_rectangle(x, y, ENTRY_SIZE, ENTRY_SIZE, Color::WHITE.fade(0.5 * alpha));
                 item.render(renderer, x + 3, y + 3, ENTRY_SIZE - 6, ENTRY_SIZE - 6);
+                fade_preview(renderer, x, y, alpha, background);
 
                 ENTRY_SIZE
             }
-            Self::NamedItem(item) => {
-                renderer.draw_rectangle(x, y, ENTRY_SIZE, ENTRY_SIZE, Color::WHITE.fade(0.5));
-                item.render(renderer, x + 3, y + 3, ENTRY_SIZE - 6, ENTRY_SIZE - 6);
-
-                let width = measure_text(item.name().as_str(), 20) + 10;
-                renderer.draw_rectangle(
-                    x + ENTRY_SIZE,
-                    y,
-                    width,
-                    ENTRY_SIZE,
-                    Color::WHITE.fade(0.5),
-                );
-                renderer.draw_text(
-                    item.name().as_str(),
-                    x + 5 + ENTRY_SIZE,
-                    y + 5,
-                    20,
-                    Color::BLACK,
-                );
-                width + ENTRY_SIZE
+            Self::NamedItem(item) => render_named_item(item, x, y, renderer, alpha, background),
+            Self::ItemCount(item, count) => {
+                let width = render_named_item(item, x, y, renderer, alpha, background);
+                width + Self::count_label(*count).render(x + width, y, renderer, false, alpha, background)
             }
         }
     }
+
+    fn count_label(count: i64) -> Self {
+        if count < 0 {
+            Self::String(format!("- {}", -count))
+        } else {
+            Self::String(format!("+ {count}"))
+        }
+    }
+}
+
+/// Covers the preview in the `ENTRY_SIZE` square at `x`, `y` with `background` by `1 - alpha`, so it fades out along
+/// with the rest of its entry
+fn fade_preview(renderer: &mut RaylibDrawHandle, x: i32, y: i32, alpha: f32, background: Color) {
+    if alpha < 1.0 {
+        renderer.draw_rectangle(x, y, ENTRY_SIZE, ENTRY_SIZE, background.fade(1.0 - alpha));
+    }
+}
+
+fn render_named_item(
+    item: &Stack,
+    x: i32,
+    y: i32,
+    renderer: &mut RaylibDrawHandle,
+    alpha: f32,
+    background: Color,
+) -> i32 {
+    renderer.draw_rectangle(x, y, ENTRY_SIZE, ENTRY_SIZE, Color::WHITE.fade(0.5 * alpha));
+    item.render(renderer, x + 3, y + 3, ENTRY_SIZE - 6, ENTRY_SIZE - 6);
+    fade_preview(renderer, x, y, alpha, background);
+
+    let width = measure_text(item.name().as_str(), 20) + 10;
+    renderer.draw_rectangle(
+        x + ENTRY_SIZE,
+        y,
+        width,
+        ENTRY_SIZE,
+        Color::WHITE.fade(0.5 * alpha),
+    );
+    renderer.draw_text(
+        item.name().as_str(),
+        x + 5 + ENTRY_SIZE,
+        y + 5,
+        20,
+        Color::BLACK.fade(alpha),
+    );
+    width + ENTRY_SIZE
 }
 
 struct NoticeboardEntry {
     contents: NoticeboardEntryRenderable,
     should_decay: SystemTime,
+    last_changed: SystemTime,
 }
 
+/// entries fade out over this long before they disappear
+const FADE_DURATION: Duration = Duration::from_secs(1);
+
+/// item count entries changed within this window get merged into one
+const COALESCE_WINDOW: Duration = Duration::from_millis(1500);
+
+/// the most entries on the board at once, adding more drops the oldest ones
+pub const MAX_ENTRIES: usize = 12;
+
 static NOTICE_BOARD: Mutex<Vec<NoticeboardEntry>> = Mutex::new(Vec::new());
 
 pub fn add_entry(contents: NoticeboardEntryRenderable, time_in_seconds: u32) {
-    let entry = NoticeboardEntry {
-        contents,
-        should_decay: SystemTime::now().add(Duration::new(time_in_seconds as u64, 0)),
-    };
+    push_entry(&mut NOTICE_BOARD.lock().unwrap(), contents, time_in_seconds, SystemTime::now());
+}
+
+fn push_entry(
+    board: &mut Vec<NoticeboardEntry>,
+    contents: NoticeboardEntryRenderable,
+    time_in_seconds: u32,
+    now: SystemTime,
+) {
+    let should_decay = now.add(Duration::new(time_in_seconds as u64, 0));
+
+    if let NoticeboardEntryRenderable::ItemCount(item, count) = &contents {
+        let existing = board.iter().rposition(|entry| match &entry.contents {
+            NoticeboardEntryRenderable::ItemCount(other, other_count) => {
+                other.identifier() == item.identifier()
+                    && (*other_count < 0) == (*count < 0)
+                    && now
+                        .duration_since(entry.last_changed)
+                        .map(|v| v <= COALESCE_WINDOW)
+                        .unwrap_or(true)
+            }
+            _ => false,
+        });
+        if let Some(idx) = existing {
+            let mut entry = board.remove(idx);
+            if let NoticeboardEntryRenderable::ItemCount(_, other_count) = &mut entry.contents {
+                *other_count += count;
+            }
+            entry.should_decay = entry.should_decay.max(should_decay);
+            entry.last_changed = now;
+            board.push(entry);
+            return;
+        }
+    }
 
-    NOTICE_BOARD.lock().unwrap().push(entry);
+    board.push(NoticeboardEntry {
+        contents,
+        should_decay,
+        last_changed: now,
+    });
+    if board.len() > MAX_ENTRIES {
+        let excess = board.len() - MAX_ENTRIES;
+        board.drain(..excess);
+    }
 }
 
 pub fn update_entries() {
@@ -154,16 +267,47 @@ pub fn update_entries() {
 }
 
 pub const ENTRY_SIZE: i32 = 30;
+/// with text only mode enabled, entries are rendered as text once more than this many are visible
+pub const TEXT_ONLY_THRESHOLD: usize = 8;
+
+static TEXT_ONLY_MODE: AtomicBool = AtomicBool::new(false);
+
+pub fn text_only_mode() -> bool {
+    TEXT_ONLY_MODE.load(Ordering::Relaxed)
+}
 
-pub fn render_entries(renderer: &mut RaylibDrawHandle, h: i32, full_screen_height: i32) {
+pub fn set_text_only_mode(enabled: bool) {
+    TEXT_ONLY_MODE.store(enabled, Ordering::Relaxed);
+}
+
+fn should_render_text_only(text_only_mode: bool, num_visible: usize) -> bool {
+    text_only_mode && num_visible > TEXT_ONLY_THRESHOLD
+}
+
+/// 1 until the last `FADE_DURATION` before `should_decay`, then goes down to 0
+fn fade_alpha(should_decay: SystemTime) -> f32 {
+    match should_decay.duration_since(SystemTime::now()) {
+        Ok(left) => (left.as_secs_f32() / FADE_DURATION.as_secs_f32()).min(1.0),
+        Err(..) => 0.0,
+    }
+}
+
+/// Renders the newest entries that fit in `h`. `background` is the colour behind the board, see
+/// `NoticeboardEntryRenderable::render`
+pub fn render_entries(renderer: &mut RaylibDrawHandle, h: i32, full_screen_height: i32, background: Color) {
     let board = NOTICE_BOARD.lock().unwrap();
 
     let max_entries = board.len().min((h / (ENTRY_SIZE + 5)).max(0) as usize) + 1;
+    let text_only = should_render_text_only(text_only_mode(), max_entries - 1);
     for i in 1..max_entries {
-        board[board.len() - i].contents.render(
+        let entry = &board[board.len() - i];
+        entry.contents.render(
             10,
             full_screen_height - i as i32 * (ENTRY_SIZE + 5) - ENTRY_SIZE - 10,
             renderer,
+            text_only,
+            fade_alpha(entry.should_decay),
+            background,
         );
     }
 }
@@ -171,3 +315,94 @@ pub fn render_entries(renderer: &mut RaylibDrawHandle, h: i32, full_screen_heigh
 pub fn reset() {
     NOTICE_BOARD.lock().unwrap().clear();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        items::{COAL_IDENTIFIER, IRON_ORE_IDENTIFIER},
+        test_utils::{item, register_all},
+    };
+
+    fn counts(board: &[NoticeboardEntry]) -> Vec<i64> {
+        board
+            .iter()
+            .map(|entry| match &entry.contents {
+                NoticeboardEntryRenderable::ItemCount(_, count) => *count,
+                _ => panic!("only item counts were added"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn rapid_item_counts_are_merged() {
+        register_all();
+        let mut board = Vec::new();
+        let start = SystemTime::now();
+        for i in 0..3 {
+            let at = start + Duration::from_millis(100 * i);
+            push_entry(&mut board, NoticeboardEntryRenderable::ItemCount(item(*COAL_IDENTIFIER, 1), 1), 5, at);
+        }
+        assert_eq!(counts(&board), vec![3]);
+        let NoticeboardEntryRenderable::String(label) = NoticeboardEntryRenderable::count_label(counts(&board)[0])
+        else {
+            unreachable!()
+        };
+        assert_eq!(label, "+ 3");
+
+        // other items, removals and anything after the window get their own entry
+        let at = start + Duration::from_millis(300);
+        push_entry(&mut board, NoticeboardEntryRenderable::ItemCount(item(*IRON_ORE_IDENTIFIER, 1), 1), 5, at);
+        push_entry(&mut board, NoticeboardEntryRenderable::ItemCount(item(*COAL_IDENTIFIER, 1), -2), 5, at);
+        let late = at + COALESCE_WINDOW + Duration::from_millis(1);
+        push_entry(&mut board, NoticeboardEntryRenderable::ItemCount(item(*COAL_IDENTIFIER, 1), 4), 5, late);
+        assert_eq!(counts(&board), vec![3, 1, -2, 4]);
+    }
+
+    fn label(entry: &NoticeboardEntry) -> &str {
+        match &entry.contents {
+            NoticeboardEntryRenderable::String(label) => label.as_str(),
+            _ => panic!("not a string entry"),
+        }
+    }
+
+    #[test]
+    fn full_boards_drop_the_oldest_entries() {
+        register_all();
+        let mut board = Vec::new();
+        let now = SystemTime::now();
+        push_entry(&mut board, NoticeboardEntryRenderable::ItemCount(item(*COAL_IDENTIFIER, 1), 1), 5, now);
+        for i in 0..MAX_ENTRIES {
+            push_entry(&mut board, NoticeboardEntryRenderable::String(i.to_string()), 5, now);
+        }
+        // the item count was the oldest, so the last entry pushed it out
+        assert_eq!(board.len(), MAX_ENTRIES);
+        assert_eq!(label(&board[0]), "0");
+        assert_eq!(label(&board[MAX_ENTRIES - 1]), (MAX_ENTRIES - 1).to_string());
+
+        // a count that merges with one on the board doesn't push anything out
+        push_entry(&mut board, NoticeboardEntryRenderable::ItemCount(item(*COAL_IDENTIFIER, 1), 1), 5, now);
+        assert_eq!(label(&board[0]), "1");
+        push_entry(&mut board, NoticeboardEntryRenderable::ItemCount(item(*COAL_IDENTIFIER, 1), 2), 5, now);
+        assert_eq!(board.len(), MAX_ENTRIES);
+        assert_eq!(label(&board[0]), "1");
+        assert_eq!(counts(&board[MAX_ENTRIES - 1..]), vec![3]);
+
+        // and the merged entry moved to the end, so it is the last one to go
+        for i in 0..MAX_ENTRIES - 1 {
+            push_entry(&mut board, NoticeboardEntryRenderable::String(format!("new {i}")), 5, now);
+        }
+        assert_eq!(counts(&board[..1]), vec![3]);
+        assert_eq!(label(&board[1]), "new 0");
+    }
+
+    #[test]
+    fn text_only_above_the_threshold() {
+        assert!(!should_render_text_only(true, 0));
+        assert!(!should_render_text_only(true, TEXT_ONLY_THRESHOLD));
+        assert!(should_render_text_only(true, TEXT_ONLY_THRESHOLD + 1));
+        // rich rendering stays on when the option is off, no matter how many entries there are
+        assert!(!should_render_text_only(false, TEXT_ONLY_THRESHOLD + 1));
+        assert!(!should_render_text_only(false, MAX_ENTRIES));
+    }
+}
@@ -0,0 +1,203 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::world::{Direction, Vec2i, World};
+
+/// A group of power carrying blocks that touch each other. Generators in it share their power with every machine
+/// in it
+#[derive(Debug, Clone, Default)]
+pub struct PowerNetwork {
+    pub members: Vec<Vec2i>,
+    pub production: i32,
+    pub demand: i32,
+    /// the power stored in the power sources of the network, which the demand gets taken out of
+    pub stored: u32,
+    /// whether there is enough stored power for the demand; machines only work while there is
+    pub satisfied: bool,
+}
+
+impl PowerNetwork {
+    /// Flood fills the power carrying blocks connected to `start`, crossing chunk borders
+    pub fn flood_fill(world: &World, start: Vec2i) -> Self {
+        let mut network = Self::default();
+        let mut seen = HashSet::from([start]);
+        let mut queue = VecDeque::from([start]);
+
+        while let Some(pos) = queue.pop_front() {
+            let Some((blk, _)) = world.get_block_at(pos.x, pos.y) else {
+                continue;
+            };
+            if !blk.carries_power() {
+                continue;
+            }
+            network.members.push(pos);
+            network.production += blk.power_production();
+            network.demand += blk.power_demand();
+            network.stored += blk.as_power_source().map(|source| source.power_output()).unwrap_or(0);
+
+            for dir in Direction::all() {
+                let other = pos.add_directional(&dir, 1);
+                if seen.insert(other) {
+                    queue.push_back(other);
+                }
+            }
+        }
+
+        network.satisfied = network.demand <= 0 || network.stored >= network.demand as u32;
+        network
+    }
+
+    /// Takes the demand of a satisfied network out of its power sources for this tick
+    pub fn draw_power(&self, world: &mut World) {
+        if !self.satisfied {
+            return;
+        }
+        let mut remaining = self.demand.max(0) as u32;
+        for pos in &self.members {
+            if remaining == 0 {
+                break;
+            }
+            if let Some(source) = world
+                .get_block_at_mut(pos.x, pos.y)
+                .and_then(|(blk, _)| blk.as_power_source_mut())
+            {
+                remaining -= source.take_power(remaining);
+            }
+        }
+    }
+}
+
+/// Every power network of the loaded chunks. Nothing of this is saved, it gets recomputed every tick
+#[derive(Debug, Clone, Default)]
+pub struct PowerNetworks {
+    networks: Vec<PowerNetwork>,
+    /// the index into `networks` of the network each power carrying block belongs to
+    by_position: HashMap<Vec2i, usize>,
+}
+
+impl PowerNetworks {
+    pub fn compute(world: &World) -> Self {
+        let mut networks = Self::default();
+        for pos in world.power_blocks() {
+            if networks.by_position.contains_key(&pos) {
+                continue;
+            }
+            let network = PowerNetwork::flood_fill(world, pos);
+            for member in &network.members {
+                networks.by_position.insert(*member, networks.networks.len());
+            }
+            networks.networks.push(network);
+        }
+        networks
+    }
+
+    pub fn networks(&self) -> &[PowerNetwork] {
+        &self.networks
+    }
+
+    pub fn network_at(&self, pos: Vec2i) -> Option<&PowerNetwork> {
+        self.by_position
+            .get(&pos)
+            .and_then(|idx| self.networks.get(*idx))
+    }
+
+    /// Whether the block at `pos` gets all the power it needs. Blocks outside of any network get none
+    pub fn is_satisfied(&self, pos: Vec2i) -> bool {
+        self.network_at(pos)
+            .map(|network| network.satisfied)
+            .unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        blocks::{assembler::AssemblerBlock, generator::GeneratorBlock, Block},
+        identifier::Identifier,
+        test_utils::register_all,
+    };
+
+    /// An assembler making iron ingots, so it wants power
+    fn powered_assembler() -> Box<dyn Block> {
+        let mut assembler = AssemblerBlock::default();
+        assembler.set_recipe(Identifier::from(("placeholder_name_2", "iron_ingot")));
+        Box::new(assembler)
+    }
+
+    fn stored_at(world: &World, x: i32, y: i32) -> u32 {
+        let blk = world.get_block_at(x, y).unwrap().0;
+        blk.as_power_source().unwrap().power_output()
+    }
+
+    #[test]
+    fn networks_cross_chunk_borders_but_not_gaps() {
+        register_all();
+        // x = -1 and x = 0 are in different chunks
+        let mut world = World::new(2, 1);
+        world.set_block_at(-1, 3, Box::new(GeneratorBlock::with_stored_power(10)), Direction::North);
+        world.set_block_at(0, 3, powered_assembler(), Direction::North);
+        world.set_block_at(1, 3, powered_assembler(), Direction::North);
+        // one empty cell away from the others
+        world.set_block_at(3, 3, powered_assembler(), Direction::North);
+
+        let network = PowerNetwork::flood_fill(&world, Vec2i::new(1, 3));
+        let mut members = network.members.clone();
+        members.sort_unstable_by_key(|pos| pos.x);
+        assert_eq!(members, [Vec2i::new(-1, 3), Vec2i::new(0, 3), Vec2i::new(1, 3)]);
+        assert_eq!((network.demand, network.stored), (4, 10));
+        assert!(network.satisfied);
+
+        let networks = PowerNetworks::compute(&world);
+        assert_eq!(networks.networks().len(), 2);
+        let lone = networks.network_at(Vec2i::new(3, 3)).unwrap();
+        assert_eq!(lone.members, [Vec2i::new(3, 3)]);
+        assert!(!lone.satisfied);
+        assert!(networks.is_satisfied(Vec2i::new(-1, 3)));
+        assert!(!networks.is_satisfied(Vec2i::new(3, 3)));
+        assert!(!networks.is_satisfied(Vec2i::new(2, 3)));
+    }
+
+    #[test]
+    fn networks_without_enough_stored_power_are_unsatisfied() {
+        register_all();
+        let mut world = World::new(1, 1);
+        world.set_block_at(0, 0, Box::new(GeneratorBlock::with_stored_power(3)), Direction::North);
+        world.set_block_at(1, 0, powered_assembler(), Direction::North);
+        world.set_block_at(2, 0, powered_assembler(), Direction::North);
+
+        let network = PowerNetwork::flood_fill(&world, Vec2i::ZERO);
+        assert_eq!((network.demand, network.stored), (4, 3));
+        assert!(!network.satisfied);
+        // nothing is taken out of an unsatisfied network
+        network.draw_power(&mut world);
+        assert_eq!(stored_at(&world, 0, 0), 3);
+
+        // assemblers without a recipe don't need power, so even an empty network is satisfied
+        let mut world = World::new(1, 1);
+        world.set_block_at(0, 0, Box::new(GeneratorBlock::with_stored_power(0)), Direction::North);
+        world.set_block_at(1, 0, Box::new(AssemblerBlock::default()), Direction::North);
+        assert!(PowerNetwork::flood_fill(&world, Vec2i::ZERO).satisfied);
+    }
+
+    #[test]
+    fn drawing_power_drains_the_sources_in_order() {
+        register_all();
+        let mut world = World::new(1, 1);
+        world.set_block_at(0, 0, Box::new(GeneratorBlock::with_stored_power(3)), Direction::North);
+        world.set_block_at(1, 0, Box::new(GeneratorBlock::with_stored_power(3)), Direction::North);
+        world.set_block_at(2, 0, powered_assembler(), Direction::North);
+        world.set_block_at(3, 0, powered_assembler(), Direction::North);
+
+        let network = PowerNetwork::flood_fill(&world, Vec2i::ZERO);
+        assert_eq!((network.demand, network.stored), (4, 6));
+        assert!(network.satisfied);
+        network.draw_power(&mut world);
+        // the first generator gives all it has, the second the rest
+        assert_eq!(stored_at(&world, 0, 0), 0);
+        assert_eq!(stored_at(&world, 1, 0), 2);
+
+        // what is left isn't enough for another tick anymore
+        let network = PowerNetwork::flood_fill(&world, Vec2i::ZERO);
+        assert!(!network.satisfied);
+    }
+}
@@ -0,0 +1,182 @@
+use std::sync::Mutex;
+
+use crate::{
+    blocks::{
+        assembler::ASSEMBLER_NAME, extractor::EXTRACTOR_NAME, get_block_by_id, BLOCK_RESOURCE_NODE_BLUE,
+        BLOCK_RESOURCE_NODE_BROWN, BLOCK_RESOURCE_NODE_GREEN,
+    },
+    identifier::{GlobalString, Identifier},
+    items::{
+        get_item_by_id, COAL_IDENTIFIER, COPPER_INGOT_IDENTIFIER, COPPER_ORE_IDENTIFIER, IRON_INGOT_IDENTIFIER,
+        IRON_ORE_IDENTIFIER,
+    },
+};
+
+#[derive(Clone)]
+pub struct Recipe {
+    pub id: Identifier,
+    /// name of the block that makes the recipe
+    pub machine: GlobalString,
+    /// identifiers of the items or blocks that get used up, with how many of them
+    pub inputs: Vec<(Identifier, u32)>,
+    pub outputs: Vec<(Identifier, u32)>,
+    pub duration_ms: u64,
+}
+
+impl Recipe {
+    /// The first output, which the recipe is named after
+    pub fn main_output(&self) -> Option<Identifier> {
+        self.outputs.first().map(|(id, _)| *id)
+    }
+}
+
+static RECIPES: Mutex<Vec<Recipe>> = Mutex::new(Vec::new());
+
+pub fn register_recipes() {
+    for (id, node, output) in [
+        ("extract_coal", *BLOCK_RESOURCE_NODE_BROWN, *COAL_IDENTIFIER),
+        ("extract_iron_ore", *BLOCK_RESOURCE_NODE_BLUE, *IRON_ORE_IDENTIFIER),
+        ("extract_copper_ore", *BLOCK_RESOURCE_NODE_GREEN, *COPPER_ORE_IDENTIFIER),
+    ] {
+        register_recipe(Recipe {
+            id: Identifier::from(("placeholder_name_2", id)),
+            machine: *EXTRACTOR_NAME,
+            inputs: vec![(node, 1)],
+            outputs: vec![(output, 1)],
+            duration_ms: 250,
+        });
+    }
+
+    for (id, ore, output) in [
+        ("iron_ingot", *IRON_ORE_IDENTIFIER, *IRON_INGOT_IDENTIFIER),
+        ("copper_ingot", *COPPER_ORE_IDENTIFIER, *COPPER_INGOT_IDENTIFIER),
+    ] {
+        register_recipe(Recipe {
+            id: Identifier::from(("placeholder_name_2", id)),
+            machine: *ASSEMBLER_NAME,
+            inputs: vec![(ore, 1), (*COAL_IDENTIFIER, 1)],
+            outputs: vec![(output, 1)],
+            duration_ms: 1000,
+        });
+    }
+}
+
+pub fn register_recipe(recipe: Recipe) {
+    RECIPES.lock().unwrap().push(recipe);
+}
+
+pub fn get_recipe_by_id(id: Identifier) -> Option<Recipe> {
+    RECIPES
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|recipe| recipe.id == id)
+        .cloned()
+}
+
+/// All recipes made in `machine`, in the order they were registered
+pub fn recipes_for(machine: GlobalString) -> Vec<Recipe> {
+    RECIPES
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|recipe| recipe.machine == machine)
+        .cloned()
+        .collect()
+}
+
+/// The production hint of the first recipe that makes `output`
+pub fn production_hint_for(output: Identifier) -> Option<String> {
+    RECIPES
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|recipe| recipe.outputs.iter().any(|(id, _)| *id == output))
+        .map(production_hint)
+}
+
+/// The name of an item or block, falling back to the identifier if neither is registered
+fn input_name(id: Identifier) -> String {
+    if let Some(item) = get_item_by_id(id) {
+        item.name().to_string()
+    } else if let Some(block) = get_block_by_id(id) {
+        block.name().to_string()
+    } else {
+        format!("{id:?}")
+    }
+}
+
+/// "Made in: <machine> from: <inputs>"
+pub fn production_hint(recipe: &Recipe) -> String {
+    let inputs = recipe
+        .inputs
+        .iter()
+        .map(|(id, count)| match count {
+            1 => input_name(*id),
+            _ => format!("{count}x {}", input_name(*id)),
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("Made in: {} from: {inputs}", recipe.machine)
+}
+
+/// The text shown under the name of an item in its tooltip: its description and, if it has a recipe, where it
+/// comes from
+pub fn tooltip_text(description: &str, production_hint: Option<String>) -> String {
+    match production_hint {
+        Some(hint) => format!("{description}\n{hint}"),
+        None => description.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{blocks::conveyor::BLOCK_CONVEYOR, test_utils::register_all};
+
+    fn name(id: Identifier) -> GlobalString {
+        get_item_by_id(id).unwrap().name()
+    }
+
+    #[test]
+    fn tooltips_say_where_items_come_from() {
+        register_all();
+        let text = tooltip_text("Shiny", production_hint_for(*IRON_INGOT_IDENTIFIER));
+        assert_eq!(
+            text,
+            format!(
+                "Shiny\nMade in: {} from: {}, {}",
+                *ASSEMBLER_NAME,
+                name(*IRON_ORE_IDENTIFIER),
+                name(*COAL_IDENTIFIER)
+            )
+        );
+        // blocks that are inputs are named like items
+        let node = get_block_by_id(*BLOCK_RESOURCE_NODE_BROWN).unwrap().name();
+        assert_eq!(
+            production_hint_for(*COAL_IDENTIFIER).unwrap(),
+            format!("Made in: {} from: {node}", *EXTRACTOR_NAME)
+        );
+    }
+
+    #[test]
+    fn tooltips_without_a_recipe_are_just_the_description() {
+        register_all();
+        assert_eq!(production_hint_for(*BLOCK_CONVEYOR), None);
+        assert_eq!(tooltip_text("Moves items", production_hint_for(*BLOCK_CONVEYOR)), "Moves items");
+
+        // more than one of an input is counted, unknown inputs fall back to their identifier
+        let unknown = Identifier::from(("test", "unknown"));
+        let recipe = Recipe {
+            id: Identifier::from(("test", "recipe")),
+            machine: *ASSEMBLER_NAME,
+            inputs: vec![(*COAL_IDENTIFIER, 3), (unknown, 1)],
+            outputs: vec![(*IRON_INGOT_IDENTIFIER, 1)],
+            duration_ms: 1,
+        };
+        assert_eq!(
+            production_hint(&recipe),
+            format!("Made in: {} from: 3x {}, {unknown:?}", *ASSEMBLER_NAME, name(*COAL_IDENTIFIER))
+        );
+    }
+}
@@ -0,0 +1,97 @@
+use std::{
+    ops::Range,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::serializable_struct;
+
+/// A small xorshift64* random number generator. It is always seeded explicitly, so everything built on top of it
+/// (like world generation) can be reproduced from the seed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rng {
+    state: u64,
+}
+
+serializable_struct!(Rng { state: u64 });
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // splitmix64, so seeds that are close together still start out far apart
+        let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        // xorshift never leaves a state of 0
+        Self {
+            state: if z == 0 { 0x2545_F491_4F6C_DD1D } else { z },
+        }
+    }
+
+    /// Seeds the generator from the current time, for when there is no seed to reproduce
+    pub fn from_time() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_nanos() as u64)
+            .unwrap_or_default();
+        Self::new(nanos)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let mut x = self.state;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.state = x;
+        x.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    /// A number in `range`. An empty range always gives its start
+    pub fn next_range(&mut self, range: Range<u32>) -> u32 {
+        if range.is_empty() {
+            return range.start;
+        }
+        range.start + self.next_u32() % (range.end - range.start)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::serialization::{Buffer, Deserialize, Serialize};
+
+    fn sequence(rng: &mut Rng) -> Vec<u32> {
+        (0..16).map(|_| rng.next_u32()).collect()
+    }
+
+    #[test]
+    fn seeded_sequences_are_reproducible() {
+        assert_eq!(sequence(&mut Rng::new(42)), sequence(&mut Rng::new(42)));
+        assert_ne!(sequence(&mut Rng::new(42)), sequence(&mut Rng::new(43)));
+        assert_ne!(sequence(&mut Rng::new(0)), sequence(&mut Rng::new(1)));
+    }
+
+    #[test]
+    fn ranges_stay_in_bounds() {
+        let mut rng = Rng::new(7);
+        for _ in 0..1000 {
+            assert!((3..10).contains(&rng.next_range(3..10)));
+        }
+        assert_eq!(rng.next_range(5..5), 5);
+    }
+
+    #[test]
+    fn saved_generators_continue_where_they_left_off() {
+        let mut rng = Rng::new(1234);
+        rng.next_u64();
+        let mut buf = Vec::new();
+        rng.serialize(&mut buf);
+        assert_eq!(buf.len(), rng.required_length());
+
+        let mut loaded = Rng::try_deserialize(&mut Buffer::new(buf)).expect("the generator reads back");
+        assert_eq!(sequence(&mut loaded), sequence(&mut rng));
+    }
+}
@@ -1,6 +1,19 @@
-use std::{mem, sync::Mutex};
+use std::{
+    cell::RefCell,
+    cmp::Ordering,
+    collections::BinaryHeap,
+    mem,
+    sync::{
+        atomic::{self, AtomicU64},
+        Mutex,
+    },
+};
 
-use crate::{screens::GUIScreen, world::{ChunkBlockMetadata, World}, GameConfig};
+use crate::{
+    screens::GUIScreen,
+    world::{ChunkBlockMetadata, Vec2i, World},
+    GameConfig,
+};
 
 pub enum Task {
     ExitGame,
@@ -8,10 +21,15 @@ pub enum Task {
     OpenScreenCentered(Box<dyn GUIScreen>),
     CloseScreen,
     WorldUpdateBlock(&'static (dyn Fn(ChunkBlockMetadata, &mut World) -> () + Sync), ChunkBlockMetadata),
+    /// like WorldUpdateBlock, but for blocks that need access to the player (e.g. their inventory)
+    WorldUpdateBlockWithConfig(&'static (dyn Fn(ChunkBlockMetadata, &mut World, &mut GameConfig) -> () + Sync), ChunkBlockMetadata),
+    /// runs the `update` of the block at the position. Unlike the other world tasks this one gets saved with the
+    /// world when it is scheduled for later
+    WakeBlock(Vec2i),
     CloseWorld,
     OpenWorld(String),
     CreateWorld,
-    __OpnWrld(World, GameConfig),
+    __OpnWrld(World, GameConfig, SavedTimedTasks),
 }
 
 static TASKS: Mutex<Vec<Task>> = Mutex::new(Vec::new());
@@ -24,6 +42,191 @@ pub fn get_tasks() -> Vec<Task> {
 //     TASKS.lock().unwrap().push(Task::Custom(task));
 // }
 
+thread_local! {
+    /// set while `collect_tasks` runs on this thread, `schedule_task` puts the tasks here instead of into `TASKS`
+    static COLLECTED: RefCell<Option<Vec<Task>>> = const { RefCell::new(None) };
+}
+
 pub fn schedule_task(task: Task) {
-    TASKS.lock().unwrap().push(task);
-}
\ No newline at end of file
+    let task = COLLECTED.with(|collected| match collected.borrow_mut().as_mut() {
+        Some(tasks) => {
+            tasks.push(task);
+            None
+        }
+        None => Some(task),
+    });
+    if let Some(task) = task {
+        TASKS.lock().unwrap().push(task);
+    }
+}
+
+/// Runs `f` and returns the tasks it scheduled, in order, instead of scheduling them. Lets other threads gather
+/// tasks that then get scheduled in a fixed order with `schedule_tasks`
+pub fn collect_tasks(f: impl FnOnce()) -> Vec<Task> {
+    COLLECTED.with(|collected| *collected.borrow_mut() = Some(Vec::new()));
+    f();
+    COLLECTED
+        .with(|collected| collected.borrow_mut().take())
+        .unwrap_or_default()
+}
+
+/// Schedules all of `tasks` in order
+pub fn schedule_tasks(tasks: Vec<Task>) {
+    let tasks = COLLECTED.with(|collected| match collected.borrow_mut().as_mut() {
+        Some(collected) => {
+            collected.extend(tasks);
+            None
+        }
+        None => Some(tasks),
+    });
+    if let Some(tasks) = tasks {
+        TASKS.lock().unwrap().extend(tasks);
+    }
+}
+
+struct TimedTask {
+    tick: u64,
+    /// tasks for the same tick run in the order they got scheduled in
+    order: u64,
+    task: Task,
+}
+
+impl PartialEq for TimedTask {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for TimedTask {}
+
+impl PartialOrd for TimedTask {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for TimedTask {
+    // reversed, so the BinaryHeap pops the earliest task first
+    fn cmp(&self, other: &Self) -> Ordering {
+        (other.tick, other.order).cmp(&(self.tick, self.order))
+    }
+}
+
+static TICK: AtomicU64 = AtomicU64::new(0);
+static NEXT_ORDER: AtomicU64 = AtomicU64::new(0);
+static TIMED_TASKS: Mutex<BinaryHeap<TimedTask>> = Mutex::new(BinaryHeap::new());
+
+/// The number of game ticks that passed since the game started
+pub fn current_tick() -> u64 {
+    TICK.load(atomic::Ordering::Relaxed)
+}
+
+/// Schedules `task` to run `ticks` game ticks from now. 0 runs it on the next tick
+pub fn schedule_in(ticks: u32, task: Task) {
+    TIMED_TASKS.lock().unwrap().push(TimedTask {
+        tick: current_tick() + ticks as u64,
+        order: NEXT_ORDER.fetch_add(1, atomic::Ordering::Relaxed),
+        task,
+    });
+}
+
+/// Advances the tick counter and schedules every timed task that is due now. Call this once per game tick
+pub fn advance_tick() {
+    let tick = TICK.fetch_add(1, atomic::Ordering::Relaxed) + 1;
+    let mut timed_tasks = TIMED_TASKS.lock().unwrap();
+    while timed_tasks.peek().is_some_and(|task| task.tick <= tick) {
+        if let Some(timed_task) = timed_tasks.pop() {
+            schedule_task(timed_task.task);
+        }
+    }
+}
+
+/// Drops all timed tasks, e.g. when the world they belong to gets closed
+pub fn clear_timed_tasks() {
+    TIMED_TASKS.lock().unwrap().clear();
+}
+
+/// The timed tasks that get saved with a world: how many ticks are left until they run and the block they wake
+pub type SavedTimedTasks = Vec<(u32, Vec2i)>;
+
+/// Gets the timed tasks that can be saved (WakeBlock) as the amount of ticks left until they run, in the order they
+/// run in
+pub fn saved_timed_tasks() -> SavedTimedTasks {
+    let tick = current_tick();
+    let mut tasks = TIMED_TASKS
+        .lock()
+        .unwrap()
+        .iter()
+        .filter_map(|timed_task| match timed_task.task {
+            Task::WakeBlock(pos) => Some((timed_task.tick, timed_task.order, pos)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    tasks.sort_by_key(|(tick, order, _)| (*tick, *order));
+    tasks
+        .into_iter()
+        .map(|(task_tick, _, pos)| (task_tick.saturating_sub(tick) as u32, pos))
+        .collect()
+}
+
+/// Replaces all timed tasks with the ones of a world that just got opened
+pub fn replace_timed_tasks(tasks: SavedTimedTasks) {
+    clear_timed_tasks();
+    for (ticks, pos) in tasks {
+        schedule_in(ticks, Task::WakeBlock(pos));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::TIMED_TASK_TESTS;
+
+    /// the positions of the blocks woken by the tasks `advance_tick` released
+    fn advance_and_collect() -> Vec<Vec2i> {
+        collect_tasks(advance_tick)
+            .into_iter()
+            .filter_map(|task| match task {
+                Task::WakeBlock(pos) => Some(pos),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn timed_tasks_run_exactly_on_their_tick() {
+        let _guard = TIMED_TASK_TESTS.lock().unwrap();
+        clear_timed_tasks();
+        schedule_in(5, Task::WakeBlock(Vec2i::new(3, 4)));
+        schedule_in(2, Task::WakeBlock(Vec2i::new(1, 1)));
+
+        let start = current_tick();
+        let mut woken = Vec::new();
+        for _ in 0..7 {
+            woken.push(advance_and_collect());
+        }
+        assert_eq!(current_tick(), start + 7);
+        assert_eq!(
+            woken,
+            [vec![], vec![Vec2i::new(1, 1)], vec![], vec![], vec![Vec2i::new(3, 4)], vec![], vec![]]
+        );
+    }
+
+    #[test]
+    fn opened_worlds_bring_their_timed_tasks() {
+        let _guard = TIMED_TASK_TESTS.lock().unwrap();
+        clear_timed_tasks();
+        schedule_in(9, Task::WakeBlock(Vec2i::new(-1, -1)));
+        schedule_in(2, Task::CloseWorld);
+        schedule_in(4, Task::WakeBlock(Vec2i::new(5, 5)));
+        // only the block wakeups can be saved
+        let saved = saved_timed_tasks();
+        assert_eq!(saved, [(4, Vec2i::new(5, 5)), (9, Vec2i::new(-1, -1))]);
+
+        advance_and_collect();
+        replace_timed_tasks(saved.clone());
+        assert_eq!(saved_timed_tasks(), saved);
+        replace_timed_tasks(Vec::new());
+        assert!(saved_timed_tasks().is_empty());
+    }
+}
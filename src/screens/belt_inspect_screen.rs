@@ -0,0 +1,175 @@
+use std::ffi::CStr;
+
+use lazy_static::lazy_static;
+use raylib::{
+    drawing::{RaylibDraw, RaylibDrawHandle},
+    math::Rectangle,
+    rgui::RaylibDrawGui,
+};
+
+use crate::{
+    blocks::{
+        conveyor::{is_conveyor, ConveyorBlock},
+        downcast_mut,
+    },
+    cstr,
+    identifier::GlobalString,
+    inventory::Inventory,
+    world::{Vec2i, World},
+    GameConfig,
+    ui::gui_button,
+};
+
+use super::{
+    get_colors,
+    slot_grid::{SlotGrid, BUTTON_MARGIN, BUTTON_PAD, ITEM_H},
+    CurrentScreen, Screen, ScreenDimensions,
+};
+
+/// Shows what a conveyor belt is currently carrying. The field is the position of the belt
+pub struct BeltInspectScreen(Vec2i);
+
+impl BeltInspectScreen {
+    pub fn new(position: Vec2i) -> Self {
+        Self(position)
+    }
+}
+
+lazy_static! {
+    pub static ref NAME: GlobalString = GlobalString::from("Conveyor Belt");
+}
+
+const EJECT: &CStr = cstr!("Eject");
+
+const WIDTH: i32 = 260;
+
+/// The buffer of the conveyor at `position`, or None if there is no conveyor there anymore
+pub fn belt_inventory(world: &World, position: Vec2i) -> Option<&Inventory> {
+    let (block, _) = world.get_block_at(position.x, position.y)?;
+    if !is_conveyor(block.identifier()) {
+        return None;
+    }
+    block.inventory()
+}
+
+impl Screen for BeltInspectScreen {
+    fn rect(&mut self, _: &ScreenDimensions) -> ScreenDimensions {
+        ScreenDimensions {
+            width: WIDTH,
+            height: SlotGrid::height(1, 1),
+        }
+    }
+
+    fn name(&mut self) -> GlobalString {
+        *NAME
+    }
+
+    fn render(
+        &mut self,
+        config: &mut GameConfig,
+        renderer: &mut RaylibDrawHandle,
+        x: i32,
+        y: i32,
+        _: i32,
+        _: i32,
+        world: &mut World,
+    ) {
+        let Some(inventory) = belt_inventory(world, self.0) else {
+            CurrentScreen::close();
+            return;
+        };
+        let colors = get_colors();
+
+        let grid = SlotGrid::new(x, y, 1);
+        let events = grid.render(renderer, inventory);
+
+        let text_x = x + SlotGrid::width(1);
+        let text_y = y + (BUTTON_MARGIN + BUTTON_PAD) as i32;
+        let item = inventory.get_item(0);
+        match item {
+            None => renderer.draw_text("Empty", text_x, text_y, 20, colors.text),
+            Some(item) => {
+                renderer.draw_text(item.name().as_str(), text_x, text_y, 20, colors.text);
+                renderer.draw_text(
+                    format!("Count: {}", item.stack_count()).as_str(),
+                    text_x,
+                    text_y + 22,
+                    10,
+                    colors.text,
+                );
+            }
+        }
+
+        if item.is_none() {
+            renderer.gui_disable();
+        }
+        let eject = gui_button(
+            renderer,
+            Rectangle::new(
+                (x + WIDTH - 90) as f32,
+                (text_y + ITEM_H as i32 - 24) as f32,
+                80.0,
+                24.0,
+            ),
+            Some(EJECT),
+        ) && item.is_some();
+        renderer.gui_enable();
+
+        SlotGrid::draw_tooltip(renderer, inventory, events.hovered);
+
+        if eject {
+            if let Some(belt) = world
+                .get_block_at_mut(self.0.x, self.0.y)
+                .and_then(|(block, _)| downcast_mut::<ConveyorBlock>(&mut **block))
+            {
+                belt.eject(&mut config.inventory);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        blocks::{Block, StorageContainer},
+        items::COAL_IDENTIFIER,
+        test_utils::{item, register_all},
+        world::{ChunkBlockMetadata, Direction},
+    };
+
+    #[test]
+    fn reads_the_item_of_the_belt_at_its_position() {
+        register_all();
+        let mut world = World::new(1, 1);
+        let mut belt = ConveyorBlock::default();
+        let meta = ChunkBlockMetadata::new(Direction::South, Vec2i::new(3, 4));
+        assert!(belt.push(Direction::North, item(*COAL_IDENTIFIER, 1), meta).is_none());
+        world.set_block_at(3, 4, Box::new(belt), Direction::South);
+        world.set_block_at(4, 4, Box::new(ConveyorBlock::default()), Direction::South);
+        world.set_block_at(5, 4, Box::new(StorageContainer::default()), Direction::South);
+
+        let carried = belt_inventory(&world, Vec2i::new(3, 4)).unwrap().get_item(0).unwrap();
+        assert_eq!(carried.identifier(), *COAL_IDENTIFIER);
+        assert_eq!(carried.stack_count(), 1);
+        // the belt next to it is empty
+        assert!(belt_inventory(&world, Vec2i::new(4, 4)).unwrap().get_item(0).is_none());
+        // anything that isn't a belt has nothing to inspect
+        assert!(belt_inventory(&world, Vec2i::new(5, 4)).is_none());
+        assert!(belt_inventory(&world, Vec2i::new(6, 4)).is_none());
+        assert!(belt_inventory(&world, Vec2i::new(-40, 4)).is_none());
+    }
+
+    #[test]
+    fn ejecting_moves_the_item_to_the_player() {
+        register_all();
+        let mut belt = ConveyorBlock::default();
+        let meta = ChunkBlockMetadata::new(Direction::South, Vec2i::new(0, 0));
+        belt.push(Direction::North, item(*COAL_IDENTIFIER, 1), meta);
+        let mut inventory = Inventory::new(1, false);
+
+        belt.eject(&mut inventory);
+        assert!(belt.inventory().unwrap().get_item(0).is_none());
+        assert_eq!(inventory.get_item(0).unwrap().identifier(), *COAL_IDENTIFIER);
+    }
+}
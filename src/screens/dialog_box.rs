@@ -1,9 +1,9 @@
 use std::ffi::CStr;
 
 use lazy_static::lazy_static;
-use raylib::{color::Color, drawing::RaylibDraw, math::Rectangle, rgui::RaylibDrawGui, text::measure_text};
+use raylib::{color::Color, drawing::RaylibDraw, math::Rectangle, text::measure_text};
 
-use crate::{cstr, identifier::GlobalString};
+use crate::{cstr, identifier::GlobalString, ui::gui_button};
 
 use super::{Screen, ScreenDimensions};
 
@@ -41,7 +41,7 @@ impl Screen for DialogBox {
 
     fn render(&mut self, _: &mut crate::GameConfig, renderer: &mut raylib::prelude::RaylibDrawHandle, x: i32, y: i32, w: i32, h: i32, _: &mut crate::world::World) {
         if self.2 {
-            if renderer.gui_button(Rectangle::new(
+            if gui_button(renderer, Rectangle::new(
                 (x + (w - 48) / 2) as f32,
                 (y + h - 34) as f32,
                 48.0,
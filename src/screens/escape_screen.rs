@@ -1,19 +1,21 @@
 use lazy_static::lazy_static;
 use raylib::{drawing::RaylibDrawHandle, math::Rectangle, rgui::RaylibDrawGui};
 
-use crate::{cstr, identifier::GlobalString, scheduler::{schedule_task, Task}, GameConfig};
+use crate::{cstr, identifier::GlobalString, scheduler::{schedule_task, Task}, GameConfig, ui::gui_button};
 
-use super::{OptionsScreen, SavegameScreen, Screen, ScreenDimensions};
+use super::{OptionsScreen, ResetWorldScreen, SavegameScreen, Screen, ScreenDimensions};
 
 pub struct EscapeScreen;
 
-const SCREEN_DIMENSIONS: ScreenDimensions = ScreenDimensions { width: 180, height: 20 /* top + bottom padding (10 px each) */ + 24 /* first button */ + 38 * 4 /* other buttons */ };
+const SCREEN_DIMENSIONS: ScreenDimensions = ScreenDimensions { width: 180, height: 20 /* top + bottom padding (10 px each) */ + 24 /* first button */ + 38 * 5 /* other buttons */ };
 
 const EXIT_GAME: &std::ffi::CStr = cstr!("Quit Game");
 const CLOSE_WORLD: &std::ffi::CStr = cstr!("Back to the Main Menu");
 const SAVE_GAME: &std::ffi::CStr = cstr!("Save Game");
+const SAVE_GAME_READ_ONLY: &std::ffi::CStr = cstr!("Read-only, can't save");
 const RESUME: &std::ffi::CStr = cstr!("Resume");
 const OPTIONS: &std::ffi::CStr = cstr!("Options");
+const RESET_WORLD: &std::ffi::CStr = cstr!("Reset World");
 
 lazy_static! {
     pub static ref NAME: GlobalString = GlobalString::from("Options");
@@ -24,21 +26,28 @@ impl Screen for EscapeScreen {
         SCREEN_DIMENSIONS
     }
 
-    fn render(&mut self, _: &mut GameConfig, renderer: &mut RaylibDrawHandle, x: i32, y: i32, _: i32, _: i32, _: &mut crate::World) {
-        if renderer.gui_button(Rectangle::new((x + 10) as f32, (y + 10) as f32, 160.0, 24.0), Some(RESUME)) {
+    fn render(&mut self, cfg: &mut GameConfig, renderer: &mut RaylibDrawHandle, x: i32, y: i32, _: i32, _: i32, _: &mut crate::World) {
+        if gui_button(renderer, Rectangle::new((x + 10) as f32, (y + 10) as f32, 160.0, 24.0), Some(RESUME)) {
             self.close();
         }
         
-        if renderer.gui_button(Rectangle::new((x + 10) as f32, (y + 10 + 38 * 1) as f32, 160.0, 24.0), Some(OPTIONS)) {
+        if gui_button(renderer, Rectangle::new((x + 10) as f32, (y + 10 + 38 * 1) as f32, 160.0, 24.0), Some(OPTIONS)) {
             schedule_task(Task::OpenScreenCentered(OptionsScreen::new()));
         }
-        if renderer.gui_button(Rectangle::new((x + 10) as f32, (y + 10 + 38 * 2) as f32, 160.0, 24.0), Some(SAVE_GAME)) {
+        if cfg.read_only {
+            renderer.gui_disable();
+        }
+        if gui_button(renderer, Rectangle::new((x + 10) as f32, (y + 10 + 38 * 2) as f32, 160.0, 24.0), Some(if cfg.read_only { SAVE_GAME_READ_ONLY } else { SAVE_GAME })) && !cfg.read_only {
             schedule_task(Task::OpenScreenCentered(Box::new(SavegameScreen::default())))
         }
-        if renderer.gui_button(Rectangle::new((x + 10) as f32, (y + 10 + 38 * 3) as f32, 160.0, 24.0), Some(CLOSE_WORLD)) {
+        renderer.gui_enable();
+        if gui_button(renderer, Rectangle::new((x + 10) as f32, (y + 10 + 38 * 3) as f32, 160.0, 24.0), Some(RESET_WORLD)) {
+            schedule_task(Task::OpenScreenCentered(Box::new(ResetWorldScreen::default())))
+        }
+        if gui_button(renderer, Rectangle::new((x + 10) as f32, (y + 10 + 38 * 4) as f32, 160.0, 24.0), Some(CLOSE_WORLD)) {
             schedule_task(Task::CloseWorld);
         }
-        if renderer.gui_button(Rectangle::new((x + 10) as f32, (y + 10 + 38 * 4) as f32, 160.0, 24.0), Some(EXIT_GAME)) {
+        if gui_button(renderer, Rectangle::new((x + 10) as f32, (y + 10 + 38 * 5) as f32, 160.0, 24.0), Some(EXIT_GAME)) {
             schedule_task(Task::ExitGame);
         }
     }
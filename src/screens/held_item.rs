@@ -0,0 +1,269 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Mutex,
+};
+
+use raylib::{drawing::RaylibDrawHandle, math::Rectangle};
+
+use crate::{
+    identifier::Identifier,
+    inventory::{Inventory, Slot},
+    items::Stack,
+    notice_board::{self, NoticeboardEntryRenderable},
+};
+
+use super::slot_grid::{draw_count_badge, ITEM_H, ITEM_W};
+
+/// the item picked up in an inventory screen; it follows the cursor until it is put into a slot
+static HELD_ITEM: Mutex<Option<Stack>> = Mutex::new(None);
+/// whether an inventory screen drew the held item this frame. If none did, it goes back into the player inventory
+static HELD_ITEM_SHOWN: AtomicBool = AtomicBool::new(false);
+
+/// The item on the cursor, shared by all inventory screens so items can be moved between their inventories
+pub struct HeldItem;
+
+impl HeldItem {
+    pub fn is_some() -> bool {
+        HELD_ITEM.lock().unwrap().is_some()
+    }
+
+    /// The identifier of the held item, if any
+    pub fn identifier() -> Option<Identifier> {
+        HELD_ITEM.lock().unwrap().as_ref().map(|item| item.identifier())
+    }
+
+    /// Picks up the item in `slot` if nothing is held. Otherwise the held item goes into the slot: into an empty
+    /// slot, onto the same item (what doesn't fit stays held) or swapped with a different item
+    pub fn click_slot(inventory: &mut Inventory, slot: usize) {
+        if slot >= inventory.size() {
+            return;
+        }
+        let mut held = HELD_ITEM.lock().unwrap();
+        let slot = inventory.get_item_mut(slot);
+        *held = match held.take() {
+            None => slot.take(),
+            Some(item) => place(slot, item),
+        };
+    }
+
+    /// Picks up the whole stack in `slot`, like clicking it with nothing held
+    pub fn pick_up(inventory: &mut Inventory, slot: usize) {
+        if !Self::is_some() {
+            Self::click_slot(inventory, slot);
+        }
+    }
+
+    /// Deletes the held item if `confirmed` (Ctrl held), otherwise tells the player how to confirm
+    pub fn trash(confirmed: bool) {
+        if !Self::is_some() {
+            return;
+        }
+        if !confirmed {
+            notice_board::add_entry(
+                NoticeboardEntryRenderable::StringRef("Hold Ctrl while clicking the trash to delete items"),
+                3,
+            );
+            return;
+        }
+        HELD_ITEM.lock().unwrap().take();
+    }
+
+    /// Draws the held item at the cursor. Call this last in the render of every screen that uses the held item
+    pub fn draw(renderer: &mut RaylibDrawHandle) {
+        HELD_ITEM_SHOWN.store(true, Ordering::Relaxed);
+        let held = HELD_ITEM.lock().unwrap();
+        let Some(item) = held.as_ref() else {
+            return;
+        };
+        let mouse = renderer.get_mouse_position();
+        let rect = Rectangle::new(
+            mouse.x - ITEM_W as f32 / 2.0,
+            mouse.y - ITEM_H as f32 / 2.0,
+            ITEM_W as f32,
+            ITEM_H as f32,
+        );
+        item.render_stack(
+            renderer,
+            rect.x as i32,
+            rect.y as i32,
+            rect.width as i32,
+            rect.height as i32,
+            item.stack_count(),
+        );
+        draw_count_badge(renderer, &**item, rect);
+    }
+
+    /// Puts the held item back into `inventory` if no screen showed it this frame, e.g. because the screen got
+    /// closed while holding it
+    pub fn return_if_unused(inventory: &mut Inventory) {
+        if HELD_ITEM_SHOWN.swap(false, Ordering::Relaxed) {
+            return;
+        }
+        let Some(item) = HELD_ITEM.lock().unwrap().take() else {
+            return;
+        };
+        if let Some(item) = inventory.try_add_item(item) {
+            notice_board::add_entry(
+                NoticeboardEntryRenderable::Joiner(
+                    Box::new(NoticeboardEntryRenderable::NamedItem(item)),
+                    Box::new(NoticeboardEntryRenderable::StringRef("was lost, no space left")),
+                ),
+                5,
+            );
+        }
+    }
+}
+
+/// Puts `item` into `slot` and returns what ends up on the cursor instead
+fn place(slot: &mut Slot, mut item: Stack) -> Option<Stack> {
+    match slot.item_mut() {
+        None => {
+            slot.replace(item);
+            None
+        }
+        Some(slot_item)
+            if slot_item.identifier() == item.identifier() && slot_item.metadata_is_stack_size() =>
+        {
+            let total = slot_item.metadata() + item.metadata();
+            let in_slot = total.min(slot_item.max_stack_size()).max(slot_item.metadata());
+            slot_item.set_metadata(in_slot);
+            if total == in_slot {
+                None
+            } else {
+                item.set_metadata(total - in_slot);
+                Some(item)
+            }
+        }
+        Some(_) => slot.replace(item),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        inventory::MAX_ITEMS_PER_SLOT,
+        items::{COAL_IDENTIFIER, IRON_ORE_IDENTIFIER},
+        test_utils::{item, register_all},
+    };
+
+    /// the held item is shared by everything, so its tests can't run in parallel
+    static HELD_ITEM_TESTS: Mutex<()> = Mutex::new(());
+
+    fn count(inventory: &Inventory) -> u32 {
+        (0..inventory.size())
+            .filter_map(|slot| inventory.get_item(slot))
+            .map(|item| item.stack_count())
+            .sum()
+    }
+
+    #[test]
+    fn moving_a_stack_onto_a_fuller_one_keeps_the_overflow() {
+        let _guard = HELD_ITEM_TESTS.lock().unwrap();
+        register_all();
+        let mut player = Inventory::new(4, false);
+        let mut container = Inventory::new(4, false);
+        player.add_item(item(*COAL_IDENTIFIER, MAX_ITEMS_PER_SLOT - 10), 0);
+        container.add_item(item(*COAL_IDENTIFIER, 40), 0);
+        let total = count(&player) + count(&container);
+
+        HeldItem::click_slot(&mut container, 0);
+        HeldItem::click_slot(&mut player, 0);
+        // the player slot is full now and the rest is still held
+        assert_eq!(player.get_item(0).unwrap().stack_count(), MAX_ITEMS_PER_SLOT);
+        assert_eq!(HELD_ITEM.lock().unwrap().as_ref().unwrap().stack_count(), 30);
+        assert_eq!(count(&player) + count(&container) + 30, total);
+
+        HeldItem::click_slot(&mut container, 0);
+        assert!(!HeldItem::is_some());
+        assert_eq!(container.get_item(0).unwrap().stack_count(), 30);
+        assert_eq!(count(&player) + count(&container), total);
+    }
+
+    #[test]
+    fn trashing_deletes_only_the_held_item() {
+        let _guard = HELD_ITEM_TESTS.lock().unwrap();
+        register_all();
+        let mut inventory = Inventory::new(3, false);
+        inventory.add_item(item(*COAL_IDENTIFIER, 12), 0);
+        inventory.add_item(item(*COAL_IDENTIFIER, 5), 1);
+
+        // nothing happens without Ctrl
+        HeldItem::click_slot(&mut inventory, 0);
+        HeldItem::trash(false);
+        assert_eq!(HeldItem::identifier(), Some(*COAL_IDENTIFIER));
+
+        HeldItem::trash(true);
+        assert!(!HeldItem::is_some());
+        assert!(inventory.get_item(0).is_none());
+        assert_eq!(count(&inventory), 5);
+
+        // picking up an empty slot holds nothing, so there is nothing to trash
+        HeldItem::click_slot(&mut inventory, 2);
+        assert!(!HeldItem::is_some());
+        HeldItem::trash(true);
+        assert_eq!(count(&inventory), 5);
+        assert_eq!(inventory.get_item(1).unwrap().stack_count(), 5);
+    }
+
+    fn held_count() -> Option<u32> {
+        HELD_ITEM.lock().unwrap().as_ref().map(|item| item.stack_count())
+    }
+
+    fn slot(inventory: &Inventory, slot: usize) -> Option<(Identifier, u32)> {
+        inventory.get_item(slot).map(|item| (item.identifier(), item.stack_count()))
+    }
+
+    #[test]
+    fn stacks_are_picked_up_placed_and_merged() {
+        let _guard = HELD_ITEM_TESTS.lock().unwrap();
+        register_all();
+        let mut inventory = Inventory::new(3, false);
+        inventory.add_item(item(*COAL_IDENTIFIER, 12), 0);
+        inventory.add_item(item(*COAL_IDENTIFIER, 5), 2);
+
+        // pick up
+        HeldItem::click_slot(&mut inventory, 0);
+        assert_eq!(held_count(), Some(12));
+        assert_eq!(slot(&inventory, 0), None);
+
+        // into an empty slot
+        HeldItem::click_slot(&mut inventory, 1);
+        assert!(!HeldItem::is_some());
+        assert_eq!(slot(&inventory, 1), Some((*COAL_IDENTIFIER, 12)));
+
+        // onto the same item, all of it fits
+        HeldItem::click_slot(&mut inventory, 1);
+        HeldItem::click_slot(&mut inventory, 2);
+        assert!(!HeldItem::is_some());
+        assert_eq!(slot(&inventory, 1), None);
+        assert_eq!(slot(&inventory, 2), Some((*COAL_IDENTIFIER, 17)));
+
+        // clicking past the end does nothing
+        HeldItem::click_slot(&mut inventory, 2);
+        HeldItem::click_slot(&mut inventory, 3);
+        assert_eq!(held_count(), Some(17));
+        HeldItem::click_slot(&mut inventory, 0);
+        assert_eq!(count(&inventory), 17);
+    }
+
+    #[test]
+    fn different_items_are_swapped() {
+        let _guard = HELD_ITEM_TESTS.lock().unwrap();
+        register_all();
+        let mut player = Inventory::new(2, false);
+        let mut container = Inventory::new(2, false);
+        player.add_item(item(*COAL_IDENTIFIER, 7), 0);
+        container.add_item(item(*IRON_ORE_IDENTIFIER, 3), 1);
+
+        HeldItem::click_slot(&mut player, 0);
+        HeldItem::click_slot(&mut container, 1);
+        assert_eq!(slot(&container, 1), Some((*COAL_IDENTIFIER, 7)));
+        assert_eq!(HeldItem::identifier(), Some(*IRON_ORE_IDENTIFIER));
+        assert_eq!(held_count(), Some(3));
+
+        HeldItem::click_slot(&mut player, 0);
+        assert!(!HeldItem::is_some());
+        assert_eq!(slot(&player, 0), Some((*IRON_ORE_IDENTIFIER, 3)));
+    }
+}
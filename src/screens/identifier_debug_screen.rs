@@ -0,0 +1,147 @@
+use std::{ffi::CStr, str::FromStr};
+
+use lazy_static::lazy_static;
+use raylib::{
+    drawing::{RaylibDraw, RaylibDrawHandle},
+    ffi::KeyboardKey,
+    math::Rectangle,
+    rgui::RaylibDrawGui,
+};
+
+use crate::{
+    blocks::get_block_by_id,
+    cstr,
+    identifier::{GlobalString, Identifier},
+    items::get_item_by_id,
+    ui::{gui_button, gui_textbox, TextboxState},
+    world::World,
+    GameConfig,
+};
+
+use super::{get_colors, Screen, ScreenDimensions};
+
+/// Debug screen to look up whether an identifier (e.g. from an "unknown identifier" save error) is registered
+#[derive(Default)]
+pub struct IdentifierDebugScreen(TextboxState, Vec<String>);
+
+lazy_static! {
+    pub static ref NAME: GlobalString = GlobalString::from("Identifier Lookup");
+}
+
+const ID_LABEL: &CStr = cstr!("Id:");
+const LOOKUP: &CStr = cstr!("Look up");
+const CLOSE: &CStr = cstr!("Close");
+
+/// Looks `input` up in the registered blocks and items and returns a line per match
+pub fn lookup_identifier(input: &str) -> Vec<String> {
+    let Ok(id) = Identifier::from_str(input) else {
+        return vec![format!("Invalid identifier '{}', expected namespace:path", input.trim())];
+    };
+    let mut lines = Vec::new();
+
+    if let Some(blk) = get_block_by_id(id) {
+        lines.push(format!("Block {}: {}", blk.name(), blk.description()));
+    }
+    if let Some(item) = get_item_by_id(id) {
+        lines.push(format!("Item {}: {}", item.name(), item.description()));
+    }
+    if lines.is_empty() {
+        lines.push(format!("{:?} is not registered", id));
+    }
+
+    lines
+}
+
+impl Screen for IdentifierDebugScreen {
+    fn rect(&mut self, _: &ScreenDimensions) -> ScreenDimensions {
+        ScreenDimensions {
+            width: 480,
+            height: 120 + 24 * self.1.len() as i32,
+        }
+    }
+
+    fn name(&mut self) -> GlobalString {
+        *NAME
+    }
+
+    fn render(
+        &mut self,
+        _: &mut GameConfig,
+        renderer: &mut RaylibDrawHandle,
+        x: i32,
+        y: i32,
+        _: i32,
+        _: i32,
+        _: &mut World,
+    ) {
+        renderer.gui_label(
+            Rectangle::new((x + 24) as f32, (y + 24) as f32, 48.0, 24.0),
+            Some(ID_LABEL),
+        );
+        if gui_textbox(
+            renderer,
+            Rectangle::new((x + 72) as f32, (y + 24) as f32, 384.0, 24.0),
+            &mut self.0,
+            Some(255),
+            Some("namespace:path"),
+        ) {
+            if renderer.is_key_pressed(KeyboardKey::KEY_ENTER) && self.0.active {
+                self.1 = lookup_identifier(&self.0.str);
+            } else {
+                self.0.active = !self.0.active;
+            }
+        }
+
+        let colors = get_colors();
+        for (i, line) in self.1.iter().enumerate() {
+            renderer.draw_text(line, x + 24, y + 60 + 24 * i as i32, 20, colors.text);
+        }
+
+        let button_y = y + 72 + 24 * self.1.len() as i32;
+        if gui_button(
+            renderer,
+            Rectangle::new((x + 24) as f32, button_y as f32, 96.0, 24.0),
+            Some(LOOKUP),
+        ) {
+            self.1 = lookup_identifier(&self.0.str);
+        }
+        if gui_button(
+            renderer,
+            Rectangle::new((x + 360) as f32, button_y as f32, 96.0, 24.0),
+            Some(CLOSE),
+        ) {
+            self.close();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{blocks::BLOCK_STORAGE_CONTAINER, items::COAL_IDENTIFIER, test_utils::register_all};
+
+    #[test]
+    fn reports_registered_and_unknown_identifiers() {
+        register_all();
+
+        let container = get_block_by_id(*BLOCK_STORAGE_CONTAINER).unwrap();
+        let lines = lookup_identifier(" placeholder_name_2:storage_container ");
+        assert_eq!(
+            lines[0],
+            format!("Block {}: {}", container.name(), container.description())
+        );
+
+        let coal = get_item_by_id(*COAL_IDENTIFIER).unwrap();
+        assert_eq!(
+            lookup_identifier("placeholder_name_2:coal"),
+            vec![format!("Item {}: {}", coal.name(), coal.description())]
+        );
+
+        let unknown = lookup_identifier("placeholder_name_2:unobtainium");
+        assert_eq!(unknown.len(), 1);
+        assert!(unknown[0].ends_with("is not registered"), "{}", unknown[0]);
+
+        let invalid = lookup_identifier("unobtainium");
+        assert_eq!(invalid, vec!["Invalid identifier 'unobtainium', expected namespace:path".to_string()]);
+    }
+}
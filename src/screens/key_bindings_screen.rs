@@ -0,0 +1,112 @@
+use lazy_static::lazy_static;
+use raylib::{
+    drawing::{RaylibDraw, RaylibDrawHandle},
+    ffi::KeyboardKey,
+    math::Rectangle,
+};
+
+use crate::{
+    cstr,
+    identifier::GlobalString,
+    keybinds::{self, key_bindings, key_name, Action},
+    scheduler::{schedule_task, Task},
+    settings::Settings,
+    ui::{get_key_pressed, gui_button},
+    world::World,
+    GameConfig,
+};
+
+use super::{get_colors, OptionsScreen, Screen, ScreenDimensions};
+
+lazy_static! {
+    pub static ref NAME: GlobalString = GlobalString::from("Key Bindings");
+}
+
+const RESET: &std::ffi::CStr = cstr!("Reset to preset");
+const ROW_H: i32 = 34;
+
+/// Rebinds the actions in `Action::ALL`. Clicking an action waits for the next key press and binds it to that key,
+/// Escape cancels. An action that had the key before gets the old key of the rebound one
+#[derive(Default)]
+pub struct KeyBindingsScreen {
+    capturing: Option<Action>,
+    /// whether the last key pressed while capturing is used by something that can't be rebound
+    rejected: bool,
+}
+
+impl Screen for KeyBindingsScreen {
+    fn name(&mut self) -> GlobalString {
+        *NAME
+    }
+
+    fn rect(&mut self, _: &ScreenDimensions) -> ScreenDimensions {
+        ScreenDimensions {
+            width: 400,
+            height: 20 + ROW_H * (Action::ALL.len() as i32 + 1) + 10,
+        }
+    }
+
+    fn render(
+        &mut self,
+        _: &mut GameConfig,
+        renderer: &mut RaylibDrawHandle,
+        x: i32,
+        y: i32,
+        _: i32,
+        _: i32,
+        _: &mut World,
+    ) {
+        let colors = get_colors();
+        let bindings = key_bindings();
+
+        if let Some(action) = self.capturing {
+            // Escape gets handled by `back`
+            let key = get_key_pressed().filter(|key| *key != KeyboardKey::KEY_ESCAPE);
+            match key.map(|key| keybinds::rebind(action, key)) {
+                Some(Ok(())) => {
+                    Settings::save_current();
+                    self.capturing = None;
+                }
+                Some(Err(_)) => self.rejected = true,
+                None => {}
+            }
+        }
+
+        for (i, action) in Action::ALL.into_iter().enumerate() {
+            let row_y = y + 20 + i as i32 * ROW_H;
+            renderer.draw_text(action.name(), x + 25, row_y + 4, 20, colors.text);
+
+            let label = if self.capturing == Some(action) && self.rejected {
+                "Key is in use".to_string()
+            } else if self.capturing == Some(action) {
+                "Press a key...".to_string()
+            } else {
+                key_name(bindings.key(action)).to_string()
+            };
+            let rect = Rectangle::new((x + 220) as f32, row_y as f32, 150.0, 24.0);
+            if gui_button(renderer, rect, None) {
+                self.capturing = Some(action);
+                self.rejected = false;
+            }
+            let text_w = raylib::text::measure_text(label.as_str(), 20);
+            renderer.draw_text(label.as_str(), rect.x as i32 + (150 - text_w) / 2, row_y + 2, 20, colors.text);
+        }
+
+        let reset_y = y + 20 + Action::ALL.len() as i32 * ROW_H;
+        if gui_button(renderer, Rectangle::new((x + 220) as f32, reset_y as f32, 150.0, 24.0), Some(RESET)) {
+            keybinds::set_key_overrides([None; keybinds::NUM_ACTIONS]);
+            Settings::save_current();
+            self.capturing = None;
+        }
+    }
+
+    fn close(&self) {
+        schedule_task(Task::OpenScreenCentered(OptionsScreen::new()));
+    }
+
+    /// Escape cancels waiting for a key instead of closing the screen
+    fn back(&mut self) -> bool {
+        self.rejected = false;
+        self.capturing.take().is_some()
+    }
+}
@@ -4,14 +4,26 @@ use lazy_static::lazy_static;
 use raylib::{math::Rectangle, rgui::RaylibDrawGui};
 
 use crate::{
-    cstr, identifier::GlobalString, notice_board::{self, NoticeboardEntryRenderable}, scheduler::{schedule_task, Task}
+    cstr, identifier::GlobalString, notice_board::{self, NoticeboardEntryRenderable}, scheduler::{schedule_task, Task},
+    ui::gui_button,
 };
 
-use super::{OptionsScreen, Screen, WorldScreen};
+use super::{
+    worlds_screen::{find_most_recent_world, NAME_LOADING},
+    DialogBox, OptionsScreen, Screen, WorldScreen,
+};
 
+/// holds the path of the world that was played last, for the continue button
 #[derive(Default)]
-pub struct MainScreen;
+pub struct MainScreen(Option<String>);
 
+impl MainScreen {
+    pub fn new() -> Self {
+        Self(find_most_recent_world())
+    }
+}
+
+const CONTINUE: &CStr = cstr!("Continue");
 const OPEN_WORLD: &CStr = cstr!("Open World");
 const CREDITS: &CStr = cstr!("Credits");
 const OPTIONS: &CStr = cstr!("Options");
@@ -39,8 +51,27 @@ impl Screen for MainScreen {
         _: i32,
         _: &mut crate::world::World,
     ) {
-        if renderer.gui_button(
-            Rectangle::new((x + 202) as f32, (y + 104) as f32, 328.0, 48.0),
+        if self.0.is_none() {
+            renderer.gui_disable();
+        }
+        if gui_button(
+            renderer,
+            Rectangle::new((x + 202) as f32, (y + 56) as f32, 328.0, 48.0),
+            Some(CONTINUE),
+        ) {
+            if let Some(path) = &self.0 {
+                schedule_task(Task::OpenScreenCentered(DialogBox::new_uncloseable(
+                    Some(*NAME_LOADING),
+                    "Loading the last world...".to_string(),
+                )));
+                schedule_task(Task::OpenWorld(path.clone()));
+            }
+        }
+        renderer.gui_enable();
+
+        if gui_button(
+            renderer,
+            Rectangle::new((x + 202) as f32, (y + 128) as f32, 328.0, 48.0),
             Some(OPEN_WORLD),
         ) {
             match WorldScreen::new() {
@@ -49,17 +80,20 @@ impl Screen for MainScreen {
             }
         }
 
-        renderer.gui_button(
+        gui_button(
+            renderer,
             Rectangle::new((x + 202) as f32, (y + 200) as f32, 328.0, 48.0),
             Some(CREDITS),
         );
-        if renderer.gui_button(
+        if gui_button(
+            renderer,
             Rectangle::new((x + 202) as f32, (y + 296) as f32, 140.0, 48.0),
             Some(EXIT_GAME),
         ) {
             schedule_task(Task::ExitGame);
         }
-        if renderer.gui_button(
+        if gui_button(
+            renderer,
             Rectangle::new((x + 390) as f32, (y + 296) as f32, 140.0, 48.0),
             Some(OPTIONS),
         ) {
@@ -2,27 +2,46 @@ use std::{ffi::CStr, fmt::Display, sync::Mutex};
 
 use raylib::{color::Color, drawing::RaylibDrawHandle, ffi::GuiControl, math::Rectangle, rgui::RaylibDrawGui};
 
+mod belt_inspect_screen;
 mod container_inventory_screen;
 mod dialog_box;
 mod escape_screen;
+mod held_item;
+mod identifier_debug_screen;
+mod key_bindings_screen;
 mod main_screen;
 mod options;
 mod player_inventory_screen;
+mod recipe_screen;
+mod reset_world_screen;
 mod save_game_screen;
 mod selector_screen;
+mod slot_grid;
+mod splitter_filter_screen;
 mod worlds_screen;
+pub use belt_inspect_screen::BeltInspectScreen;
 pub use container_inventory_screen::ContainerInventoryScreen;
 pub use dialog_box::DialogBox;
 pub use escape_screen::EscapeScreen;
+pub use held_item::HeldItem;
+pub use identifier_debug_screen::IdentifierDebugScreen;
+pub use key_bindings_screen::KeyBindingsScreen;
 pub use main_screen::MainScreen;
 pub use options::OptionsScreen;
 pub use player_inventory_screen::PlayerInventoryScreen;
+pub use recipe_screen::RecipeScreen;
+pub use reset_world_screen::ResetWorldScreen;
 pub use save_game_screen::SavegameScreen;
 pub use selector_screen::SelectorScreen;
+pub use slot_grid::draw_tooltip_box;
+pub use splitter_filter_screen::SplitterFilterScreen;
 pub use worlds_screen::WorldScreen;
 
 use crate::{
     identifier::GlobalString,
+    input::InputState,
+    keybinds::key_bindings,
+    ui::{begin_focus, end_focus, reset_focus},
     scheduler::{schedule_task, Task},
     world::World,
     GameConfig,
@@ -74,6 +93,10 @@ trait Screen {
     fn close(&self) {
         schedule_task(Task::CloseScreen);
     }
+    /// Called when the player presses Escape, before the screen gets closed. Returns true to keep it open
+    fn back(&mut self) -> bool {
+        false
+    }
 }
 
 pub trait GUIScreen: Send {
@@ -91,6 +114,10 @@ pub trait GUIScreen: Send {
         schedule_task(Task::CloseScreen);
     }
     fn name(&mut self) -> GlobalString;
+    /// See `Screen::back`
+    fn back(&mut self) -> bool {
+        false
+    }
     fn is_in_bounds(&mut self, x: i32, y: i32, screen: &ScreenDimensions) -> bool {
         let ScreenDimensions { width, height } = self.get_dimensions(screen);
 
@@ -103,6 +130,7 @@ static CURRENT_SCREEN: Mutex<(Option<Box<dyn GUIScreen>>, i32, i32)> = Mutex::ne
 pub fn open_screen(screen: Box<dyn GUIScreen>, x: i32, y: i32) {
     let mut sc = CURRENT_SCREEN.lock().unwrap();
     *sc = (Some(screen), x, y);
+    reset_focus();
 }
 
 pub fn move_screen(x: i32, y: i32) {
@@ -114,6 +142,7 @@ pub fn move_screen(x: i32, y: i32) {
 
 pub fn close_screen() {
     *CURRENT_SCREEN.lock().unwrap() = (None, 0, 0);
+    reset_focus();
 }
 
 impl<T: Screen + Send> GUIScreen for T {
@@ -129,6 +158,10 @@ impl<T: Screen + Send> GUIScreen for T {
         Screen::name(self)
     }
 
+    fn back(&mut self) -> bool {
+        Screen::back(self)
+    }
+
     fn render(
         &mut self,
         cfg: &mut GameConfig,
@@ -197,8 +230,13 @@ impl CurrentScreen {
         let y = sc.2;
         match &mut sc.0 {
             None => {}
-            Some(sc) => sc.render(cfg, renderer, x, y, screen, world),
+            Some(sc) => {
+                begin_focus(&InputState::read(renderer, &key_bindings()));
+                sc.render(cfg, renderer, x, y, screen, world);
+                end_focus();
+            }
         }
+        HeldItem::return_if_unused(&mut cfg.inventory);
     }
 
     pub fn is_screen_open() -> bool {
@@ -209,6 +247,17 @@ impl CurrentScreen {
         schedule_task(Task::CloseScreen);
     }
 
+    /// Closes the screen when Escape was pressed, unless the screen uses it itself
+    pub fn back() {
+        let used = match &mut CURRENT_SCREEN.lock().unwrap().0 {
+            None => false,
+            Some(sc) => sc.back(),
+        };
+        if !used {
+            Self::close();
+        }
+    }
+
     pub fn open_centered(mut screen: Box<dyn GUIScreen>, window: &ScreenDimensions) {
         let screen_dims = screen.get_dimensions(window);
         let x = (window.width - screen_dims.width) / 2;
@@ -1,9 +1,9 @@
 use lazy_static::lazy_static;
 use raylib::{drawing::RaylibDraw, math::Rectangle, rgui::RaylibDrawGui};
 
-use crate::{identifier::GlobalString, styles};
+use crate::{blocks::{self, OverflowPolicy}, cstr, game, identifier::GlobalString, keybinds, notice_board, scheduler::{schedule_task, Task}, settings::{self, Settings}, styles, ui::gui_button};
 
-use super::{get_colors, Screen};
+use super::{get_colors, KeyBindingsScreen, Screen};
 
 #[derive(Default)]
 pub struct OptionsScreen;
@@ -14,6 +14,14 @@ impl OptionsScreen {
     }
 }
 
+const TEXT_ONLY: &std::ffi::CStr = cstr!("Show only text when there are many entries");
+const SNAP_MOVEMENT: &std::ffi::CStr = cstr!("Move one block per key press");
+const LEFT_HANDED: &std::ffi::CStr = cstr!("Left-handed controls (move with IJKL)");
+const SMART_ORIENT: &std::ffi::CStr = cstr!("Line up belts and containers with their neighbors when building");
+const KEY_BINDINGS: &std::ffi::CStr = cstr!("Change key bindings");
+const VOID_OVERFLOW: &std::ffi::CStr = cstr!("Machines throw away items they can't pass on");
+const VSYNC: &std::ffi::CStr = cstr!("Use vsync (after a restart)");
+
 lazy_static! {
     pub static ref NAME: GlobalString = GlobalString::from("Options");
 }
@@ -33,10 +41,72 @@ impl Screen for OptionsScreen {
         renderer.draw_text("Style", x + 25, orig_y + 10, 20, colors.text);
         for i in 0..styles::STYLES.len() {
             let y = i as i32;
-            if renderer.gui_button(Rectangle::new((x + 40 + (y % 2) * 230) as f32, (orig_y + 40 + 38 * (y / 2)) as f32, 190.0, 24.0), Some(styles::STYLES[i].0)) {
-                styles::STYLES[i].1();
+            if gui_button(renderer, Rectangle::new((x + 40 + (y % 2) * 230) as f32, (orig_y + 40 + 38 * (y / 2)) as f32, 190.0, 24.0), Some(styles::STYLES[i].0)) {
+                styles::apply(i);
+                Settings::save_current();
             }
         }
-        
+
+        let y = orig_y + 40 + 38 * ((styles::STYLES.len() as i32 + 1) / 2) + 10;
+        renderer.draw_text("Notice Board", x + 25, y, 20, colors.text);
+        let text_only = notice_board::text_only_mode();
+        if renderer.gui_check_box(Rectangle::new((x + 40) as f32, (y + 30) as f32, 24.0, 24.0), Some(TEXT_ONLY), text_only) != text_only {
+            notice_board::set_text_only_mode(!text_only);
+            Settings::save_current();
+        }
+
+        let y = y + 70;
+        renderer.draw_text("Movement", x + 25, y, 20, colors.text);
+        let snap_movement = game::snap_movement();
+        if renderer.gui_check_box(Rectangle::new((x + 40) as f32, (y + 30) as f32, 24.0, 24.0), Some(SNAP_MOVEMENT), snap_movement) != snap_movement {
+            game::set_snap_movement(!snap_movement);
+            Settings::save_current();
+        }
+        let left_handed = keybinds::left_handed();
+        if renderer.gui_check_box(Rectangle::new((x + 40) as f32, (y + 60) as f32, 24.0, 24.0), Some(LEFT_HANDED), left_handed) != left_handed {
+            keybinds::set_left_handed(!left_handed);
+            Settings::save_current();
+        }
+
+        let y = y + 100;
+        renderer.draw_text("Building", x + 25, y, 20, colors.text);
+        let smart_orient = game::smart_orient();
+        if renderer.gui_check_box(Rectangle::new((x + 40) as f32, (y + 30) as f32, 24.0, 24.0), Some(SMART_ORIENT), smart_orient) != smart_orient {
+            game::set_smart_orient(!smart_orient);
+            Settings::save_current();
+        }
+        let void_overflow = blocks::overflow_policy() == OverflowPolicy::Void;
+        if renderer.gui_check_box(Rectangle::new((x + 40) as f32, (y + 60) as f32, 24.0, 24.0), Some(VOID_OVERFLOW), void_overflow) != void_overflow {
+            blocks::set_overflow_policy(if void_overflow { OverflowPolicy::Block } else { OverflowPolicy::Void });
+            Settings::save_current();
+        }
+
+        let y = y + 100;
+        renderer.draw_text("Performance", x + 25, y, 20, colors.text);
+        let threshold = game::low_tps_threshold();
+        if gui_button(renderer, Rectangle::new((x + 40) as f32, (y + 30) as f32, 24.0, 24.0), Some(cstr!("-"))) && threshold > 0 {
+            game::set_low_tps_threshold(threshold - 1);
+            Settings::save_current();
+        }
+        if gui_button(renderer, Rectangle::new((x + 70) as f32, (y + 30) as f32, 24.0, 24.0), Some(cstr!("+"))) {
+            game::set_low_tps_threshold(threshold + 1);
+            Settings::save_current();
+        }
+        let label = match threshold {
+            0 => "Never warn about low TPS".to_string(),
+            _ => format!("Warn when the TPS stay below {threshold}"),
+        };
+        renderer.draw_text(label.as_str(), x + 105, y + 32, 20, colors.text);
+        let vsync = settings::vsync();
+        if renderer.gui_check_box(Rectangle::new((x + 40) as f32, (y + 60) as f32, 24.0, 24.0), Some(VSYNC), vsync) != vsync {
+            settings::set_vsync(!vsync);
+            Settings::save_current();
+        }
+
+        let y = y + 100;
+        renderer.draw_text("Controls", x + 25, y, 20, colors.text);
+        if gui_button(renderer, Rectangle::new((x + 40) as f32, (y + 30) as f32, 190.0, 24.0), Some(KEY_BINDINGS)) {
+            schedule_task(Task::OpenScreenCentered(Box::new(KeyBindingsScreen::default())));
+        }
     }
 }
\ No newline at end of file
@@ -1,71 +1,42 @@
 use lazy_static::lazy_static;
-use raylib::{
-    color::Color, drawing::{RaylibDraw, RaylibDrawHandle}, ffi::GuiControl, math::Rectangle, rgui::RaylibDrawGui, text::{measure_text, measure_text_ex}
-};
+use raylib::{ffi::KeyboardKey, math::Rectangle};
 
-use crate::{identifier::GlobalString, inventory::NUM_SLOTS_PLAYER, items::Item};
+use crate::{
+    identifier::GlobalString,
+    inventory::NUM_SLOTS_PLAYER,
+    notice_board::{self, NoticeboardEntryRenderable},
+    ui::{gui_textbox, TextboxState},
+};
 
-use super::{get_colors, Screen};
+use super::{
+    slot_grid::{gui_trash, SlotGrid, BUTTON_MARGIN, TRASH_H},
+    HeldItem, Screen,
+};
 
 #[derive(Default)]
 pub struct PlayerInventoryScreen {
-    selected_slot: Option<usize>,
+    /// the slot being split by shift-clicking it and the textbox for the amount to take
+    split: Option<(usize, TextboxState)>,
 }
 
-const ITEM_W: u32 = 40;
-const ITEM_H: u32 = 40;
-const BUTTON_PAD: u32 = 7;
-const BUTTON_MARGIN: u32 = 10;
 const BUTTONS_PER_ROW: u32 = 9;
 
 lazy_static! {
     pub static ref NAME: GlobalString = GlobalString::from("Inventory");
 }
 
-pub fn tooltip(item: &Box<dyn Item>, renderer: &mut RaylibDrawHandle) {
-    let colors = get_colors();
-
-    let text_size = measure_text_ex(renderer.get_font_default(), item.description(), 10.0, 1.0);
-    let name_width = measure_text(item.name().as_str(), 20);
-    let mut width = name_width.max(text_size.x as i32) + 10;
-    let mut height = 30 + text_size.y as i32;
-    if width > 170 {
-        height += 10 * width / 170;
-        width = 170;
+/// Parses the amount to split off a stack of `available` items, clamped to what can be taken without emptying the slot
+pub fn parse_split_count(input: &str, available: u32) -> Option<u32> {
+    let input = input.trim();
+    if input.is_empty() || !input.bytes().all(|c| c.is_ascii_digit()) {
+        return None;
     }
+    let count = input.parse::<u32>().unwrap_or(u32::MAX);
 
-    let mouse_pos = renderer.get_mouse_position();
-    let x =
-        mouse_pos.x as i32 + 5 + (renderer.get_screen_width() - (width + mouse_pos.x as i32 + 5)).min(0);
-    let y =
-        mouse_pos.y as i32 + 5 + (renderer.get_screen_height() - (height + mouse_pos.y as i32 + 5)).min(0);
-
-    renderer.draw_rectangle_rounded(Rectangle::new(x as f32, y as f32, width as f32, height as f32), 0.2, 1, colors.bg);
-    renderer.draw_rectangle_rounded_lines(
-        Rectangle::new(x as f32, y as f32, width as f32, height as f32),
-        0.2,
-        1,
-        2,
-        colors.border,
-    );
-    renderer.draw_text_rec(
-        renderer.get_font_default(),
-        &item.name().as_str(),
-        Rectangle::new((x + 5) as f32, (y + 5) as f32, (width - 10) as f32, 20.0),
-        20.0,
-        2.0,
-        false,
-        colors.text,
-    );
-    renderer.draw_text_rec(
-        renderer.get_font_default(),
-        &item.description(),
-        Rectangle::new((x + 5) as f32, (y + 25) as f32, (width - 10) as f32, (height - 30) as f32),
-        10.0,
-        1.0,
-        false,
-        colors.text,
-    );
+    match count.min(available.saturating_sub(1)) {
+        0 => None,
+        count => Some(count),
+    }
 }
 
 impl Screen for PlayerInventoryScreen {
@@ -74,9 +45,10 @@ impl Screen for PlayerInventoryScreen {
     }
     fn rect(&mut self, _: &super::ScreenDimensions) -> super::ScreenDimensions {
         super::ScreenDimensions {
-            width: ((ITEM_W + BUTTON_MARGIN * 2 + BUTTON_PAD * 2) * BUTTONS_PER_ROW) as i32,
-            height: ((ITEM_H + BUTTON_MARGIN * 2 + BUTTON_PAD * 2) * NUM_SLOTS_PLAYER as u32
-                / BUTTONS_PER_ROW) as i32,
+            width: SlotGrid::width(BUTTONS_PER_ROW),
+            height: SlotGrid::height(NUM_SLOTS_PLAYER, BUTTONS_PER_ROW)
+                + TRASH_H
+                + BUTTON_MARGIN as i32,
         }
     }
     fn render(
@@ -89,115 +61,94 @@ impl Screen for PlayerInventoryScreen {
         _: i32,
         _: &mut crate::World,
     ) {
-        let border_pressed = Color::get_color(renderer.gui_get_style(GuiControl::DEFAULT, 3));
-        let button_pressed = Color::get_color(renderer.gui_get_style(GuiControl::DEFAULT, 4));
-
-        let mut switch_slots = (0, 0);
-        let pos = renderer.get_mouse_position();
-        let mut idx = NUM_SLOTS_PLAYER;
+        let grid = SlotGrid::new(x, y, BUTTONS_PER_ROW);
+        let events = grid.render(renderer, &cfg.inventory);
+
+        let trash_rect = Rectangle::new(
+            (x + BUTTON_MARGIN as i32) as f32,
+            (y + SlotGrid::height(NUM_SLOTS_PLAYER, BUTTONS_PER_ROW)) as f32,
+            (SlotGrid::width(BUTTONS_PER_ROW) - BUTTON_MARGIN as i32 * 2) as f32,
+            TRASH_H as f32,
+        );
+        if gui_trash(renderer, trash_rect) {
+            HeldItem::trash(renderer.is_key_down(KeyboardKey::KEY_LEFT_CONTROL));
+        }
 
-        for slot in 0..NUM_SLOTS_PLAYER {
+        if let Some(slot) = events.clicked {
             let item = cfg.inventory.get_item(slot);
-            let row = slot as u32 % BUTTONS_PER_ROW;
-            let col = slot as u32 / BUTTONS_PER_ROW;
-            let x =
-                x + (BUTTON_MARGIN + row * (BUTTON_MARGIN * 2 + BUTTON_PAD * 2 + ITEM_W)) as i32;
-            let y =
-                y + (BUTTON_MARGIN + col * (BUTTON_MARGIN * 2 + BUTTON_PAD * 2 + ITEM_H)) as i32;
-
-            if idx >= NUM_SLOTS_PLAYER
-                && Rectangle::new(
-                    x as f32,
-                    y as f32,
-                    (ITEM_W + BUTTON_PAD * 2) as f32,
-                    (ITEM_H + BUTTON_PAD * 2) as f32,
-                )
-                .check_collision_point_rec(pos)
+            if renderer.is_key_down(KeyboardKey::KEY_LEFT_SHIFT)
+                && !HeldItem::is_some()
+                && matches!(item, Some(item) if item.metadata_is_stack_size() && item.metadata() > 1)
             {
-                idx = slot;
+                self.split = Some((slot, TextboxState::default()));
+            } else {
+                HeldItem::click_slot(&mut cfg.inventory, slot);
             }
+        }
 
-            if renderer.gui_button(
-                Rectangle::new(
-                    x as f32,
-                    y as f32,
-                    (BUTTON_PAD * 2 + ITEM_W) as f32,
-                    (BUTTON_PAD * 2 + ITEM_H) as f32,
-                ),
-                None,
-            ) {
-                if let Some(selected_slot) = self.selected_slot {
-                    self.selected_slot = None;
-                    if selected_slot != slot && selected_slot < NUM_SLOTS_PLAYER {
-                        switch_slots = (selected_slot, slot);
-                    }
+        if let Some((slot, state)) = &mut self.split {
+            let slot = *slot;
+            let slot_rect = grid.slot_rect(slot);
+            let rect = Rectangle::new(
+                slot_rect.x - BUTTON_MARGIN as f32,
+                slot_rect.y + slot_rect.height + BUTTON_MARGIN as f32,
+                slot_rect.width + BUTTON_MARGIN as f32 * 2.0,
+                24.0,
+            );
+
+            let finished = gui_textbox(renderer, rect, state, Some(3), Some("Amount"));
+            state.str.retain(|c| c.is_ascii_digit());
+            if finished {
+                let available = match cfg.inventory.get_item(slot) {
+                    Some(item) if item.metadata_is_stack_size() => item.metadata(),
+                    _ => 0,
+                };
+                let new_slot = if renderer.is_key_pressed(KeyboardKey::KEY_ENTER) {
+                    parse_split_count(&state.str, available)
+                        .and_then(|count| cfg.inventory.split_stack(slot, count))
                 } else {
-                    self.selected_slot = Some(slot);
+                    None
+                };
+                if new_slot.is_none() && renderer.is_key_pressed(KeyboardKey::KEY_ENTER) {
+                    notice_board::add_entry(
+                        NoticeboardEntryRenderable::StringRef("Couldn't split the stack"),
+                        5,
+                    );
                 }
-            }
-            if matches!(self.selected_slot, Some(selected_slot) if selected_slot == slot) {
-                renderer.draw_rectangle(
-                    x,
-                    y,
-                    (BUTTON_PAD * 2 + ITEM_W) as i32,
-                    (BUTTON_PAD * 2 + ITEM_H) as i32,
-                    button_pressed,
-                );
-                renderer.draw_rectangle_lines_ex(
-                    Rectangle::new(
-                        x as f32,
-                        y as f32,
-                        (BUTTON_PAD * 2 + ITEM_W) as f32,
-                        (BUTTON_PAD * 2 + ITEM_H) as f32,
-                    ),
-                    2,
-                    border_pressed,
-                );
-            }
-
-            if let Some(item) = item {
-                item.render(
-                    renderer,
-                    x + BUTTON_PAD as i32,
-                    y + BUTTON_PAD as i32,
-                    ITEM_W as i32,
-                    ITEM_H as i32,
-                );
-
-                let sz = format!(
-                    "x{}",
-                    if item.metadata_is_stack_size() {
-                        item.metadata()
-                    } else {
-                        1
-                    }
-                );
-                let len = measure_text(sz.as_str(), 20);
-                renderer.draw_rectangle(
-                    x + BUTTON_PAD as i32 + ITEM_W as i32 - 3 - len / 2,
-                    y + ITEM_H as i32 + (BUTTON_PAD * 2) as i32 - 11,
-                    len + 6,
-                    22,
-                    Color::ORANGE,
-                );
-                renderer.draw_text(
-                    sz.as_str(),
-                    x + BUTTON_PAD as i32 + ITEM_W as i32 - len / 2,
-                    y + ITEM_H as i32 + (BUTTON_PAD * 2) as i32 - 10,
-                    20,
-                    Color::WHITE,
-                );
+                // the split off stack is held until it is clicked into another slot
+                if let Some(new_slot) = new_slot {
+                    HeldItem::pick_up(&mut cfg.inventory, new_slot);
+                }
+                self.split = None;
             }
         }
 
-        if switch_slots.0 != switch_slots.1 {
-            cfg.inventory.switch_items(switch_slots.0, switch_slots.1);
-        }
+        SlotGrid::draw_tooltip(renderer, &cfg.inventory, events.hovered);
+        HeldItem::draw(renderer);
+    }
+}
 
-        if idx < NUM_SLOTS_PLAYER {
-            if let Some(item) = cfg.inventory.get_item(idx) {
-                tooltip(item, renderer);
-            }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_count_only_takes_digits() {
+        assert_eq!(parse_split_count("12", 40), Some(12));
+        assert_eq!(parse_split_count(" 7 ", 40), Some(7));
+        for input in ["", "  ", "-3", "+3", "1.5", "12a", "twelve"] {
+            assert_eq!(parse_split_count(input, 40), None, "{input:?}");
         }
     }
+
+    #[test]
+    fn split_count_leaves_an_item_behind() {
+        assert_eq!(parse_split_count("39", 40), Some(39));
+        assert_eq!(parse_split_count("40", 40), Some(39));
+        assert_eq!(parse_split_count("99999999999999", 40), Some(39));
+        assert_eq!(parse_split_count("0", 40), None);
+        // a single item can't be split
+        assert_eq!(parse_split_count("1", 1), None);
+        assert_eq!(parse_split_count("1", 0), None);
+    }
 }
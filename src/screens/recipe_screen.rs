@@ -0,0 +1,130 @@
+use lazy_static::lazy_static;
+use raylib::{
+    drawing::RaylibDraw,
+    math::Rectangle,
+    prelude::RaylibDrawHandle,
+    rgui::RaylibDrawGui,
+    text::measure_text,
+};
+
+use crate::{
+    identifier::{GlobalString, Identifier},
+    items::get_item_by_id,
+    recipe::recipes_for,
+    world::{Vec2i, World},
+    ui::gui_button,
+};
+
+use super::{get_colors, CurrentScreen, Screen, ScreenDimensions};
+
+lazy_static! {
+    pub static ref NAME: GlobalString = GlobalString::from("Recipes");
+}
+
+const ROW_HEIGHT: i32 = 40;
+const PADDING: i32 = 10;
+const ITEM_SIZE: i32 = 30;
+
+/// Lists the recipes of `machine` and sets the one that gets clicked on the block at `pos`
+pub struct RecipeScreen {
+    pos: Vec2i,
+    machine: GlobalString,
+    scroll: u32,
+}
+
+impl RecipeScreen {
+    pub fn new(pos: Vec2i, machine: GlobalString) -> Self {
+        Self {
+            pos,
+            machine,
+            scroll: 0,
+        }
+    }
+}
+
+/// Draws the items with their counts next to each other, returns the x after the last one
+fn draw_items(renderer: &mut RaylibDrawHandle, items: &[(Identifier, u32)], mut x: i32, y: i32) -> i32 {
+    let colors = get_colors();
+    for (id, count) in items {
+        if let Some(item) = get_item_by_id(*id) {
+            item.render(renderer, x, y, ITEM_SIZE, ITEM_SIZE);
+        }
+        x += ITEM_SIZE;
+        if *count > 1 {
+            let text = format!("x{count}");
+            renderer.draw_text(text.as_str(), x + 2, y + ITEM_SIZE - 12, 10, colors.text);
+            x += measure_text(text.as_str(), 10) + 4;
+        }
+        x += 5;
+    }
+    x
+}
+
+impl Screen for RecipeScreen {
+    fn name(&mut self) -> GlobalString {
+        *NAME
+    }
+
+    fn rect(&mut self, screen: &ScreenDimensions) -> ScreenDimensions {
+        ScreenDimensions {
+            width: 360,
+            height: (screen.height - 35) / 4 * 3,
+        }
+    }
+
+    fn render(
+        &mut self,
+        _: &mut crate::GameConfig,
+        renderer: &mut RaylibDrawHandle,
+        x: i32,
+        y: i32,
+        w: i32,
+        h: i32,
+        world: &mut World,
+    ) {
+        let Some(selected) = world
+            .get_block_at(self.pos.x, self.pos.y)
+            .map(|(blk, _)| blk.recipe())
+        else {
+            CurrentScreen::close();
+            return;
+        };
+        let recipes = recipes_for(self.machine);
+        let colors = get_colors();
+
+        let max_height = (recipes.len() as i32 * (ROW_HEIGHT + PADDING) + PADDING).saturating_sub(h);
+        if max_height > 0 {
+            self.scroll = renderer.gui_scroll_bar(
+                Rectangle::new((x + w - 10) as f32, (y + 10) as f32, 10.0, (h - 20) as f32),
+                self.scroll as i32,
+                0,
+                max_height,
+            ) as u32;
+        } else {
+            self.scroll = 0;
+        }
+
+        for (i, recipe) in recipes.iter().enumerate() {
+            let row_y = y + PADDING + i as i32 * (ROW_HEIGHT + PADDING) - self.scroll as i32;
+            // items can't be drawn in scissor mode, so rows that are partly out of the screen are left out instead
+            if row_y < y || row_y + ROW_HEIGHT > y + h {
+                continue;
+            }
+            let rect = Rectangle::new((x + 20) as f32, row_y as f32, (w - 50) as f32, ROW_HEIGHT as f32);
+            if gui_button(renderer, rect, None) {
+                if let Some((blk, _)) = world.get_block_at_mut(self.pos.x, self.pos.y) {
+                    blk.set_recipe(recipe.id);
+                }
+                CurrentScreen::close();
+            }
+            if selected == Some(recipe.id) {
+                renderer.draw_rectangle_lines_ex(rect, 2, colors.text);
+            }
+
+            let item_y = rect.y as i32 + (ROW_HEIGHT - ITEM_SIZE) / 2;
+            let arrow_x = draw_items(renderer, &recipe.inputs, rect.x as i32 + 5, item_y);
+            renderer.draw_text("->", arrow_x, item_y + 5, 20, colors.text);
+            draw_items(renderer, &recipe.outputs, arrow_x + 30, item_y);
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use std::ffi::CStr;
+
+use lazy_static::lazy_static;
+use raylib::{
+    drawing::{RaylibDraw, RaylibDrawHandle},
+    math::Rectangle,
+    rgui::RaylibDrawGui,
+};
+
+use crate::{cstr, identifier::GlobalString, world::World, GameConfig, ui::gui_button};
+
+use super::{get_colors, Screen, ScreenDimensions};
+
+/// Asks for confirmation before removing all blocks of the world. The field is whether to give the items that were
+/// in the blocks to the player
+pub struct ResetWorldScreen(bool);
+
+impl Default for ResetWorldScreen {
+    fn default() -> Self {
+        Self(true)
+    }
+}
+
+lazy_static! {
+    pub static ref NAME: GlobalString = GlobalString::from("Reset World");
+}
+
+const RETURN_ITEMS: &CStr = cstr!("Give the items in the blocks back to me");
+const RESET: &CStr = cstr!("Reset");
+const CANCEL: &CStr = cstr!("Cancel");
+
+impl Screen for ResetWorldScreen {
+    fn rect(&mut self, _: &ScreenDimensions) -> ScreenDimensions {
+        ScreenDimensions {
+            width: 420,
+            height: 130,
+        }
+    }
+
+    fn name(&mut self) -> GlobalString {
+        *NAME
+    }
+
+    fn render(
+        &mut self,
+        config: &mut GameConfig,
+        renderer: &mut RaylibDrawHandle,
+        x: i32,
+        y: i32,
+        _: i32,
+        _: i32,
+        world: &mut World,
+    ) {
+        let colors = get_colors();
+        renderer.draw_text(
+            "Remove every block in this world?",
+            x + 10,
+            y + 10,
+            20,
+            colors.text,
+        );
+        self.0 = renderer.gui_check_box(
+            Rectangle::new((x + 10) as f32, (y + 46) as f32, 24.0, 24.0),
+            Some(RETURN_ITEMS),
+            self.0,
+        );
+
+        if gui_button(
+            renderer,
+            Rectangle::new((x + 10) as f32, (y + 90) as f32, 120.0, 24.0),
+            Some(RESET),
+        ) {
+            let items = world.clear_blocks();
+            if self.0 {
+                for item in items {
+                    config.inventory.try_add_item(item);
+                }
+            }
+            self.close();
+        }
+        if gui_button(
+            renderer,
+            Rectangle::new((x + 290) as f32, (y + 90) as f32, 120.0, 24.0),
+            Some(CANCEL),
+        ) {
+            self.close();
+        }
+    }
+}
@@ -4,7 +4,7 @@ use lazy_static::lazy_static;
 use raylib::{drawing::RaylibDrawHandle, ffi::KeyboardKey, math::Rectangle, rgui::RaylibDrawGui};
 
 use crate::{
-    asset, cstr, identifier::GlobalString, notice_board::{self, NoticeboardEntryRenderable}, scheduler::{schedule_task, Task}, screens::EscapeScreen, serialization::save_game, ui::{gui_textbox, TextboxState}, world::World, GameConfig
+    asset, cstr, log_info, identifier::GlobalString, notice_board::{self, NoticeboardEntryRenderable}, scheduler::{schedule_task, Task}, screens::EscapeScreen, serialization::{save_game, SaveLoadPermit, SaveMode}, ui::{gui_button, gui_textbox, TextboxState}, world::World, GameConfig
 };
 
 use super::{Screen, ScreenDimensions};
@@ -59,13 +59,15 @@ impl Screen for SavegameScreen {
                 self.0.active = !self.0.active;
             }
         }
-        if renderer.gui_button(
+        if gui_button(
+            renderer,
             Rectangle::new((x + 24) as f32, (y + 72) as f32, 96.0, 24.0),
             Some(SAVE),
         ) {
             self.save(world, cfg);
         }
-        if renderer.gui_button(
+        if gui_button(
+            renderer,
             Rectangle::new((x + 168) as f32, (y + 72) as f32, 96.0, 24.0),
             Some(CANCEL),
         ) {
@@ -83,7 +85,14 @@ impl SavegameScreen {
         if self.0.str.len() < 1 {
             return;
         }
-        println!("Save uwu: {}", self.0.str);
+        let Some(permit) = SaveLoadPermit::try_acquire() else {
+            notice_board::add_entry(
+                NoticeboardEntryRenderable::StringRef("Another save or load is still in progress"),
+                5,
+            );
+            return;
+        };
+        log_info!("Saving to {}", self.0.str);
         self.0.str.push_str(".pn2s");
         let path = asset!("worlds", self.0.str.clone());
         notice_board::add_entry(NoticeboardEntryRenderable::StringRef("Saving Game..."), 5);
@@ -91,7 +100,8 @@ impl SavegameScreen {
         let cfg = (*cfg).clone();
 
         thread::spawn(move || {
-            let result = match save_game(&world, &cfg, path) {
+            let _permit = permit;
+            let result = match save_game(&world, &cfg, path, SaveMode::default()) {
                 Err(e) => format!("Couldn't save game: {:?}", e),
                 Ok(bytes) => format!("Game Saved ({bytes} bytes)"),
             };
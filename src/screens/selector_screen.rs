@@ -2,22 +2,37 @@ use lazy_static::lazy_static;
 use raylib::{
     drawing::{RaylibDraw, RaylibDrawHandle},
     math::Rectangle,
-    rgui::RaylibDrawGui,
+    ffi::KeyboardKey,
     text::measure_text,
 };
 
 use crate::{
-    blocks::BLOCKS, identifier::GlobalString, world::ChunkBlockMetadata, GameConfig, game::InteractionMode, game::RenderLayer
+    blocks::{blocks_in_category, BlockCategory, BLOCKS}, cstr, identifier::GlobalString, world::ChunkBlockMetadata, GameConfig, game::{InteractionMode, HOTBAR_KEYS},
+    ui::{get_char_pressed, gui_button, gui_textbox, TextboxState},
 };
 
 use super::{get_colors, Screen, ScreenDimensions};
 
-pub struct SelectorScreen;
+#[derive(Default)]
+pub struct SelectorScreen {
+    /// the selected tab; `None` shows every block
+    category: Option<BlockCategory>,
+    /// only blocks with this in their name are shown, ignoring case
+    search: TextboxState,
+    /// whether the screen was rendered before. The key that opened it is still waiting to be typed on the first frame
+    shown: bool,
+}
 
 const BLOCK_W: u32 = 40;
 const BLOCK_H: u32 = 40;
 const BUTTON_PAD: u32 = 7;
 const BUTTON_MARGIN: u32 = 10;
+const TAB_W: i32 = 100;
+const TAB_H: i32 = 24;
+const TAB_MARGIN: i32 = 10;
+const ALL_TAB: &std::ffi::CStr = cstr!("All");
+const SEARCH_W: i32 = 200;
+const SEARCH_H: i32 = 24;
 
 lazy_static! {
     pub static ref NAME: GlobalString = GlobalString::from("Building");
@@ -50,13 +65,52 @@ impl Screen for SelectorScreen {
         let buttons_per_row = w.max(0) as u32 / (BUTTON_MARGIN * 2 + BUTTON_PAD * 2 + BLOCK_W);
 
         let mouse_pos = renderer.get_mouse_position();
+        let colors = get_colors();
 
-        let mut block_idx: usize = 0;
-        for i in unsafe { 1..BLOCKS.len() } {
-            let blk = unsafe { &BLOCKS[i] };
-            if blk.is_none() {
-                continue;
+        let tabs = std::iter::once(None).chain(BlockCategory::ALL.into_iter().map(Some));
+        for (i, tab) in tabs.enumerate() {
+            let tab_rect = Rectangle::new(
+                (x + TAB_MARGIN + i as i32 * (TAB_W + TAB_MARGIN)) as f32,
+                y as f32,
+                TAB_W as f32,
+                TAB_H as f32,
+            );
+            if gui_button(renderer, tab_rect, Some(tab.map(|cat| cat.name()).unwrap_or(ALL_TAB))) {
+                self.category = tab;
+            }
+            if self.category == tab {
+                renderer.draw_rectangle_lines_ex(tab_rect, 2, colors.border);
             }
+        }
+        let y = y + TAB_H + TAB_MARGIN;
+        let h = h - TAB_H - TAB_MARGIN;
+
+        if !self.shown {
+            while get_char_pressed().is_some() {}
+            self.shown = true;
+        }
+        // a number key held to put a block into a hotbar slot doesn't get typed into the search
+        if HOTBAR_KEYS.iter().any(|key| renderer.is_key_down(*key)) {
+            while get_char_pressed().is_some() {}
+        }
+        if gui_textbox(
+            renderer,
+            Rectangle::new((x + TAB_MARGIN) as f32, y as f32, SEARCH_W as f32, SEARCH_H as f32),
+            &mut self.search,
+            Some(64),
+            Some("Search"),
+        ) && !renderer.is_key_pressed(KeyboardKey::KEY_ENTER)
+        {
+            self.search.active = !self.search.active;
+        }
+        let query = self.search.str.to_lowercase();
+        let y = y + SEARCH_H;
+        let h = h - SEARCH_H;
+
+        let mut block_idx: usize = 0;
+        let blocks = blocks_in_category(self.category)
+            .filter(|(_, blk)| blk.name().as_str().to_lowercase().contains(&query));
+        for (i, blk) in blocks {
             // if !blk.is_building() {
             //     continue;
             // }
@@ -78,25 +132,27 @@ impl Screen for SelectorScreen {
                 selected_block = Some(i);
             }
 
-            if renderer.gui_button(button_rect, None) {
-                cfg.current_selected_block = blk;
-                cfg.interaction_mode = InteractionMode::Building;
-                self.close();
+            if gui_button(renderer, button_rect, None) {
+                // holding a number key puts the block into that hotbar slot instead of picking it
+                if let Some(slot) = HOTBAR_KEYS.iter().position(|key| renderer.is_key_down(*key)) {
+                    cfg.hotbar[slot] = Some(blk);
+                } else {
+                    cfg.current_selected_block = blk;
+                    cfg.interaction_mode = InteractionMode::Building;
+                    self.close();
+                }
             }
-            blk.render(
+            blk.render_preview(
                 renderer,
                 x + BUTTON_PAD as i32,
                 y + BUTTON_PAD as i32,
                 BLOCK_W as i32,
                 BLOCK_H as i32,
                 ChunkBlockMetadata::default(),
-                RenderLayer::default_preview(),
             );
             block_idx += 1;
         }
 
-        let colors = get_colors();
-
         renderer.draw_rectangle(x + w + 4, y - 6, 2, h + 10, colors.border);
         
         if let Some(selected_block) = selected_block {
@@ -115,14 +171,13 @@ impl Screen for SelectorScreen {
                     colors.border,
                 );
 
-                selected_block.render(
+                selected_block.render_preview(
                     renderer,
                     x_preview + ((w_preview - 72) / 2 + 4),
                     y + 9,
                     64,
                     64,
                     ChunkBlockMetadata::default(),
-                    RenderLayer::default_preview(),
                 );
 
                 let text = selected_block.name().as_str();
@@ -0,0 +1,274 @@
+use raylib::{
+    color::Color,
+    drawing::{RaylibDraw, RaylibDrawHandle},
+    math::{Rectangle, Vector2},
+    text::{measure_text, measure_text_ex},
+};
+
+use crate::{
+    cstr,
+    inventory::Inventory,
+    items::Item,
+    recipe::{production_hint_for, tooltip_text},
+    ui::gui_button,
+};
+
+use super::{get_colors, HeldItem};
+
+pub const ITEM_W: u32 = 40;
+pub const ITEM_H: u32 = 40;
+pub const BUTTON_PAD: u32 = 7;
+pub const BUTTON_MARGIN: u32 = 10;
+/// distance between the start of two neighbouring slots
+pub const SLOT_STRIDE_X: u32 = ITEM_W + BUTTON_PAD * 2 + BUTTON_MARGIN * 2;
+pub const SLOT_STRIDE_Y: u32 = ITEM_H + BUTTON_PAD * 2 + BUTTON_MARGIN * 2;
+
+pub const TRASH_H: i32 = 30;
+const TRASH_LABEL: &std::ffi::CStr = cstr!("Trash (hold Ctrl)");
+
+const BADGE_FONT_SIZE: i32 = 20;
+const BADGE_H: i32 = 22;
+
+/// A grid of inventory slots, laid out row by row with `columns` slots per row
+#[derive(Debug, Clone, Copy)]
+pub struct SlotGrid {
+    pub x: i32,
+    pub y: i32,
+    pub columns: u32,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SlotGridEvents {
+    pub clicked: Option<usize>,
+    pub hovered: Option<usize>,
+}
+
+impl SlotGrid {
+    pub fn new(x: i32, y: i32, columns: u32) -> Self {
+        Self { x, y, columns }
+    }
+
+    pub fn width(columns: u32) -> i32 {
+        (SLOT_STRIDE_X * columns) as i32
+    }
+
+    pub fn height(num_slots: usize, columns: u32) -> i32 {
+        (SLOT_STRIDE_Y * (num_slots as u32).div_ceil(columns)) as i32
+    }
+
+    /// The rectangle of the button of `slot`
+    pub fn slot_rect(&self, slot: usize) -> Rectangle {
+        let row = slot as u32 % self.columns;
+        let col = slot as u32 / self.columns;
+
+        Rectangle::new(
+            (self.x + (BUTTON_MARGIN + row * SLOT_STRIDE_X) as i32) as f32,
+            (self.y + (BUTTON_MARGIN + col * SLOT_STRIDE_Y) as i32) as f32,
+            (ITEM_W + BUTTON_PAD * 2) as f32,
+            (ITEM_H + BUTTON_PAD * 2) as f32,
+        )
+    }
+
+    /// The slot under `pos`, if any. The margins between slots don't belong to any slot
+    pub fn slot_at(&self, pos: Vector2, num_slots: usize) -> Option<usize> {
+        (0..num_slots).find(|slot| {
+            let rect = self.slot_rect(*slot);
+            pos.x >= rect.x && pos.x < rect.x + rect.width && pos.y >= rect.y && pos.y < rect.y + rect.height
+        })
+    }
+
+    /// Renders all slots of `inventory` with their items.
+    /// Returns the slot that was clicked and the one the mouse is over.
+    pub fn render(&self, renderer: &mut RaylibDrawHandle, inventory: &Inventory) -> SlotGridEvents {
+        let mut events = SlotGridEvents {
+            clicked: None,
+            hovered: self.slot_at(renderer.get_mouse_position(), inventory.size()),
+        };
+
+        for slot in 0..inventory.size() {
+            let rect = self.slot_rect(slot);
+
+            if gui_button(renderer, rect, None) {
+                events.clicked = Some(slot);
+            }
+
+            if let Some(item) = inventory.get_item(slot) {
+                item.render_stack(
+                    renderer,
+                    rect.x as i32 + BUTTON_PAD as i32,
+                    rect.y as i32 + BUTTON_PAD as i32,
+                    ITEM_W as i32,
+                    ITEM_H as i32,
+                    item.stack_count(),
+                );
+                draw_count_badge(renderer, item, rect);
+            }
+        }
+
+        events
+    }
+
+    /// Draws the tooltip of the item in `hovered`. Call this after rendering everything else so it ends up on top.
+    /// There is no tooltip while an item is held, it would cover the held item
+    pub fn draw_tooltip(
+        renderer: &mut RaylibDrawHandle,
+        inventory: &Inventory,
+        hovered: Option<usize>,
+    ) {
+        if HeldItem::is_some() {
+            return;
+        }
+        if let Some(item) = hovered.and_then(|slot| inventory.get_item(slot)) {
+            tooltip(item, renderer);
+        }
+    }
+}
+
+/// Draws the trash zone and returns whether it was clicked
+pub fn gui_trash(renderer: &mut RaylibDrawHandle, rect: Rectangle) -> bool {
+    gui_button(renderer, rect, Some(TRASH_LABEL))
+}
+
+/// Gets the rectangle of the item count badge for a slot, anchored to the bottom right and clamped to the slot
+pub fn count_badge_rect(slot: Rectangle, text_width: i32) -> Rectangle {
+    let width = (text_width + 6).min(slot.width as i32);
+    let x = slot.x as i32 + (slot.width as i32 - width).max(0);
+    let y = slot.y as i32 + slot.height as i32 - BADGE_H;
+
+    Rectangle::new(x as f32, y as f32, width as f32, BADGE_H as f32)
+}
+
+/// Draws the "x{count}" badge of an item within the slot
+pub fn draw_count_badge(renderer: &mut RaylibDrawHandle, item: &dyn Item, slot: Rectangle) {
+    let sz = format!("x{}", item.stack_count());
+    let len = measure_text(sz.as_str(), BADGE_FONT_SIZE);
+    let rect = count_badge_rect(slot, len);
+
+    renderer.draw_rectangle_rec(rect, Color::ORANGE);
+    renderer.draw_text(
+        sz.as_str(),
+        rect.x as i32 + (rect.width as i32 - len) / 2,
+        rect.y as i32 + 1,
+        BADGE_FONT_SIZE,
+        Color::WHITE,
+    );
+}
+
+pub fn tooltip(item: &dyn Item, renderer: &mut RaylibDrawHandle) {
+    let text = tooltip_text(item.description(), production_hint_for(item.identifier()));
+    draw_tooltip_box(renderer, item.name().as_str(), text.as_str());
+}
+
+/// Draws a tooltip with `name` as the title above `text` next to the mouse, moved back inside the screen when it
+/// would go past an edge
+pub fn draw_tooltip_box(renderer: &mut RaylibDrawHandle, name: &str, text: &str) {
+    let colors = get_colors();
+
+    let text_size = measure_text_ex(renderer.get_font_default(), text, 10.0, 1.0);
+    let name_width = measure_text(name, 20);
+    let mut width = name_width.max(text_size.x as i32) + 10;
+    let mut height = 30 + text_size.y as i32;
+    if width > 170 {
+        height += 10 * width / 170;
+        width = 170;
+    }
+
+    let mouse_pos = renderer.get_mouse_position();
+    let x =
+        mouse_pos.x as i32 + 5 + (renderer.get_screen_width() - (width + mouse_pos.x as i32 + 5)).min(0);
+    let y =
+        mouse_pos.y as i32 + 5 + (renderer.get_screen_height() - (height + mouse_pos.y as i32 + 5)).min(0);
+
+    renderer.draw_rectangle_rounded(Rectangle::new(x as f32, y as f32, width as f32, height as f32), 0.2, 1, colors.bg);
+    renderer.draw_rectangle_rounded_lines(
+        Rectangle::new(x as f32, y as f32, width as f32, height as f32),
+        0.2,
+        1,
+        2,
+        colors.border,
+    );
+    renderer.draw_text_rec(
+        renderer.get_font_default(),
+        name,
+        Rectangle::new((x + 5) as f32, (y + 5) as f32, (width - 10) as f32, 20.0),
+        20.0,
+        2.0,
+        false,
+        colors.text,
+    );
+    renderer.draw_text_rec(
+        renderer.get_font_default(),
+        text,
+        Rectangle::new((x + 5) as f32, (y + 25) as f32, (width - 10) as f32, (height - 30) as f32),
+        10.0,
+        1.0,
+        false,
+        colors.text,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// about what `measure_text` returns for "x{count}" with the default font at `BADGE_FONT_SIZE`
+    fn badge_text_width(count: u32) -> i32 {
+        format!("x{count}").len() as i32 * 11
+    }
+
+    #[test]
+    fn count_badge_stays_within_the_slot() {
+        let slot = Rectangle::new(100.0, 60.0, ITEM_W as f32, ITEM_H as f32);
+        let mut widths = Vec::new();
+        for count in [1, 10, 255] {
+            let badge = count_badge_rect(slot, badge_text_width(count));
+            assert!(badge.x >= slot.x && badge.y >= slot.y, "x{count}: {badge:?}");
+            assert!(badge.x + badge.width <= slot.x + slot.width, "x{count}: {badge:?}");
+            assert!(badge.y + badge.height <= slot.y + slot.height, "x{count}: {badge:?}");
+            // anchored to the bottom right corner
+            assert_eq!(badge.x + badge.width, slot.x + slot.width);
+            assert_eq!(badge.y + badge.height, slot.y + slot.height);
+            widths.push(badge.width);
+        }
+        assert!(widths[0] < widths[1]);
+        assert_eq!(widths[2], slot.width);
+    }
+
+    fn center(rect: Rectangle) -> Vector2 {
+        Vector2::new(rect.x + rect.width / 2.0, rect.y + rect.height / 2.0)
+    }
+
+    #[test]
+    fn slots_are_found_under_the_mouse() {
+        let grid = SlotGrid::new(30, 50, 4);
+        // every slot's button is where the grid finds that slot
+        for slot in 0..10 {
+            assert_eq!(grid.slot_at(center(grid.slot_rect(slot)), 10), Some(slot));
+        }
+        // the fifth slot starts the second row
+        let first = grid.slot_rect(0);
+        let fifth = grid.slot_rect(4);
+        assert_eq!(fifth.x, first.x);
+        assert_eq!(fifth.y, first.y + SLOT_STRIDE_Y as f32);
+        // the top left corner of the first button belongs to it, the margin before it doesn't
+        assert_eq!(grid.slot_at(Vector2::new(first.x, first.y), 10), Some(0));
+        assert_eq!(grid.slot_at(Vector2::new(first.x - 1.0, first.y), 10), None);
+    }
+
+    #[test]
+    fn clicks_outside_the_slots_hit_nothing() {
+        let grid = SlotGrid::new(30, 50, 4);
+        // between the first two buttons
+        let first = grid.slot_rect(0);
+        let gap = Vector2::new(first.x + first.width + BUTTON_MARGIN as f32, first.y + 1.0);
+        assert_eq!(grid.slot_at(gap, 10), None);
+        // where the 11th and 12th slot would be in a row that's only partly filled
+        assert_eq!(grid.slot_at(center(grid.slot_rect(10)), 10), None);
+        assert_eq!(grid.slot_at(center(grid.slot_rect(11)), 10), None);
+        // past the right edge of the grid
+        let right = Vector2::new((grid.x + SlotGrid::width(4) + 5) as f32, first.y + 1.0);
+        assert_eq!(grid.slot_at(right, 10), None);
+        // an empty inventory has no slots to click
+        assert_eq!(grid.slot_at(center(first), 0), None);
+    }
+}
@@ -0,0 +1,118 @@
+use lazy_static::lazy_static;
+use raylib::{
+    drawing::{RaylibDraw, RaylibDrawHandle},
+    ffi::KeyboardKey,
+    math::Rectangle,
+};
+
+use crate::{
+    blocks::{downcast_mut, splitter::ConveyorSplitter},
+    identifier::GlobalString,
+    inventory::NUM_SLOTS_PLAYER,
+    items::get_item_by_id,
+    world::{Vec2i, World},
+    GameConfig,
+    ui::gui_button,
+};
+
+use super::{
+    get_colors,
+    slot_grid::{gui_trash, SlotGrid, BUTTON_MARGIN, BUTTON_PAD, ITEM_H, ITEM_W, TRASH_H},
+    CurrentScreen, HeldItem, Screen, ScreenDimensions,
+};
+
+lazy_static! {
+    pub static ref NAME: GlobalString = GlobalString::from("Splitter Filters");
+}
+
+const BUTTONS_PER_ROW: u32 = 5;
+const LABEL_H: i32 = 20;
+const SIDE_NAMES: [&str; 3] = ["Left", "Front", "Right"];
+
+/// Lets the player set the item filter of each output of the splitter at `pos`. Clicking a filter slot while
+/// holding an item filters that side for the item, clicking it with nothing held clears the filter
+pub struct SplitterFilterScreen {
+    pos: Vec2i,
+}
+
+impl SplitterFilterScreen {
+    pub fn new(pos: Vec2i) -> Self {
+        Self { pos }
+    }
+}
+
+impl Screen for SplitterFilterScreen {
+    fn name(&mut self) -> GlobalString {
+        *NAME
+    }
+
+    fn rect(&mut self, _: &ScreenDimensions) -> ScreenDimensions {
+        ScreenDimensions {
+            width: SlotGrid::width(BUTTONS_PER_ROW),
+            height: LABEL_H
+                + SlotGrid::height(3, BUTTONS_PER_ROW)
+                + SlotGrid::height(NUM_SLOTS_PLAYER, BUTTONS_PER_ROW)
+                + TRASH_H
+                + BUTTON_MARGIN as i32,
+        }
+    }
+
+    fn render(
+        &mut self,
+        cfg: &mut GameConfig,
+        renderer: &mut RaylibDrawHandle,
+        x: i32,
+        y: i32,
+        _: i32,
+        _: i32,
+        world: &mut World,
+    ) {
+        let Some(splitter) = world
+            .get_block_at_mut(self.pos.x, self.pos.y)
+            .and_then(|(blk, _)| downcast_mut::<ConveyorSplitter>(&mut **blk))
+        else {
+            CurrentScreen::close();
+            return;
+        };
+        let colors = get_colors();
+
+        // the filters are centered above the player inventory
+        let filter_grid = SlotGrid::new(x + SlotGrid::width(1), y + LABEL_H, BUTTONS_PER_ROW);
+        for (side, filter) in splitter.filters().into_iter().enumerate() {
+            let rect = filter_grid.slot_rect(side);
+            renderer.draw_text(SIDE_NAMES[side], rect.x as i32, y + BUTTON_MARGIN as i32, 10, colors.text);
+            if gui_button(renderer, rect, None) {
+                splitter.set_filter(side, HeldItem::identifier());
+            }
+            if let Some(item) = filter.and_then(get_item_by_id) {
+                item.render(
+                    renderer,
+                    rect.x as i32 + BUTTON_PAD as i32,
+                    rect.y as i32 + BUTTON_PAD as i32,
+                    ITEM_W as i32,
+                    ITEM_H as i32,
+                );
+            }
+        }
+
+        let player_y = y + LABEL_H + SlotGrid::height(3, BUTTONS_PER_ROW);
+        let player_grid = SlotGrid::new(x, player_y, BUTTONS_PER_ROW);
+        let player_events = player_grid.render(renderer, &cfg.inventory);
+
+        let trash_rect = Rectangle::new(
+            (x + BUTTON_MARGIN as i32) as f32,
+            (player_y + SlotGrid::height(NUM_SLOTS_PLAYER, BUTTONS_PER_ROW)) as f32,
+            (SlotGrid::width(BUTTONS_PER_ROW) - BUTTON_MARGIN as i32 * 2) as f32,
+            TRASH_H as f32,
+        );
+        if gui_trash(renderer, trash_rect) {
+            HeldItem::trash(renderer.is_key_down(KeyboardKey::KEY_LEFT_CONTROL));
+        }
+
+        if let Some(slot) = player_events.clicked {
+            HeldItem::click_slot(&mut cfg.inventory, slot);
+        }
+        SlotGrid::draw_tooltip(renderer, &cfg.inventory, player_events.hovered);
+        HeldItem::draw(renderer);
+    }
+}
@@ -1,15 +1,22 @@
-use std::{ffi::CStr, fs::read_dir};
+use std::{ffi::CStr, fs::read_dir, path::PathBuf, time::SystemTime};
 
 use lazy_static::lazy_static;
-use raylib::{drawing::RaylibScissorModeExt, math::Rectangle, rgui::RaylibDrawGui};
+use raylib::{
+    drawing::{RaylibDraw, RaylibScissorModeExt},
+    math::Rectangle,
+    rgui::RaylibDrawGui,
+};
 
 use crate::{
-    asset, cstr, identifier::GlobalString, notice_board::{self, NoticeboardEntryRenderable}, scheduler::{schedule_task, Task}, screens::DialogBox
+    asset, cstr, log_info, identifier::GlobalString, notice_board::{self, NoticeboardEntryRenderable}, scheduler::{schedule_task, Task}, screens::DialogBox,
+    serialization::{read_save_header, set_read_only},
+    ui::gui_button,
 };
 
-use super::{Screen, ScreenDimensions};
+use super::{get_colors, Screen, ScreenDimensions};
 
-pub struct WorldScreen(Vec<Vec<u8>>, u32);
+/// the save file names (nul terminated), the scroll offset and which saves are read-only
+pub struct WorldScreen(Vec<Vec<u8>>, u32, Vec<bool>);
 
 lazy_static! {
     pub static ref NAME: GlobalString = GlobalString::from("Worlds");
@@ -35,11 +42,55 @@ impl WorldScreen {
                 };
             }
 
-            Box::new(Self(entries, 0))
+            let read_only = entries
+                .iter()
+                .map(|name| {
+                    let name = String::from_utf8_lossy(&name[0..name.len() - 1]).to_string();
+                    read_save_header(&asset!("worlds", name))
+                        .map(|header| header.read_only)
+                        .unwrap_or(false)
+                })
+                .collect();
+
+            Box::new(Self(entries, 0, read_only))
         })
     }
 }
 
+/// The save that was modified last, ties go to the first one
+pub fn most_recent_world(saves: impl IntoIterator<Item = (PathBuf, SystemTime)>) -> Option<PathBuf> {
+    saves
+        .into_iter()
+        .fold(None, |newest: Option<(PathBuf, SystemTime)>, (path, modified)| match newest {
+            Some((_, newest_modified)) if newest_modified >= modified => newest,
+            _ => Some((path, modified)),
+        })
+        .map(|(path, _)| path)
+}
+
+/// The path of the world that was saved last, if there is any
+pub fn find_most_recent_world() -> Option<String> {
+    let saves = read_dir(asset!("worlds"))
+        .ok()?
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let metadata = entry.metadata().ok()?;
+            if !metadata.is_file() {
+                return None;
+            }
+            Some((entry.path(), metadata.modified().ok()?))
+        });
+    most_recent_world(saves).and_then(|path| path.to_str().map(str::to_string))
+}
+
+/// Draws a padlock into the 24x24 square at `x`, `y`, with the shackle open if it isn't `locked`
+fn draw_lock<T: RaylibDraw>(renderer: &mut T, x: i32, y: i32, locked: bool) {
+    let colors = get_colors();
+    let shackle_y = if locked { y + 4 } else { y + 1 };
+    renderer.draw_rectangle_lines(x + 8, shackle_y, 8, 10, colors.text);
+    renderer.draw_rectangle(x + 5, y + 11, 14, 10, colors.text);
+}
+
 const HEIGHT: i32 = 24;
 const PADDING: i32 = 10;
 
@@ -84,17 +135,18 @@ impl Screen for WorldScreen {
         y -= self.1 as i32;
 
         for i in 0..self.0.len() {
-            if renderer.gui_button(
+            if gui_button(
+                &mut renderer,
                 Rectangle::new(
                     (x + 20) as f32,
                     ((i + 1) as i32 * (HEIGHT + PADDING) + y + PADDING) as f32,
-                    240.0,
+                    206.0,
                     24.0,
                 ),
                 unsafe { Some(CStr::from_bytes_with_nul_unchecked(self.0[i].as_slice())) },
             ) {
                 if let Ok(mut name) = String::from_utf8(self.0[i].clone()) {
-                    println!("Load {}", String::from_utf8_lossy(self.0[i].as_slice()));
+                    log_info!("Load {}", String::from_utf8_lossy(self.0[i].as_slice()));
                     schedule_task(Task::OpenScreenCentered(DialogBox::new_uncloseable(
                         Some(*NAME_LOADING),
                         format!(
@@ -110,9 +162,28 @@ impl Screen for WorldScreen {
                     notice_board::add_entry(NoticeboardEntryRenderable::StringRef("Could not load savefile"), 5);
                 }
             }
+
+            let lock_rect = Rectangle::new(
+                (x + 236) as f32,
+                ((i + 1) as i32 * (HEIGHT + PADDING) + y + PADDING) as f32,
+                24.0,
+                24.0,
+            );
+            if gui_button(&mut renderer, lock_rect, None) {
+                let name = String::from_utf8_lossy(&self.0[i][0..self.0[i].len() - 1]).to_string();
+                match set_read_only(&asset!("worlds", name), !self.2[i]) {
+                    Ok(()) => self.2[i] = !self.2[i],
+                    Err(e) => notice_board::add_entry(
+                        NoticeboardEntryRenderable::String(format!("Could not change the world: {e}")),
+                        5,
+                    ),
+                }
+            }
+            draw_lock(&mut renderer, lock_rect.x as i32, lock_rect.y as i32, self.2[i]);
         }
 
-        if renderer.gui_button(
+        if gui_button(
+            &mut renderer,
             Rectangle::new((x + 20) as f32, (y + PADDING) as f32, 240.0, 24.0),
             Some(NEW),
         ) {
@@ -120,3 +191,34 @@ impl Screen for WorldScreen {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::*;
+
+    fn save(name: &str, modified_secs: u64) -> (PathBuf, SystemTime) {
+        (PathBuf::from(name), UNIX_EPOCH + Duration::from_secs(modified_secs))
+    }
+
+    #[test]
+    fn picks_the_last_modified_save() {
+        let saves = [save("a.pn2s", 100), save("b.pn2s", 300), save("c.pn2s", 200)];
+        assert_eq!(most_recent_world(saves), Some(PathBuf::from("b.pn2s")));
+        // the order they're listed in doesn't matter
+        let saves = [save("c.pn2s", 200), save("b.pn2s", 300), save("a.pn2s", 100)];
+        assert_eq!(most_recent_world(saves), Some(PathBuf::from("b.pn2s")));
+    }
+
+    #[test]
+    fn ties_go_to_the_first_save() {
+        let saves = [save("a.pn2s", 100), save("b.pn2s", 500), save("c.pn2s", 500)];
+        assert_eq!(most_recent_world(saves), Some(PathBuf::from("b.pn2s")));
+    }
+
+    #[test]
+    fn no_saves_no_world() {
+        assert_eq!(most_recent_world([]), None);
+    }
+}
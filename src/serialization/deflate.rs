@@ -0,0 +1,423 @@
+//! A small raw deflate (RFC 1951) encoder and decoder for save files. The encoder finds repeats with a hash chain
+//! and writes them with the fixed huffman codes, which is plenty for saves, as they are mostly long runs of the
+//! same bytes. The decoder reads any deflate stream.
+
+use super::SerializationError;
+
+const WINDOW_SIZE: usize = 1 << 15;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+const HASH_BITS: u32 = 15;
+/// how many earlier positions with the same hash are tried per byte; more compresses better but takes longer
+const MAX_CHAIN: usize = 64;
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227,
+    258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097,
+    6145, 8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13,
+];
+/// the order the code length code lengths of a dynamic block are stored in
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+struct BitWriter {
+    out: Vec<u8>,
+    bits: u64,
+    num_bits: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u32, num_bits: u32) {
+        self.bits |= (value as u64) << self.num_bits;
+        self.num_bits += num_bits;
+        while self.num_bits >= 8 {
+            self.out.push(self.bits as u8);
+            self.bits >>= 8;
+            self.num_bits -= 8;
+        }
+    }
+
+    /// Huffman codes are stored starting at their most significant bit, unlike every other value
+    fn write_code(&mut self, code: u32, num_bits: u32) {
+        self.write(code.reverse_bits() >> (32 - num_bits), num_bits);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.num_bits > 0 {
+            self.out.push(self.bits as u8);
+        }
+        self.out
+    }
+
+    fn write_literal(&mut self, symbol: u16) {
+        let symbol = symbol as u32;
+        match symbol {
+            0..=143 => self.write_code(0x30 + symbol, 8),
+            144..=255 => self.write_code(0x190 + symbol - 144, 9),
+            256..=279 => self.write_code(symbol - 256, 7),
+            _ => self.write_code(0xc0 + symbol - 280, 8),
+        }
+    }
+
+    fn write_match(&mut self, length: usize, distance: usize) {
+        let idx = LENGTH_BASE.iter().rposition(|base| *base as usize <= length).unwrap_or(0);
+        self.write_literal(257 + idx as u16);
+        self.write((length - LENGTH_BASE[idx] as usize) as u32, LENGTH_EXTRA[idx] as u32);
+
+        let idx = DIST_BASE.iter().rposition(|base| *base as usize <= distance).unwrap_or(0);
+        self.write_code(idx as u32, 5);
+        self.write((distance - DIST_BASE[idx] as usize) as u32, DIST_EXTRA[idx] as u32);
+    }
+}
+
+fn hash(data: &[u8], pos: usize) -> usize {
+    let value = (data[pos] as u32) << 16 | (data[pos + 1] as u32) << 8 | data[pos + 2] as u32;
+    (value.wrapping_mul(2654435761) >> (32 - HASH_BITS)) as usize
+}
+
+/// Compresses `data` into a single fixed huffman deflate block
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    let mut writer = BitWriter {
+        out: Vec::with_capacity(data.len() / 4),
+        bits: 0,
+        num_bits: 0,
+    };
+    // the last block, with fixed huffman codes
+    writer.write(1, 1);
+    writer.write(1, 2);
+
+    // the last position of every hash and the position before each one with the same hash
+    let mut head = vec![usize::MAX; 1 << HASH_BITS];
+    let mut prev = vec![usize::MAX; WINDOW_SIZE];
+    let insert = |pos: usize, head: &mut [usize], prev: &mut [usize]| {
+        if pos + MIN_MATCH <= data.len() {
+            let h = hash(data, pos);
+            prev[pos % WINDOW_SIZE] = head[h];
+            head[h] = pos;
+        }
+    };
+
+    let mut pos = 0;
+    while pos < data.len() {
+        let mut best_len = 0;
+        let mut best_dist = 0;
+        if pos + MIN_MATCH <= data.len() {
+            let max_len = MAX_MATCH.min(data.len() - pos);
+            let mut candidate = head[hash(data, pos)];
+            let mut chain = 0;
+            while candidate != usize::MAX && pos - candidate <= WINDOW_SIZE && chain < MAX_CHAIN {
+                let len = data[candidate..]
+                    .iter()
+                    .zip(&data[pos..pos + max_len])
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                if len > best_len {
+                    best_len = len;
+                    best_dist = pos - candidate;
+                    if len == max_len {
+                        break;
+                    }
+                }
+                let next = prev[candidate % WINDOW_SIZE];
+                // older entries of the ring got overwritten by newer positions
+                if next == usize::MAX || next >= candidate {
+                    break;
+                }
+                candidate = next;
+                chain += 1;
+            }
+        }
+
+        if best_len >= MIN_MATCH {
+            writer.write_match(best_len, best_dist);
+            for p in pos..pos + best_len {
+                insert(p, &mut head, &mut prev);
+            }
+            pos += best_len;
+        } else {
+            writer.write_literal(data[pos] as u16);
+            insert(pos, &mut head, &mut prev);
+            pos += 1;
+        }
+    }
+
+    writer.write_literal(256);
+    writer.finish()
+}
+
+/// A canonical huffman code: how many codes there are of each length and the symbols ordered by their code
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Self {
+        let mut counts = [0_u16; 16];
+        for len in lengths {
+            counts[*len as usize] += 1;
+        }
+        counts[0] = 0;
+        let mut offsets = [0_u16; 16];
+        for len in 1..15 {
+            offsets[len + 1] = offsets[len] + counts[len];
+        }
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, len) in lengths.iter().enumerate() {
+            if *len != 0 {
+                symbols[offsets[*len as usize] as usize] = symbol as u16;
+                offsets[*len as usize] += 1;
+            }
+        }
+        Self { counts, symbols }
+    }
+
+    fn fixed() -> (Self, Self) {
+        let mut lengths = [0_u8; 288];
+        lengths[0..144].fill(8);
+        lengths[144..256].fill(9);
+        lengths[256..280].fill(7);
+        lengths[280..288].fill(8);
+        (Self::new(&lengths), Self::new(&[5; 30]))
+    }
+}
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    bits: u32,
+    num_bits: u32,
+}
+
+impl BitReader<'_> {
+    fn read(&mut self, num_bits: u32) -> Result<u32, SerializationError> {
+        while self.num_bits < num_bits {
+            let byte = *self.data.get(self.pos).ok_or(SerializationError::NotEnoughSpace)?;
+            self.pos += 1;
+            self.bits |= (byte as u32) << self.num_bits;
+            self.num_bits += 8;
+        }
+        let value = self.bits & ((1_u64 << num_bits) - 1) as u32;
+        self.bits >>= num_bits;
+        self.num_bits -= num_bits;
+        Ok(value)
+    }
+
+    fn decode(&mut self, huffman: &Huffman) -> Result<u16, SerializationError> {
+        let mut code = 0_i32;
+        let mut first = 0_i32;
+        let mut index = 0_i32;
+        for len in 1..16 {
+            code |= self.read(1)? as i32;
+            let count = huffman.counts[len] as i32;
+            if code - count < first {
+                return Ok(huffman.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(SerializationError::InvalidData)
+    }
+}
+
+fn read_dynamic(reader: &mut BitReader) -> Result<(Huffman, Huffman), SerializationError> {
+    let num_literals = reader.read(5)? as usize + 257;
+    let num_distances = reader.read(5)? as usize + 1;
+    let num_code_lengths = reader.read(4)? as usize + 4;
+
+    let mut code_lengths = [0_u8; 19];
+    for idx in &CODE_LENGTH_ORDER[..num_code_lengths] {
+        code_lengths[*idx] = reader.read(3)? as u8;
+    }
+    let code_lengths = Huffman::new(&code_lengths);
+
+    let mut lengths = Vec::with_capacity(num_literals + num_distances);
+    while lengths.len() < num_literals + num_distances {
+        let (value, repeat) = match reader.decode(&code_lengths)? {
+            symbol @ 0..=15 => (symbol as u8, 1),
+            16 => (
+                *lengths.last().ok_or(SerializationError::InvalidData)?,
+                3 + reader.read(2)?,
+            ),
+            17 => (0, 3 + reader.read(3)?),
+            18 => (0, 11 + reader.read(7)?),
+            _ => return Err(SerializationError::InvalidData),
+        };
+        lengths.extend(std::iter::repeat(value).take(repeat as usize));
+    }
+    if lengths.len() != num_literals + num_distances {
+        return Err(SerializationError::InvalidData);
+    }
+    Ok((
+        Huffman::new(&lengths[..num_literals]),
+        Huffman::new(&lengths[num_literals..]),
+    ))
+}
+
+/// Decompresses a deflate stream that is `expected_len` bytes long once decompressed
+pub fn decompress(data: &[u8], expected_len: usize) -> Result<Vec<u8>, SerializationError> {
+    // deflate can't shrink data more than 1032 times, a bigger length is broken and shouldn't allocate that much
+    let mut out = Vec::with_capacity(expected_len.min(data.len().saturating_mul(1032)));
+    let mut reader = BitReader {
+        data,
+        pos: 0,
+        bits: 0,
+        num_bits: 0,
+    };
+
+    loop {
+        let last = reader.read(1)? == 1;
+        let (literals, distances) = match reader.read(2)? {
+            0 => {
+                // stored blocks start at the next byte
+                reader.bits = 0;
+                reader.num_bits = 0;
+                let header = data
+                    .get(reader.pos..reader.pos + 4)
+                    .ok_or(SerializationError::NotEnoughSpace)?;
+                let len = u16::from_le_bytes([header[0], header[1]]) as usize;
+                if len != !u16::from_le_bytes([header[2], header[3]]) as usize {
+                    return Err(SerializationError::InvalidData);
+                }
+                reader.pos += 4;
+                let bytes = data
+                    .get(reader.pos..reader.pos + len)
+                    .ok_or(SerializationError::NotEnoughSpace)?;
+                if out.len() + len > expected_len {
+                    return Err(SerializationError::InvalidData);
+                }
+                out.extend_from_slice(bytes);
+                reader.pos += len;
+                if last {
+                    break;
+                }
+                continue;
+            }
+            1 => Huffman::fixed(),
+            2 => read_dynamic(&mut reader)?,
+            _ => return Err(SerializationError::InvalidData),
+        };
+
+        loop {
+            // a broken stream could go on for much longer than the data it is supposed to hold
+            if out.len() > expected_len {
+                return Err(SerializationError::InvalidData);
+            }
+            let symbol = reader.decode(&literals)? as usize;
+            if symbol < 256 {
+                out.push(symbol as u8);
+                continue;
+            }
+            if symbol == 256 {
+                break;
+            }
+            let idx = symbol - 257;
+            if idx >= LENGTH_BASE.len() {
+                return Err(SerializationError::InvalidData);
+            }
+            let length = LENGTH_BASE[idx] as usize + reader.read(LENGTH_EXTRA[idx] as u32)? as usize;
+            let idx = reader.decode(&distances)? as usize;
+            if idx >= DIST_BASE.len() {
+                return Err(SerializationError::InvalidData);
+            }
+            let distance = DIST_BASE[idx] as usize + reader.read(DIST_EXTRA[idx] as u32)? as usize;
+            if distance > out.len() || out.len() + length > expected_len {
+                return Err(SerializationError::InvalidData);
+            }
+            // the match can overlap the bytes it produces, so it has to be copied a byte at a time
+            let start = out.len() - distance;
+            for i in 0..length {
+                out.push(out[start + i]);
+            }
+        }
+        if last {
+            break;
+        }
+    }
+
+    if out.len() != expected_len {
+        return Err(SerializationError::InvalidData);
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::Rng;
+
+    fn round_trip(data: &[u8]) {
+        let compressed = compress(data);
+        assert_eq!(decompress(&compressed, data.len()).unwrap(), data);
+    }
+
+    #[test]
+    fn round_trips() {
+        round_trip(&[]);
+        round_trip(&[42]);
+
+        let repetitive: Vec<u8> = (0..100_000).map(|i| (i / 1000 % 3) as u8).collect();
+        assert!(compress(&repetitive).len() < repetitive.len() / 50);
+        round_trip(&repetitive);
+
+        let mut rng = Rng::new(7);
+        let random: Vec<u8> = (0..70_000).map(|_| rng.next_u32() as u8).collect();
+        round_trip(&random);
+    }
+
+    #[test]
+    fn reads_stored_blocks() {
+        // what zlib writes for "stored bytes" at compression level 0
+        let stored = [1, 12, 0, 243, 255, 115, 116, 111, 114, 101, 100, 32, 98, 121, 116, 101, 115];
+        assert_eq!(decompress(&stored, 12).unwrap(), b"stored bytes");
+
+        // a stored block that isn't the last one, followed by a fixed huffman block
+        let mut mixed = vec![0, 3, 0, 252, 255, b'a', b'b', b'c'];
+        mixed.extend(compress(b"defdefdef"));
+        assert_eq!(decompress(&mixed, 12).unwrap(), b"abcdefdefdef");
+    }
+
+    #[test]
+    fn reads_dynamic_blocks() {
+        // what zlib writes for this at compression level 9: a single dynamic huffman block with literals and matches
+        let dynamic = [
+            13, 132, 1, 17, 0, 0, 12, 68, 178, 242, 235, 159, 97, 112, 64, 137, 62, 199, 234, 210, 249,
+        ];
+        assert_eq!(decompress(&dynamic, 26).unwrap(), b"aabbaaaabbacbcaccacdcdcbcb");
+    }
+
+    #[test]
+    fn rejects_broken_streams() {
+        let data = b"the quick brown fox jumps over the lazy dog, the quick brown fox";
+        let compressed = compress(data);
+
+        for len in 0..compressed.len() {
+            assert!(decompress(&compressed[..len], data.len()).is_err(), "cut off after {len} bytes");
+        }
+        // the wrong length, in both directions
+        assert!(matches!(decompress(&compressed, data.len() + 1), Err(SerializationError::InvalidData)));
+        assert!(matches!(decompress(&compressed, data.len() - 1), Err(SerializationError::InvalidData)));
+        // a block type that doesn't exist
+        assert!(matches!(decompress(&[0b111], 0), Err(SerializationError::InvalidData)));
+        // a stored block whose length doesn't match its complement
+        assert!(matches!(decompress(&[1, 3, 0, 0, 0, 1, 2, 3], 3), Err(SerializationError::InvalidData)));
+        // a match that reaches back before the start: fixed block, length 3 (symbol 257), distance 1 (code 0)
+        assert!(matches!(decompress(&[0b0000_0011, 0b0000_0010, 0], 3), Err(SerializationError::InvalidData)));
+    }
+
+    #[test]
+    fn stops_at_the_expected_length() {
+        // a million zeros, said to be 10 bytes long
+        let compressed = compress(&vec![0; 1_000_000]);
+        assert!(matches!(decompress(&compressed, 10), Err(SerializationError::InvalidData)));
+    }
+}
@@ -1,24 +1,47 @@
 use std::{
     collections::HashMap,
-    fmt::Debug,
+    fmt::{Debug, Display},
     hash::Hash,
+    io::Read,
     ops::Add,
+    sync::atomic::{AtomicBool, Ordering},
     time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 use crate::{
     blocks::{empty_block, get_block_by_id, Block, BLOCK_EMPTY},
     identifier::Identifier,
-    items::{get_item_by_id, Item},
+    items::{Item, Stack},
+    log_debug, log_warn,
+    rng::Rng,
+    scheduler::{saved_timed_tasks, SavedTimedTasks},
     world::World,
     GameConfig,
 };
 
-pub struct Buffer(Vec<u8>, usize);
+pub mod deflate;
+
+/// The bytes being read, the read position and the save version they were written by
+pub struct Buffer(Vec<u8>, usize, u16);
 
 impl Buffer {
+    /// A buffer of data in the current format
     pub fn new(vec: Vec<u8>) -> Self {
-        Self(vec, 0)
+        Self(vec, 0, CURRENT_SAVE_VERSION)
+    }
+
+    /// A buffer of data written by a save of `version`
+    pub fn with_version(vec: Vec<u8>, version: u16) -> Self {
+        Self(vec, 0, version)
+    }
+
+    /// The save version the data was written by, for the parts whose layout changed between versions
+    pub fn version(&self) -> u16 {
+        self.2
+    }
+
+    pub fn set_version(&mut self, version: u16) {
+        self.2 = version;
     }
 
     pub fn len(&self) -> usize {
@@ -26,20 +49,21 @@ impl Buffer {
     }
 
     pub fn read_elements<'a>(&'a mut self, num: usize) -> &'a [u8] {
-        self.1 += num;
-        if self.1 >= self.0.len() {
+        if num > self.len() {
             panic!("read more elements than possible ohnyu");
         }
+        self.1 += num;
         &self.0[self.1 - num..self.1]
     }
 
+    /// Reads the next `num` bytes. Reading up to exactly the end is fine, reading past it fails without moving
+    /// the read position
     pub fn try_read_elements<'a>(&'a mut self, num: usize) -> Result<&'a [u8], SerializationError> {
-        self.1 += num;
-        if self.1 >= self.0.len() {
-            Err(SerializationError::NotEnoughSpace)
-        } else {
-            Ok(&self.0[self.1 - num..self.1])
+        if num > self.len() {
+            return Err(SerializationError::NotEnoughSpace);
         }
+        self.1 += num;
+        Ok(&self.0[self.1 - num..self.1])
     }
 
     pub fn read_element(&mut self) -> u8 {
@@ -49,6 +73,11 @@ impl Buffer {
         self.1 += 1;
         self.0[self.1 - 1]
     }
+    /// The next byte, without moving the read position
+    pub fn peek_element(&self) -> Result<u8, SerializationError> {
+        self.0.get(self.1).copied().ok_or(SerializationError::NotEnoughSpace)
+    }
+
     pub fn try_read_element(&mut self) -> Result<u8, SerializationError> {
         if self.len() < 1 {
             Err(SerializationError::NotEnoughSpace)
@@ -81,6 +110,35 @@ pub enum SerializationError {
         found: SerializationTrap,
         expected: SerializationTrap,
     },
+    /// the save was written by a newer version of the game
+    UnsupportedVersion(u16),
+}
+
+impl Display for SerializationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotEnoughSpace => f.write_str("unexpected end of data"),
+            Self::Other => f.write_str("unknown serialization error"),
+            Self::Io(e) => write!(f, "i/o error: {e}"),
+            Self::InvalidData => f.write_str("invalid or corrupted data"),
+            Self::SerializeTrap { found, expected } => {
+                write!(f, "corrupted data: expected {expected:?} but found {found:?}")
+            }
+            Self::UnsupportedVersion(version) => write!(
+                f,
+                "the save is version {version}, but this game only reads up to version {CURRENT_SAVE_VERSION}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SerializationError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
 }
 
 macro_rules! num_serializable {
@@ -111,6 +169,78 @@ macro_rules! num_serializable {
 
 num_serializable!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
 
+/// Implements `Serialize` for a struct by writing its fields in the listed order.
+/// With `as vec`, the fields are prefixed like a `Vec` of their count, for types that used to be saved as a list
+#[macro_export]
+macro_rules! serialize_struct {
+    ($name: ident as vec { $($field: ident: $type: ty),+ $(,)? }) => {
+        impl $crate::serialization::Serialize for $name {
+            fn required_length(&self) -> usize {
+                $crate::serialization::Serialize::required_length(&$crate::serialization::SerializationTrap::Vec)
+                    + $crate::serialization::Serialize::required_length(&0usize)
+                    $(+ $crate::serialization::Serialize::required_length(&self.$field))+
+            }
+
+            fn serialize(&self, buf: &mut Vec<u8>) {
+                $crate::serialization::Serialize::serialize(&$crate::serialization::SerializationTrap::Vec, buf);
+                $crate::serialization::Serialize::serialize(&[$(stringify!($field)),+].len(), buf);
+                $($crate::serialization::Serialize::serialize(&self.$field, buf);)+
+            }
+        }
+    };
+    ($name: ident { $($field: ident: $type: ty),+ $(,)? }) => {
+        impl $crate::serialization::Serialize for $name {
+            fn required_length(&self) -> usize {
+                0 $(+ $crate::serialization::Serialize::required_length(&self.$field))+
+            }
+
+            fn serialize(&self, buf: &mut Vec<u8>) {
+                $($crate::serialization::Serialize::serialize(&self.$field, buf);)+
+            }
+        }
+    };
+}
+
+/// Implements `Deserialize` for a struct by reading its fields in the listed order. See `serialize_struct!`
+#[macro_export]
+macro_rules! deserialize_struct {
+    ($name: ident as vec { $($field: ident: $type: ty),+ $(,)? }) => {
+        impl $crate::serialization::Deserialize for $name {
+            fn try_deserialize(
+                buf: &mut $crate::serialization::Buffer,
+            ) -> Result<Self, $crate::serialization::SerializationError> {
+                $crate::serialization::SerializationTrap::Vec.try_deserialize(buf)?;
+                let len = <usize as $crate::serialization::Deserialize>::try_deserialize(buf)?;
+                if len != [$(stringify!($field)),+].len() {
+                    return Err($crate::serialization::SerializationError::InvalidData);
+                }
+                $(let $field = <$type as $crate::serialization::Deserialize>::try_deserialize(buf)?;)+
+                Ok(Self { $($field),+ })
+            }
+        }
+    };
+    ($name: ident { $($field: ident: $type: ty),+ $(,)? }) => {
+        impl $crate::serialization::Deserialize for $name {
+            fn try_deserialize(
+                buf: &mut $crate::serialization::Buffer,
+            ) -> Result<Self, $crate::serialization::SerializationError> {
+                $(let $field = <$type as $crate::serialization::Deserialize>::try_deserialize(buf)?;)+
+                Ok(Self { $($field),+ })
+            }
+        }
+    };
+}
+
+/// Implements both `Serialize` and `Deserialize` for a struct from a single field list, so the order in which
+/// fields are written and read can't drift apart
+#[macro_export]
+macro_rules! serializable_struct {
+    ($name: ident $(as $layout: ident)? { $($field: ident: $type: ty),+ $(,)? }) => {
+        $crate::serialize_struct!($name $(as $layout)? { $($field: $type),+ });
+        $crate::deserialize_struct!($name $(as $layout)? { $($field: $type),+ });
+    };
+}
+
 impl Serialize for bool {
     fn serialize(&self, buf: &mut Vec<u8>) {
         if *self {
@@ -171,7 +301,8 @@ impl<T: Deserialize> Deserialize for Vec<T> {
     fn try_deserialize(buf: &mut Buffer) -> Result<Self, SerializationError> {
         SerializationTrap::Vec.try_deserialize(buf)?;
         let len = usize::try_deserialize(buf)?;
-        let mut vec: Vec<T> = Vec::with_capacity(len);
+        // every element takes up at least a byte, so a broken length can't allocate more than the buffer holds
+        let mut vec: Vec<T> = Vec::with_capacity(len.min(buf.len()));
 
         for _ in 0..len {
             vec.push(T::try_deserialize(buf)?);
@@ -217,7 +348,7 @@ impl<K: Deserialize + Hash + Eq, V: Deserialize> Deserialize for HashMap<K, V> {
     fn try_deserialize(buf: &mut Buffer) -> Result<Self, SerializationError> {
         SerializationTrap::HashMap.try_deserialize(buf)?;
         let num_elements = usize::try_deserialize(buf)?;
-        let mut hashmap: HashMap<K, V> = HashMap::with_capacity(num_elements);
+        let mut hashmap: HashMap<K, V> = HashMap::with_capacity(num_elements.min(buf.len()));
 
         for _ in 0..num_elements {
             let key = K::try_deserialize(buf)?;
@@ -344,7 +475,7 @@ impl SerializationTrap {
     }
 
     pub fn try_deserialize(&self, buf: &mut Buffer) -> Result<(), SerializationError> {
-        let read = buf.read_element();
+        let read = buf.try_read_element()?;
         if read != *self as u8 {
             Err(SerializationError::SerializeTrap {
                 found: Self::from_u8(read),
@@ -372,31 +503,39 @@ impl SerializationTrap {
     }
 }
 
-impl Serialize for Box<dyn Item> {
+/// The length of `item` when it is written by `serialize_item`
+pub fn item_required_length(item: &dyn Item) -> usize {
+    item.identifier().required_length()
+        + u32::required_length(&0)
+        + Item::required_length(item)
+        + SerializationTrap::required_length()
+}
+
+/// Writes `item` along with its identifier and metadata, so it can be read back as a `Stack`
+pub fn serialize_item(item: &dyn Item, buf: &mut Vec<u8>) {
+    buf.reserve(item_required_length(item));
+    SerializationTrap::Item.serialize(buf);
+    item.identifier().serialize(buf);
+    item.metadata().serialize(buf);
+    Item::serialize(item, buf);
+}
+
+impl Serialize for Stack {
     fn required_length(&self) -> usize {
-        self.identifier().required_length()
-            + u32::required_length(&0)
-            + Item::required_length(&**self)
-            + SerializationTrap::required_length()
+        item_required_length(&**self)
     }
 
     fn serialize(&self, buf: &mut Vec<u8>) {
-        buf.reserve(self.required_length());
-        SerializationTrap::Item.serialize(buf);
-        self.identifier().serialize(buf);
-        self.metadata().serialize(buf);
-        Item::serialize(&**self, buf);
+        serialize_item(&**self, buf)
     }
 }
 
-impl Deserialize for Box<dyn Item> {
+impl Deserialize for Stack {
     fn try_deserialize(buf: &mut Buffer) -> Result<Self, SerializationError> {
         SerializationTrap::Item.try_deserialize(buf)?;
-        let mut item = match get_item_by_id(Identifier::try_deserialize(buf)?) {
-            None => return Err(SerializationError::InvalidData),
-            Some(item) => item.clone_item(),
-        };
-        item.set_metadata(u32::try_deserialize(buf)?);
+        let id = Identifier::try_deserialize(buf)?;
+        let count = u32::try_deserialize(buf)?;
+        let mut item = Stack::of(id, count).ok_or(SerializationError::InvalidData)?;
         Item::try_deserialize(&mut *item, buf)?;
         Ok(item)
     }
@@ -427,7 +566,7 @@ impl Serialize for Box<dyn Block> {
 impl Deserialize for Box<dyn Block> {
     fn try_deserialize(buf: &mut Buffer) -> Result<Self, SerializationError> {
         SerializationTrap::Block.try_deserialize(buf)?;
-        let is_empty = bool::deserialize(buf);
+        let is_empty = bool::try_deserialize(buf)?;
         if is_empty {
             Ok(empty_block().clone_block())
         } else {
@@ -512,7 +651,10 @@ impl Deserialize for SystemTime {
 
     fn try_deserialize(buf: &mut Buffer) -> Result<Self, SerializationError> {
         SerializationTrap::Time.try_deserialize(buf)?;
-        Ok(UNIX_EPOCH.add(Duration::new(u64::try_deserialize(buf)?, 0)))
+        // adding panics on overflow
+        UNIX_EPOCH
+            .checked_add(Duration::new(u64::try_deserialize(buf)?, 0))
+            .ok_or(SerializationError::InvalidData)
     }
 }
 
@@ -521,49 +663,504 @@ impl<T: Serialize + Deserialize> Serializable for T {}
 
 const SIGNATURE: &[u8] = b"PN2S_SAV";
 
-pub fn save_game(world: &World, cfg: &GameConfig, file: String) -> std::io::Result<usize> {
-    let mut buf: Vec<u8> = Vec::with_capacity(4096);
+/// The version `save_game` writes right after the signature. Bump it when the format of anything in a save changes,
+/// and keep reading the older versions in `load_game`. Blocks read `Buffer::version` to tell their layouts apart.
+///
+/// 1. the first saves. They have no version, the save time follows the signature right away
+/// 2. the version, the read-only flag, the save mode and the compression flag in the header. Resource nodes write
+///    the item they yield, splitters their item filters, and the game config the hotbar and the selection
+pub const CURRENT_SAVE_VERSION: u16 = 2;
+/// the version of saves from before the version was written
+pub const LEGACY_SAVE_VERSION: u16 = 1;
+// legacy saves are told apart by the trap of the save time where newer saves have the version, so no version may
+// start with that byte
+const _: () = assert!(CURRENT_SAVE_VERSION as u8 != SerializationTrap::Time as u8);
+
+/// the flag byte after the header, saying how the rest of the save is stored
+const UNCOMPRESSED: u8 = 0;
+/// followed by the uncompressed length as a u64 and the deflate stream
+const DEFLATE: u8 = 1;
+
+static SAVE_LOAD_BUSY: AtomicBool = AtomicBool::new(false);
+
+/// Only one save or load may run in the background at a time, so saves can't race each other writing the same
+/// file. The permit is given back when it gets dropped, no matter if the operation worked
+pub struct SaveLoadPermit(());
+
+impl SaveLoadPermit {
+    /// Gets the permit, or None if another save or load is still running
+    pub fn try_acquire() -> Option<Self> {
+        SAVE_LOAD_BUSY
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .ok()
+            .map(|_| Self(()))
+    }
+}
 
-    // PN2S_SAV: signature
-    buf.extend(SIGNATURE);
-    // save time
-    SystemTime::now().serialize(&mut buf);
+impl Drop for SaveLoadPermit {
+    fn drop(&mut self) {
+        SAVE_LOAD_BUSY.store(false, Ordering::Release);
+    }
+}
 
-    // save world
-    world.serialize(&mut buf);
+/// How the world in a save is laid out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum SaveMode {
+    /// every block is written out in full with all its traps, which makes broken saves easier to look into
+    Debug,
+    /// runs of empty blocks are only stored as their length
+    Compact,
+}
 
-    // save config
-    cfg.serialize(&mut buf);
+impl Default for SaveMode {
+    fn default() -> Self {
+        if cfg!(debug_assertions) {
+            Self::Debug
+        } else {
+            Self::Compact
+        }
+    }
+}
+
+impl Serialize for SaveMode {
+    fn required_length(&self) -> usize {
+        1
+    }
 
+    fn serialize(&self, buf: &mut Vec<u8>) {
+        (*self as u8).serialize(buf);
+    }
+}
+
+impl Deserialize for SaveMode {
+    fn try_deserialize(buf: &mut Buffer) -> Result<Self, SerializationError> {
+        match u8::try_deserialize(buf)? {
+            0 => Ok(Self::Debug),
+            1 => Ok(Self::Compact),
+            _ => Err(SerializationError::InvalidData),
+        }
+    }
+}
+
+/// The part of a save in front of the world
+#[derive(Debug, Clone, Copy)]
+pub struct SaveHeader {
+    pub version: u16,
+    pub saved_at: SystemTime,
+    /// read-only worlds can't be overwritten by saving
+    pub read_only: bool,
+    pub mode: SaveMode,
+}
+
+impl SaveHeader {
+    fn read(buf: &mut Buffer) -> Result<Self, SerializationError> {
+        if buf.try_read_elements(SIGNATURE.len())? != SIGNATURE {
+            return Err(SerializationError::InvalidData);
+        }
+        if buf.peek_element()? == SerializationTrap::Time as u8 {
+            return Ok(Self {
+                version: LEGACY_SAVE_VERSION,
+                saved_at: SystemTime::try_deserialize(buf)?,
+                read_only: false,
+                mode: SaveMode::Debug,
+            });
+        }
+        let version = u16::try_deserialize(buf)?;
+        if version > CURRENT_SAVE_VERSION {
+            return Err(SerializationError::UnsupportedVersion(version));
+        }
+        if version <= LEGACY_SAVE_VERSION {
+            return Err(SerializationError::InvalidData);
+        }
+        Ok(Self {
+            version,
+            saved_at: SystemTime::try_deserialize(buf)?,
+            read_only: bool::try_deserialize(buf)?,
+            mode: SaveMode::try_deserialize(buf)?,
+        })
+    }
+}
+
+impl Serialize for SaveHeader {
+    fn required_length(&self) -> usize {
+        SIGNATURE.len()
+            + self.version.required_length()
+            + self.saved_at.required_length()
+            + self.read_only.required_length()
+            + self.mode.required_length()
+    }
+
+    fn serialize(&self, buf: &mut Vec<u8>) {
+        buf.extend(SIGNATURE);
+        self.version.serialize(buf);
+        self.saved_at.serialize(buf);
+        self.read_only.serialize(buf);
+        self.mode.serialize(buf);
+    }
+}
+
+/// Reads just the header of the save at `file`
+pub fn read_save_header(file: &str) -> Result<SaveHeader, SerializationError> {
+    let mut bytes = Vec::new();
+    // the header is at most this long, no need to read the whole world
+    std::fs::File::open(file)
+        .and_then(|f| f.take(64).read_to_end(&mut bytes))
+        .map_err(SerializationError::Io)?;
+    SaveHeader::read(&mut Buffer::new(bytes))
+}
+
+/// Marks the save at `file` as read-only or writable again, without touching the world in it
+pub fn set_read_only(file: &str, read_only: bool) -> Result<(), SerializationError> {
+    let mut buf = std::fs::read(file)
+        .map(Buffer::new)
+        .map_err(SerializationError::Io)?;
+    let mut header = SaveHeader::read(&mut buf)?;
+    header.read_only = read_only;
+
+    let mut bytes = Vec::with_capacity(header.required_length() + buf.len());
+    header.serialize(&mut bytes);
+    bytes.extend(buf.read_elements(buf.len()));
+    std::fs::write(file, bytes).map_err(SerializationError::Io)
+}
+
+/// Logs a warning when a part of a save didn't write as many bytes as its `required_length` says
+fn check_written_length(section: &str, required: usize, written: usize) {
+    if required != written {
+        log_warn!("{section} wrote {written} bytes, but its required_length is {required}");
+    }
+}
+
+pub fn save_game(
+    world: &World,
+    cfg: &GameConfig,
+    file: String,
+    mode: SaveMode,
+) -> std::io::Result<usize> {
+    if read_save_header(&file).is_ok_and(|header| header.read_only) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::PermissionDenied,
+            "the world is read-only",
+        ));
+    }
+    let buf = serialize_game(world, cfg, mode);
     let len = buf.len();
-    println!("Save Size: {} bytes", len);
+    log_debug!("Save Size: {} bytes", len);
     std::fs::write(file, buf)?;
     Ok(len)
 }
 
-pub fn load_game(file: String) -> Result<(World, GameConfig, SystemTime), SerializationError> {
-    let mut buf = std::fs::read(file)
-        .map(|bytes| Buffer::new(bytes))
-        .map_err(|e| SerializationError::Io(e))?;
-    if buf.len() < 8 {
-        return Err(SerializationError::InvalidData);
+pub fn load_game(file: String) -> Result<(World, GameConfig, SystemTime, SavedTimedTasks), SerializationError> {
+    let buf = std::fs::read(file).map(Buffer::new).map_err(SerializationError::Io)?;
+    deserialize_game(buf)
+}
+
+/// Writes a whole save into memory, the way `save_game` stores it in a file
+pub fn serialize_game(world: &World, cfg: &GameConfig, mode: SaveMode) -> Vec<u8> {
+    let mut buf: Vec<u8> = Vec::with_capacity(4096);
+
+    // signature, version, save time, flags and save mode
+    let header = SaveHeader {
+        version: CURRENT_SAVE_VERSION,
+        saved_at: SystemTime::now(),
+        read_only: false,
+        mode,
+    };
+    header.serialize(&mut buf);
+    let header_len = buf.len();
+
+    // everything after the header can be compressed, the header has to stay readable on its own
+    let mut body: Vec<u8> = Vec::with_capacity(4096);
+
+    // save world
+    world.serialize_with_mode(&mut body, mode);
+    let world_end = body.len();
+
+    // save config
+    cfg.serialize(&mut body);
+
+    if cfg!(debug_assertions) {
+        check_written_length("Save header", header.required_length(), header_len);
+        // required_length only knows the size of the debug format
+        if mode == SaveMode::Debug {
+            check_written_length("World", world.required_length(), world_end);
+        }
+        check_written_length("Game config", cfg.required_length(), body.len() - world_end);
     }
-    if buf.read_elements(8) != SIGNATURE {
-        return Err(SerializationError::InvalidData);
+
+    // timed tasks
+    saved_timed_tasks().serialize(&mut body);
+
+    // the world's random number generator
+    world.rng.serialize(&mut body);
+
+    // debug saves stay uncompressed so they can be looked into, and so does anything compression doesn't shrink
+    let compressed = (mode == SaveMode::Compact)
+        .then(|| deflate::compress(&body))
+        .filter(|compressed| compressed.len() + u64::required_length(&0) < body.len());
+    match compressed {
+        Some(compressed) => {
+            DEFLATE.serialize(&mut buf);
+            (body.len() as u64).serialize(&mut buf);
+            buf.extend(compressed);
+        }
+        None => {
+            UNCOMPRESSED.serialize(&mut buf);
+            buf.extend(body);
+        }
     }
+    buf
+}
 
-    // save time
-    let time = SystemTime::try_deserialize(&mut buf)?;
+/// Reads a whole save written by `serialize_game`. Returns the world, the config, when it was saved and its timed
+/// tasks. Those only get scheduled once the world is opened, see `scheduler::replace_timed_tasks`
+pub fn deserialize_game(
+    mut buf: Buffer,
+) -> Result<(World, GameConfig, SystemTime, SavedTimedTasks), SerializationError> {
+    // signature, version, save time, flags and save mode. Legacy saves only have the signature and the save time
+    let header = SaveHeader::read(&mut buf)?;
+    buf.set_version(header.version);
+
+    // how the rest is stored, legacy saves are uncompressed
+    if header.version > LEGACY_SAVE_VERSION {
+        match u8::try_deserialize(&mut buf)? {
+            UNCOMPRESSED => {}
+            DEFLATE => {
+                let len = u64::try_deserialize(&mut buf)? as usize;
+                let compressed = buf.read_elements(buf.len());
+                buf = Buffer::with_version(deflate::decompress(compressed, len)?, header.version);
+            }
+            _ => return Err(SerializationError::InvalidData),
+        }
+    }
 
     // world
-    let world = World::try_deserialize(&mut buf)?;
+    let mut world = World::try_deserialize_with_mode(&mut buf, header.mode)?;
 
     // config
-    let config = GameConfig::try_deserialize(&mut buf)?;
+    let mut config = GameConfig::try_deserialize_with_version(&mut buf, header.version)?;
+    config.read_only = header.read_only;
+
+    // timed tasks, saves from before they existed end here
+    let timed_tasks = if buf.len() > 0 {
+        SavedTimedTasks::try_deserialize(&mut buf)?
+    } else {
+        Vec::new()
+    };
+
+    // random number generator, saves from before it existed get a fresh one
+    world.rng = if buf.len() > 0 {
+        Rng::try_deserialize(&mut buf)?
+    } else {
+        Rng::from_time()
+    };
 
     if buf.len() > 0 {
         return Err(SerializationError::InvalidData);
     }
 
-    Ok((world, config, time))
+    Ok((world, config, header.saved_at, timed_tasks))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use super::*;
+    use crate::{
+        blocks::StorageContainer,
+        items::COAL_IDENTIFIER,
+        scheduler,
+        world::{Direction, Vec2i},
+    };
+
+    #[test]
+    fn errors_read_like_sentences() {
+        let cases = [
+            (SerializationError::NotEnoughSpace, "end of data"),
+            (SerializationError::Other, "unknown"),
+            (SerializationError::InvalidData, "corrupted"),
+            (
+                SerializationError::SerializeTrap {
+                    found: SerializationTrap::Vec,
+                    expected: SerializationTrap::String,
+                },
+                "expected String but found Vec",
+            ),
+            (SerializationError::UnsupportedVersion(u16::MAX), "version 65535"),
+        ];
+        for (err, phrase) in cases {
+            assert!(err.to_string().contains(phrase), "{err} doesn't contain {phrase}");
+            assert!(err.source().is_none());
+        }
+
+        let err = SerializationError::Io(std::io::Error::new(std::io::ErrorKind::NotFound, "no such save"));
+        assert!(err.to_string().contains("no such save"));
+        let source = err.source().expect("io errors have a source");
+        assert_eq!(
+            source.downcast_ref::<std::io::Error>().unwrap().kind(),
+            std::io::ErrorKind::NotFound
+        );
+    }
+
+    #[test]
+    fn only_one_save_or_load_at_a_time() {
+        let permit = SaveLoadPermit::try_acquire().expect("nothing else is saving");
+        assert!(SaveLoadPermit::try_acquire().is_none());
+        // not even from another thread, which is where saves and loads run
+        assert!(std::thread::spawn(|| SaveLoadPermit::try_acquire().is_none()).join().unwrap());
+
+        // a failed operation gives the permit back just the same, it's dropped either way
+        fn failing_load(_permit: SaveLoadPermit) -> Result<(), SerializationError> {
+            Err(SerializationError::InvalidData)
+        }
+        assert!(failing_load(permit).is_err());
+        let again = SaveLoadPermit::try_acquire();
+        assert!(again.is_some());
+        drop(again);
+        assert!(SaveLoadPermit::try_acquire().is_some());
+    }
+
+    /// a file in the temp dir that is deleted again when this is dropped
+    struct TempFile(String);
+
+    impl TempFile {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("pn2-{}-{name}", std::process::id()));
+            Self(path.to_string_lossy().into_owned())
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn read_only_saves_are_not_overwritten() {
+        crate::test_utils::register_all();
+        let world = World::new(1, 1);
+        let config = GameConfig::default();
+        let file = TempFile::new("read-only.pn2s");
+        save_game(&world, &config, file.0.clone(), SaveMode::Compact).unwrap();
+
+        set_read_only(&file.0, true).unwrap();
+        assert!(read_save_header(&file.0).unwrap().read_only);
+        let before = std::fs::read(&file.0).unwrap();
+        let error = save_game(&world, &config, file.0.clone(), SaveMode::Debug).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::PermissionDenied);
+        assert_eq!(std::fs::read(&file.0).unwrap(), before);
+
+        // writable again, everything but the flag stays the same
+        set_read_only(&file.0, false).unwrap();
+        let header = read_save_header(&file.0).unwrap();
+        assert!(!header.read_only);
+        assert_eq!(header.version, CURRENT_SAVE_VERSION);
+        assert_eq!(std::fs::read(&file.0).unwrap().len(), before.len());
+        save_game(&world, &config, file.0.clone(), SaveMode::Debug).unwrap();
+    }
+
+    #[test]
+    fn missing_saves_are_io_errors() {
+        let file = TempFile::new("missing.pn2s");
+        assert!(matches!(read_save_header(&file.0), Err(SerializationError::Io(_))));
+        assert!(matches!(set_read_only(&file.0, true), Err(SerializationError::Io(_))));
+        assert!(std::fs::metadata(&file.0).is_err());
+    }
+
+    #[test]
+    fn truncated_headers_are_errors() {
+        // the trap of the save time and half of the time itself
+        let mut time = Vec::new();
+        SystemTime::now().serialize(&mut time);
+        let half_a_time = &time[..time.len() / 2];
+
+        let mut partial_time = SIGNATURE.to_vec();
+        CURRENT_SAVE_VERSION.serialize(&mut partial_time);
+        partial_time.extend(half_a_time);
+        let legacy_partial_time = [SIGNATURE, half_a_time].concat();
+        let truncated = [
+            Vec::new(),
+            SIGNATURE[..5].to_vec(),
+            SIGNATURE.to_vec(),
+            partial_time,
+            legacy_partial_time,
+        ];
+        for bytes in truncated {
+            let result = deserialize_game(Buffer::new(bytes.clone()));
+            assert!(
+                matches!(result, Err(SerializationError::NotEnoughSpace)),
+                "{:?} didn't run out of data",
+                String::from_utf8_lossy(&bytes)
+            );
+        }
+    }
+
+    #[test]
+    fn empty_save_files_are_errors() {
+        let file = TempFile::new("empty.pn2s");
+        std::fs::write(&file.0, []).unwrap();
+        assert!(matches!(read_save_header(&file.0), Err(SerializationError::NotEnoughSpace)));
+        assert!(matches!(load_game(file.0.clone()), Err(SerializationError::NotEnoughSpace)));
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Entry {
+        name: String,
+        count: u32,
+        last_seen: u64,
+    }
+    serializable_struct!(Entry { name: String, count: u32, last_seen: u64 });
+
+    #[test]
+    fn the_last_field_may_end_on_the_last_byte() {
+        let entry = Entry {
+            name: "coal".to_string(),
+            count: 17,
+            last_seen: u64::MAX,
+        };
+        let mut data = Vec::new();
+        entry.serialize(&mut data);
+        assert_eq!(data.len(), entry.required_length());
+
+        let mut buf = Buffer::new(data.clone());
+        assert_eq!(Entry::try_deserialize(&mut buf).unwrap(), entry);
+        assert_eq!(buf.len(), 0);
+        // nothing left to read after it
+        assert!(matches!(u8::try_deserialize(&mut buf), Err(SerializationError::NotEnoughSpace)));
+
+        // one byte short is an error rather than a read past the end
+        data.pop();
+        assert!(matches!(Entry::try_deserialize(&mut Buffer::new(data)), Err(SerializationError::NotEnoughSpace)));
+    }
+
+    #[test]
+    fn games_round_trip_without_a_file() {
+        crate::test_utils::register_all();
+        let _guard = crate::test_utils::TIMED_TASK_TESTS.lock().unwrap();
+        scheduler::clear_timed_tasks();
+        scheduler::schedule_in(3, scheduler::Task::WakeBlock(Vec2i::new(5, 5)));
+
+        let mut world = World::new(1, 1);
+        let mut container = StorageContainer::default();
+        container.get_inventory_capability().unwrap().try_add_item(crate::test_utils::item(*COAL_IDENTIFIER, 17));
+        world.set_block_at(5, 5, Box::new(container), Direction::North);
+        let mut config = GameConfig::default();
+        config.player = Vec2i::new(40, -12);
+        let mut world_data = Vec::new();
+        world.serialize(&mut world_data);
+
+        for mode in [SaveMode::Compact, SaveMode::Debug] {
+            let data = serialize_game(&world, &config, mode);
+            let (loaded, loaded_config, _, tasks) = deserialize_game(Buffer::new(data)).unwrap();
+            assert_eq!(tasks, [(3, Vec2i::new(5, 5))], "{mode:?}");
+            assert_eq!(loaded_config.player, config.player, "{mode:?}");
+            assert_eq!(loaded.total_item_count(), 17, "{mode:?}");
+            let mut loaded_data = Vec::new();
+            loaded.serialize(&mut loaded_data);
+            assert!(loaded_data == world_data, "{mode:?}");
+        }
+        scheduler::clear_timed_tasks();
+    }
 }
@@ -0,0 +1,392 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Mutex,
+};
+
+use raylib::{ffi::KeyboardKey, input::key_from_i32};
+
+use crate::{
+    asset,
+    blocks::{self, OverflowPolicy},
+    game, keybinds, log_warn, notice_board,
+    serialization::{Buffer, Deserialize, SerializationError, Serialize},
+    styles,
+};
+
+pub const DEFAULT_WINDOW_WIDTH: i32 = 1280;
+pub const DEFAULT_WINDOW_HEIGHT: i32 = 720;
+pub const MIN_WINDOW_WIDTH: i32 = 640;
+pub const MIN_WINDOW_HEIGHT: i32 = 360;
+
+/// Window geometry of the last session, restored on startup. Part of `Settings`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowSettings {
+    pub width: i32,
+    pub height: i32,
+    pub position: Option<(i32, i32)>,
+}
+
+impl Default for WindowSettings {
+    fn default() -> Self {
+        Self {
+            width: DEFAULT_WINDOW_WIDTH,
+            height: DEFAULT_WINDOW_HEIGHT,
+            position: None,
+        }
+    }
+}
+
+impl WindowSettings {
+    /// Makes sure the window doesn't end up too small to use or entirely off screen
+    pub fn clamped(self) -> Self {
+        Self {
+            width: self.width.max(MIN_WINDOW_WIDTH),
+            height: self.height.max(MIN_WINDOW_HEIGHT),
+            position: self.position.map(|(x, y)| (x.max(0), y.max(0))),
+        }
+    }
+}
+
+static WINDOW: Mutex<WindowSettings> = Mutex::new(WindowSettings {
+    width: DEFAULT_WINDOW_WIDTH,
+    height: DEFAULT_WINDOW_HEIGHT,
+    position: None,
+});
+static VSYNC: AtomicBool = AtomicBool::new(false);
+
+/// The window geometry that gets saved, see `set_window`
+pub fn window() -> WindowSettings {
+    *WINDOW.lock().unwrap()
+}
+
+/// Remembers the geometry of the window, `main` sets it when the game closes
+pub fn set_window(window: WindowSettings) {
+    *WINDOW.lock().unwrap() = window;
+}
+
+/// Whether the window gets created with vsync on the next start. The env var in `main` can override this
+pub fn vsync() -> bool {
+    VSYNC.load(Ordering::Relaxed)
+}
+
+pub fn set_vsync(enabled: bool) {
+    VSYNC.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether to use vsync on `os` (as in `std::env::consts::OS`) by default.
+/// vsync is off by default on linux because nvidia fucks with vsync :sob:
+fn default_vsync(os: &str) -> bool {
+    os != "linux"
+}
+
+impl Serialize for WindowSettings {
+    fn required_length(&self) -> usize {
+        self.width.required_length() + self.height.required_length() + self.position.required_length()
+    }
+
+    fn serialize(&self, buf: &mut Vec<u8>) {
+        self.width.serialize(buf);
+        self.height.serialize(buf);
+        self.position.serialize(buf);
+    }
+}
+
+impl Deserialize for WindowSettings {
+    fn try_deserialize(buf: &mut Buffer) -> Result<Self, SerializationError> {
+        Ok(Self {
+            width: i32::try_deserialize(buf)?,
+            height: i32::try_deserialize(buf)?,
+            position: Deserialize::try_deserialize(buf)?,
+        })
+    }
+}
+
+/// Everything the options screen changes and the window of the last session, kept in `assets/settings.pn2cfg`. While
+/// the game runs the options live in their own modules; this only collects them for saving and loading
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Settings {
+    /// index into `styles::STYLES`
+    pub style: usize,
+    pub text_only_notices: bool,
+    pub snap_movement: bool,
+    pub left_handed: bool,
+    pub smart_orient: bool,
+    pub overflow_policy: OverflowPolicy,
+    /// see `game::low_tps_threshold`
+    pub low_tps_threshold: u32,
+    /// see `keybinds::key_overrides`
+    pub key_overrides: [Option<KeyboardKey>; keybinds::NUM_ACTIONS],
+    pub window: WindowSettings,
+    /// raylib only takes this when creating the window, so changes only apply after a restart
+    pub vsync: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            style: styles::DEFAULT_STYLE,
+            text_only_notices: false,
+            snap_movement: false,
+            left_handed: false,
+            smart_orient: false,
+            overflow_policy: OverflowPolicy::default(),
+            low_tps_threshold: game::DEFAULT_LOW_TPS_THRESHOLD,
+            key_overrides: [None; keybinds::NUM_ACTIONS],
+            window: WindowSettings::default(),
+            vsync: default_vsync(std::env::consts::OS),
+        }
+    }
+}
+
+impl Settings {
+    /// The settings as they are right now
+    pub fn current() -> Self {
+        Self {
+            style: styles::current(),
+            text_only_notices: notice_board::text_only_mode(),
+            snap_movement: game::snap_movement(),
+            left_handed: keybinds::left_handed(),
+            smart_orient: game::smart_orient(),
+            overflow_policy: blocks::overflow_policy(),
+            low_tps_threshold: game::low_tps_threshold(),
+            key_overrides: keybinds::key_overrides(),
+            window: window(),
+            vsync: vsync(),
+        }
+    }
+
+    pub fn apply(&self) {
+        styles::apply(self.style);
+        notice_board::set_text_only_mode(self.text_only_notices);
+        game::set_snap_movement(self.snap_movement);
+        keybinds::set_left_handed(self.left_handed);
+        game::set_smart_orient(self.smart_orient);
+        blocks::set_overflow_policy(self.overflow_policy);
+        game::set_low_tps_threshold(self.low_tps_threshold);
+        keybinds::set_key_overrides(self.key_overrides);
+        set_window(self.window);
+        set_vsync(self.vsync);
+    }
+
+    /// Loads the settings file, creating it with the defaults if there is none. Broken files give the defaults
+    pub fn load() -> Self {
+        match std::fs::read(asset!("settings.pn2cfg")) {
+            Ok(bytes) => Self::try_deserialize(&mut Buffer::new(bytes)).unwrap_or_default(),
+            Err(_) => {
+                let settings = Self::default();
+                settings.save();
+                settings
+            }
+        }
+    }
+
+    /// Saves the settings, only logging when that fails since the game works fine without them
+    pub fn save(&self) {
+        let mut buf = Vec::with_capacity(self.required_length());
+        self.serialize(&mut buf);
+        if let Err(e) = std::fs::write(asset!("settings.pn2cfg"), buf) {
+            log_warn!("Couldn't save the settings: {e}");
+        }
+    }
+
+    /// Saves the current settings
+    pub fn save_current() {
+        Self::current().save();
+    }
+}
+
+impl Serialize for Settings {
+    fn required_length(&self) -> usize {
+        (self.style as u32).required_length()
+            + self.text_only_notices.required_length()
+            + self.snap_movement.required_length()
+            + self.left_handed.required_length()
+            + self.smart_orient.required_length()
+            + (self.overflow_policy as u8).required_length()
+            + self.low_tps_threshold.required_length()
+            + self.key_override_codes().required_length()
+            + self.window.required_length()
+            + self.vsync.required_length()
+    }
+
+    fn serialize(&self, buf: &mut Vec<u8>) {
+        (self.style as u32).serialize(buf);
+        self.text_only_notices.serialize(buf);
+        self.snap_movement.serialize(buf);
+        self.left_handed.serialize(buf);
+        self.smart_orient.serialize(buf);
+        (self.overflow_policy as u8).serialize(buf);
+        self.low_tps_threshold.serialize(buf);
+        self.key_override_codes().serialize(buf);
+        self.window.serialize(buf);
+        self.vsync.serialize(buf);
+    }
+}
+
+impl Settings {
+    /// the overrides as raw key codes, since `KeyboardKey` can't be serialized itself
+    fn key_override_codes(&self) -> Vec<Option<i32>> {
+        self.key_overrides.iter().map(|key| key.map(|key| key as i32)).collect()
+    }
+}
+
+/// Reads the next field if there is one left. Files from older versions end early, so their newer fields keep the
+/// value they already have
+fn read_field<T: Deserialize>(buf: &mut Buffer, field: &mut T) -> Result<(), SerializationError> {
+    if buf.len() > 0 {
+        *field = T::try_deserialize(buf)?;
+    }
+    Ok(())
+}
+
+impl Deserialize for Settings {
+    fn try_deserialize(buf: &mut Buffer) -> Result<Self, SerializationError> {
+        let mut settings = Self::default();
+        let mut style = settings.style as u32;
+        let mut overflow_policy = settings.overflow_policy as u8;
+
+        read_field(buf, &mut style)?;
+        read_field(buf, &mut settings.text_only_notices)?;
+        read_field(buf, &mut settings.snap_movement)?;
+        read_field(buf, &mut settings.left_handed)?;
+        read_field(buf, &mut settings.smart_orient)?;
+        read_field(buf, &mut overflow_policy)?;
+        read_field(buf, &mut settings.low_tps_threshold)?;
+        let mut key_overrides = settings.key_override_codes();
+        read_field(buf, &mut key_overrides)?;
+        read_field(buf, &mut settings.window)?;
+        read_field(buf, &mut settings.vsync)?;
+
+        settings.style = style as usize;
+        settings.overflow_policy = match overflow_policy {
+            1 => OverflowPolicy::Void,
+            _ => OverflowPolicy::Block,
+        };
+        for (key, code) in settings.key_overrides.iter_mut().zip(key_overrides) {
+            *key = code.and_then(key_from_i32);
+        }
+        Ok(settings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip<T: Serialize + Deserialize>(value: &T) -> T {
+        let mut buf = Vec::new();
+        value.serialize(&mut buf);
+        assert_eq!(buf.len(), value.required_length());
+        let mut buf = Buffer::new(buf);
+        let read = T::try_deserialize(&mut buf).unwrap();
+        assert_eq!(buf.len(), 0);
+        read
+    }
+
+    #[test]
+    fn window_geometry_round_trips() {
+        let placed = WindowSettings {
+            width: 1920,
+            height: 1080,
+            position: Some((40, 25)),
+        };
+        assert_eq!(round_trip(&placed), placed);
+        assert_eq!(round_trip(&WindowSettings::default()), WindowSettings::default());
+
+        // a file cut off in the middle is broken, not a smaller window
+        let mut buf = Vec::new();
+        placed.serialize(&mut buf);
+        buf.truncate(6);
+        assert!(WindowSettings::try_deserialize(&mut Buffer::new(buf)).is_err());
+    }
+
+    #[test]
+    fn window_geometry_is_clamped() {
+        let broken = WindowSettings {
+            width: 10,
+            height: -3,
+            position: Some((-200, 15)),
+        };
+        assert_eq!(
+            round_trip(&broken).clamped(),
+            WindowSettings {
+                width: MIN_WINDOW_WIDTH,
+                height: MIN_WINDOW_HEIGHT,
+                position: Some((0, 15)),
+            }
+        );
+        // sane values stay the way they are
+        let fine = WindowSettings {
+            width: MIN_WINDOW_WIDTH + 1,
+            height: DEFAULT_WINDOW_HEIGHT,
+            position: None,
+        };
+        assert_eq!(fine.clamped(), fine);
+    }
+
+    /// every field changed from its default
+    fn changed() -> Settings {
+        let mut key_overrides = [None; keybinds::NUM_ACTIONS];
+        key_overrides[0] = Some(KeyboardKey::KEY_J);
+        key_overrides[keybinds::NUM_ACTIONS - 1] = Some(KeyboardKey::KEY_F5);
+        Settings {
+            style: styles::DEFAULT_STYLE + 1,
+            text_only_notices: true,
+            snap_movement: true,
+            left_handed: true,
+            smart_orient: true,
+            overflow_policy: OverflowPolicy::Void,
+            low_tps_threshold: game::DEFAULT_LOW_TPS_THRESHOLD + 7,
+            key_overrides,
+            window: WindowSettings {
+                width: 1920,
+                height: 1080,
+                position: Some((40, 25)),
+            },
+            vsync: !Settings::default().vsync,
+        }
+    }
+
+    #[test]
+    fn settings_round_trip() {
+        let settings = changed();
+        assert_ne!(settings, Settings::default());
+        assert_eq!(round_trip(&settings), settings);
+        assert_eq!(round_trip(&Settings::default()), Settings::default());
+    }
+
+    #[test]
+    fn missing_fields_fall_back_to_defaults() {
+        // a file from before the overflow policy, the tps threshold, the keybinds and the window were settings
+        let mut buf = Vec::new();
+        ((styles::DEFAULT_STYLE + 1) as u32).serialize(&mut buf);
+        true.serialize(&mut buf);
+        true.serialize(&mut buf);
+        false.serialize(&mut buf);
+        true.serialize(&mut buf);
+        let mut buf = Buffer::new(buf);
+        let settings = Settings::try_deserialize(&mut buf).unwrap();
+        assert_eq!(buf.len(), 0);
+        assert_eq!(
+            settings,
+            Settings {
+                style: styles::DEFAULT_STYLE + 1,
+                text_only_notices: true,
+                snap_movement: true,
+                left_handed: false,
+                smart_orient: true,
+                ..Settings::default()
+            }
+        );
+
+        // an empty file has nothing but defaults
+        assert_eq!(Settings::try_deserialize(&mut Buffer::new(Vec::new())).unwrap(), Settings::default());
+    }
+
+    #[test]
+    fn vsync_is_off_on_linux_by_default() {
+        assert!(!default_vsync("linux"));
+        assert!(default_vsync("windows"));
+        assert!(default_vsync("macos"));
+    }
+}
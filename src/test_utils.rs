@@ -0,0 +1,64 @@
+//! Things the tests of all modules need
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+    sync::{Mutex, Once},
+};
+
+use crate::{
+    blocks::register_blocks,
+    identifier::Identifier,
+    items::{register_items, Stack},
+    recipe::register_recipes,
+};
+
+static REGISTER: Once = Once::new();
+
+/// The timed tasks and the tick counter of the scheduler are shared by everything, so tests that use them hold this
+/// to not run at the same time
+pub static TIMED_TASK_TESTS: Mutex<()> = Mutex::new(());
+
+/// Registers all blocks, items and recipes the way the game does at startup. Every test that touches blocks or items
+/// calls this first, it only registers them once
+pub fn register_all() {
+    REGISTER.call_once(|| {
+        register_blocks();
+        register_items();
+        register_recipes();
+    });
+}
+
+/// A stack of `count` of the registered item `id`
+pub fn item(id: Identifier, count: u32) -> Stack {
+    Stack::of(id, count).expect("the item is registered")
+}
+
+thread_local! {
+    static ALLOCATIONS: Cell<u64> = const { Cell::new(0) };
+}
+
+/// The system allocator, but it counts the allocations of every thread so tests can check what allocates
+struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        // the counter is gone while the thread shuts down
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// How many allocations `f` made on this thread
+pub fn count_allocations(f: impl FnOnce()) -> u64 {
+    let before = ALLOCATIONS.with(Cell::get);
+    f();
+    ALLOCATIONS.with(Cell::get) - before
+}
@@ -1,18 +1,98 @@
+use std::{ffi::CStr, sync::Mutex};
+
 use raylib::{
     color::Color,
     drawing::{RaylibDraw, RaylibDrawHandle},
-    ffi::{KeyboardKey, MouseButton},
+    ffi::{GuiControlState, KeyboardKey, MouseButton},
     input::key_from_i32,
     math::Rectangle,
+    rgui::RaylibDrawGui,
     text::measure_text,
 };
 
+use crate::input::InputState;
+
 const BORDER_ACTIVE: Color = Color::new(0x04, 0x92, 0xc7, 0xff);
 const COLOR_ACTIVE: Color = Color::new(0x97, 0xe8, 0xff, 0xff);
 
 const BORDER_INACTIVE: Color = Color::BLACK;
 const COLOR_INACTIVE: Color = Color::WHITE;
 
+/// Lets the gamepad use the buttons of the open screen: the d-pad moves the focus through the buttons in the order they
+/// are drawn and confirm presses the focused one. Buttons take part by being drawn with `gui_button`
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Focus {
+    focused: Option<usize>,
+    /// the number of buttons drawn so far this frame
+    drawn: usize,
+    step: i32,
+    confirm: bool,
+}
+
+impl Focus {
+    const fn new() -> Self {
+        Self {
+            focused: None,
+            drawn: 0,
+            step: 0,
+            confirm: false,
+        }
+    }
+
+    /// moves the focus by `step` buttons, wrapping around. Nothing is focused until the d-pad is first used
+    fn advance(&mut self, buttons: usize, step: i32) {
+        if buttons == 0 {
+            self.focused = None;
+            return;
+        }
+        self.focused = match self.focused {
+            None if step > 0 => Some(0),
+            None if step < 0 => Some(buttons - 1),
+            None => None,
+            Some(focused) => Some((focused as i32 + step).rem_euclid(buttons as i32) as usize),
+        };
+    }
+}
+
+static FOCUS: Mutex<Focus> = Mutex::new(Focus::new());
+
+/// call before drawing a screen
+pub fn begin_focus(input: &InputState) {
+    let mut focus = FOCUS.lock().unwrap();
+    focus.drawn = 0;
+    focus.step = input.focus_step;
+    focus.confirm = input.confirm;
+}
+
+/// call after drawing a screen, moves the focus for the next frame
+pub fn end_focus() {
+    let mut focus = FOCUS.lock().unwrap();
+    let (buttons, step) = (focus.drawn, focus.step);
+    focus.advance(buttons, step);
+}
+
+/// forgets the focused button, for when another screen is opened
+pub fn reset_focus() {
+    *FOCUS.lock().unwrap() = Focus::new();
+}
+
+/// `RaylibDrawGui::gui_button` that can also be focused and pressed with the gamepad
+pub fn gui_button(renderer: &mut impl RaylibDraw, rect: Rectangle, text: Option<&CStr>) -> bool {
+    let (focused, confirm) = {
+        let mut focus = FOCUS.lock().unwrap();
+        let focused = focus.focused == Some(focus.drawn);
+        focus.drawn += 1;
+        (focused, focus.confirm)
+    };
+    let clicked = renderer.gui_button(rect, text);
+    if focused {
+        renderer.draw_rectangle_lines_ex(rect, 2, BORDER_ACTIVE);
+    }
+    // unlike clicks, confirm also reaches buttons drawn between `gui_disable` and `gui_enable`
+    let disabled = unsafe { raylib::ffi::GuiGetState() } == GuiControlState::GUI_STATE_DISABLED as i32;
+    clicked || (focused && confirm && !disabled)
+}
+
 pub struct TextboxState {
     pub active: bool,
     pub str: String,
@@ -195,3 +275,27 @@ pub fn gui_textbox(
 
     return_val
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn focus_wraps_around() {
+        let mut focus = Focus::new();
+        focus.advance(3, 0);
+        assert_eq!(focus.focused, None);
+        focus.advance(3, -1);
+        assert_eq!(focus.focused, Some(2));
+        focus.advance(3, 1);
+        assert_eq!(focus.focused, Some(0));
+        focus.advance(3, 1);
+        assert_eq!(focus.focused, Some(1));
+        // the screen changed and has fewer buttons now
+        focus.focused = Some(4);
+        focus.advance(2, 0);
+        assert_eq!(focus.focused, Some(0));
+        focus.advance(0, 1);
+        assert_eq!(focus.focused, None);
+    }
+}
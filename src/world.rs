@@ -1,28 +1,107 @@
 use raylib::{
-    drawing::RaylibDrawHandle,
+    color::Color,
+    drawing::{RaylibDraw, RaylibDrawHandle},
     math::Vector2,
 };
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet, VecDeque},
     fmt::Display,
-    ops::{Add, AddAssign, Sub, SubAssign},
+    ops::{Add, AddAssign, Range, Sub, SubAssign},
+    sync::atomic::{AtomicBool, AtomicI64, Ordering},
+    thread,
+    time::Instant,
 };
 
 use crate::{
-    blocks::{empty_block, Block},
-    game::RenderLayer,
+    blocks::{empty_block, Block, ResourceNode},
+    game::{arrow_vertices, GameConfig, RenderLayer},
     identifier::Identifier,
     inventory::Inventory,
-    serialization::{Buffer, Deserialize, SerializationError, SerializationTrap, Serialize},
+    items::Stack,
+    log_error, log_warn,
+    power::PowerNetworks,
+    rng::Rng,
+    scheduler::{collect_tasks, schedule_tasks, Task},
+    screens::ScreenDimensions,
+    serializable_struct,
+    serialization::{
+        deflate, Buffer, Deserialize, SaveMode, SerializationError, SerializationTrap, Serialize,
+    },
 };
 
 #[derive(Clone)]
 pub struct World {
     pub chunks: HashMap<(i32, i32), Chunk>,
+    /// chunks that were far away from the player and got serialized to save memory
+    pub suspended: HashMap<(i32, i32), SuspendedChunk>,
+    /// radius (in chunks) around the player in which chunks stay loaded; None disables suspending
+    pub suspend_radius: Option<u32>,
     pub w: u32,
     pub h: u32,
     pub startx: i32,
     pub starty: i32,
+    /// randomness for everything in the world, saved along with it so it continues where it left off
+    pub rng: Rng,
+    /// how long the last call to `update` took
+    update_nanos: u64,
+    /// recomputed every tick, see `update_power`
+    power: PowerNetworks,
+}
+
+pub const DEFAULT_SUSPEND_RADIUS: u32 = 3;
+/// the fewest chunks with machines a thread gets to update. The threads are started again every tick, and starting
+/// one takes tens of microseconds, about as long as updating a few chunks full of machines. So updates only get
+/// spread over threads once each of them has enough chunks to make up for that
+const MIN_CHUNKS_PER_THREAD: usize = 32;
+
+static ITEM_AUDIT: AtomicBool = AtomicBool::new(false);
+/// items that got created (positive) or destroyed (negative) on purpose since the last audit
+static EXPECTED_ITEM_DELTA: AtomicI64 = AtomicI64::new(0);
+
+/// whether to check every tick that machines don't create or destroy items they shouldn't
+pub fn item_audit() -> bool {
+    ITEM_AUDIT.load(Ordering::Relaxed)
+}
+
+pub fn set_item_audit(enabled: bool) {
+    ITEM_AUDIT.store(enabled, Ordering::Relaxed);
+}
+
+/// Tells the item audit that `delta` items were created or destroyed on purpose, like by a resource node
+pub fn record_item_delta(delta: i64) {
+    if item_audit() {
+        EXPECTED_ITEM_DELTA.fetch_add(delta, Ordering::Relaxed);
+    }
+}
+
+/// Gets the items recorded by `record_item_delta` since the last call
+pub fn take_item_delta() -> i64 {
+    EXPECTED_ITEM_DELTA.swap(0, Ordering::Relaxed)
+}
+/// how many resource nodes world generation places per chunk
+const NODES_PER_CHUNK: Range<u32> = 2..5;
+/// blocks around the spawn point in which world generation places nothing
+const SPAWN_CLEARANCE: i32 = 3;
+
+fn get_chunk_pos(x: i32, y: i32) -> (i32, i32) {
+    chunk_pos_in(x, y, BLOCKS_PER_CHUNK_X, BLOCKS_PER_CHUNK_Y)
+}
+
+/// The index in `Chunk::blocks` of the block at the world position `x`, `y`
+fn block_index(x: i32, y: i32) -> usize {
+    block_index_in(x, y, BLOCKS_PER_CHUNK_X, BLOCKS_PER_CHUNK_Y)
+}
+
+/// `get_chunk_pos` for chunks of `chunk_w`x`chunk_h` blocks
+fn chunk_pos_in(x: i32, y: i32, chunk_w: u32, chunk_h: u32) -> (i32, i32) {
+    (x.div_euclid(chunk_w as i32), y.div_euclid(chunk_h as i32))
+}
+
+/// `block_index` for chunks of `chunk_w`x`chunk_h` blocks
+fn block_index_in(x: i32, y: i32, chunk_w: u32, chunk_h: u32) -> usize {
+    let off_x = x.rem_euclid(chunk_w as i32) as usize;
+    let off_y = y.rem_euclid(chunk_h as i32) as usize;
+    off_y * chunk_w as usize + off_x
 }
 
 impl World {
@@ -30,21 +109,121 @@ impl World {
         self.chunks.insert((x, y), Chunk::default(x, y));
     }
 
+    /// Replaces every block with an empty one, keeping the size of the world. Returns the items that were in the blocks
+    pub fn clear_blocks(&mut self) -> Vec<Stack> {
+        let suspended: Vec<(i32, i32)> = self.suspended.keys().copied().collect();
+        for (x, y) in suspended {
+            if !self.restore_chunk(x, y) {
+                // the chunk is broken anyway, there is nothing to get out of it
+                self.suspended.remove(&(x, y));
+                self.load_chunk(x, y);
+            }
+        }
+
+        let mut items = Vec::new();
+        for (&(x, y), chunk) in self.chunks.iter_mut() {
+            for blk in &chunk.blocks {
+                items.extend(blk.inner.destroy_items());
+            }
+            *chunk = Chunk::default(x, y);
+            chunk.init();
+        }
+
+        items
+    }
+
+    /// Serializes and compresses the chunk and drops it from the loaded chunks
+    pub fn suspend_chunk(&mut self, x: i32, y: i32) -> bool {
+        let chunk = match self.chunks.remove(&(x, y)) {
+            Some(chunk) => chunk,
+            None => return false,
+        };
+        self.suspended.insert((x, y), SuspendedChunk::new(&chunk));
+
+        true
+    }
+
+    /// Loads a suspended chunk back in; returns false if the chunk wasn't suspended or couldn't be restored
+    pub fn restore_chunk(&mut self, x: i32, y: i32) -> bool {
+        let data = match self.suspended.remove(&(x, y)) {
+            Some(data) => data,
+            None => return false,
+        };
+
+        match data.restore() {
+            Ok(mut chunk) => {
+                chunk.init();
+                self.chunks.insert((x, y), chunk);
+                true
+            }
+            Err(e) => {
+                log_error!("Failed to restore chunk {x} {y}: {e}");
+                self.suspended.insert((x, y), data);
+                false
+            }
+        }
+    }
+
+    /// Suspends all chunks outside of `suspend_radius` around `center` (a block position) that neither contain
+    /// machines nor border a chunk that does, and restores suspended chunks that came back into the radius or next
+    /// to a machine
+    pub fn update_residency(&mut self, center: Vec2i) {
+        let radius = match self.suspend_radius {
+            Some(radius) => radius as i32,
+            None => return,
+        };
+        let (center_x, center_y) = get_chunk_pos(center.x, center.y);
+        let is_in_radius =
+            |&(x, y): &(i32, i32)| (x - center_x).abs() <= radius && (y - center_y).abs() <= radius;
+        let machine_chunks: HashSet<(i32, i32)> = self
+            .chunks
+            .iter()
+            .filter(|(_, chunk)| chunk.has_machines())
+            .map(|(&pos, _)| pos)
+            .collect();
+        // machines push into and pull from the blocks next to them, which can be in the neighbouring chunk
+        let is_used_by_machines = |&(x, y): &(i32, i32)| {
+            [(0, 0), (0, -1), (1, 0), (0, 1), (-1, 0)]
+                .iter()
+                .any(|(dx, dy)| machine_chunks.contains(&(x + dx, y + dy)))
+        };
+
+        let to_suspend = self
+            .chunks
+            .keys()
+            .filter(|pos| !is_in_radius(pos) && !is_used_by_machines(pos))
+            .copied()
+            .collect::<Vec<(i32, i32)>>();
+        let to_restore = self
+            .suspended
+            .keys()
+            .filter(|pos| is_in_radius(pos) || is_used_by_machines(pos))
+            .copied()
+            .collect::<Vec<(i32, i32)>>();
+
+        for (x, y) in to_suspend {
+            self.suspend_chunk(x, y);
+        }
+        for (x, y) in to_restore {
+            self.restore_chunk(x, y);
+        }
+    }
+
+    /// Gets a loaded chunk, loading it back in if it was suspended
+    fn get_chunk_mut(&mut self, chunk_x: i32, chunk_y: i32) -> Option<&mut Chunk> {
+        if self.suspended.contains_key(&(chunk_x, chunk_y)) {
+            self.restore_chunk(chunk_x, chunk_y);
+        }
+        self.chunks.get_mut(&(chunk_x, chunk_y))
+    }
+
     pub fn get_block_at<'a>(
         &'a self,
         x: i32,
         y: i32,
     ) -> Option<(&'a Box<dyn Block>, ChunkBlockMetadata)> {
-        let mut chunk_x = x / BLOCKS_PER_CHUNK_X as i32;
-        let mut chunk_y = y / BLOCKS_PER_CHUNK_Y as i32;
-
-        if (x % BLOCKS_PER_CHUNK_X as i32) < 0 {
-            chunk_x -= 1;
-        }
-        if (y % BLOCKS_PER_CHUNK_Y as i32) < 0 {
-            chunk_y -= 1;
-        }
-        let blk = self.chunks.get(&(chunk_x, chunk_y))?.get_block_at(x, y);
+        // suspended chunks are treated as not being there; they get loaded back in on mutation
+        let blk = self.chunks.get(&get_chunk_pos(x, y))?.get_block_at(x, y);
         Some((&blk.inner, blk.data))
     }
 
@@ -53,20 +232,220 @@ impl World {
         x: i32,
         y: i32,
     ) -> Option<(&'a mut Box<dyn Block>, ChunkBlockMetadata)> {
-        let mut chunk_x = x / BLOCKS_PER_CHUNK_X as i32;
-        let mut chunk_y = y / BLOCKS_PER_CHUNK_Y as i32;
+        let (chunk_x, chunk_y) = get_chunk_pos(x, y);
+        let blk = self.get_chunk_mut(chunk_x, chunk_y)?.get_block_at_mut(x, y);
+        Some((&mut blk.inner, blk.data))
+    }
+
+    /// Gets the block next to `pos` in `dir`
+    pub fn neighbor(&self, pos: Vec2i, dir: Direction) -> Option<(&dyn Block, ChunkBlockMetadata)> {
+        let pos = pos.add_directional(&dir, 1);
+        self.get_block_at(pos.x, pos.y).map(|(blk, meta)| (blk.as_ref(), meta))
+    }
+
+    /// Gets the block next to `pos` in `dir`
+    pub fn neighbor_mut(&mut self, pos: Vec2i, dir: Direction) -> Option<(&mut dyn Block, ChunkBlockMetadata)> {
+        let pos = pos.add_directional(&dir, 1);
+        let (blk, meta) = self.get_block_at_mut(pos.x, pos.y)?;
+        Some((blk.as_mut(), meta))
+    }
 
-        if (x % BLOCKS_PER_CHUNK_X as i32) < 0 {
-            chunk_x -= 1;
+    /// Gets all 4 neighbors of `pos`, in the order North, East, South, West
+    pub fn neighbors(&self, pos: Vec2i) -> [Option<(&dyn Block, ChunkBlockMetadata)>; 4] {
+        Direction::all().map(|dir| self.neighbor(pos, dir))
+    }
+
+    /// Finds all blocks connected to the one at `start` through their push/pull capabilities, including `start`
+    /// itself. Stops after `MAX_NETWORK_SIZE` blocks.
+    pub fn connected_network(&self, start: Vec2i) -> Vec<Vec2i> {
+        let links = |blk: &dyn Block, side: Direction, meta: ChunkBlockMetadata| {
+            blk.has_capability_push(side, meta) || blk.has_capability_pull(side, meta)
+        };
+
+        let mut network = Vec::new();
+        match self.get_block_at(start.x, start.y) {
+            Some((blk, _)) if !blk.is_none() => {}
+            _ => return network,
         }
-        if (y % BLOCKS_PER_CHUNK_Y as i32) < 0 {
-            chunk_y -= 1;
+        let mut seen = HashSet::from([start]);
+        let mut queue = VecDeque::from([start]);
+
+        while let Some(pos) = queue.pop_front() {
+            network.push(pos);
+            if network.len() >= MAX_NETWORK_SIZE {
+                break;
+            }
+            let Some((blk, meta)) = self.get_block_at(pos.x, pos.y) else {
+                continue;
+            };
+
+            for (dir, neighbor) in [
+                Direction::North,
+                Direction::East,
+                Direction::South,
+                Direction::West,
+            ]
+            .into_iter()
+            .zip(self.neighbors(pos))
+            {
+                let Some((other, other_meta)) = neighbor else {
+                    continue;
+                };
+                let other_pos = pos.add_directional(&dir, 1);
+                if other.is_none() || seen.contains(&other_pos) {
+                    continue;
+                }
+                if links(blk.as_ref(), dir, meta) || links(other, dir.opposite(), other_meta) {
+                    seen.insert(other_pos);
+                    queue.push_back(other_pos);
+                }
+            }
         }
-        let blk = self
-            .chunks
-            .get_mut(&(chunk_x, chunk_y))?
-            .get_block_at_mut(x, y);
-        Some((&mut blk.inner, blk.data))
+
+        network
+    }
+
+    /// Estimates how many items per minute arrive at the sinks (blocks that don't move items on) of `network`.
+    /// Machines without any input run at full speed, every other machine passes on at most its own throughput and
+    /// splits it evenly between its outputs. Blocks in a loop are skipped. Returns None if nothing reaches a sink
+    pub fn network_flow(&self, network: &[Vec2i]) -> Option<u32> {
+        let members: HashSet<Vec2i> = network.iter().copied().collect();
+        let mut outputs: HashMap<Vec2i, Vec<Vec2i>> = HashMap::new();
+        let mut num_inputs: HashMap<Vec2i, usize> = network.iter().map(|pos| (*pos, 0)).collect();
+
+        for pos in network {
+            let Some((blk, meta)) = self.get_block_at(pos.x, pos.y) else {
+                continue;
+            };
+            let targets: Vec<Vec2i> = blk
+                .flow_outputs(meta)
+                .into_iter()
+                .filter_map(|dir| {
+                    let (other, other_meta) = self.neighbor(*pos, dir)?;
+                    let other_pos = pos.add_directional(&dir, 1);
+                    (members.contains(&other_pos) && other.has_capability_push(dir.opposite(), other_meta))
+                        .then_some(other_pos)
+                })
+                .collect();
+            for target in &targets {
+                if let Some(inputs) = num_inputs.get_mut(target) {
+                    *inputs += 1;
+                }
+            }
+            outputs.insert(*pos, targets);
+        }
+
+        let mut inflow: HashMap<Vec2i, f32> = HashMap::new();
+        let mut queue: VecDeque<Vec2i> = num_inputs
+            .iter()
+            .filter(|(_, inputs)| **inputs == 0)
+            .map(|(pos, _)| *pos)
+            .collect();
+        let mut sink_flow = None;
+
+        while let Some(pos) = queue.pop_front() {
+            let Some((blk, _)) = self.get_block_at(pos.x, pos.y) else {
+                continue;
+            };
+            let targets = outputs.get(&pos).map(Vec::as_slice).unwrap_or_default();
+            let input = inflow.get(&pos).copied();
+            if targets.is_empty() {
+                if let (Some(input), None) = (input, blk.throughput_per_min()) {
+                    *sink_flow.get_or_insert(0.0) += input;
+                }
+                continue;
+            }
+
+            let flow = match (input, blk.throughput_per_min()) {
+                (None, Some(throughput)) => throughput as f32,
+                (Some(input), Some(throughput)) => input.min(throughput as f32),
+                (input, None) => input.unwrap_or_default(),
+            };
+            for target in targets {
+                *inflow.entry(*target).or_default() += flow / targets.len() as f32;
+                if let Some(inputs) = num_inputs.get_mut(target) {
+                    *inputs -= 1;
+                    if *inputs == 0 {
+                        queue.push_back(*target);
+                    }
+                }
+            }
+        }
+
+        sink_flow.map(|flow: f32| flow.round() as u32)
+    }
+
+    /// Rotates every block in `cells` by 90°, keeping their contents, and lets direction dependent blocks
+    /// (e.g. tunnels) pair up again with their new direction
+    pub fn rotate_region(&mut self, cells: &[Vec2i], right: bool) {
+        for pos in cells {
+            self.turn_block_at(*pos, right);
+        }
+    }
+
+    /// Turns the block at `x`, `y` clockwise. Returns whether there was a block to turn
+    pub fn rotate_block_at(&mut self, x: i32, y: i32) -> bool {
+        self.turn_block_at(Vec2i::new(x, y), true)
+    }
+
+    fn turn_block_at(&mut self, pos: Vec2i, right: bool) -> bool {
+        let (chunk_x, chunk_y) = get_chunk_pos(pos.x, pos.y);
+        let Some(chunk) = self.get_chunk_mut(chunk_x, chunk_y) else {
+            return false;
+        };
+        let blk = chunk.get_block_at_mut(pos.x, pos.y);
+        if blk.inner.is_none() {
+            return false;
+        }
+        blk.data.direction = blk.data.direction.next(right);
+        let meta = blk.data;
+        let mut inner = std::mem::replace(&mut blk.inner, empty_block().clone());
+
+        inner.on_rotate(meta, self);
+
+        if let Some(chunk) = self.get_chunk_mut(chunk_x, chunk_y) {
+            let blk = chunk.get_block_at_mut(pos.x, pos.y);
+            blk.inner = inner;
+            blk.init();
+        }
+        true
+    }
+
+    /// Turns the block at `pos` to `direction` and applies `config` (see `Block::copy_config`) if it is a block of
+    /// kind `id`. Returns whether there was a block to paste onto
+    pub fn paste_config(
+        &mut self,
+        pos: Vec2i,
+        id: Identifier,
+        direction: Direction,
+        config: Option<&[u8]>,
+    ) -> bool {
+        let (chunk_x, chunk_y) = get_chunk_pos(pos.x, pos.y);
+        let Some(chunk) = self.get_chunk_mut(chunk_x, chunk_y) else {
+            return false;
+        };
+        let blk = chunk.get_block_at_mut(pos.x, pos.y);
+        if blk.inner.is_none() {
+            return false;
+        }
+        blk.data.direction = direction;
+        let meta = blk.data;
+        let mut inner = std::mem::replace(&mut blk.inner, empty_block().clone());
+
+        if let Some(config) = config.filter(|_| inner.identifier() == id) {
+            if let Err(e) = inner.apply_config(&mut Buffer::new(config.to_vec())) {
+                log_warn!("Couldn't apply the copied settings to {:?}: {e:?}", inner.identifier());
+            }
+        }
+        // so tunnels link up with their new partner
+        inner.on_rotate(meta, self);
+
+        if let Some(chunk) = self.get_chunk_mut(chunk_x, chunk_y) {
+            let blk = chunk.get_block_at_mut(pos.x, pos.y);
+            blk.inner = inner;
+            blk.init();
+        }
+        true
     }
 
     pub fn destroy_block_at(
@@ -75,33 +454,14 @@ impl World {
         y: i32,
         inventory: &mut Inventory,
     ) -> Option<(Box<dyn Block>, ChunkBlockMetadata)> {
-        let mut chunk_x = x / BLOCKS_PER_CHUNK_X as i32;
-        let mut chunk_y = y / BLOCKS_PER_CHUNK_Y as i32;
-
-        if (x % BLOCKS_PER_CHUNK_X as i32) < 0 {
-            chunk_x -= 1;
-        }
-        if (y % BLOCKS_PER_CHUNK_Y as i32) < 0 {
-            chunk_y -= 1;
-        }
-
-        self.chunks
-            .get_mut(&(chunk_x, chunk_y))
+        let (chunk_x, chunk_y) = get_chunk_pos(x, y);
+        self.get_chunk_mut(chunk_x, chunk_y)
             .and_then(|chunk| chunk.destroy_block_at(x, y, inventory))
     }
 
     pub fn set_block_at(&mut self, x: i32, y: i32, block: Box<dyn Block>, dir: Direction) -> bool {
-        let mut chunk_x = x / BLOCKS_PER_CHUNK_X as i32;
-        let mut chunk_y = y / BLOCKS_PER_CHUNK_Y as i32;
-
-        if (x % BLOCKS_PER_CHUNK_X as i32) < 0 {
-            chunk_x -= 1;
-        }
-        if (y % BLOCKS_PER_CHUNK_Y as i32) < 0 {
-            chunk_y -= 1;
-        }
-
-        if let Some(chunk) = self.chunks.get_mut(&(chunk_x, chunk_y)) {
+        let (chunk_x, chunk_y) = get_chunk_pos(x, y);
+        if let Some(chunk) = self.get_chunk_mut(chunk_x, chunk_y) {
             chunk.set_block_at(x, y, block, dir);
             true
         } else {
@@ -115,10 +475,15 @@ impl World {
 
         let mut world = Self {
             chunks: HashMap::with_capacity(w as usize * h as usize),
+            suspended: HashMap::new(),
+            suspend_radius: Some(DEFAULT_SUSPEND_RADIUS),
             startx: off_x,
             starty: off_y,
             w,
             h,
+            rng: Rng::new(0),
+            update_nanos: 0,
+            power: PowerNetworks::default(),
         };
 
         for x in 0..w as i32 {
@@ -130,18 +495,176 @@ impl World {
         world
     }
 
+    /// Creates a new world and scatters resource nodes over it. The same seed always gives the same world
+    pub fn generate(w: u32, h: u32, seed: u64) -> Self {
+        let mut world = Self::new(w, h);
+        world.rng = Rng::new(seed);
+
+        let width = w * BLOCKS_PER_CHUNK_X;
+        let height = h * BLOCKS_PER_CHUNK_Y;
+        let num_nodes = world.rng.next_range(NODES_PER_CHUNK) * w * h;
+        for _ in 0..num_nodes {
+            let x = world.startx * BLOCKS_PER_CHUNK_X as i32 + world.rng.next_range(0..width) as i32;
+            let y = world.starty * BLOCKS_PER_CHUNK_Y as i32 + world.rng.next_range(0..height) as i32;
+            // keep the area the player spawns in free
+            if x.abs() <= SPAWN_CLEARANCE && y.abs() <= SPAWN_CLEARANCE {
+                continue;
+            }
+            let node = match world.rng.next_range(0..3) {
+                0 => ResourceNode::brown(),
+                1 => ResourceNode::blue(),
+                _ => ResourceNode::green(),
+            };
+            world.set_block_at(x, y, Box::new(node), Direction::default());
+        }
+
+        world
+    }
+
+    /// The player offset that puts the block at `pos` in the middle of a screen of the size `screen`.
+    /// This is the inverse of turning the cursor position into a block position in `run_game`
+    pub fn center_player_on(pos: Vec2i, screen: &ScreenDimensions) -> Vec2i {
+        Vec2i::new(
+            pos.x * BLOCK_DEFAULT_W as i32 + BLOCK_DEFAULT_W as i32 / 2 - screen.width / 2,
+            pos.y * BLOCK_DEFAULT_H as i32 + BLOCK_DEFAULT_H as i32 / 2 - screen.height / 2,
+        )
+    }
+
+    /// Grows the world by the given amount of chunks on each side, keeping all existing chunks where they are
+    pub fn expand(&mut self, dx_neg: u32, dx_pos: u32, dy_neg: u32, dy_pos: u32) {
+        self.startx -= dx_neg as i32;
+        self.starty -= dy_neg as i32;
+        self.w += dx_neg + dx_pos;
+        self.h += dy_neg + dy_pos;
+
+        for x in 0..self.w as i32 {
+            for y in 0..self.h as i32 {
+                let pos = (self.startx + x, self.starty + y);
+                if !self.chunks.contains_key(&pos) && !self.suspended.contains_key(&pos) {
+                    self.load_chunk(pos.0, pos.1);
+                    if let Some(chunk) = self.chunks.get_mut(&pos) {
+                        chunk.init();
+                    }
+                }
+            }
+        }
+    }
+
     pub fn init(&mut self) {
         for (_, chunk) in self.chunks.iter_mut() {
             chunk.init();
         }
+        self.update_power();
+    }
+
+    /// Recomputes which power carrying blocks are connected and whether their networks have enough power, and takes
+    /// the power this tick needs out of them
+    pub fn update_power(&mut self) {
+        let power = PowerNetworks::compute(self);
+        for network in power.networks() {
+            network.draw_power(self);
+        }
+        self.power = power;
+    }
+
+    pub fn power_networks(&self) -> &PowerNetworks {
+        &self.power
+    }
+
+    /// Positions of all power carrying blocks in loaded chunks
+    pub fn power_blocks(&self) -> Vec<Vec2i> {
+        self.chunks
+            .values()
+            .flat_map(|chunk| chunk.blocks.iter())
+            .filter(|blk| blk.inner.carries_power())
+            .map(|blk| blk.data.position)
+            .collect()
     }
 
     pub fn update(&mut self) {
-        for (_, chunk) in self.chunks.iter_mut() {
-            chunk.update();
+        let start = Instant::now();
+        self.update_power();
+        self.update_chunks(thread::available_parallelism().map_or(1, |n| n.get()));
+        self.update_nanos = start.elapsed().as_nanos() as u64;
+    }
+
+    /// Updates the blocks of all chunks with machines, spread over up to `threads` threads when there are enough
+    fn update_chunks(&mut self, threads: usize) {
+        // sorted so the block updates get scheduled in the same order every tick, however many threads there are
+        let mut chunks: Vec<_> = self
+            .chunks
+            .iter_mut()
+            .filter(|(_, chunk)| !chunk.machines.is_empty())
+            .collect();
+        chunks.sort_unstable_by_key(|(pos, _)| **pos);
+
+        if threads < 2 || chunks.len() < MIN_CHUNKS_PER_THREAD * 2 {
+            for (_, chunk) in chunks {
+                chunk.update();
+            }
+        } else {
+            // block updates only schedule tasks, which then run on this thread; so each thread collects the tasks
+            // of its chunks and they get scheduled in chunk order afterwards
+            let per_thread = chunks.len().div_ceil(threads).max(MIN_CHUNKS_PER_THREAD);
+            // tests move the block timers of their own thread, the update threads have to see the same time
+            #[cfg(test)]
+            let clock = crate::blocks::clock_offset();
+            let tasks: Vec<Vec<Task>> = thread::scope(|scope| {
+                let handles: Vec<_> = chunks
+                    .chunks_mut(per_thread)
+                    .map(|group| {
+                        scope.spawn(move || {
+                            #[cfg(test)]
+                            crate::blocks::advance_clock(clock);
+                            collect_tasks(|| {
+                                for (_, chunk) in group.iter_mut() {
+                                    chunk.update();
+                                }
+                            })
+                        })
+                    })
+                    .collect();
+                handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+            });
+            for tasks in tasks {
+                schedule_tasks(tasks);
+            }
         }
     }
 
+    /// Runs the world tasks among `tasks` (block updates and wakes) in order and gives back the other ones, like
+    /// opening a screen
+    pub fn run_tasks(&mut self, tasks: Vec<Task>, config: &mut GameConfig) -> Vec<Task> {
+        let mut other_tasks = Vec::new();
+        for t in tasks {
+            match t {
+                Task::WorldUpdateBlock(func, meta) => func(meta, self),
+                Task::WorldUpdateBlockWithConfig(func, meta) => func(meta, self, config),
+                Task::WakeBlock(pos) => {
+                    if let Some((blk, meta)) = self.get_block_at_mut(pos.x, pos.y) {
+                        blk.update(meta);
+                    }
+                }
+                other => other_tasks.push(other),
+            }
+        }
+        other_tasks
+    }
+
+    /// The number of items in the inventories of all loaded chunks
+    pub fn total_item_count(&self) -> u64 {
+        self.chunks
+            .values()
+            .flat_map(|chunk| chunk.blocks.iter())
+            .map(|blk| blk.inner.item_count())
+            .sum()
+    }
+
+    /// How long the last `update` took in nanoseconds, for profiling
+    pub fn last_update_nanos(&self) -> u64 {
+        self.update_nanos
+    }
+
     pub fn render(
         &mut self,
         d: &mut RaylibDrawHandle,
@@ -152,7 +675,7 @@ impl World {
         layer: RenderLayer,
         blk_w: u32,
         blk_h: u32,
-    ) {
+    ) -> usize {
         let chunk_w: u32 = blk_w * BLOCKS_PER_CHUNK_X;
         let chunk_h: u32 = blk_h * BLOCKS_PER_CHUNK_Y;
         let first_chunk_x = 0.max((x.wrapping_div(chunk_w as i32)) - self.startx - 1) as u32;
@@ -165,6 +688,7 @@ impl World {
             .h
             .min(h.wrapping_div_euclid(chunk_h) + 3 + first_chunk_y);
 
+        let mut rendered = 0;
         for chunk_x in first_chunk_x..last_chunk_x {
             for chunk_y in first_chunk_y..last_chunk_y {
                 let chunk_x = chunk_x as i32 + self.startx;
@@ -173,10 +697,11 @@ impl World {
                 let sc_y = chunk_y * chunk_h as i32 - y;
 
                 if let Some(chunk) = self.chunks.get_mut(&(chunk_x, chunk_y)) {
-                    chunk.render(d, sc_x, sc_y, chunk_w, chunk_h, blk_w, blk_h, layer);
+                    rendered += chunk.render(d, sc_x, sc_y, w, h, blk_w, blk_h, layer);
                 }
             }
         }
+        rendered
     }
 
     pub fn get_effective_render_position(
@@ -201,6 +726,7 @@ impl Serialize for World {
             .map(|chunk| chunk.required_length())
             .reduce(|a, b| a + b)
             .unwrap_or_default()
+            + self.suspended.values().map(SuspendedChunk::serialized_len).sum::<usize>()
             + self.w.required_length()
             + self.h.required_length()
             + self.startx.required_length()
@@ -209,63 +735,110 @@ impl Serialize for World {
     }
 
     fn serialize(&self, buf: &mut Vec<u8>) {
+        self.serialize_with_mode(buf, SaveMode::Debug);
+    }
+}
+
+impl World {
+    /// Writes the world in the format of `mode`. `SaveMode::Debug` is the same as `serialize`
+    pub fn serialize_with_mode(&self, buf: &mut Vec<u8>, mode: SaveMode) {
         SerializationTrap::World.serialize(buf);
         self.startx.serialize(buf);
         self.starty.serialize(buf);
         self.w.serialize(buf);
         self.h.serialize(buf);
-        assert_eq!(self.w as usize * self.h as usize, self.chunks.len());
+        assert_eq!(
+            self.w as usize * self.h as usize,
+            self.chunks.len() + self.suspended.len()
+        );
         let mut vals = self
             .chunks
-            .iter()
-            .map(|(&(a, b), chunk)| {
+            .keys()
+            .chain(self.suspended.keys())
+            .map(|&(a, b)| {
                 (
-                    (a + self.startx.abs()) as usize
-                        + (b + self.startx.abs()) as usize * self.w as usize,
-                    chunk,
+                    (a - self.startx) as usize + (b - self.starty) as usize * self.w as usize,
+                    (a, b),
                 )
             })
-            .collect::<Vec<(usize, &Chunk)>>();
+            .collect::<Vec<(usize, (i32, i32))>>();
         vals.sort_by(|a, b| a.0.cmp(&b.0));
-        for (_, chunk) in vals {
-            chunk.serialize(buf);
+        for (_, pos) in vals {
+            if let Some(chunk) = self.chunks.get(&pos) {
+                match mode {
+                    SaveMode::Debug => chunk.serialize(buf),
+                    SaveMode::Compact => chunk.serialize_compact(buf),
+                }
+            } else if let Some(data) = self.suspended.get(&pos) {
+                match (mode, data.decompress()) {
+                    // suspended chunks are already stored in their serialized form
+                    (SaveMode::Debug, Ok(data)) => buf.extend(data),
+                    (SaveMode::Compact, Ok(data)) => match Chunk::try_deserialize(&mut Buffer::new(data)) {
+                        Ok(chunk) => chunk.serialize_compact(buf),
+                        Err(e) => {
+                            log_error!("Failed to compact chunk {} {}: {e}", pos.0, pos.1);
+                            Chunk::default(pos.0, pos.1).serialize_compact(buf);
+                        }
+                    },
+                    (mode, Err(e)) => {
+                        log_error!("Failed to decompress chunk {} {}: {e}", pos.0, pos.1);
+                        match mode {
+                            SaveMode::Debug => Chunk::default(pos.0, pos.1).serialize(buf),
+                            SaveMode::Compact => Chunk::default(pos.0, pos.1).serialize_compact(buf),
+                        }
+                    }
+                }
+            }
         }
     }
-}
 
-impl Deserialize for World {
-    fn deserialize(buf: &mut Buffer) -> Self {
-        SerializationTrap::World.deserialize(buf);
-        let startx = i32::deserialize(buf);
-        let starty = i32::deserialize(buf);
-        let w = u32::deserialize(buf);
-        let h = u32::deserialize(buf);
+    /// Reads a world written by `serialize_with_mode` with the same `mode`
+    pub fn try_deserialize_with_mode(
+        buf: &mut Buffer,
+        mode: SaveMode,
+    ) -> Result<Self, SerializationError> {
+        SerializationTrap::World.try_deserialize(buf)?;
+        let startx = i32::try_deserialize(buf)?;
+        let starty = i32::try_deserialize(buf)?;
+        let w = u32::try_deserialize(buf)?;
+        let h = u32::try_deserialize(buf)?;
 
         let num_chunks = w as usize * h as usize;
-        let mut chunks = HashMap::with_capacity(num_chunks);
+        let mut chunks = HashMap::with_capacity(num_chunks.min(buf.len()));
 
         for i in 0..(w as usize * h as usize) {
             let x = (i % w as usize) as i32 + startx;
             let y = (i / w as usize) as i32 + starty;
 
-            chunks.insert((x, y), Chunk::deserialize(buf));
+            let chunk = match mode {
+                SaveMode::Debug => Chunk::try_deserialize(buf)?,
+                SaveMode::Compact => Chunk::try_deserialize_compact(buf)?,
+            };
+            chunks.insert((x, y), chunk);
         }
 
-        Self {
+        Ok(Self {
             chunks,
+            suspended: HashMap::new(),
+            suspend_radius: Some(DEFAULT_SUSPEND_RADIUS),
             startx,
             starty,
             w,
             h,
-        }
+            rng: Rng::new(0),
+            update_nanos: 0,
+            power: PowerNetworks::default(),
+        })
     }
+}
 
-    fn try_deserialize(buf: &mut Buffer) -> Result<Self, SerializationError> {
-        SerializationTrap::World.try_deserialize(buf)?;
-        let startx = i32::try_deserialize(buf)?;
-        let starty = i32::try_deserialize(buf)?;
-        let w = u32::try_deserialize(buf)?;
-        let h = u32::try_deserialize(buf)?;
+impl Deserialize for World {
+    fn deserialize(buf: &mut Buffer) -> Self {
+        SerializationTrap::World.deserialize(buf);
+        let startx = i32::deserialize(buf);
+        let starty = i32::deserialize(buf);
+        let w = u32::deserialize(buf);
+        let h = u32::deserialize(buf);
 
         let num_chunks = w as usize * h as usize;
         let mut chunks = HashMap::with_capacity(num_chunks);
@@ -274,16 +847,25 @@ impl Deserialize for World {
             let x = (i % w as usize) as i32 + startx;
             let y = (i / w as usize) as i32 + starty;
 
-            chunks.insert((x, y), Chunk::try_deserialize(buf)?);
+            chunks.insert((x, y), Chunk::deserialize(buf));
         }
 
-        Ok(Self {
+        Self {
             chunks,
+            suspended: HashMap::new(),
+            suspend_radius: Some(DEFAULT_SUSPEND_RADIUS),
             startx,
             starty,
             w,
             h,
-        })
+            rng: Rng::new(0),
+            update_nanos: 0,
+            power: PowerNetworks::default(),
+        }
+    }
+
+    fn try_deserialize(buf: &mut Buffer) -> Result<Self, SerializationError> {
+        Self::try_deserialize_with_mode(buf, SaveMode::Debug)
     }
 }
 
@@ -291,14 +873,66 @@ pub const BLOCK_DEFAULT_W: u32 = 64;
 pub const BLOCK_DEFAULT_H: u32 = 64;
 pub const BLOCKS_PER_CHUNK_X: u32 = 32;
 pub const BLOCKS_PER_CHUNK_Y: u32 = 32;
+// compact saves store runs of empty blocks as a u16, and a run can span a whole chunk
+const _: () = assert!(
+    BLOCKS_PER_CHUNK_X > 0
+        && BLOCKS_PER_CHUNK_Y > 0
+        && BLOCKS_PER_CHUNK_X as usize * BLOCKS_PER_CHUNK_Y as usize <= u16::MAX as usize
+);
+/// the most blocks `World::connected_network` returns
+pub const MAX_NETWORK_SIZE: usize = 4096;
+
+/// A chunk `World::suspend_chunk` took out of the world, serialized and compressed
+#[derive(Clone)]
+pub struct SuspendedChunk {
+    /// the length of the serialized chunk, before compressing it
+    len: usize,
+    data: Vec<u8>,
+}
+
+impl SuspendedChunk {
+    fn new(chunk: &Chunk) -> Self {
+        let mut buf = Vec::with_capacity(chunk.required_length());
+        chunk.serialize(&mut buf);
+        Self {
+            len: buf.len(),
+            data: deflate::compress(&buf),
+        }
+    }
+
+    /// The length of the chunk in its serialized form, which is what saving it writes
+    pub fn serialized_len(&self) -> usize {
+        self.len
+    }
+
+    /// Gets the chunk back in its serialized form
+    fn decompress(&self) -> Result<Vec<u8>, SerializationError> {
+        deflate::decompress(&self.data, self.len)
+    }
+
+    fn restore(&self) -> Result<Chunk, SerializationError> {
+        Chunk::try_deserialize(&mut Buffer::new(self.decompress()?))
+    }
+}
 
-/// chunks: 32x32 area
+/// chunks: BLOCKS_PER_CHUNK_X x BLOCKS_PER_CHUNK_Y area, stored row by row
 #[derive(Clone)]
 #[allow(dead_code)]
 pub struct Chunk {
     pub blocks: Vec<ChunkBlock>,
     chunk_x: i32,
     chunk_y: i32,
+    /// indices into `blocks` of every machine, the only blocks that do anything on update
+    machines: Vec<usize>,
+}
+
+fn machine_indices(blocks: &[ChunkBlock]) -> Vec<usize> {
+    blocks
+        .iter()
+        .enumerate()
+        .filter(|(_, blk)| blk.inner.is_machine())
+        .map(|(idx, _)| idx)
+        .collect()
 }
 
 impl Chunk {
@@ -322,6 +956,7 @@ impl Chunk {
             blocks: vec,
             chunk_x,
             chunk_y,
+            machines: Vec::new(),
         }
     }
 
@@ -331,17 +966,8 @@ impl Chunk {
         y: i32,
         inventory: &mut Inventory,
     ) -> Option<(Box<dyn Block>, ChunkBlockMetadata)> {
-        let mut off_x = x % BLOCKS_PER_CHUNK_X as i32;
-        let mut off_y = y % BLOCKS_PER_CHUNK_Y as i32;
-
-        if off_x < 0 {
-            off_x += BLOCKS_PER_CHUNK_X as i32;
-        }
-        if off_y < 0 {
-            off_y += BLOCKS_PER_CHUNK_X as i32;
-        }
-
-        if self.blocks[off_y as usize * BLOCKS_PER_CHUNK_X as usize + off_x as usize]
+        let idx = block_index(x, y);
+        if self.blocks[idx]
             .inner
             .is_none()
         {
@@ -350,11 +976,11 @@ impl Chunk {
 
         let block = (
             std::mem::replace(
-                &mut self.blocks[off_y as usize * BLOCKS_PER_CHUNK_X as usize + off_x as usize]
+                &mut self.blocks[idx]
                     .inner,
                 empty_block().clone(),
             ),
-            self.blocks[off_y as usize * BLOCKS_PER_CHUNK_X as usize + off_x as usize].data,
+            self.blocks[idx].data,
         );
 
         let return_blocks = block.0.destroy_items();
@@ -362,60 +988,37 @@ impl Chunk {
             inventory.try_add_item(itm);
         }
 
-        self.blocks[off_y as usize * BLOCKS_PER_CHUNK_X as usize + off_x as usize].init();
+        self.blocks[idx].init();
+        self.machines.retain(|machine| *machine != idx);
 
         Some(block)
     }
 
     pub fn set_block_at(&mut self, x: i32, y: i32, new_block: Box<dyn Block>, dir: Direction) {
-        let mut off_x = x % BLOCKS_PER_CHUNK_X as i32;
-        let mut off_y = y % BLOCKS_PER_CHUNK_Y as i32;
-
-        if off_x < 0 {
-            off_x += BLOCKS_PER_CHUNK_X as i32;
-        }
-        if off_y < 0 {
-            off_y += BLOCKS_PER_CHUNK_X as i32;
-        }
-
-        if !self.blocks[off_y as usize * BLOCKS_PER_CHUNK_X as usize + off_x as usize]
+        let idx = block_index(x, y);
+        if !self.blocks[idx]
             .inner
             .is_none()
         {
             return;
         }
 
+        if new_block.is_machine() {
+            self.machines.push(idx);
+        }
         let blk = ChunkBlock::new(new_block, x, y, dir);
-        self.blocks[off_y as usize * BLOCKS_PER_CHUNK_X as usize + off_x as usize] = blk;
-        self.blocks[off_y as usize * BLOCKS_PER_CHUNK_X as usize + off_x as usize].init();
+        self.blocks[idx] = blk;
+        self.blocks[idx].init();
     }
 
     pub fn get_block_at<'a>(&'a self, x: i32, y: i32) -> &'a ChunkBlock {
-        let mut off_x = x % BLOCKS_PER_CHUNK_X as i32;
-        let mut off_y = y % BLOCKS_PER_CHUNK_Y as i32;
-
-        if off_x < 0 {
-            off_x += BLOCKS_PER_CHUNK_X as i32;
-        }
-        if off_y < 0 {
-            off_y += BLOCKS_PER_CHUNK_X as i32;
-        }
-
-        &self.blocks[off_y as usize * BLOCKS_PER_CHUNK_X as usize + off_x as usize]
+        let idx = block_index(x, y);
+        &self.blocks[idx]
     }
 
     pub fn get_block_at_mut<'a>(&'a mut self, x: i32, y: i32) -> &'a mut ChunkBlock {
-        let mut off_x = x % BLOCKS_PER_CHUNK_X as i32;
-        let mut off_y = y % BLOCKS_PER_CHUNK_Y as i32;
-
-        if off_x < 0 {
-            off_x += BLOCKS_PER_CHUNK_X as i32;
-        }
-        if off_y < 0 {
-            off_y += BLOCKS_PER_CHUNK_X as i32;
-        }
-
-        &mut self.blocks[off_y as usize * BLOCKS_PER_CHUNK_X as usize + off_x as usize]
+        let idx = block_index(x, y);
+        &mut self.blocks[idx]
     }
 
     pub fn init(&mut self) {
@@ -424,12 +1027,18 @@ impl Chunk {
         }
     }
 
+    pub fn has_machines(&self) -> bool {
+        !self.machines.is_empty()
+    }
+
     pub fn update(&mut self) {
-        for blk in &mut self.blocks {
-            blk.update();
+        for idx in &self.machines {
+            self.blocks[*idx].update();
         }
     }
 
+    /// Renders the blocks of the chunk that are on screen, with the top left corner of the chunk at `x`, `y` and
+    /// a screen of `w` by `h`. Returns how many blocks got rendered
     pub fn render(
         &mut self,
         d: &mut RaylibDrawHandle,
@@ -440,12 +1049,17 @@ impl Chunk {
         block_w: u32,
         block_h: u32,
         layer: RenderLayer,
-    ) -> (u32, u32) {
-        let blocks_x = w.div_ceil(block_w).min(BLOCKS_PER_CHUNK_X);
-        let blocks_y = h.div_ceil(block_h).min(BLOCKS_PER_CHUNK_Y);
+    ) -> usize {
+        let visible = |offset: i32, screen: u32, block: u32, blocks: u32| {
+            let first = (-offset).max(0) / block as i32;
+            let last = ((screen as i32 - offset).max(0) as u32).div_ceil(block) as i32;
+            first.min(blocks as i32) as u32..last.min(blocks as i32) as u32
+        };
+        let blocks_x = visible(x, w, block_w, BLOCKS_PER_CHUNK_X);
+        let blocks_y = visible(y, h, block_h, BLOCKS_PER_CHUNK_Y);
 
-        for blk_y in 0..blocks_y {
-            for blk_x in 0..blocks_x {
+        for blk_y in blocks_y.clone() {
+            for blk_x in blocks_x.clone() {
                 self.blocks[blk_y as usize * BLOCKS_PER_CHUNK_X as usize + blk_x as usize].render(
                     d,
                     x + (blk_x * block_w) as i32,
@@ -457,7 +1071,7 @@ impl Chunk {
             }
         }
 
-        (w.min(blocks_x * block_w), h.min(blocks_y * block_h))
+        blocks_x.len() * blocks_y.len()
     }
 }
 
@@ -487,6 +1101,73 @@ impl Serialize for Chunk {
     }
 }
 
+impl Chunk {
+    /// Like `serialize`, but every run of empty blocks is stored as just its length
+    pub fn serialize_compact(&self, buf: &mut Vec<u8>) {
+        SerializationTrap::Chunk.serialize(buf);
+        self.chunk_x.serialize(buf);
+        self.chunk_y.serialize(buf);
+        self.blocks.len().serialize(buf);
+
+        let mut i = 0;
+        while i < self.blocks.len() {
+            let num_empty = self.blocks[i..]
+                .iter()
+                .take_while(|blk| blk.inner.is_none())
+                .count();
+            (num_empty as u16).serialize(buf);
+            i += num_empty;
+            if let Some(b) = self.blocks.get(i) {
+                b.data.direction.serialize(buf);
+                b.inner.serialize(buf);
+                i += 1;
+            }
+        }
+    }
+
+    /// Reads a chunk written by `serialize_compact`
+    pub fn try_deserialize_compact(buf: &mut Buffer) -> Result<Self, SerializationError> {
+        SerializationTrap::Chunk.try_deserialize(buf)?;
+        let chunk_x = i32::try_deserialize(buf)?;
+        let chunk_y = i32::try_deserialize(buf)?;
+        let num_blocks = usize::try_deserialize(buf)?;
+        if num_blocks != BLOCKS_PER_CHUNK_X as usize * BLOCKS_PER_CHUNK_Y as usize {
+            return Err(SerializationError::InvalidData);
+        }
+        let mut blocks: Vec<ChunkBlock> = Vec::with_capacity(num_blocks);
+        let position = |i: usize| {
+            (
+                (i % BLOCKS_PER_CHUNK_X as usize) as i32 + chunk_x * BLOCKS_PER_CHUNK_X as i32,
+                (i / BLOCKS_PER_CHUNK_X as usize) as i32 + chunk_y * BLOCKS_PER_CHUNK_Y as i32,
+            )
+        };
+
+        while blocks.len() < num_blocks {
+            let num_empty = u16::try_deserialize(buf)? as usize;
+            if blocks.len() + num_empty > num_blocks {
+                return Err(SerializationError::InvalidData);
+            }
+            for _ in 0..num_empty {
+                let (x, y) = position(blocks.len());
+                blocks.push(ChunkBlock::new(empty_block().clone_block(), x, y, Direction::North));
+            }
+            if blocks.len() < num_blocks {
+                let direction = Direction::try_deserialize(buf)?;
+                let inner = <Box<dyn Block>>::try_deserialize(buf)?;
+                let (x, y) = position(blocks.len());
+                blocks.push(ChunkBlock::new(inner, x, y, direction));
+            }
+        }
+
+        Ok(Self {
+            machines: machine_indices(&blocks),
+            blocks,
+            chunk_x,
+            chunk_y,
+        })
+    }
+}
+
 impl Deserialize for Chunk {
     fn deserialize(buf: &mut Buffer) -> Self {
         SerializationTrap::Chunk.deserialize(buf);
@@ -510,6 +1191,7 @@ impl Deserialize for Chunk {
             }
         }
         Self {
+            machines: machine_indices(&blocks),
             blocks,
             chunk_x,
             chunk_y,
@@ -521,6 +1203,9 @@ impl Deserialize for Chunk {
         let chunk_x = i32::try_deserialize(buf)?;
         let chunk_y = i32::try_deserialize(buf)?;
         let num_blocks = usize::try_deserialize(buf)?;
+        if num_blocks != BLOCKS_PER_CHUNK_X as usize * BLOCKS_PER_CHUNK_Y as usize {
+            return Err(SerializationError::InvalidData);
+        }
         let mut blocks: Vec<ChunkBlock> = Vec::with_capacity(num_blocks);
 
         for y in 0..BLOCKS_PER_CHUNK_Y {
@@ -538,6 +1223,7 @@ impl Deserialize for Chunk {
             }
         }
         Ok(Self {
+            machines: machine_indices(&blocks),
             blocks,
             chunk_x,
             chunk_y,
@@ -614,54 +1300,31 @@ impl Direction {
             Self::East => Self::West,
         }
     }
-}
 
-#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
-pub struct Vec2i {
-    pub x: i32,
-    pub y: i32,
-}
-
-impl Serialize for Vec2i {
-    fn required_length(&self) -> usize {
-        self.x.required_length()
-            + self.y.required_length()
-            + usize::required_length(&0)
-            + SerializationTrap::Vec.required_length()
+    /// Every direction, in the order North, East, South, West
+    pub fn all() -> [Direction; 4] {
+        [Self::North, Self::East, Self::South, Self::West]
     }
 
-    fn serialize(&self, buf: &mut Vec<u8>) {
-        SerializationTrap::Vec.serialize(buf);
-        usize::serialize(&2, buf);
-        self.x.serialize(buf);
-        self.y.serialize(buf);
+    /// The offset of one step in this direction, the same one `Vec2i::add_directional` moves by
+    pub fn to_vec2i(self) -> Vec2i {
+        Vec2i::ZERO.add_directional(&self, 1)
     }
-}
 
-impl Deserialize for Vec2i {
-    fn deserialize(buf: &mut Buffer) -> Self {
-        SerializationTrap::Vec.deserialize(buf);
-        let len = usize::deserialize(buf);
-        if len != 2 {
-            panic!("Vec2i: Expected a vector length of 2");
-        }
-        let x = i32::deserialize(buf);
-        let y = i32::deserialize(buf);
-        Self { x, y }
+    /// The direction to go from `from` to get to `to`, if `to` is right next to it
+    pub fn from_delta(from: Vec2i, to: Vec2i) -> Option<Direction> {
+        Self::all().into_iter().find(|dir| dir.to_vec2i() == to - from)
     }
+}
 
-    fn try_deserialize(buf: &mut Buffer) -> Result<Self, SerializationError> {
-        SerializationTrap::Vec.try_deserialize(buf)?;
-        let len = usize::try_deserialize(buf)?;
-        if len != 2 {
-            return Err(SerializationError::InvalidData);
-        }
-        let x = i32::try_deserialize(buf)?;
-        let y = i32::try_deserialize(buf)?;
-        Ok(Self { x, y })
-    }
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Vec2i {
+    pub x: i32,
+    pub y: i32,
 }
 
+serializable_struct!(Vec2i as vec { x: i32, y: i32 });
+
 impl Add for Vec2i {
     type Output = Self;
 
@@ -759,38 +1422,10 @@ impl From<Direction> for ChunkBlockMetadata {
     }
 }
 
-impl Serialize for ChunkBlockMetadata {
-    fn required_length(&self) -> usize {
-        self.position.required_length() + self.direction.required_length()
-    }
-
-    fn serialize(&self, buf: &mut Vec<u8>) {
-        self.position.serialize(buf);
-        self.direction.serialize(buf);
-    }
-}
-
-impl Deserialize for ChunkBlockMetadata {
-    fn deserialize(buf: &mut Buffer) -> Self {
-        let position = Vec2i::deserialize(buf);
-        let direction = Direction::deserialize(buf);
-
-        Self {
-            position,
-            direction,
-        }
-    }
-
-    fn try_deserialize(buf: &mut Buffer) -> Result<Self, SerializationError> {
-        let position = Vec2i::try_deserialize(buf)?;
-        let direction = Direction::try_deserialize(buf)?;
-
-        Ok(Self {
-            position,
-            direction,
-        })
-    }
-}
+serializable_struct!(ChunkBlockMetadata {
+    position: Vec2i,
+    direction: Direction,
+});
 
 pub struct ChunkBlock {
     inner: Box<dyn Block>,
@@ -844,11 +1479,24 @@ impl ChunkBlock {
         h: i32,
         layer: RenderLayer,
     ) {
+        if layer == RenderLayer::FlowArrows {
+            if self.inner.throughput_per_min().is_some() {
+                let [a, b, c] = arrow_vertices(x, y, w, h, self.data.direction);
+                d.draw_triangle(a, b, c, Color::YELLOW.fade(0.8));
+            }
+            return;
+        }
         self.inner.render(d, x, y, w, h, self.data, layer)
     }
     pub fn identifier(&self) -> Identifier {
         self.inner.identifier()
     }
+    pub fn is_none(&self) -> bool {
+        self.inner.is_none()
+    }
+    pub fn position(&self) -> Vec2i {
+        self.data.position
+    }
     pub fn update(&mut self) {
         self.inner.update(self.data);
     }
@@ -877,3 +1525,772 @@ impl Display for ChunkBlock {
         ))
     }
 }
+
+#[cfg(test)]
+impl World {
+    /// One game tick without the game around it: moves the block timers forward by a tick, updates the world and
+    /// runs the world tasks that got scheduled, along with the ones those schedule. The tasks never leave this
+    /// thread, so tests can tick their worlds side by side. Gives back the tasks that aren't world tasks
+    pub fn tick(&mut self, config: &mut GameConfig) -> Vec<Task> {
+        crate::blocks::advance_clock(crate::game::TICK_DURATION);
+        let mut tasks = collect_tasks(|| self.update());
+        let mut other_tasks = Vec::new();
+        while !tasks.is_empty() {
+            let world_tasks = std::mem::take(&mut tasks);
+            tasks = collect_tasks(|| other_tasks.extend(self.run_tasks(world_tasks, config)));
+        }
+        other_tasks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        blocks::{
+            assembler::AssemblerBlock,
+            conveyor::{ConveyorBlock, CONVEYOR_WORK_MS},
+            distributor::DistributorBlock,
+            extractor::ExtractorBlock,
+            splitter::ConveyorSplitter,
+            StorageContainer,
+        },
+        items::{get_item_by_id, COAL_IDENTIFIER, IRON_ORE_IDENTIFIER},
+        test_utils::{count_allocations, item, register_all},
+    };
+
+    #[test]
+    fn suspended_chunk_keeps_its_blocks() {
+        register_all();
+        let mut world = World::new(2, 2);
+        let mut container = StorageContainer::default();
+        container.get_inventory_capability().unwrap().try_add_item(item(*COAL_IDENTIFIER, 17));
+        world.set_block_at(-5, -7, Box::new(container), Direction::West);
+        world.set_block_at(-4, -7, Box::new(ConveyorBlock::default()), Direction::South);
+        let serialized_len = world.chunks[&(-1, -1)].required_length();
+
+        assert!(world.suspend_chunk(-1, -1));
+        assert!(world.get_block_at(-5, -7).is_none());
+        let suspended = &world.suspended[&(-1, -1)];
+        assert_eq!(suspended.serialized_len(), serialized_len);
+        assert!(suspended.data.len() < serialized_len);
+
+        assert!(world.restore_chunk(-1, -1));
+        assert!(world.suspended.is_empty());
+        let (blk, meta) = world.get_block_at(-5, -7).unwrap();
+        assert_eq!(blk.identifier(), StorageContainer::default().identifier());
+        assert_eq!(meta.direction, Direction::West);
+        assert_eq!(blk.item_count(), 17);
+        let (blk, meta) = world.get_block_at(-4, -7).unwrap();
+        assert_eq!(blk.identifier(), ConveyorBlock::default().identifier());
+        assert_eq!(meta.direction, Direction::South);
+        assert!(world.get_block_at(-3, -7).unwrap().0.is_none());
+    }
+
+    #[test]
+    fn saving_writes_suspended_chunks_uncompressed() {
+        register_all();
+        let mut world = World::new(2, 1);
+        world.set_block_at(3, 0, Box::new(StorageContainer::default()), Direction::East);
+        let mut before = Vec::new();
+        world.serialize(&mut before);
+
+        world.suspend_chunk(0, 0);
+        let mut after = Vec::new();
+        world.serialize(&mut after);
+        assert_eq!(before, after);
+        assert_eq!(world.required_length(), after.len());
+    }
+
+    #[test]
+    fn expanding_keeps_the_old_blocks_in_place() {
+        register_all();
+        let mut world = World::new(2, 2);
+        world.set_block_at(-32, -32, Box::new(StorageContainer::default()), Direction::West);
+        world.set_block_at(31, 31, Box::new(ConveyorBlock::default()), Direction::South);
+        assert!(world.get_block_at(-33, 0).is_none());
+
+        world.expand(1, 1, 1, 1);
+        assert_eq!((world.startx, world.starty, world.w, world.h), (-2, -2, 4, 4));
+        let (blk, meta) = world.get_block_at(-32, -32).unwrap();
+        assert_eq!(blk.identifier(), StorageContainer::default().identifier());
+        assert_eq!(meta.direction, Direction::West);
+        let (blk, meta) = world.get_block_at(31, 31).unwrap();
+        assert_eq!(blk.identifier(), ConveyorBlock::default().identifier());
+        assert_eq!(meta.direction, Direction::South);
+        for (x, y) in [(-64, -64), (-33, 0), (32, 0), (0, 63), (63, -64)] {
+            assert!(world.get_block_at(x, y).unwrap().0.is_none(), "({x}, {y})");
+        }
+        assert!(world.get_block_at(-65, 0).is_none());
+        assert!(world.get_block_at(0, 64).is_none());
+
+        // the chunks are written and read back in the same order
+        let mut data = Vec::new();
+        world.serialize(&mut data);
+        assert_eq!(data.len(), world.required_length());
+        let loaded = World::deserialize(&mut Buffer::new(data));
+        assert_eq!(loaded.get_block_at(-32, -32).unwrap().0.identifier(), StorageContainer::default().identifier());
+        assert_eq!(loaded.get_block_at(31, 31).unwrap().0.identifier(), ConveyorBlock::default().identifier());
+    }
+
+    #[test]
+    fn machines_keep_the_chunks_next_to_them_loaded() {
+        register_all();
+        let mut world = World::new(4, 1);
+        world.suspend_radius = Some(0);
+        let mut config = GameConfig::default();
+        let mut source = StorageContainer::default();
+        source.get_inventory_capability().unwrap().try_add_item(item(*COAL_IDENTIFIER, 10));
+        // the extractor is on the last column of chunk (0, 0) and pushes into a container in chunk (1, 0)
+        world.set_block_at(30, 5, Box::new(source), Direction::West);
+        world.set_block_at(31, 5, Box::new(ExtractorBlock::default()), Direction::West);
+        world.set_block_at(32, 5, Box::new(StorageContainer::default()), Direction::West);
+
+        // the player is all the way on the other side of the world
+        let far_away = Vec2i::new(-64, 5);
+        for _ in 0..100 {
+            world.update_residency(far_away);
+            world.tick(&mut config);
+        }
+        assert!(world.chunks.contains_key(&(0, 0)));
+        assert!(world.chunks.contains_key(&(1, 0)));
+        assert!(world.chunks.contains_key(&(-1, 0)));
+        assert!(world.suspended.is_empty());
+        assert_eq!(item_count_at(&world, 32, 5), 10);
+
+        // without the machine nothing keeps them around
+        world.destroy_block_at(31, 5, &mut Inventory::new(1, false));
+        world.update_residency(far_away);
+        assert_eq!(world.chunks.keys().collect::<Vec<_>>(), [&(-2, 0)]);
+
+        // and a new machine brings its neighbours back
+        world.set_block_at(31, 5, Box::new(ExtractorBlock::default()), Direction::West);
+        world.update_residency(far_away);
+        assert!(world.chunks.contains_key(&(1, 0)));
+        assert!(world.get_block_at(32, 5).is_some());
+    }
+
+    /// a container at each neighbor of `pos`, each facing the direction it is in from `pos`
+    fn surround(world: &mut World, pos: Vec2i) {
+        for dir in Direction::all() {
+            let at = pos.add_directional(&dir, 1);
+            world.set_block_at(at.x, at.y, Box::new(StorageContainer::default()), dir);
+        }
+    }
+
+    #[test]
+    fn neighbors_across_chunk_borders() {
+        register_all();
+        let mut world = World::new(2, 2);
+        // (0, 0) is the top left corner of its chunk, so its north and east neighbors are in other chunks
+        surround(&mut world, Vec2i::ZERO);
+        assert_eq!(world.neighbor(Vec2i::ZERO, Direction::North).unwrap().1.position, Vec2i::new(0, -1));
+        assert_eq!(world.neighbor(Vec2i::ZERO, Direction::East).unwrap().1.position, Vec2i::new(-1, 0));
+
+        for (dir, neighbor) in Direction::all().into_iter().zip(world.neighbors(Vec2i::ZERO)) {
+            let (blk, meta) = neighbor.unwrap();
+            assert!(!blk.is_none());
+            assert_eq!(meta.direction, dir);
+            assert_eq!(meta.position, Vec2i::ZERO.add_directional(&dir, 1));
+            assert_eq!(world.neighbor(Vec2i::ZERO, dir).unwrap().1.direction, dir);
+        }
+        for dir in Direction::all() {
+            assert_eq!(world.neighbor_mut(Vec2i::ZERO, dir).unwrap().1.direction, dir);
+        }
+    }
+
+    #[test]
+    fn neighbors_within_a_chunk() {
+        register_all();
+        let mut world = World::new(2, 2);
+        let pos = Vec2i::new(5, 9);
+        surround(&mut world, pos);
+        for (dir, neighbor) in Direction::all().into_iter().zip(world.neighbors(pos)) {
+            assert_eq!(neighbor.unwrap().1.direction, dir);
+        }
+        assert_eq!(world.neighbor(pos, Direction::North).unwrap().1.position, Vec2i::new(5, 8));
+        assert!(world.neighbor(Vec2i::new(5, 10), Direction::South).unwrap().0.is_none());
+    }
+
+    #[test]
+    fn neighbors_outside_of_the_world() {
+        register_all();
+        let mut world = World::new(2, 2);
+        // the world ends at x -32 and y 31
+        let corner = Vec2i::new(-32, 31);
+        assert!(world.neighbor(corner, Direction::East).is_none());
+        assert!(world.neighbor_mut(corner, Direction::South).is_none());
+        assert!(world.neighbor(corner, Direction::West).is_some());
+        assert!(world.neighbor(corner, Direction::North).is_some());
+    }
+
+    fn item_count_at(world: &World, x: i32, y: i32) -> u64 {
+        world.get_block_at(x, y).unwrap().0.item_count()
+    }
+
+    /// coal node -> extractor -> 3 belts -> splitter -> a container on each side, running south from (0, 0)
+    fn canonical_factory() -> World {
+        let mut world = World::new(2, 2);
+        world.set_block_at(0, 0, Box::new(ResourceNode::brown()), Direction::South);
+        world.set_block_at(0, 1, Box::new(ExtractorBlock::default()), Direction::South);
+        for y in 2..5 {
+            world.set_block_at(0, y, Box::new(ConveyorBlock::default()), Direction::South);
+        }
+        world.set_block_at(0, 5, Box::new(ConveyorSplitter::default()), Direction::South);
+        world.set_block_at(-1, 5, Box::new(StorageContainer::default()), Direction::East);
+        world.set_block_at(1, 5, Box::new(StorageContainer::default()), Direction::East);
+        world
+    }
+
+    #[test]
+    fn canonical_factory_fills_both_containers() {
+        register_all();
+        let mut world = canonical_factory();
+        let mut config = GameConfig::default();
+
+        // 20 seconds. The first coal needs 3.5s to get to the splitter, after that the belts are the bottleneck
+        for _ in 0..400 {
+            assert!(world.tick(&mut config).is_empty());
+        }
+
+        // the splitter takes turns between its outputs, so both containers get the same amount
+        assert_eq!(item_count_at(&world, -1, 5), 8);
+        assert_eq!(item_count_at(&world, 1, 5), 8);
+        // the extractor and every belt hold an item, the splitter just passed its one on
+        for y in 1..5 {
+            assert_eq!(item_count_at(&world, 0, y), 1);
+        }
+        assert_eq!(item_count_at(&world, 0, 5), 0);
+        assert_eq!(world.total_item_count(), 20);
+    }
+
+    #[test]
+    fn long_extractor_reaches_over_a_gap() {
+        register_all();
+        let mut config = GameConfig::default();
+        for (extractor, moved) in [(ExtractorBlock::long(), 5), (ExtractorBlock::default(), 0)] {
+            let mut world = World::new(2, 2);
+            let mut source = StorageContainer::default();
+            source.get_inventory_capability().unwrap().try_add_item(item(*COAL_IDENTIFIER, 5));
+            // two empty blocks on either side of the extractor
+            world.set_block_at(0, -3, Box::new(source), Direction::South);
+            world.set_block_at(0, 0, Box::new(extractor), Direction::South);
+            world.set_block_at(0, 3, Box::new(StorageContainer::default()), Direction::South);
+
+            for _ in 0..100 {
+                world.tick(&mut config);
+            }
+            assert_eq!(item_count_at(&world, 0, -3), 5 - moved);
+            assert_eq!(item_count_at(&world, 0, 0), 0);
+            assert_eq!(item_count_at(&world, 0, 3), moved);
+        }
+    }
+
+    #[test]
+    fn only_machines_get_updated() {
+        register_all();
+        let mut world = World::new(1, 1);
+        for y in 0..BLOCKS_PER_CHUNK_Y as i32 {
+            for x in 0..BLOCKS_PER_CHUNK_X as i32 {
+                let blk: Box<dyn Block> = if (x, y) == (5, 7) {
+                    Box::new(ConveyorBlock::default())
+                } else if (x + y) % 2 == 0 {
+                    Box::new(StorageContainer::default())
+                } else {
+                    Box::new(ResourceNode::brown())
+                };
+                world.set_block_at(x, y, blk, Direction::East);
+            }
+        }
+
+        // the conveyor only schedules its update once its timer ran out
+        crate::blocks::advance_clock(std::time::Duration::from_millis(CONVEYOR_WORK_MS as u64));
+        let tasks = collect_tasks(|| world.update());
+        assert_eq!(tasks.len(), 1);
+        let Task::WorldUpdateBlock(_, meta) = &tasks[0] else {
+            panic!("the conveyor schedules a world update");
+        };
+        assert_eq!(meta.position, Vec2i::new(5, 7));
+
+        // dismantling the machine takes it off the list
+        world.destroy_block_at(5, 7, &mut Inventory::new(1, false));
+        assert!(!world.chunks[&(0, 0)].has_machines());
+        assert!(collect_tasks(|| world.update()).is_empty());
+    }
+
+    #[test]
+    fn rotating_a_region_keeps_the_contents() {
+        register_all();
+        let mut world = World::new(2, 2);
+        let mut belt = ConveyorBlock::default();
+        // the belts only hand out their inventory once their timer ran out
+        crate::blocks::advance_clock(std::time::Duration::from_millis(CONVEYOR_WORK_MS as u64));
+        belt.get_inventory_capability().unwrap().try_add_item(item(*COAL_IDENTIFIER, 1));
+        world.set_block_at(-1, -1, Box::new(belt), Direction::North);
+        world.set_block_at(0, -1, Box::new(ConveyorBlock::default()), Direction::East);
+        world.set_block_at(-1, 0, Box::new(ConveyorBlock::default()), Direction::West);
+        let region = [Vec2i::new(-1, -1), Vec2i::new(0, -1), Vec2i::new(-1, 0), Vec2i::new(0, 0)];
+
+        world.rotate_region(&region, true);
+        let direction = |world: &World, x, y| world.get_block_at(x, y).unwrap().1.direction;
+        assert_eq!(direction(&world, -1, -1), Direction::East);
+        assert_eq!(direction(&world, 0, -1), Direction::South);
+        assert_eq!(direction(&world, -1, 0), Direction::North);
+        assert!(world.get_block_at(0, 0).unwrap().0.is_none());
+        assert_eq!(item_count_at(&world, -1, -1), 1);
+        assert_eq!(world.total_item_count(), 1);
+
+        world.rotate_region(&region, false);
+        world.rotate_region(&region, false);
+        assert_eq!(direction(&world, -1, -1), Direction::West);
+        assert_eq!(direction(&world, 0, -1), Direction::North);
+        assert_eq!(direction(&world, -1, 0), Direction::South);
+        assert_eq!(item_count_at(&world, -1, -1), 1);
+    }
+
+    #[test]
+    fn connected_network_follows_the_splitter_branches() {
+        register_all();
+        let mut world = World::new(1, 1);
+        for x in 0..3 {
+            world.set_block_at(x, 5, Box::new(ConveyorBlock::default()), Direction::East);
+        }
+        world.set_block_at(3, 5, Box::new(ConveyorSplitter::default()), Direction::East);
+        world.set_block_at(4, 5, Box::new(ConveyorBlock::default()), Direction::East);
+        world.set_block_at(3, 4, Box::new(ConveyorBlock::default()), Direction::North);
+        world.set_block_at(3, 6, Box::new(ConveyorBlock::default()), Direction::South);
+        // a gap away from the line, so not part of it
+        world.set_block_at(6, 5, Box::new(ConveyorBlock::default()), Direction::East);
+
+        let mut network = world.connected_network(Vec2i::new(0, 5));
+        network.sort_by_key(|pos| (pos.x, pos.y));
+        let expected = [(0, 5), (1, 5), (2, 5), (3, 4), (3, 5), (3, 6), (4, 5)].map(|(x, y)| Vec2i::new(x, y));
+        assert_eq!(network, expected);
+
+        assert_eq!(world.connected_network(Vec2i::new(6, 5)), [Vec2i::new(6, 5)]);
+        assert!(world.connected_network(Vec2i::new(5, 5)).is_empty());
+    }
+
+    #[test]
+    fn clearing_empties_every_cell() {
+        register_all();
+        let mut world = World::new(2, 2);
+        let mut container = StorageContainer::default();
+        container.get_inventory_capability().unwrap().try_add_item(item(*COAL_IDENTIFIER, 17));
+        world.set_block_at(-20, -3, Box::new(container), Direction::North);
+        world.set_block_at(4, 30, Box::new(ConveyorBlock::default()), Direction::East);
+        world.set_block_at(31, -32, Box::new(ResourceNode::brown()), Direction::North);
+        // suspended chunks get cleared as well
+        let mut container = StorageContainer::default();
+        container.get_inventory_capability().unwrap().try_add_item(item(*COAL_IDENTIFIER, 3));
+        world.set_block_at(-1, 20, Box::new(container), Direction::North);
+        assert!(world.suspend_chunk(-1, 0));
+        let size = (world.startx, world.starty, world.w, world.h);
+
+        let items = world.clear_blocks();
+        assert_eq!(items.iter().map(|item| item.stack_count()).sum::<u32>(), 20);
+        assert_eq!((world.startx, world.starty, world.w, world.h), size);
+        assert!(world.suspended.is_empty());
+        for x in -32..32 {
+            for y in -32..32 {
+                assert!(world.get_block_at(x, y).unwrap().0.is_none(), "({x}, {y})");
+            }
+        }
+        assert_eq!(world.total_item_count(), 0);
+    }
+
+    #[test]
+    fn flow_is_limited_by_the_slowest_stage() {
+        register_all();
+        // the belt is slower than the extractor, the distributor faster
+        let middles: [(Box<dyn Block>, u32); 2] = [
+            (Box::new(ConveyorBlock::default()), 60_000 / CONVEYOR_WORK_MS),
+            (Box::new(DistributorBlock::default()), ExtractorBlock::default().throughput_per_min().unwrap()),
+        ];
+        for (middle, expected) in middles {
+            let mut world = World::new(1, 1);
+            world.set_block_at(0, 0, Box::new(ResourceNode::brown()), Direction::South);
+            world.set_block_at(0, 1, Box::new(ExtractorBlock::default()), Direction::South);
+            world.set_block_at(0, 2, middle, Direction::South);
+            world.set_block_at(0, 3, Box::new(StorageContainer::default()), Direction::South);
+
+            let network = world.connected_network(Vec2i::new(0, 0));
+            assert_eq!(network.len(), 4);
+            assert_eq!(world.network_flow(&network), Some(expected));
+        }
+
+        // without a sink nothing arrives anywhere
+        let mut world = World::new(1, 1);
+        world.set_block_at(0, 0, Box::new(ResourceNode::brown()), Direction::South);
+        world.set_block_at(0, 1, Box::new(ExtractorBlock::default()), Direction::South);
+        assert_eq!(world.network_flow(&world.connected_network(Vec2i::new(0, 0))), None);
+    }
+
+    fn round_trip<T: Serialize + Deserialize>(value: &T) -> T {
+        let mut data = Vec::new();
+        value.serialize(&mut data);
+        assert_eq!(data.len(), value.required_length());
+        let mut buf = Buffer::new(data);
+        let read = T::try_deserialize(&mut buf).unwrap();
+        assert_eq!(buf.len(), 0);
+        read
+    }
+
+    #[test]
+    fn positions_round_trip() {
+        for pos in [Vec2i::ZERO, Vec2i::new(-7, 12), Vec2i::new(i32::MIN, i32::MAX)] {
+            assert_eq!(round_trip(&pos), pos);
+        }
+        // still read like the list of coordinates it used to be saved as
+        let mut data = Vec::new();
+        vec![3i32, -4].serialize(&mut data);
+        assert_eq!(Vec2i::try_deserialize(&mut Buffer::new(data)).unwrap(), Vec2i::new(3, -4));
+        let mut data = Vec::new();
+        vec![3i32, -4, 5].serialize(&mut data);
+        assert!(matches!(
+            Vec2i::try_deserialize(&mut Buffer::new(data)),
+            Err(SerializationError::InvalidData)
+        ));
+    }
+
+    #[test]
+    fn block_metadata_round_trips() {
+        for direction in Direction::all() {
+            let meta = ChunkBlockMetadata::new(direction, Vec2i::new(-33, 64));
+            let read = round_trip(&meta);
+            assert_eq!((read.direction, read.position), (direction, meta.position));
+        }
+    }
+
+    #[test]
+    fn required_length_matches_the_written_world() {
+        register_all();
+        let mut world = World::generate(2, 2, 7);
+        for (i, (_, blk)) in crate::blocks::blocks_in_category(None).enumerate() {
+            let x = -12 + i as i32;
+            world.set_block_at(x, -3, blk.clone_block(), Direction::North);
+            assert_eq!(world.get_block_at(x, -3).unwrap().0.identifier(), blk.identifier());
+        }
+        let mut container = StorageContainer::default();
+        container.get_inventory_capability().unwrap().try_add_item(item(*COAL_IDENTIFIER, 17));
+        world.set_block_at(5, 5, Box::new(container), Direction::East);
+        world.set_block_at(6, 5, Box::new(ConveyorBlock::default()), Direction::East);
+        assert!(world.suspend_chunk(0, 0));
+
+        let mut buf = Vec::new();
+        world.serialize(&mut buf);
+        assert_eq!(buf.len(), world.required_length());
+    }
+
+    fn node_positions(world: &World) -> Vec<(i32, i32, Identifier)> {
+        let (cw, ch) = (BLOCKS_PER_CHUNK_X as i32, BLOCKS_PER_CHUNK_Y as i32);
+        let mut nodes = Vec::new();
+        for x in world.startx * cw..(world.startx + world.w as i32) * cw {
+            for y in world.starty * ch..(world.starty + world.h as i32) * ch {
+                if let Some((blk, _)) = world.get_block_at(x, y) {
+                    if !blk.is_none() {
+                        nodes.push((x, y, blk.identifier()));
+                    }
+                }
+            }
+        }
+        nodes
+    }
+
+    #[test]
+    fn the_same_seed_generates_the_same_map() {
+        register_all();
+        let map = node_positions(&World::generate(3, 3, 99));
+        assert!(!map.is_empty());
+        assert_eq!(node_positions(&World::generate(3, 3, 99)), map);
+        assert_ne!(node_positions(&World::generate(3, 3, 100)), map);
+    }
+
+    #[test]
+    fn both_save_modes_load_the_same_world() {
+        register_all();
+        let mut world = World::generate(3, 2, 5);
+        let mut container = StorageContainer::default();
+        container.get_inventory_capability().unwrap().try_add_item(item(*COAL_IDENTIFIER, 9));
+        world.set_block_at(4, 4, Box::new(container), Direction::South);
+        world.set_block_at(5, 4, Box::new(ConveyorBlock::fast()), Direction::West);
+        world.set_block_at(-20, 3, Box::new(ExtractorBlock::default()), Direction::North);
+        assert!(world.suspend_chunk(0, 0));
+
+        let load = |mode: SaveMode| {
+            let mut buf = Vec::new();
+            world.serialize_with_mode(&mut buf, mode);
+            let mut buffer = Buffer::new(buf);
+            let loaded = World::try_deserialize_with_mode(&mut buffer, mode).expect("the world reads back");
+            assert_eq!(buffer.len(), 0, "{:?}", mode);
+            let mut written = Vec::new();
+            loaded.serialize(&mut written);
+            written
+        };
+        let debug = load(SaveMode::Debug);
+        assert_eq!(load(SaveMode::Compact), debug);
+
+        let mut original = Vec::new();
+        world.serialize(&mut original);
+        assert_eq!(debug, original);
+    }
+
+    #[test]
+    fn centering_puts_the_block_under_the_middle_of_the_screen() {
+        let screen = ScreenDimensions {
+            width: 800,
+            height: 600,
+        };
+        assert_eq!(World::center_player_on(Vec2i::ZERO, &screen), Vec2i::new(-368, -268));
+        assert_eq!(World::center_player_on(Vec2i::new(3, -2), &screen), Vec2i::new(-176, -396));
+
+        let odd = ScreenDimensions {
+            width: 1025,
+            height: 767,
+        };
+        for screen in [screen, odd] {
+            let middle = Vec2i::new(screen.width / 2, screen.height / 2);
+            for pos in [Vec2i::ZERO, Vec2i::new(3, -2), Vec2i::new(-17, 40), Vec2i::new(-1, -1)] {
+                let player = World::center_player_on(pos, &screen);
+                let under_cursor =
+                    crate::game::block_under_cursor(middle, player, BLOCK_DEFAULT_W, BLOCK_DEFAULT_H);
+                assert_eq!(under_cursor, pos, "{} on {}", pos, screen);
+            }
+        }
+    }
+
+    #[test]
+    fn generated_factory_runs_headless() {
+        register_all();
+        let mut world = World::generate(5, 5, 2024);
+        let mut config = GameConfig::default();
+
+        // an extractor and a container under every node that has room for them
+        let mut sinks = Vec::new();
+        for (x, y, _) in node_positions(&world) {
+            let free = |world: &World, y: i32| world.get_block_at(x, y).is_some_and(|(blk, _)| blk.is_none());
+            if free(&world, y + 1) && free(&world, y + 2) {
+                world.set_block_at(x, y + 1, Box::new(ExtractorBlock::default()), Direction::South);
+                world.set_block_at(x, y + 2, Box::new(StorageContainer::default()), Direction::South);
+                sinks.push((x, y + 2));
+            }
+        }
+        assert!(!sinks.is_empty());
+        // and a loop of belts around the spawn, which is always kept free
+        for i in -2..2 {
+            world.set_block_at(i, -2, Box::new(ConveyorBlock::default()), Direction::West);
+            world.set_block_at(2, i, Box::new(ConveyorBlock::default()), Direction::South);
+            world.set_block_at(-i, 2, Box::new(ConveyorBlock::default()), Direction::East);
+            world.set_block_at(-2, -i, Box::new(ConveyorBlock::default()), Direction::North);
+        }
+
+        for _ in 0..100 {
+            world.tick(&mut config);
+        }
+        assert!(world.last_update_nanos() > 0);
+        assert!(sinks.iter().map(|&(x, y)| item_count_at(&world, x, y)).sum::<u64>() > 0);
+    }
+
+    #[test]
+    fn belt_lines_conserve_items() {
+        register_all();
+        let mut world = World::new(1, 2);
+        let mut config = GameConfig::default();
+        let mut source = StorageContainer::default();
+        source.get_inventory_capability().unwrap().try_add_item(item(*COAL_IDENTIFIER, 30));
+        world.set_block_at(0, 0, Box::new(source), Direction::South);
+        world.set_block_at(0, 1, Box::new(ExtractorBlock::default()), Direction::South);
+        for y in 2..12 {
+            world.set_block_at(0, y, Box::new(ConveyorBlock::default()), Direction::South);
+        }
+        world.set_block_at(0, 12, Box::new(StorageContainer::default()), Direction::South);
+
+        let total = world.total_item_count();
+        assert_eq!(total, 30);
+        for tick in 0..600 {
+            world.tick(&mut config);
+            assert_eq!(world.total_item_count(), total, "after tick {}", tick);
+        }
+        // 30 seconds are enough for items to make it all the way down
+        assert!(item_count_at(&world, 0, 12) > 0);
+        assert!(item_count_at(&world, 0, 0) < 30);
+    }
+
+    /// a container with `source` in it that an extractor empties onto a line of belts
+    fn belt_line(source: Option<Stack>) -> World {
+        let mut world = World::new(1, 2);
+        let mut container = StorageContainer::default();
+        if let Some(source) = source {
+            container.get_inventory_capability().unwrap().try_add_item(source);
+        }
+        world.set_block_at(0, 0, Box::new(container), Direction::South);
+        world.set_block_at(0, 1, Box::new(ExtractorBlock::default()), Direction::South);
+        for y in 2..12 {
+            world.set_block_at(0, y, Box::new(ConveyorBlock::default()), Direction::South);
+        }
+        world.set_block_at(0, 12, Box::new(StorageContainer::default()), Direction::South);
+        world
+    }
+
+    #[test]
+    fn moving_plain_items_does_not_allocate() {
+        register_all();
+        let mut boxed = get_item_by_id(*COAL_IDENTIFIER).unwrap().clone_item();
+        boxed.set_metadata(30);
+        let mut runs = Vec::new();
+        for source in [None, Some(item(*COAL_IDENTIFIER, 30)), Some(Stack::Special(boxed))] {
+            let mut world = belt_line(source);
+            let mut config = GameConfig::default();
+            // the first ticks grow the task queues and such to their full size
+            for _ in 0..20 {
+                world.tick(&mut config);
+            }
+            let left_before = item_count_at(&world, 0, 0);
+            let allocations = count_allocations(|| {
+                for _ in 0..200 {
+                    world.tick(&mut config);
+                }
+            });
+            runs.push((allocations, left_before - item_count_at(&world, 0, 0)));
+        }
+        let [(empty, _), (plain, plain_moved), (boxed, boxed_moved)] = runs[..] else { unreachable!() };
+        assert!(plain_moved > 0);
+        assert_eq!(plain_moved, boxed_moved);
+        // carrying items costs nothing over ticking an idle line
+        assert!(plain <= empty, "{} {}", plain, empty);
+        // a boxed stack needs a new allocation for every item taken off of it
+        assert!(boxed >= plain + plain_moved, "{} {} {}", boxed, plain, plain_moved);
+    }
+
+    #[test]
+    fn directions_from_neighbouring_positions() {
+        let from = Vec2i::new(-3, 7);
+        assert_eq!(Direction::from_delta(from, Vec2i::new(-3, 6)), Some(Direction::North));
+        assert_eq!(Direction::from_delta(from, Vec2i::new(-3, 8)), Some(Direction::South));
+        assert_eq!(Direction::from_delta(from, Vec2i::new(-4, 7)), Some(Direction::East));
+        assert_eq!(Direction::from_delta(from, Vec2i::new(-2, 7)), Some(Direction::West));
+        for dir in Direction::all() {
+            assert_eq!(Direction::from_delta(from, from + dir.to_vec2i()), Some(dir));
+        }
+
+        // diagonals, blocks further away and the position itself aren't next to it
+        for (x, y) in [(-2, 6), (-4, 8), (-2, 8), (-4, 6), (-3, 5), (-1, 7), (0, 0), (-3, 7)] {
+            assert_eq!(Direction::from_delta(from, Vec2i::new(x, y)), None, "{x} {y}");
+        }
+    }
+
+    #[test]
+    fn pasting_only_applies_to_the_same_kind() {
+        register_all();
+        let mut world = World::new(1, 1);
+        let splitter_id = ConveyorSplitter::default().identifier();
+        let assembler_id = AssemblerBlock::default().identifier();
+        let recipe = Identifier::from(("placeholder_name_2", "iron_ingot"));
+        let filtered = |pos: Vec2i, world: &World| {
+            let blk = world.get_block_at(pos.x, pos.y).unwrap().0;
+            crate::blocks::downcast::<ConveyorSplitter>(&**blk).unwrap().filters()
+        };
+        let recipe_at = |pos: Vec2i, world: &World| world.get_block_at(pos.x, pos.y).unwrap().0.recipe();
+
+        let mut source = ConveyorSplitter::default();
+        source.set_filter(0, Some(*COAL_IDENTIFIER));
+        source.set_filter(2, Some(*IRON_ORE_IDENTIFIER));
+        let splitter_config = source.copy_config().unwrap();
+        let mut source = AssemblerBlock::default();
+        source.set_recipe(recipe);
+        let assembler_config = source.copy_config().unwrap();
+        let unset_config = AssemblerBlock::default().copy_config().unwrap();
+
+        let splitter = Vec2i::new(1, 1);
+        let assembler = Vec2i::new(3, 1);
+        world.set_block_at(splitter.x, splitter.y, Box::new(ConveyorSplitter::default()), Direction::North);
+        world.set_block_at(assembler.x, assembler.y, Box::new(AssemblerBlock::default()), Direction::North);
+
+        // the same kind takes the settings and the direction
+        assert!(world.paste_config(splitter, splitter_id, Direction::East, Some(splitter_config.as_slice())));
+        assert_eq!(filtered(splitter, &world), [Some(*COAL_IDENTIFIER), None, Some(*IRON_ORE_IDENTIFIER)]);
+        assert_eq!(world.get_block_at(splitter.x, splitter.y).unwrap().1.direction, Direction::East);
+        assert!(world.paste_config(assembler, assembler_id, Direction::South, Some(assembler_config.as_slice())));
+        assert_eq!(recipe_at(assembler, &world), Some(recipe));
+
+        // another kind only gets turned
+        assert!(world.paste_config(splitter, assembler_id, Direction::West, Some(assembler_config.as_slice())));
+        assert_eq!(filtered(splitter, &world), [Some(*COAL_IDENTIFIER), None, Some(*IRON_ORE_IDENTIFIER)]);
+        assert_eq!(world.get_block_at(splitter.x, splitter.y).unwrap().1.direction, Direction::West);
+        assert!(world.paste_config(assembler, splitter_id, Direction::North, Some(splitter_config.as_slice())));
+        assert_eq!(recipe_at(assembler, &world), Some(recipe));
+        assert_eq!(world.get_block_at(assembler.x, assembler.y).unwrap().1.direction, Direction::North);
+
+        // an assembler without a recipe clears the recipe of the one it gets pasted onto
+        assert!(world.paste_config(assembler, assembler_id, Direction::North, Some(unset_config.as_slice())));
+        assert_eq!(recipe_at(assembler, &world), None);
+
+        // empty cells have nothing to paste onto
+        assert!(!world.paste_config(Vec2i::new(5, 1), splitter_id, Direction::North, Some(splitter_config.as_slice())));
+    }
+
+    #[test]
+    fn threaded_updates_schedule_in_chunk_order() {
+        register_all();
+        // an extractor in every chunk, so every one of them has a machine
+        let mut world = World::new(8, 8);
+        for (x, y) in world.chunks.keys().copied().collect::<Vec<_>>() {
+            let (x, y) = (x * BLOCKS_PER_CHUNK_X as i32 + 3, y * BLOCKS_PER_CHUNK_Y as i32 + 5);
+            world.set_block_at(x, y, Box::new(ExtractorBlock::default()), Direction::South);
+        }
+        assert!(world.chunks.len() >= MIN_CHUNKS_PER_THREAD * 2);
+
+        let updated = |world: &mut World, threads| -> Vec<Vec2i> {
+            collect_tasks(|| world.update_chunks(threads))
+                .into_iter()
+                .map(|task| match task {
+                    Task::WorldUpdateBlock(_, meta) => meta.position,
+                    _ => panic!("only block updates get scheduled"),
+                })
+                .collect()
+        };
+        let single = updated(&mut world, 1);
+        assert_eq!(single.len(), world.chunks.len());
+        assert_eq!(updated(&mut world, 4), single);
+        assert_eq!(updated(&mut world, 64), single);
+    }
+
+    #[test]
+    fn non_square_chunks_map_every_edge() {
+        let (w, h) = (5u32, 3u32);
+        // the corners of the chunk at (0, 0) and the blocks just outside of them
+        let cases = [
+            ((0, 0), (0, 0), 0),
+            ((4, 0), (0, 0), 4),
+            ((0, 2), (0, 0), 10),
+            ((4, 2), (0, 0), 14),
+            ((5, 0), (1, 0), 0),
+            ((0, 3), (0, 1), 0),
+            ((-1, 0), (-1, 0), 4),
+            ((0, -1), (0, -1), 10),
+            ((-1, -1), (-1, -1), 14),
+            ((-5, -3), (-1, -1), 0),
+            ((-6, -4), (-2, -2), 14),
+        ];
+        for ((x, y), chunk, idx) in cases {
+            assert_eq!(chunk_pos_in(x, y, w, h), chunk, "{} {}", x, y);
+            assert_eq!(block_index_in(x, y, w, h), idx, "{} {}", x, y);
+        }
+
+        // every block of a chunk gets its own index, and the chunk and index lead back to the position
+        for (cx, cy) in [(0, 0), (-1, 2), (3, -4)] {
+            let mut seen = vec![false; (w * h) as usize];
+            for y in cy * h as i32..(cy + 1) * h as i32 {
+                for x in cx * w as i32..(cx + 1) * w as i32 {
+                    assert_eq!(chunk_pos_in(x, y, w, h), (cx, cy));
+                    let idx = block_index_in(x, y, w, h);
+                    assert!(!seen[idx], "{} {}", x, y);
+                    seen[idx] = true;
+                    assert_eq!(cx * w as i32 + (idx % w as usize) as i32, x);
+                    assert_eq!(cy * h as i32 + (idx / w as usize) as i32, y);
+                }
+            }
+            assert!(seen.iter().all(|&seen| seen));
+        }
+    }
+}